@@ -139,7 +139,7 @@ impl AIEngine {
         Err("Local LLM not yet implemented".to_string())
     }
 
-    fn classify_with_rules(&self, text: &str) -> Result<IntentClassification, String> {
+    pub fn classify_with_rules(&self, text: &str) -> Result<IntentClassification, String> {
         let text_lower = text.to_lowercase();
         
         let intent_type = if text_lower.starts_with("open ") {
@@ -8,6 +8,8 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+use crate::cloud::{CustomCommandRef, DictationStyle, VoiceContext, WireAction};
+
 /// API client configuration
 #[derive(Debug, Clone)]
 pub struct ApiConfig {
@@ -45,35 +47,13 @@ pub struct TranscriptionResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessRequest {
+    /// Action wire format the client understands
+    pub version: u32,
     pub text: String,
     pub context: Option<VoiceContext>,
     pub conversation_history: Option<String>,
-    pub custom_commands: Option<Vec<CustomCommand>>,
-    pub dictation_style: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct VoiceContext {
-    pub active_app: Option<String>,
-    pub selected_text: Option<String>,
-    pub os: String,
-    pub mode: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct CustomCommand {
-    pub trigger: String,
-    pub name: String,
-    pub id: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ActionResponse {
-    pub action_type: String,
-    pub payload: serde_json::Value,
-    pub refined_text: Option<String>,
-    pub response_text: Option<String>,
-    pub requires_confirmation: bool,
+    pub custom_commands: Option<Vec<CustomCommandRef>>,
+    pub dictation_style: Option<DictationStyle>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -209,7 +189,7 @@ impl ApiClient {
     }
 
     /// Process text intent using the backend API
-    pub async fn process_intent(&self, request: ProcessRequest) -> Result<ActionResponse, String> {
+    pub async fn process_intent(&self, request: ProcessRequest) -> Result<WireAction, String> {
         let url = format!("{}/api/voice/process", self.config.base_url);
         
         // Build request with auth
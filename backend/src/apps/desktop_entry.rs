@@ -0,0 +1,335 @@
+//! Parsing for XDG `.desktop` files
+//!
+//! Follows the Desktop Entry Specification closely enough for launching:
+//! the `[Desktop Entry]` group, localized keys, string lists, the
+//! visibility keys and the quoting and field codes of `Exec`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Where an entry was installed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppSource {
+    System,
+    User,
+    Flatpak,
+    Snap,
+}
+
+/// An installed application
+#[derive(Debug, Clone, Serialize)]
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. "org.kde.dolphin.desktop"
+    pub id: String,
+    pub name: String,
+    pub generic_name: Option<String>,
+    /// Name and GenericName in the user's languages
+    pub localized_names: Vec<String>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub exec: String,
+    pub icon: Option<String>,
+    pub working_dir: Option<String>,
+    pub terminal: bool,
+    /// WM_CLASS of the windows the app opens, when it differs from the ID
+    pub startup_wm_class: Option<String>,
+    pub path: PathBuf,
+    pub source: AppSource,
+}
+
+/// What decides how an entry reads and whether it is shown
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// The user's locale keys, most specific first ("de_DE", "de")
+    pub locales: Vec<String>,
+    /// `XDG_CURRENT_DESKTOP` names, lowercase ("gnome", "kde")
+    pub desktops: Vec<String>,
+}
+
+/// Undo the value escapes `\s`, `\n`, `\t`, `\r` and `\\`
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Split a `;`-separated list, honouring `\;`
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&';') => {
+                current.push(';');
+                chars.next();
+            }
+            ';' => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+    items
+        .into_iter()
+        .map(|item| unescape(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Whether a `TryExec` program is installed
+fn try_exec_found(program: &str) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    if program.starts_with('/') {
+        std::fs::metadata(program).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    } else {
+        super::find_in_path(program).is_some()
+    }
+}
+
+/// Parse one desktop file. Returns None for entries that are not visible
+/// applications: links, directories, `NoDisplay`, `Hidden`, no `Exec`, a
+/// missing `TryExec` program, or `OnlyShowIn`/`NotShowIn` ruling out the
+/// current desktop.
+pub fn parse(id: &str, path: &Path, contents: &str, env: &Environment, source: AppSource) -> Option<DesktopEntry> {
+    let mut in_main_group = false;
+    let mut keys: HashMap<&str, &str> = HashMap::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            keys.entry(key.trim()).or_insert(value.trim());
+        }
+    }
+
+    let flag = |key: &str| keys.get(key).is_some_and(|value| *value == "true");
+    if keys.get("Type").copied() != Some("Application") || flag("NoDisplay") || flag("Hidden") {
+        return None;
+    }
+    let on_current_desktop = |key: &str| {
+        keys.get(key).map(|value| {
+            split_list(value)
+                .iter()
+                .any(|desktop| env.desktops.contains(&desktop.to_lowercase()))
+        })
+    };
+    if on_current_desktop("OnlyShowIn") == Some(false) || on_current_desktop("NotShowIn") == Some(true) {
+        return None;
+    }
+    if keys.get("TryExec").is_some_and(|program| !try_exec_found(&unescape(program))) {
+        return None;
+    }
+    let exec = keys.get("Exec").map(|value| unescape(value)).filter(|exec| !exec.trim().is_empty())?;
+    let name = keys.get("Name").map(|value| unescape(value))?;
+
+    let localized = |key: &str| -> Vec<&str> {
+        env.locales
+            .iter()
+            .filter_map(|locale| keys.get(format!("{}[{}]", key, locale).as_str()).copied())
+            .collect()
+    };
+
+    let mut localized_names: Vec<String> = localized("Name")
+        .into_iter()
+        .chain(localized("GenericName"))
+        .map(unescape)
+        .filter(|localized| *localized != name)
+        .collect();
+    localized_names.dedup();
+
+    let mut keywords: Vec<String> = keys.get("Keywords").map(|value| split_list(value)).unwrap_or_default();
+    for value in localized("Keywords") {
+        keywords.extend(split_list(value));
+    }
+
+    Some(DesktopEntry {
+        id: id.to_string(),
+        name,
+        generic_name: keys.get("GenericName").map(|value| unescape(value)),
+        localized_names,
+        keywords,
+        categories: keys.get("Categories").map(|value| split_list(value)).unwrap_or_default(),
+        exec,
+        icon: keys.get("Icon").map(|value| unescape(value)),
+        working_dir: keys.get("Path").map(|value| unescape(value)).filter(|dir| !dir.is_empty()),
+        terminal: flag("Terminal"),
+        startup_wm_class: keys.get("StartupWMClass").map(|value| unescape(value)).filter(|class| !class.is_empty()),
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Turn an `Exec` value into argv: split on unquoted spaces, undo the
+/// double-quote escapes and expand field codes for a launch without files.
+/// Flatpak's `@@`/`@@u` file-forwarding markers go with the file codes.
+pub fn exec_argv(entry: &DesktopEntry) -> Result<Vec<String>, String> {
+    let mut words: Vec<(String, bool)> = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_word = false;
+    let mut chars = entry.exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = true;
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => current.extend(chars.next()),
+                        Some(inner) => current.push(inner),
+                        None => return Err(format!("Unterminated quote in Exec of {}", entry.id)),
+                    }
+                }
+            }
+            ' ' | '\t' => {
+                if in_word {
+                    words.push((std::mem::take(&mut current), quoted));
+                    in_word = false;
+                    quoted = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push((current, quoted));
+    }
+
+    let mut argv = Vec::new();
+    for (word, quoted) in words {
+        // Field codes only count outside quotes
+        if quoted {
+            argv.push(word);
+            continue;
+        }
+        match word.as_str() {
+            "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" | "@@" | "@@u" => {}
+            "%i" => {
+                if let Some(icon) = &entry.icon {
+                    argv.push("--icon".to_string());
+                    argv.push(icon.clone());
+                }
+            }
+            _ => argv.push(
+                word.replace("%c", &entry.name)
+                    .replace("%k", &entry.path.to_string_lossy())
+                    .replace("%%", "%"),
+            ),
+        }
+    }
+
+    if argv.is_empty() {
+        return Err(format!("Empty Exec in {}", entry.id));
+    }
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOLPHIN: &str = r#"
+[Desktop Entry]
+Type=Application
+Name=Dolphin
+Name[de]=Dolphin
+GenericName=File Manager
+GenericName[de]=Dateiverwaltung
+Keywords=files;file management;file browsing\;samba;
+Exec=dolphin %u
+Icon=system-file-manager
+Categories=Qt;KDE;System;FileManager;
+
+[Desktop Action new-window]
+Name=Open a New Window
+Exec=dolphin --new-window
+"#;
+
+    fn dolphin() -> DesktopEntry {
+        let env = Environment { locales: vec!["de_DE".to_string(), "de".to_string()], desktops: Vec::new() };
+        parse("org.kde.dolphin.desktop", Path::new("/usr/share/applications/org.kde.dolphin.desktop"), DOLPHIN, &env, AppSource::System).unwrap()
+    }
+
+    #[test]
+    fn parses_main_group_with_locales() {
+        let entry = dolphin();
+        assert_eq!(entry.name, "Dolphin");
+        assert_eq!(entry.generic_name.as_deref(), Some("File Manager"));
+        assert_eq!(entry.localized_names, vec!["Dateiverwaltung"]);
+        assert_eq!(entry.keywords, vec!["files", "file management", "file browsing;samba"]);
+        assert_eq!(entry.categories, vec!["Qt", "KDE", "System", "FileManager"]);
+        assert_eq!(entry.exec, "dolphin %u");
+
+        let hidden = "[Desktop Entry]\nType=Application\nName=Helper\nExec=helper\nNoDisplay=true\n";
+        assert!(parse("helper.desktop", Path::new("helper.desktop"), hidden, &Environment::default(), AppSource::User).is_none());
+    }
+
+    #[test]
+    fn honours_visibility_keys() {
+        let kde = Environment { locales: Vec::new(), desktops: vec!["kde".to_string()] };
+        let visible = |extra: &str, env: &Environment| {
+            let contents = format!("[Desktop Entry]\nType=Application\nName=Tool\nExec=tool\n{}\n", extra);
+            parse("tool.desktop", Path::new("tool.desktop"), &contents, env, AppSource::System).is_some()
+        };
+
+        assert!(visible("", &kde));
+        assert!(!visible("Hidden=true", &kde));
+        assert!(visible("OnlyShowIn=GNOME;KDE;", &kde));
+        assert!(!visible("OnlyShowIn=GNOME;", &kde));
+        assert!(!visible("OnlyShowIn=KDE;", &Environment::default()));
+        assert!(!visible("NotShowIn=KDE;", &kde));
+        assert!(visible("NotShowIn=GNOME;", &kde));
+        assert!(visible("TryExec=/bin/sh", &kde));
+        assert!(!visible("TryExec=/nonexistent/listenos-tool", &kde));
+        assert!(!visible("TryExec=listenos-no-such-program", &kde));
+    }
+
+    #[test]
+    fn expands_exec_field_codes() {
+        let mut entry = dolphin();
+        assert_eq!(exec_argv(&entry).unwrap(), vec!["dolphin"]);
+
+        entry.exec = r#"/usr/bin/flatpak run --command=code "my \"app\"" %i %F 100%%"#.to_string();
+        assert_eq!(
+            exec_argv(&entry).unwrap(),
+            vec!["/usr/bin/flatpak", "run", "--command=code", "my \"app\"", "--icon", "system-file-manager", "100%"]
+        );
+
+        entry.exec = "/usr/bin/flatpak run --branch=stable --command=spotify --file-forwarding com.spotify.Client @@u %U @@".to_string();
+        assert_eq!(
+            exec_argv(&entry).unwrap(),
+            vec!["/usr/bin/flatpak", "run", "--branch=stable", "--command=spotify", "--file-forwarding", "com.spotify.Client"]
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Installed-app index for OpenApp on Linux
+//!
+//! Built from the XDG `.desktop` files in the user's and system data
+//! directories, including Flatpak and Snap exports, so any desktop can launch
+//! apps by their spoken name. Names, generic names, keywords and categories
+//! are matched loosely (spacing, small mishearings, similar sounds), and the
+//! index is rebuilt whenever one of the application directories changes.
+
+mod desktop_entry;
+
+pub use desktop_entry::{AppSource, DesktopEntry};
+
+use desktop_entry::Environment;
+
+use std::collections::HashSet;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Lowest score that still counts as a match
+const MIN_SCORE: u32 = 40;
+
+lazy_static::lazy_static! {
+    static ref APP_INDEX: Mutex<Option<AppIndex>> = Mutex::new(None);
+}
+
+/// Application directories in precedence order, with where their entries
+/// come from. Earlier directories override entries with the same ID.
+fn application_dirs() -> Vec<(PathBuf, AppSource)> {
+    let home = dirs_next::home_dir().unwrap_or_default();
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home.join(".local/share"));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.trim().is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    let source_of = |dir: &Path| {
+        let dir = dir.to_string_lossy();
+        if dir.contains("/flatpak/") {
+            AppSource::Flatpak
+        } else if dir.contains("/snapd/") {
+            AppSource::Snap
+        } else if dir.starts_with(&*home.to_string_lossy()) && !home.as_os_str().is_empty() {
+            AppSource::User
+        } else {
+            AppSource::System
+        }
+    };
+
+    // Flatpak and Snap normally add their exports to XDG_DATA_DIRS, but not
+    // for apps started before the session picked that up
+    let mut roots = vec![data_home.clone()];
+    roots.extend(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from));
+    roots.push(data_home.join("flatpak/exports/share"));
+    roots.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    roots.push(PathBuf::from("/var/lib/snapd/desktop"));
+
+    let mut seen = HashSet::new();
+    roots
+        .into_iter()
+        .map(|root| root.join("applications"))
+        .filter(|dir| seen.insert(dir.clone()))
+        .map(|dir| {
+            let source = source_of(&dir);
+            (dir, source)
+        })
+        .collect()
+}
+
+/// The user's locale keys, most specific first: "de_DE.UTF-8@euro" gives
+/// "de_DE@euro", "de_DE", "de@euro", "de"
+fn locale_keys() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+        .unwrap_or_default();
+
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale.to_string(), Some(modifier.to_string())),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default().to_string();
+    let language = locale.split('_').next().unwrap_or_default().to_string();
+
+    let mut keys = Vec::new();
+    for base in [&locale, &language] {
+        if base.is_empty() {
+            continue;
+        }
+        if let Some(modifier) = &modifier {
+            keys.push(format!("{}@{}", base, modifier));
+        }
+        keys.push(base.clone());
+    }
+    keys.dedup();
+    keys
+}
+
+/// `XDG_CURRENT_DESKTOP` as lowercase names ("ubuntu:GNOME" gives "ubuntu",
+/// "gnome"), matched against `OnlyShowIn` and `NotShowIn`
+fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .map(|desktop| desktop.trim().to_lowercase())
+        .filter(|desktop| !desktop.is_empty())
+        .collect()
+}
+
+/// Installed applications plus what is needed to notice changes
+pub struct AppIndex {
+    entries: Vec<DesktopEntry>,
+    /// Every scanned directory with its modification time, None if missing
+    stamps: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn modified(dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(dir).and_then(|meta| meta.modified()).ok()
+}
+
+impl AppIndex {
+    pub fn build() -> Self {
+        let env = Environment { locales: locale_keys(), desktops: current_desktops() };
+        let mut entries = Vec::new();
+        let mut stamps = Vec::new();
+        let mut ids = HashSet::new();
+
+        for (dir, source) in application_dirs() {
+            scan_dir(&dir, &dir, source, &env, &mut ids, &mut entries, &mut stamps);
+        }
+        log::info!("Indexed {} applications", entries.len());
+        Self { entries, stamps }
+    }
+
+    /// Whether an application directory appeared, disappeared or changed
+    pub fn is_stale(&self) -> bool {
+        self.stamps.iter().any(|(dir, stamp)| modified(dir) != *stamp)
+    }
+
+    pub fn entries(&self) -> &[DesktopEntry] {
+        &self.entries
+    }
+
+    /// The best entry for a spoken app name
+    pub fn find(&self, query: &str) -> Option<&DesktopEntry> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return None;
+        }
+        let mut best: Option<(&DesktopEntry, u32)> = None;
+        for entry in &self.entries {
+            let score = match_score(entry, &query);
+            if score >= MIN_SCORE && best.map_or(true, |(_, top)| score > top) {
+                best = Some((entry, score));
+            }
+        }
+        best.map(|(entry, _)| entry)
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<&DesktopEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// The entry that opened a window, from its Wayland app_id or X11
+    /// WM_CLASS: the desktop file ID first, then StartupWMClass, then the
+    /// program the entry runs
+    pub fn for_window(&self, app: &str) -> Option<&DesktopEntry> {
+        let app = app.trim().to_lowercase();
+        if app.is_empty() {
+            return None;
+        }
+        let stem = |entry: &DesktopEntry| entry.id.trim_end_matches(".desktop").to_lowercase();
+        let program = |entry: &DesktopEntry| {
+            desktop_entry::exec_argv(entry).ok().and_then(|argv| {
+                Path::new(&argv[0]).file_name().map(|name| name.to_string_lossy().to_lowercase())
+            })
+        };
+
+        self.entries
+            .iter()
+            .find(|entry| stem(entry) == app)
+            .or_else(|| {
+                self.entries.iter().find(|entry| {
+                    entry.startup_wm_class.as_deref().is_some_and(|class| class.to_lowercase() == app)
+                })
+            })
+            .or_else(|| self.entries.iter().find(|entry| stem(entry).rsplit('.').next() == Some(app.as_str())))
+            .or_else(|| self.entries.iter().find(|entry| program(entry).as_deref() == Some(app.as_str())))
+    }
+}
+
+/// Read the desktop files under `dir`. Subdirectories become part of the
+/// ID ("kde4/konsole.desktop" is "kde4-konsole.desktop").
+fn scan_dir(
+    root: &Path,
+    dir: &Path,
+    source: AppSource,
+    env: &Environment,
+    ids: &mut HashSet<String>,
+    entries: &mut Vec<DesktopEntry>,
+    stamps: &mut Vec<(PathBuf, Option<SystemTime>)>,
+) {
+    stamps.push((dir.to_path_buf(), modified(dir)));
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for item in read_dir.flatten() {
+        let path = item.path();
+        if path.is_dir() {
+            scan_dir(root, &path, source, env, ids, entries, stamps);
+            continue;
+        }
+        if path.extension().and_then(|ext| ext.to_str()) != Some("desktop") {
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let id = relative.to_string_lossy().replace('/', "-");
+        // An ID seen in an earlier directory shadows this one, even if that
+        // entry was hidden
+        if !ids.insert(id.clone()) {
+            continue;
+        }
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Some(entry) = desktop_entry::parse(&id, &path, &contents, env, source) {
+            entries.push(entry);
+        }
+    }
+}
+
+/// Lowercase, with punctuation turned into single spaces
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// "FileManager" -> "file manager"
+fn split_camel_case(category: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in category.chars().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            out.push(' ');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Soundex-style key without the length limit, so "spotify" and "spotifai"
+/// or "gimp" and "jimp" sound the same
+fn phonetic_key(word: &str) -> String {
+    let code = |c: char| match c {
+        'b' | 'f' | 'p' | 'v' => '1',
+        'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => '2',
+        'd' | 't' => '3',
+        'l' => '4',
+        'm' | 'n' => '5',
+        'r' => '6',
+        _ => '0',
+    };
+
+    let letters: Vec<char> = word.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+    let Some(&first) = letters.first() else {
+        return String::new();
+    };
+    // Soft g and j, and c and k, are the same sound at the start
+    let mut key = String::from(match first {
+        'j' => 'g',
+        'k' => 'c',
+        other => other,
+    });
+    let mut last = code(first);
+    for &c in &letters[1..] {
+        let digit = code(c);
+        if digit != '0' && digit != last {
+            key.push(digit);
+        }
+        // h and w don't separate repeated codes
+        if c != 'h' && c != 'w' {
+            last = digit;
+        }
+    }
+    key
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Everything an entry can be called, by how strongly it identifies the app
+fn entry_names(entry: &DesktopEntry) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut names = vec![normalize(&entry.name)];
+    names.extend(entry.localized_names.iter().map(|name| normalize(name)));
+
+    // "org.kde.dolphin.desktop" -> "dolphin", "/usr/bin/code --new" -> "code"
+    let stem = entry.id.trim_end_matches(".desktop");
+    names.push(normalize(stem.rsplit('.').next().unwrap_or(stem)));
+    if let Ok(argv) = desktop_entry::exec_argv(entry) {
+        if argv[0].ends_with("flatpak") {
+            // "flatpak run --command=spotify com.spotify.Client": the command
+            // and the last part of the app ID
+            names.extend(argv.iter().filter_map(|arg| arg.strip_prefix("--command=")).map(normalize));
+            let app_id = argv[1..].iter().find(|arg| !arg.starts_with('-') && *arg != "run");
+            names.extend(app_id.and_then(|id| id.rsplit('.').next()).map(normalize));
+        } else if argv[0].ends_with("snap") {
+            names.extend(argv.last().and_then(|arg| arg.rsplit('.').next()).map(normalize));
+        } else {
+            names.extend(Path::new(&argv[0]).file_name().and_then(|name| name.to_str()).map(normalize));
+        }
+    }
+
+    let generic: Vec<String> = entry.generic_name.iter().map(|name| normalize(name)).collect();
+    let mut tags: Vec<String> = entry.keywords.iter().map(|keyword| normalize(keyword)).collect();
+    tags.extend(entry.categories.iter().map(|category| split_camel_case(category)));
+
+    (names, generic, tags)
+}
+
+/// How well `query` (normalized) names `entry`
+fn match_score(entry: &DesktopEntry, query: &str) -> u32 {
+    let (names, generic, tags) = entry_names(entry);
+    let compact_query = compact(query);
+    let mut score = 0;
+
+    for name in names.iter().filter(|name| !name.is_empty()) {
+        let compact_name = compact(name);
+        // Prefixes need a few letters, or a whole word, to say anything
+        let prefix = (compact_query.len() >= 3
+            && (name.starts_with(query) || compact_name.starts_with(&compact_query)))
+            || name.starts_with(&format!("{} ", query));
+        let candidate = if *name == query || compact_name == compact_query {
+            100
+        } else if prefix {
+            80
+        } else if name.split_whitespace().any(|word| word == query) {
+            65
+        } else if compact_query.len() >= 4 && phonetic_key(&compact_name) == phonetic_key(&compact_query) {
+            50
+        } else if compact_query.len() >= 5 {
+            let distance = edit_distance(&compact_query, &compact_name);
+            if distance <= compact_query.len() / 4 {
+                45 - distance as u32
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+        score = score.max(candidate);
+    }
+
+    for name in &generic {
+        if name == query {
+            score = score.max(75);
+        } else if name.split_whitespace().any(|word| word == query) {
+            score = score.max(55);
+        }
+    }
+    if tags.iter().any(|tag| tag == query || compact(tag) == compact_query) {
+        score = score.max(70);
+    }
+    score
+}
+
+/// Run `f` against the index, rebuilding it first if it is missing or stale
+fn with_index<T>(f: impl FnOnce(&AppIndex) -> T) -> T {
+    let mut index = APP_INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    if index.as_ref().map_or(true, AppIndex::is_stale) {
+        *index = Some(AppIndex::build());
+    }
+    f(index.as_ref().expect("index was just built"))
+}
+
+/// The installed app that best matches a spoken name
+pub fn find(query: &str) -> Option<DesktopEntry> {
+    with_index(|index| index.find(query).cloned())
+}
+
+/// The installed app with this desktop file ID
+pub fn find_by_id(id: &str) -> Option<DesktopEntry> {
+    with_index(|index| index.by_id(id).cloned())
+}
+
+/// The installed app a window belongs to, from its app_id or WM_CLASS
+pub fn find_for_window(app: &str) -> Option<DesktopEntry> {
+    with_index(|index| index.for_window(app).cloned())
+}
+
+/// All visible installed apps, sorted by name
+pub fn installed_apps() -> Vec<DesktopEntry> {
+    let mut apps = with_index(|index| index.entries().to_vec());
+    apps.sort_by_key(|entry| entry.name.to_lowercase());
+    apps
+}
+
+/// Start an app the way its desktop entry describes, detached from ListenOS
+pub fn launch(entry: &DesktopEntry) -> Result<(), String> {
+    let mut argv = desktop_entry::exec_argv(entry)?;
+    if entry.terminal {
+        let terminal = std::env::var("TERMINAL")
+            .ok()
+            .filter(|terminal| !terminal.trim().is_empty())
+            .or_else(|| find_in_path("x-terminal-emulator").map(|_| "x-terminal-emulator".to_string()))
+            .ok_or_else(|| format!("{} needs a terminal, but none was found", entry.name))?;
+        argv.splice(0..0, [terminal, "-e".to_string()]);
+    }
+
+    let mut command = Command::new(&argv[0]);
+    command.args(&argv[1..]);
+    if let Some(dir) = entry.working_dir.as_deref().filter(|dir| Path::new(dir).is_dir()) {
+        command.current_dir(dir);
+    }
+
+    spawn_detached(command).map_err(|e| format!("Failed to start {}: {}", entry.name, e))?;
+    log::info!("Launched {} from {}", entry.name, entry.path.display());
+    Ok(())
+}
+
+/// Start a bare executable, detached the same way as a desktop entry
+pub fn launch_program(path: &Path) -> Result<(), String> {
+    spawn_detached(Command::new(path)).map_err(|e| format!("Failed to start {}: {}", path.display(), e))?;
+    log::info!("Launched {}", path.display());
+    Ok(())
+}
+
+fn spawn_detached(mut command: Command) -> std::io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    // Reap the process when it exits so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Full path of an executable on PATH
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if program.contains('/') {
+        return None;
+    }
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join(program))
+        .find(|path| {
+            std::fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, name: &str, generic: Option<&str>, keywords: &[&str], categories: &[&str], exec: &str) -> DesktopEntry {
+        DesktopEntry {
+            id: id.to_string(),
+            name: name.to_string(),
+            generic_name: generic.map(str::to_string),
+            localized_names: Vec::new(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            exec: exec.to_string(),
+            icon: None,
+            working_dir: None,
+            terminal: false,
+            startup_wm_class: None,
+            path: PathBuf::from(format!("/usr/share/applications/{}", id)),
+            source: AppSource::System,
+        }
+    }
+
+    #[test]
+    fn matches_spoken_names_loosely() {
+        let index = AppIndex {
+            entries: vec![
+                entry("org.kde.dolphin.desktop", "Dolphin", Some("File Manager"), &["files"], &["System", "FileManager"], "dolphin %u"),
+                entry("org.kde.konsole.desktop", "Konsole", Some("Terminal"), &["shell", "prompt"], &["TerminalEmulator"], "konsole"),
+                entry("firefox.desktop", "Firefox Web Browser", Some("Web Browser"), &["internet"], &["Network"], "firefox %u"),
+                entry("com.spotify.Client.desktop", "Spotify", None, &["music"], &["Audio"], "/usr/bin/flatpak run --branch=stable com.spotify.Client"),
+                entry("code.desktop", "Visual Studio Code", Some("Text Editor"), &["vscode"], &["Development"], "/usr/share/code/code %F"),
+            ],
+            stamps: Vec::new(),
+        };
+        let find = |query: &str| index.find(query).map(|entry| entry.id.as_str());
+
+        assert_eq!(find("firefox"), Some("firefox.desktop"));
+        assert_eq!(find("fire fox"), Some("firefox.desktop"));
+        assert_eq!(find("terminal"), Some("org.kde.konsole.desktop"));
+        assert_eq!(find("file manager"), Some("org.kde.dolphin.desktop"));
+        assert_eq!(find("files"), Some("org.kde.dolphin.desktop"));
+        assert_eq!(find("spotifai"), Some("com.spotify.Client.desktop"));
+        assert_eq!(find("vs code"), Some("code.desktop"));
+        assert_eq!(find("code"), Some("code.desktop"));
+        assert_eq!(find("konsol"), Some("org.kde.konsole.desktop"));
+        assert_eq!(find("photoshop"), None);
+        assert_eq!(find("f"), None);
+        assert_eq!(find("ko"), None);
+        assert_eq!(find("kon"), Some("org.kde.konsole.desktop"));
+    }
+
+    #[test]
+    fn finds_the_entry_behind_a_window() {
+        let mut spotify = entry("com.spotify.Client.desktop", "Spotify", None, &[], &[], "/usr/bin/flatpak run com.spotify.Client");
+        spotify.startup_wm_class = Some("spotify".to_string());
+        let index = AppIndex {
+            entries: vec![
+                entry("org.gnome.Terminal.desktop", "Terminal", None, &[], &[], "gnome-terminal --window"),
+                entry("google-chrome.desktop", "Google Chrome", None, &[], &[], "/usr/bin/google-chrome-stable %U"),
+                entry("org.gnome.TextEditor.desktop", "Text Editor", None, &[], &[], "gnome-text-editor %U"),
+                entry("org.kde.dolphin.desktop", "Dolphin", None, &[], &[], "dolphin %u"),
+                spotify,
+            ],
+            stamps: Vec::new(),
+        };
+        let for_window = |app: &str| index.for_window(app).map(|entry| entry.id.as_str());
+
+        // X11 WM_CLASS values and Wayland app_ids, as window managers report them
+        assert_eq!(for_window("Gnome-terminal"), Some("org.gnome.Terminal.desktop"));
+        assert_eq!(for_window("Google-chrome"), Some("google-chrome.desktop"));
+        assert_eq!(for_window("org.gnome.TextEditor"), Some("org.gnome.TextEditor.desktop"));
+        assert_eq!(for_window("dolphin"), Some("org.kde.dolphin.desktop"));
+        assert_eq!(for_window("Spotify"), Some("com.spotify.Client.desktop"));
+        assert_eq!(for_window("bwrap"), None);
+        assert_eq!(index.by_id("google-chrome.desktop").map(|entry| entry.name.as_str()), Some("Google Chrome"));
+    }
+
+    #[test]
+    fn builds_phonetic_keys() {
+        assert_eq!(phonetic_key("gimp"), phonetic_key("jimp"));
+        assert_eq!(phonetic_key("spotify"), phonetic_key("spotifai"));
+        assert_ne!(phonetic_key("slack"), phonetic_key("steam"));
+        assert_eq!(edit_distance("konsol", "konsole"), 1);
+    }
+}
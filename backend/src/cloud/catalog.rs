@@ -0,0 +1,235 @@
+//! Action catalogue for the intent LLM
+//!
+//! Built from the registered integrations and the user's custom commands so
+//! the prompt only ever advertises actions that exist and are enabled.
+
+use super::CustomCommandRef;
+use crate::integrations::{ActionParameter, IntegrationInfo};
+
+/// One integration action as presented to the LLM
+#[derive(Debug, Clone)]
+pub struct CatalogAction {
+    /// LLM-facing action type, e.g. "system_control"
+    pub action_type: String,
+    /// Value of `payload.action`, e.g. "lock"
+    pub sub_action: String,
+    pub name: String,
+    pub description: String,
+    pub parameters: Vec<ActionParameter>,
+    pub example_phrases: Vec<String>,
+}
+
+/// Group of actions belonging to one integration
+#[derive(Debug, Clone)]
+pub struct CatalogGroup {
+    pub integration: String,
+    pub description: String,
+    pub action_type: String,
+    pub actions: Vec<CatalogAction>,
+}
+
+/// Everything the LLM is allowed to route to besides the core actions
+#[derive(Debug, Clone, Default)]
+pub struct ActionCatalog {
+    pub groups: Vec<CatalogGroup>,
+    pub custom_commands: Vec<CustomCommandRef>,
+    /// Action types of every registered integration, advertised or not
+    pub integration_types: Vec<String>,
+}
+
+/// Map an integration name to the action type the LLM emits for it
+pub fn action_type_for_integration(integration: &str) -> String {
+    format!("{}_control", integration)
+}
+
+impl ActionCatalog {
+    /// Build the catalogue from integration metadata, keeping only enabled and
+    /// available integrations
+    pub fn from_integrations(
+        integrations: &[IntegrationInfo],
+        custom_commands: &[CustomCommandRef],
+    ) -> Self {
+        let mut groups: Vec<CatalogGroup> = integrations
+            .iter()
+            .filter(|info| info.enabled && info.available)
+            .map(|info| {
+                let action_type = action_type_for_integration(&info.name);
+                let prefix = format!("{}_", info.name);
+                let actions = info
+                    .actions
+                    .iter()
+                    .map(|a| CatalogAction {
+                        action_type: action_type.clone(),
+                        sub_action: a.id.strip_prefix(&prefix).unwrap_or(&a.id).to_string(),
+                        name: a.name.clone(),
+                        description: a.description.clone(),
+                        parameters: a.parameters.clone(),
+                        example_phrases: a.example_phrases.clone(),
+                    })
+                    .collect();
+
+                CatalogGroup {
+                    integration: info.name.clone(),
+                    description: info.description.clone(),
+                    action_type,
+                    actions,
+                }
+            })
+            .collect();
+
+        // HashMap order is random; keep the prompt stable between requests
+        groups.sort_by(|a, b| a.integration.cmp(&b.integration));
+
+        Self {
+            groups,
+            custom_commands: custom_commands.to_vec(),
+            integration_types: integrations.iter().map(|info| action_type_for_integration(&info.name)).collect(),
+        }
+    }
+
+    /// Check whether the LLM picked an action that actually exists. Core
+    /// actions always do; integration actions and custom commands only when
+    /// this catalogue advertised them.
+    pub fn allows(&self, action_type: &str, payload: &serde_json::Value) -> bool {
+        if action_type == "custom_command" {
+            let id = payload.get("command_id").and_then(|v| v.as_str());
+            let trigger = payload.get("trigger_phrase").and_then(|v| v.as_str());
+            return self.custom_commands.iter().any(|c| {
+                Some(c.id.as_str()) == id
+                    || trigger.map(|tr| tr.eq_ignore_ascii_case(&c.trigger)).unwrap_or(false)
+            });
+        }
+        if !self.integration_types.iter().any(|t| t == action_type) {
+            return true;
+        }
+
+        let sub_action = payload.get("action").and_then(|v| v.as_str()).unwrap_or("");
+        self.groups
+            .iter()
+            .filter(|g| g.action_type == action_type)
+            .flat_map(|g| g.actions.iter())
+            .any(|a| a.sub_action == sub_action)
+    }
+
+    /// Render the integration and custom command sections of the system prompt
+    pub fn render_prompt(&self) -> String {
+        let mut out = String::new();
+
+        if !self.custom_commands.is_empty() {
+            out.push_str("=== USER'S CUSTOM COMMANDS (HIGHEST PRIORITY) ===\n");
+            out.push_str("If the user says ANY of these trigger phrases, use custom_command action:\n\n");
+            for command in &self.custom_commands {
+                out.push_str(&format!(
+                    "- \"{}\" -> {{\"action\": \"custom_command\", \"payload\": {{\"command_id\": \"{}\", \"trigger_phrase\": \"{}\"}}}} ({})\n",
+                    command.trigger, command.id, command.trigger, command.name
+                ));
+            }
+            out.push('\n');
+        }
+
+        if self.groups.is_empty() {
+            return out;
+        }
+
+        out.push_str("=== INTEGRATIONS (only these actions exist) ===\n");
+        for group in &self.groups {
+            out.push_str(&format!("\n{} ({}):\n", group.action_type, group.description));
+            for action in &group.actions {
+                out.push_str(&format!(
+                    "- \"{}\" ({}): {}",
+                    action.sub_action, action.name, action.description
+                ));
+                if let Some(phrase) = action.example_phrases.first() {
+                    out.push_str(&format!(" (e.g. \"{}\")", phrase));
+                }
+                out.push('\n');
+
+                let mut example = serde_json::Map::new();
+                example.insert("action".to_string(), serde_json::json!(action.sub_action));
+                for param in &action.parameters {
+                    let kind = if param.required { "required" } else { "optional" };
+                    out.push_str(&format!(
+                        "    {} ({}, {}): {}\n",
+                        param.name, param.param_type, kind, param.description
+                    ));
+                    example.insert(param.name.clone(), placeholder_for(&param.param_type));
+                }
+                out.push_str(&format!(
+                    "    -> {{\"action\": \"{}\", \"payload\": {}}}\n",
+                    action.action_type,
+                    serde_json::Value::Object(example)
+                ));
+            }
+        }
+        out.push('\n');
+
+        out
+    }
+}
+
+fn placeholder_for(param_type: &str) -> serde_json::Value {
+    match param_type {
+        "number" => serde_json::json!(0),
+        "boolean" => serde_json::json!(true),
+        _ => serde_json::json!("..."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrations::IntegrationAction;
+
+    fn info(name: &str, enabled: bool, available: bool, ids: &[&str]) -> IntegrationInfo {
+        IntegrationInfo {
+            name: name.to_string(),
+            description: String::new(),
+            available,
+            enabled,
+            actions: ids
+                .iter()
+                .map(|id| IntegrationAction {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    description: String::new(),
+                    parameters: vec![],
+                    example_phrases: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn skips_disabled_and_unavailable_integrations() {
+        let catalog = ActionCatalog::from_integrations(
+            &[
+                info("system", true, true, &["system_lock", "system_recycle_bin"]),
+                info("spotify", true, false, &["spotify_next"]),
+                info("discord", false, true, &["discord_mute"]),
+            ],
+            &[],
+        );
+
+        assert!(catalog.allows("system_control", &serde_json::json!({"action": "recycle_bin"})));
+        assert!(!catalog.allows("system_control", &serde_json::json!({"action": "format_disk"})));
+        assert!(!catalog.allows("spotify_control", &serde_json::json!({"action": "next"})));
+        assert!(!catalog.allows("discord_control", &serde_json::json!({"action": "mute"})));
+        assert!(catalog.allows("open_app", &serde_json::json!({"app": "firefox"})));
+    }
+
+    #[test]
+    fn custom_commands_match_by_id_or_trigger() {
+        let catalog = ActionCatalog::from_integrations(
+            &[],
+            &[CustomCommandRef {
+                trigger: "start my day".to_string(),
+                name: "Morning".to_string(),
+                id: "abc".to_string(),
+            }],
+        );
+
+        assert!(catalog.allows("custom_command", &serde_json::json!({"command_id": "abc"})));
+        assert!(catalog.allows("custom_command", &serde_json::json!({"trigger_phrase": "Start My Day"})));
+        assert!(!catalog.allows("custom_command", &serde_json::json!({"command_id": "zzz"})));
+    }
+}
@@ -4,8 +4,16 @@
 //! 1. Remote mode (default): Uses backend API server for AI processing
 //! 2. Local mode (fallback): Direct API calls with environment keys
 
+pub mod catalog;
+pub mod stream;
+
 use serde::{Deserialize, Serialize};
 use reqwest::Client;
+use crate::config::AliasConfig;
+use crate::prompts::{self, PromptVars};
+use crate::usage::UsageFeature;
+
+pub use catalog::ActionCatalog;
 
 // ============ API MODE ============
 
@@ -51,7 +59,7 @@ pub fn get_deepgram_key() -> String {
 /// Extract a number from text (for brightness level, volume, etc.)
 fn extract_number(text: &str) -> Option<u32> {
     text.split_whitespace()
-        .find_map(|word| word.parse::<u32>().ok())
+        .find_map(|word| word.trim_end_matches('%').parse::<u32>().ok())
 }
 
 fn trim_spoken_punctuation(value: &str) -> String {
@@ -188,7 +196,7 @@ fn normalize_spoken_command_text(text: &str) -> String {
 }
 
 /// Post-process dictation text to clean up common issues
-fn post_process_dictation(text: &str) -> String {
+pub fn post_process_dictation(text: &str) -> String {
     let mut result = text.to_string();
     
     // Remove multiple consecutive spaces
@@ -329,6 +337,40 @@ pub struct ActionResult {
     pub response_text: Option<String>,
     /// Whether this action requires user confirmation
     pub requires_confirmation: bool,
+    /// Router confidence in this interpretation (0.0 - 1.0)
+    #[serde(default = "default_action_confidence")]
+    pub confidence: f32,
+    /// Runner-up interpretations, best first
+    #[serde(default)]
+    pub alternatives: Vec<ActionCandidate>,
+}
+
+fn default_action_confidence() -> f32 {
+    1.0
+}
+
+/// A competing interpretation of the same utterance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionCandidate {
+    pub action_type: ActionType,
+    pub payload: serde_json::Value,
+    pub refined_text: Option<String>,
+    pub confidence: f32,
+}
+
+impl ActionCandidate {
+    /// Turn the candidate back into an executable action
+    pub fn into_action(self) -> ActionResult {
+        ActionResult {
+            action_type: self.action_type,
+            payload: self.payload,
+            refined_text: self.refined_text,
+            response_text: None,
+            requires_confirmation: false,
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        }
+    }
 }
 
 impl ActionResult {
@@ -340,6 +382,8 @@ impl ActionResult {
             refined_text: None,
             response_text: None,
             requires_confirmation: false,
+            confidence: 1.0,
+            alternatives: Vec::new(),
         }
     }
 
@@ -351,6 +395,8 @@ impl ActionResult {
             refined_text: Some(text),
             response_text: None,
             requires_confirmation: false,
+            confidence: 1.0,
+            alternatives: Vec::new(),
         }
     }
 
@@ -362,6 +408,8 @@ impl ActionResult {
             refined_text: None,
             response_text: Some(text),
             requires_confirmation: false,
+            confidence: 1.0,
+            alternatives: Vec::new(),
         }
     }
 
@@ -373,12 +421,36 @@ impl ActionResult {
             refined_text: None,
             response_text: Some(question),
             requires_confirmation: false,
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        }
+    }
+
+    /// Attach a router confidence score
+    pub fn with_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Attach a runner-up interpretation
+    pub fn with_alternative(mut self, alternative: ActionCandidate) -> Self {
+        self.alternatives.push(alternative);
+        self
+    }
+
+    /// Snapshot this action as a clarification candidate
+    pub fn to_candidate(&self) -> ActionCandidate {
+        ActionCandidate {
+            action_type: self.action_type,
+            payload: self.payload.clone(),
+            refined_text: self.refined_text.clone(),
+            confidence: self.confidence,
         }
     }
 }
 
 /// Dictation style setting (matches config)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum DictationStyle {
     /// Caps + Full punctuation
     #[default]
@@ -389,6 +461,14 @@ pub enum DictationStyle {
     VeryCasual,
 }
 
+/// A user's custom command as advertised to the LLM and the backend server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomCommandRef {
+    pub trigger: String,
+    pub name: String,
+    pub id: String,
+}
+
 /// Conversation context for multi-turn dialogues
 #[derive(Debug, Clone, Default)]
 pub struct ConversationContext {
@@ -403,11 +483,15 @@ pub struct ConversationContext {
     /// Extracted user facts/preferences
     pub user_facts: Vec<String>,
     /// User's custom commands (trigger phrases and IDs)
-    pub custom_commands: Vec<(String, String, String)>, // (trigger, name, id)
+    pub custom_commands: Vec<CustomCommandRef>,
     /// User's text expansion snippets (trigger, expansion)
     pub snippets: Vec<(String, String)>,
+    /// User's app, website and search-engine aliases
+    pub aliases: AliasConfig,
     /// Current dictation style
     pub dictation_style: DictationStyle,
+    /// Integration actions and custom commands the LLM may route to
+    pub action_catalog: ActionCatalog,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -435,6 +519,7 @@ pub enum ActionType {
     
     // App integration actions
     SpotifyControl,     // Control Spotify (play, pause, next, etc.)
+    MediaControl,       // Control any media player (play, pause, seek, what's playing)
     DiscordControl,     // Control Discord (mute, deafen, etc.)
     SystemControl,      // System controls (brightness, lock, etc.)
     
@@ -448,6 +533,642 @@ pub enum ActionType {
     WindowControl,      // Control windows (minimize, maximize, close, etc.)
 }
 
+/// "repeat last command" -> 1, "repeat command 3" / "repeat command number three" -> 3
+fn parse_repeat_command(t: &str) -> Option<u64> {
+    if matches!(t, "repeat last command" | "repeat the last command" | "do that again" | "run that again") {
+        return Some(1);
+    }
+
+    let rest = t
+        .strip_prefix("repeat command ")
+        .or_else(|| t.strip_prefix("repeat the command "))?;
+    let rest = rest.strip_prefix("number ").unwrap_or(rest).trim();
+    spoken_number(rest).map(u64::from)
+}
+
+/// "3" / "three" -> 3, for the small numbers people say in commands
+fn spoken_number(word: &str) -> Option<u32> {
+    let words = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+    word.parse::<u32>()
+        .ok()
+        .or_else(|| words.iter().position(|w| *w == word).map(|i| i as u32 + 1))
+        .filter(|n| *n > 0)
+}
+
+/// A spoken window name without filler: "the slack window" -> "slack".
+/// None when it refers to the focused window instead.
+#[cfg(target_os = "linux")]
+fn window_target(words: &str) -> Option<String> {
+    let target = words.trim();
+    let target = target.strip_prefix("the ").unwrap_or(target);
+    let target = target
+        .strip_suffix(" window")
+        .or_else(|| target.strip_suffix(" app"))
+        .unwrap_or(target)
+        .trim();
+    if matches!(target, "" | "this" | "it" | "that" | "window" | "this window" | "current window") {
+        None
+    } else {
+        Some(target.to_string())
+    }
+}
+
+/// The layout name in "layout as coding", "coding layout", "the coding layout"
+#[cfg(target_os = "linux")]
+fn layout_name(words: &str) -> Option<String> {
+    let words = words.trim();
+    let words = ["the ", "my ", "this ", "current "]
+        .iter()
+        .fold(words, |rest, filler| rest.strip_prefix(filler).unwrap_or(rest));
+    let name = words
+        .strip_prefix("layout as ")
+        .or_else(|| words.strip_prefix("layout "))
+        .or_else(|| words.strip_suffix(" layout"))?
+        .trim();
+    (!name.is_empty() && name.split_whitespace().count() <= 3).then(|| name.to_string())
+}
+
+/// Transport and player commands for whatever is playing: "pause", "pause firefox",
+/// "skip forward 30 seconds", "shuffle on", "repeat this song", "what's playing".
+/// Words that might name a player are passed on as-is; the media integration
+/// decides whether they match a running one, so parsing never does I/O.
+fn parse_media_command(t: &str) -> Option<serde_json::Value> {
+    let simple = match t {
+        "play" | "resume" | "resume music" | "play music" => Some("play"),
+        "pause" | "pause music" | "pause the music" | "stop music" | "stop the music" => Some("pause"),
+        "next" | "skip" | "next song" | "next track" => Some("next"),
+        "previous" | "previous song" | "previous track" | "last song" => Some("previous"),
+        "what's playing" | "whats playing" | "what is playing" | "what song is this" | "what's this song" => Some("now_playing"),
+        "list players" | "list media players" | "which players are running" => Some("list_players"),
+        "shuffle" | "toggle shuffle" => Some("shuffle"),
+        _ => None,
+    };
+    if let Some(action) = simple {
+        return Some(serde_json::json!({"action": action}));
+    }
+
+    // "pause firefox", "resume spotify", "next track on vlc"
+    let targeted = |action: &str, words: &str| serde_json::json!({"action": action, "player": words});
+    for (prefix, action) in [("pause ", "pause"), ("resume ", "play")] {
+        if let Some(player) = t.strip_prefix(prefix) {
+            let player = player.strip_prefix("the ").unwrap_or(player).trim();
+            if !player.is_empty() && player.split_whitespace().count() <= 2 && player != "music" {
+                return Some(targeted(action, player));
+            }
+        }
+    }
+    for (prefix, action) in [("next track on ", "next"), ("next song on ", "next"), ("previous track on ", "previous")] {
+        if let Some(player) = t.strip_prefix(prefix).filter(|p| !p.is_empty()) {
+            return Some(targeted(action, player.strip_prefix("the ").unwrap_or(player)));
+        }
+    }
+
+    // "skip forward 30 seconds", "rewind 10 seconds", "go back a minute"
+    let seek = [
+        ("skip forward ", 1.0),
+        ("fast forward ", 1.0),
+        ("jump ahead ", 1.0),
+        ("skip ahead ", 1.0),
+        ("rewind ", -1.0),
+        ("skip back ", -1.0),
+        ("go back ", -1.0),
+        ("jump back ", -1.0),
+    ];
+    for (prefix, sign) in seek {
+        if let Some(amount) = t.strip_prefix(prefix) {
+            let seconds = spoken_duration_secs(amount)?;
+            return Some(serde_json::json!({"action": "seek", "seconds": sign * seconds}));
+        }
+    }
+
+    if let Some(state) = t
+        .strip_prefix("shuffle ")
+        .or_else(|| t.strip_prefix("turn shuffle "))
+    {
+        return match state {
+            "on" => Some(serde_json::json!({"action": "shuffle", "enabled": true})),
+            "off" => Some(serde_json::json!({"action": "shuffle", "enabled": false})),
+            _ => None,
+        };
+    }
+    if matches!(t, "turn on shuffle" | "turn off shuffle") {
+        return Some(serde_json::json!({"action": "shuffle", "enabled": t == "turn on shuffle"}));
+    }
+
+    // "repeat this song", "loop the playlist", "repeat off" - but not "repeat command 2"
+    if let Some(target) = t.strip_prefix("repeat ").or_else(|| t.strip_prefix("loop ")) {
+        let target = ["this ", "the ", "my "]
+            .iter()
+            .fold(target, |rest, filler| rest.strip_prefix(filler).unwrap_or(rest));
+        let mode = match target {
+            "song" | "track" | "one" => "track",
+            "playlist" | "album" | "all" | "queue" => "playlist",
+            "off" | "none" => "none",
+            _ => return None,
+        };
+        return Some(serde_json::json!({"action": "loop", "mode": mode}));
+    }
+
+    // "music volume 40", "player volume down"
+    let volume = ["music volume ", "player volume ", "media volume "]
+        .iter()
+        .find_map(|prefix| t.strip_prefix(prefix))?;
+    let volume = volume.strip_prefix("to ").unwrap_or(volume).trim_end_matches(" percent").trim_end_matches('%');
+    match volume {
+        "up" | "down" | "mute" => Some(serde_json::json!({"action": "volume", "direction": volume})),
+        level => {
+            let level = level.parse::<u32>().ok().filter(|n| *n <= 100)?;
+            Some(serde_json::json!({"action": "volume", "level": level}))
+        }
+    }
+}
+
+/// "30 seconds" / "ten seconds" / "a minute" / "2 minutes" -> seconds
+fn spoken_duration_secs(words: &str) -> Option<f64> {
+    let mut parts = words.split_whitespace();
+    let amount = parts.next()?;
+    let unit = parts.next().unwrap_or("seconds");
+    if parts.next().is_some() {
+        return None;
+    }
+    let amount = match amount {
+        "a" | "an" => 1,
+        other => spoken_number(other)?,
+    };
+    let scale = match unit {
+        "second" | "seconds" | "secs" => 1.0,
+        "minute" | "minutes" | "mins" => 60.0,
+        _ => return None,
+    };
+    Some(amount as f64 * scale)
+}
+
+/// Words that only make sense as the name of an audio device
+fn names_audio_device(words: &str) -> bool {
+    let device_words = [
+        "headphones", "headphone", "headset", "earbuds", "earphones", "airpods", "speakers", "speaker",
+        "hdmi", "microphone", "mic", "soundbar",
+    ];
+    words.split_whitespace().any(|w| device_words.contains(&w))
+}
+
+/// What "turn X down" or "mute X" refers to when X is not an app
+fn is_whole_volume(target: &str) -> bool {
+    matches!(
+        target,
+        "it" | "volume" | "the volume" | "sound" | "the sound" | "audio" | "the audio" | "everything"
+            | "music" | "the music" | "computer" | "the computer" | "speakers" | "the speakers"
+    )
+}
+
+/// Volume commands beyond up/down: "volume 35 percent", "what's the volume",
+/// "turn spotify down", "set firefox volume to 30", "mute discord",
+/// "switch to headphones", "use the usb microphone", "list audio devices"
+fn parse_volume_command(t: &str) -> Option<serde_json::Value> {
+    if matches!(
+        t,
+        "what's the volume" | "whats the volume" | "what is the volume" | "current volume" | "how loud is it"
+    ) {
+        return Some(serde_json::json!({"action": "get"}));
+    }
+    if matches!(
+        t,
+        "list audio devices" | "list sound devices" | "list audio outputs" | "show audio devices"
+            | "what audio devices are there"
+    ) {
+        return Some(serde_json::json!({"action": "list_devices"}));
+    }
+
+    // "switch to headphones", "switch audio to hdmi", "use the usb microphone"
+    let device_prefixes = [
+        ("switch audio to ", true),
+        ("switch output to ", true),
+        ("switch sound to ", true),
+        ("play audio through ", true),
+        ("switch input to ", true),
+        ("switch microphone to ", true),
+        ("switch mic to ", true),
+        ("switch to ", false),
+        ("use ", false),
+    ];
+    for (prefix, explicit) in device_prefixes {
+        let Some(device) = t.strip_prefix(prefix) else { continue };
+        if !explicit && !names_audio_device(device) {
+            continue;
+        }
+        let device = device.strip_prefix("the ").or_else(|| device.strip_prefix("my ")).unwrap_or(device);
+        let input = ["input", "microphone", "mic"]
+            .iter()
+            .any(|word| prefix.contains(word) || device.split_whitespace().any(|w| w == *word));
+        let action = if input { "set_input" } else { "set_output" };
+        return Some(serde_json::json!({"action": action, "device": device}));
+    }
+
+    // "mute discord", "unmute firefox"
+    for (prefix, direction) in [("mute ", "mute"), ("unmute ", "unmute")] {
+        if let Some(target) = t.strip_prefix(prefix) {
+            if is_whole_volume(target) {
+                return Some(serde_json::json!({"direction": direction}));
+            }
+            if target.split_whitespace().count() > 2 || names_audio_device(target) {
+                return None;
+            }
+            return Some(serde_json::json!({"app": target, "direction": direction}));
+        }
+    }
+
+    // "turn spotify down", "turn down firefox", "turn it up"
+    if let Some(rest) = t.strip_prefix("turn ") {
+        let (target, direction) = if let Some(target) = rest.strip_prefix("up ") {
+            (target, "up")
+        } else if let Some(target) = rest.strip_prefix("down ") {
+            (target, "down")
+        } else if let Some(target) = rest.strip_suffix(" up") {
+            (target, "up")
+        } else if let Some(target) = rest.strip_suffix(" down") {
+            (target, "down")
+        } else {
+            return None;
+        };
+        let target = target.trim();
+        if is_whole_volume(target) {
+            return Some(serde_json::json!({"direction": direction}));
+        }
+        if target.is_empty() || target.split_whitespace().count() > 2 || t.contains("brightness") {
+            return None;
+        }
+        let target = target.strip_prefix("the ").unwrap_or(target);
+        return Some(serde_json::json!({"app": target, "direction": direction}));
+    }
+
+    // "volume 35 percent", "set the volume to 35", "spotify volume 30"
+    let rest = t.strip_prefix("set ").unwrap_or(t);
+    let (owner, level) = rest.split_once("volume")?;
+    let level = level.trim();
+    let level = level.strip_prefix("to ").or_else(|| level.strip_prefix("at ")).unwrap_or(level);
+    let level = level.trim_end_matches(" percent").trim_end_matches('%');
+    let level = level.parse::<u32>().ok().filter(|n| *n <= 100)?;
+    let owner = owner.trim().trim_end_matches("'s");
+    let owner = if owner == "the" { "" } else { owner.strip_prefix("the ").unwrap_or(owner) };
+    if owner.is_empty() {
+        Some(serde_json::json!({"level": level}))
+    } else if owner.split_whitespace().count() <= 2 {
+        Some(serde_json::json!({"app": owner, "level": level}))
+    } else {
+        None
+    }
+}
+
+/// Wi-Fi, VPN and Bluetooth connections by name: "connect to the office wifi",
+/// "connect to the work vpn", "turn off vpn", "connect my airpods",
+/// "disconnect the keyboard", "what network am i on", "list bluetooth devices"
+fn parse_connection_command(t: &str) -> Option<serde_json::Value> {
+    if matches!(
+        t,
+        "what network am i on" | "which network am i on" | "what wifi am i on" | "which wifi am i on"
+            | "what wifi am i connected to" | "network status" | "am i connected" | "am i on the vpn"
+            | "am i connected to the vpn" | "is the vpn on" | "is the vpn connected"
+    ) {
+        return Some(serde_json::json!({"action": "network_status"}));
+    }
+    if matches!(
+        t,
+        "list bluetooth devices" | "show bluetooth devices" | "list paired devices"
+            | "what bluetooth devices are paired" | "what bluetooth devices are connected"
+            | "which bluetooth devices are connected"
+    ) {
+        return Some(serde_json::json!({"action": "bluetooth_devices"}));
+    }
+
+    let (connect, target) = if let Some(rest) = t.strip_prefix("disconnect ") {
+        (false, rest)
+    } else if let Some(rest) = t.strip_prefix("connect ").or_else(|| t.strip_prefix("join ")) {
+        (true, rest)
+    } else if let Some(rest) = t.strip_prefix("turn on ").filter(|rest| rest.ends_with("vpn")) {
+        (true, rest)
+    } else if let Some(rest) = t.strip_prefix("turn off ").filter(|rest| rest.ends_with("vpn")) {
+        (false, rest)
+    } else {
+        return None;
+    };
+    let target = target.strip_prefix("to ").or_else(|| target.strip_prefix("from ")).unwrap_or(target);
+    let words: Vec<&str> = target.split_whitespace().collect();
+    let name_without = |filler: &[&str]| {
+        words
+            .iter()
+            .filter(|w| !filler.contains(w) && !matches!(**w, "the" | "my" | "a"))
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    // "vpn" alone means the only VPN, or every connected one
+    if words.contains(&"vpn") {
+        let action = if connect { "vpn_connect" } else { "vpn_disconnect" };
+        let name = name_without(&["vpn"]);
+        let mut payload = serde_json::json!({"action": action});
+        if !name.is_empty() {
+            payload["name"] = serde_json::json!(name);
+        }
+        return Some(payload);
+    }
+    // "connect to wifi" alone still opens the settings
+    if words.iter().any(|w| matches!(*w, "wifi" | "wi-fi" | "network")) {
+        let name = name_without(&["wifi", "wi-fi", "network"]);
+        if !connect || name.is_empty() {
+            return None;
+        }
+        return Some(serde_json::json!({"action": "wifi_connect", "name": name}));
+    }
+    // Bluetooth only for something that sounds like a device, so
+    // "connect to the database" still reaches the model
+    let device = name_without(&["bluetooth"]);
+    let names_device = target.starts_with("my ")
+        || words.contains(&"bluetooth")
+        || names_audio_device(target)
+        || words.iter().any(|w| matches!(*w, "keyboard" | "mouse" | "trackpad" | "controller" | "gamepad"));
+    if device.is_empty() || !names_device {
+        return None;
+    }
+    let action = if connect { "bluetooth_connect" } else { "bluetooth_disconnect" };
+    Some(serde_json::json!({"action": action, "device": device}))
+}
+
+/// Trash contents and restoring by name: "what's in the trash",
+/// "restore report.pdf from the trash", "undelete budget"
+fn parse_trash_command(t: &str) -> Option<serde_json::Value> {
+    if matches!(
+        t,
+        "what's in the trash" | "whats in the trash" | "what is in the trash" | "list the trash" | "list trash"
+            | "show the trash" | "show trash" | "show me the trash" | "what's in the recycle bin"
+    ) {
+        return Some(serde_json::json!({"action": "trash_list"}));
+    }
+
+    // "restore" alone is also a window and layout verb, so it needs "from the trash"
+    let from_trash = [" from the trash", " from trash", " from the recycle bin", " from the bin"]
+        .iter()
+        .find_map(|suffix| t.strip_suffix(suffix));
+    let name = match from_trash {
+        Some(rest) => ["restore ", "recover ", "undelete ", "bring back "]
+            .iter()
+            .find_map(|verb| rest.strip_prefix(verb))?,
+        None => t.strip_prefix("undelete ")?,
+    };
+    let name = name.strip_prefix("the ").unwrap_or(name).trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some(serde_json::json!({"action": "trash_restore", "name": name}))
+}
+
+/// Searches that name one of the user's engines: "search github for tokio",
+/// "search for tokio on github", "look up rust in wikipedia"
+fn parse_engine_search(t: &str, aliases: &AliasConfig) -> Option<serde_json::Value> {
+    let rest = ["search for ", "search ", "look up "]
+        .iter()
+        .find_map(|prefix| t.strip_prefix(prefix))?;
+    let found = |engine: &str, query: &str| {
+        let engine = engine.trim();
+        let engine = engine.strip_prefix("the ").unwrap_or(engine);
+        let query = query.trim();
+        let engine = aliases.engine(engine).filter(|_| !query.is_empty())?;
+        Some(serde_json::json!({"query": query, "engine": engine.keyword}))
+    };
+
+    if let Some(search) = rest.split_once(" for ").and_then(|(engine, query)| found(engine, query)) {
+        return Some(search);
+    }
+    [" on ", " in ", " using ", " with "]
+        .iter()
+        .find_map(|separator| rest.rsplit_once(separator).and_then(|(query, engine)| found(engine, query)))
+}
+
+/// Window commands that name a window: "switch to slack", "close spotify",
+/// "move firefox to workspace 2", "tile code and terminal", "list windows",
+/// and saved layouts: "save layout as coding", "restore coding layout"
+#[cfg(target_os = "linux")]
+fn parse_window_command(t: &str) -> Option<serde_json::Value> {
+    let layout_verbs = [
+        ("save ", "save_layout"),
+        ("remember ", "save_layout"),
+        ("restore ", "restore_layout"),
+        ("load ", "restore_layout"),
+        ("switch to ", "restore_layout"),
+    ];
+    for (verb, action) in layout_verbs {
+        if let Some(name) = t.strip_prefix(verb).and_then(layout_name) {
+            return Some(serde_json::json!({"action": action, "name": name}));
+        }
+    }
+
+    if matches!(
+        t,
+        "list windows" | "list open windows" | "list all windows" | "show open windows"
+            | "what windows are open" | "which windows are open"
+    ) {
+        return Some(serde_json::json!({"action": "list"}));
+    }
+
+    // "tile slack and firefox", "put slack and firefox side by side"
+    let tile_body = t.strip_suffix(" side by side").map(|body| {
+        ["put ", "place ", "show ", "tile "]
+            .iter()
+            .find_map(|prefix| body.strip_prefix(prefix))
+            .unwrap_or(body)
+    });
+    if let Some(body) = tile_body.or_else(|| t.strip_prefix("tile ")) {
+        let (left, right) = body.split_once(" and ").or_else(|| body.split_once(" with "))?;
+        let (left, right) = (window_target(left)?, window_target(right)?);
+        return Some(serde_json::json!({"action": "tile", "targets": [left, right]}));
+    }
+
+    // "move firefox to workspace 2", "send this to the other monitor"
+    if let Some(rest) = t.strip_prefix("move ").or_else(|| t.strip_prefix("send ")) {
+        let (who, place) = rest.split_once(" to ")?;
+        let target = window_target(who);
+        let place = place.strip_prefix("the ").unwrap_or(place);
+        let words: Vec<&str> = place.split_whitespace().collect();
+
+        if matches!(words.first(), Some(&"workspace") | Some(&"desktop")) {
+            let workspace = spoken_number(words.last()?)?;
+            return Some(serde_json::json!({"action": "move_to_workspace", "target": target, "workspace": workspace}));
+        }
+        if words.iter().any(|w| matches!(*w, "monitor" | "screen" | "display")) {
+            let monitor = if words.contains(&"left") {
+                "left".to_string()
+            } else if words.contains(&"right") {
+                "right".to_string()
+            } else if let Some(n) = words.last().and_then(|w| spoken_number(w)) {
+                n.to_string()
+            } else {
+                "next".to_string()
+            };
+            return Some(serde_json::json!({"action": "move_to_monitor", "target": target, "monitor": monitor}));
+        }
+        return None;
+    }
+
+    let focus = ["switch to ", "focus on ", "focus ", "bring up "]
+        .iter()
+        .find_map(|prefix| t.strip_prefix(prefix));
+    let (action, target) = match (focus, t.strip_prefix("close ")) {
+        (Some(target), _) => ("focus", target),
+        (None, Some(target)) => ("close", target),
+        _ => return None,
+    };
+    // Leave desktops, tabs, modes and long phrases to the other rules
+    let excluded = ["desktop", "workspace", "tab", "mode", "next", "previous", "all"];
+    if target.split_whitespace().count() > 4 || target.split_whitespace().any(|w| excluded.contains(&w)) {
+        return None;
+    }
+    let target = window_target(target)?;
+    Some(serde_json::json!({"action": action, "target": target}))
+}
+
+/// Version of the action wire format shared with the backend server.
+/// v1 carried the core fields only; v2 added confidence and an alternative.
+pub const ACTION_WIRE_VERSION: u32 = 2;
+
+impl ActionType {
+    /// Every variant; keep in step with the enum
+    pub const ALL: [ActionType; 22] = [
+        Self::TypeText,
+        Self::RunCommand,
+        Self::OpenApp,
+        Self::OpenUrl,
+        Self::WebSearch,
+        Self::VolumeControl,
+        Self::SendEmail,
+        Self::MultiStep,
+        Self::NoAction,
+        Self::Respond,
+        Self::Clarify,
+        Self::ClipboardFormat,
+        Self::ClipboardTranslate,
+        Self::ClipboardSummarize,
+        Self::ClipboardClean,
+        Self::SpotifyControl,
+        Self::MediaControl,
+        Self::DiscordControl,
+        Self::SystemControl,
+        Self::CustomCommand,
+        Self::KeyboardShortcut,
+        Self::WindowControl,
+    ];
+
+    /// Parse the variant name used on the wire ("OpenApp", "CustomCommand", ...)
+    pub fn from_wire(name: &str) -> Result<Self, String> {
+        serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .map_err(|_| format!("Unknown action type '{}'", name))
+    }
+
+    /// Like `from_wire`, but for names people type ("runcommand", "OPENAPP")
+    pub fn from_name_ignore_case(name: &str) -> Result<Self, String> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|action_type| format!("{:?}", action_type).eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown action type '{}'", name))
+    }
+}
+
+fn wire_v1() -> u32 {
+    1
+}
+
+/// Action as returned by the backend server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireAction {
+    #[serde(default = "wire_v1")]
+    pub version: u32,
+    pub action_type: String,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    #[serde(default)]
+    pub refined_text: Option<String>,
+    #[serde(default)]
+    pub response_text: Option<String>,
+    #[serde(default)]
+    pub requires_confirmation: bool,
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    #[serde(default)]
+    pub alternative: Option<WireCandidate>,
+    /// Tokens the server spent on this request, if it reports them
+    #[serde(default)]
+    pub usage: Option<crate::usage::TokenUsage>,
+}
+
+/// Runner-up interpretation on the wire
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WireCandidate {
+    pub action_type: String,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    #[serde(default)]
+    pub refined_text: Option<String>,
+    #[serde(default)]
+    pub confidence: Option<f32>,
+}
+
+impl WireAction {
+    /// Convert to the local action model. Unknown action types are an error
+    /// rather than silently becoming typed text.
+    pub fn into_action(self) -> Result<ActionResult, String> {
+        if self.version > ACTION_WIRE_VERSION {
+            return Err(format!(
+                "Unsupported action wire version {} (client supports up to {})",
+                self.version, ACTION_WIRE_VERSION
+            ));
+        }
+
+        let action_type = ActionType::from_wire(&self.action_type)?;
+        // v1 servers only answer when sure enough, so treat a missing score as fairly sure
+        let confidence = self.confidence.unwrap_or(0.8);
+
+        let mut action = ActionResult {
+            action_type,
+            payload: self.payload,
+            refined_text: self.refined_text,
+            response_text: self.response_text,
+            requires_confirmation: self.requires_confirmation,
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        }
+        .with_confidence(confidence);
+
+        if let Some(alt) = self.alternative {
+            match ActionType::from_wire(&alt.action_type) {
+                Ok(alt_type) => {
+                    action = action.with_alternative(ActionCandidate {
+                        action_type: alt_type,
+                        payload: alt.payload,
+                        refined_text: alt.refined_text,
+                        confidence: alt.confidence.unwrap_or(1.0 - confidence).clamp(0.0, 1.0),
+                    });
+                }
+                Err(e) => log::warn!("Dropping server alternative: {}", e),
+            }
+        }
+
+        Ok(action)
+    }
+}
+
+/// Variables for the intent router prompt. Custom commands and integration
+/// actions are generated from what is actually registered, so the LLM never
+/// sees actions that don't exist.
+pub fn router_prompt_vars(voice_context: &VoiceContext, conv_context: &ConversationContext) -> PromptVars {
+    PromptVars::new()
+        .context(voice_context, conv_context)
+        .facts(&conv_context.user_facts)
+        .catalog(&conv_context.action_catalog)
+        .snippets(&conv_context.snippets)
+        .aliases(&conv_context.aliases)
+        .text("style_rules", prompts::style_rules(conv_context.dictation_style))
+}
+
 /// Groq API client - Ultra-fast transcription and LLM
 pub struct GroqClient {
     client: Client,
@@ -541,7 +1262,11 @@ impl GroqClient {
     /// Process text with Groq LLM for intent classification (Llama 3.3 70B)
     /// Legacy method - forwards to process_intent_with_context with empty context
     pub async fn process_intent(&self, text: &str, voice_context: &VoiceContext) -> Result<ActionResult, String> {
-        let conv_context = ConversationContext::default();
+        let integrations = crate::integrations::IntegrationManager::new().list_integrations();
+        let conv_context = ConversationContext {
+            action_catalog: ActionCatalog::from_integrations(&integrations, &[]),
+            ..Default::default()
+        };
         self.process_intent_with_context(text, voice_context, &conv_context).await
     }
 
@@ -552,17 +1277,15 @@ impl GroqClient {
         voice_context: &VoiceContext,
         conv_context: &ConversationContext,
     ) -> Result<ActionResult, String> {
-        // Rate limiting disabled for testing
-        // crate::rate_limit::check_llm_limit()?;
-        
         // 1. Check for local command execution FIRST (bypass LLM for speed/reliability)
-        if let Some(action) = self.detect_local_command(text) {
+        if let Some(action) = self.detect_local_command(text, &conv_context.aliases) {
             log::info!("Local command detected: {:?}", action.action_type);
-            return Ok(action);
+            return Ok(with_local_router_confidence(action, text));
         }
         
         // 2. Fallback to LLM for complex queries
-        let system_prompt = self.build_system_prompt(voice_context, conv_context);
+        crate::rate_limit::check_llm_limit()?;
+        let system_prompt = self.build_system_prompt(voice_context, conv_context)?;
         let user_message = format!(
             "User request: \"{}\"\n\nAnalyze and respond with the appropriate action.",
             text
@@ -595,6 +1318,7 @@ impl GroqClient {
 
         let result: serde_json::Value = response.json().await
             .map_err(|e| format!("Failed to parse Groq LLM response: {}", e))?;
+        crate::usage::record_completion("groq", UsageFeature::Intent, &result);
 
         let content = result["choices"][0]["message"]["content"]
             .as_str()
@@ -603,7 +1327,7 @@ impl GroqClient {
         let parsed: serde_json::Value = serde_json::from_str(content)
             .unwrap_or(serde_json::json!({"action": "type_text", "refined_text": text}));
 
-        self.parse_llm_response(&parsed, text)
+        self.parse_llm_response(&parsed, text, &conv_context.action_catalog)
     }
 
     /// Detect if the text is a simple command that can be handled locally
@@ -614,7 +1338,7 @@ impl GroqClient {
     /// 1. Short (1-4 words typically)
     /// 2. Start with a clear command verb
     /// 3. Have no ambiguity with normal dictation
-    fn detect_local_command(&self, text: &str) -> Option<ActionResult> {
+    fn detect_local_command(&self, text: &str, aliases: &AliasConfig) -> Option<ActionResult> {
         // Pre-process: clean up transcription artifacts
         let t = normalize_spoken_command_text(text);
         // Remove trailing punctuation
@@ -693,6 +1417,36 @@ impl GroqClient {
             || (t.contains("screenshot") && t.contains("folder"))
             || (t.contains("screen shot") && t.contains("folder"));
 
+        let wants_open_it = t.ends_with("open it") || t.ends_with("show it") || t.ends_with("show it to me");
+        if wants_screenshot && wants_open_it && !t.contains("folder") {
+            return Some(ActionResult::action(
+                ActionType::MultiStep,
+                serde_json::json!({
+                    "on_failure": "stop",
+                    "steps": [
+                        { "id": "shot", "action": "system_control", "payload": { "action": "screenshot" } },
+                        { "action": "system_control", "payload": { "action": "open_file", "path": "{{shot.output.path}}" } }
+                    ]
+                }),
+            ));
+        }
+
+        let wants_type_count = t.contains("type the number")
+            || t.contains("type the count")
+            || t.contains("type how many");
+        if wants_download_count && wants_type_count && !wants_organize_downloads {
+            return Some(ActionResult::action(
+                ActionType::MultiStep,
+                serde_json::json!({
+                    "on_failure": "stop",
+                    "steps": [
+                        { "id": "count", "action": "system_control", "payload": { "action": "downloads_count" } },
+                        { "action": "type_text", "refined_text": "{{count.output.top_level_total}}" }
+                    ]
+                }),
+            ));
+        }
+
         if wants_screenshot && wants_open && t.contains("folder") {
             return Some(ActionResult::action(
                 ActionType::MultiStep,
@@ -775,6 +1529,11 @@ impl GroqClient {
                 serde_json::json!({"action": "screenshot"}),
             ));
         }
+        // Named Wi-Fi networks, VPNs and Bluetooth devices; before the
+        // toggles so "connect my bluetooth speaker" isn't a settings page
+        if let Some(connection) = parse_connection_command(&t) {
+            return Some(ActionResult::action(ActionType::SystemControl, connection));
+        }
         // Bluetooth control - distinguish between toggle and settings
         if t.contains("bluetooth") {
             // Check if user wants to turn on/off/enable/disable
@@ -803,24 +1562,60 @@ impl GroqClient {
         }
         if t.contains("brightness") {
             // Try to extract level
-            let level = extract_number(&t).unwrap_or(50);
+            let level = extract_number(&t);
             if t.contains("up") || t.contains("increase") {
                 return Some(ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "brightness", "level": "up"})));
             } else if t.contains("down") || t.contains("decrease") || t.contains("dim") {
                 return Some(ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "brightness", "level": "down"})));
             }
-            return Some(ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "brightness", "level": level})));
+            // No level reads the current brightness back
+            let mut payload = serde_json::json!({"action": "brightness"});
+            if let Some(level) = level {
+                payload["level"] = serde_json::json!(level);
+            }
+            return Some(ActionResult::action(ActionType::SystemControl, payload));
         }
+        let turns_off = t.contains("turn off") || t.contains("disable") || t.contains("switch off") || t.ends_with(" off");
+        let turns_on = t.contains("turn on") || t.contains("enable") || t.contains("switch on") || t.ends_with(" on");
         if t.contains("night light") || t.contains("night mode") || t.contains("blue light") {
-            return Some(ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "night_light"})));
+            let mut payload = serde_json::json!({"action": "night_light"});
+            if turns_off || turns_on {
+                payload["enable"] = serde_json::json!(!turns_off);
+            }
+            return Some(ActionResult::action(ActionType::SystemControl, payload));
+        }
+        // "dark mode", "turn off dark mode", "switch to light mode", "toggle dark theme"
+        let dark = t.contains("dark mode") || t.contains("dark theme");
+        let light = t.contains("light mode") || t.contains("light theme");
+        if dark || light {
+            let mut payload = serde_json::json!({"action": "dark_mode"});
+            if !t.contains("toggle") {
+                payload["enable"] = serde_json::json!(dark != turns_off);
+            }
+            return Some(ActionResult::action(ActionType::SystemControl, payload));
         }
         if t.contains("do not disturb") || t.contains("dnd") || t.contains("focus mode") {
             return Some(ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "dnd"})));
         }
+        // Before emptying, so "restore the empty folder from the trash" restores it
+        if let Some(trash) = parse_trash_command(&t) {
+            return Some(ActionResult::action(ActionType::SystemControl, trash));
+        }
         if t.contains("empty") && (t.contains("trash") || t.contains("recycle") || t.contains("bin")) {
             return Some(ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "recycle_bin"})));
         }
         
+        // Media control for whichever player is active (or a named one);
+        // before volume so "music volume 40" reaches the player
+        if let Some(media) = parse_media_command(&t) {
+            return Some(ActionResult::action(ActionType::MediaControl, media));
+        }
+
+        // Volume levels, per-app volume and audio devices
+        if let Some(volume) = parse_volume_command(&t) {
+            return Some(ActionResult::action(ActionType::VolumeControl, volume));
+        }
+
         // Volume control
         if t.contains("volume") || t.contains("louder") || t.contains("quieter") {
             let direction = if t.contains("up") || t.contains("louder") || t.contains("increase") {
@@ -835,7 +1630,7 @@ impl GroqClient {
             return Some(ActionResult::action(ActionType::VolumeControl, serde_json::json!({"direction": direction})));
         }
         if t == "mute" || t == "unmute" {
-            return Some(ActionResult::action(ActionType::VolumeControl, serde_json::json!({"direction": "mute"})));
+            return Some(ActionResult::action(ActionType::VolumeControl, serde_json::json!({"direction": t})));
         }
         
         // App/URL opening - check websites first, then apps
@@ -887,6 +1682,14 @@ impl GroqClient {
                 return None;
             }
 
+            // The user's own app and website names win over everything built in
+            if let Some(url) = aliases.website_for(&app_name) {
+                return Some(ActionResult::action(ActionType::OpenUrl, serde_json::json!({"url": url})));
+            }
+            if let Some(app) = aliases.app_for(&app_name) {
+                return Some(ActionResult::action(ActionType::OpenApp, serde_json::json!({"app": app})));
+            }
+
             // Direct URL/domain opening (e.g., "open x.com", "visit github.com")
             if prefers_web_target {
                 if let Some(url) = infer_web_target_from_phrase(&app_name) {
@@ -939,12 +1742,16 @@ impl GroqClient {
                     ("activity monitor", "activity monitor"),
                     ("task manager", "activity monitor"),
                 ],
+                // Generic names; the desktop-entry index picks whichever
+                // file manager, terminal or monitor is installed
                 "linux" => &[
-                    ("files", "nautilus"),
-                    ("file explorer", "nautilus"),
-                    ("file manager", "nautilus"),
-                    ("settings", "gnome-control-center"),
-                    ("terminal", "gnome-terminal"),
+                    ("file explorer", "file manager"),
+                    ("explorer", "file manager"),
+                    ("finder", "file manager"),
+                    ("task manager", "system monitor"),
+                    ("activity monitor", "system monitor"),
+                    ("control panel", "settings"),
+                    ("system preferences", "settings"),
                 ],
                 _ => &[],
             };
@@ -988,7 +1795,10 @@ impl GroqClient {
             return Some(ActionResult::action(ActionType::OpenApp, serde_json::json!({"app": app_name})));
         }
         
-        // Web search
+        // Web search, on a named engine or the default one
+        if let Some(search) = parse_engine_search(&t, aliases) {
+            return Some(ActionResult::action(ActionType::WebSearch, search));
+        }
         if t.starts_with("search ") || t.starts_with("google ") || t.starts_with("search for ") || t.starts_with("look up ") {
             let query = t
                 .replace("search for ", "")
@@ -998,21 +1808,14 @@ impl GroqClient {
                 .trim()
                 .to_string();
             if !query.is_empty() {
-                return Some(ActionResult::action(ActionType::WebSearch, serde_json::json!({"query": query})));
+                let mut payload = serde_json::json!({"query": query});
+                if t.starts_with("google ") && aliases.engine("google").is_some() {
+                    payload["engine"] = serde_json::json!("google");
+                }
+                return Some(ActionResult::action(ActionType::WebSearch, payload));
             }
         }
         
-        // Media control (Spotify/general)
-        // Simple controls: play, pause, next, previous
-        if t == "play" || t == "pause" || t == "stop music" || t == "resume" || t == "resume music" || t == "play music" || t == "pause music" {
-            return Some(ActionResult::action(ActionType::SpotifyControl, serde_json::json!({"action": "play_pause"})));
-        }
-        if t == "next" || t == "skip" || t == "next song" || t == "next track" {
-            return Some(ActionResult::action(ActionType::SpotifyControl, serde_json::json!({"action": "next"})));
-        }
-        if t == "previous" || t == "previous song" || t == "last song" {
-            return Some(ActionResult::action(ActionType::SpotifyControl, serde_json::json!({"action": "previous"})));
-        }
         
         // Play specific song/artist - "play [song name]" or "play [artist]"
         // Opens Spotify, searches, and plays the first result
@@ -1039,6 +1842,23 @@ impl GroqClient {
         if t == "select all" || t == "select everything" {
             return Some(ActionResult::action(ActionType::KeyboardShortcut, serde_json::json!({"shortcut": "select_all"})));
         }
+        let wants_undo_last_action = matches!(
+            t.as_str(),
+            "undo last action" | "undo the last action" | "undo last command" | "undo the last command"
+                | "undo what you did" | "undo what you just did" | "revert last action" | "revert the last action"
+        );
+        if wants_undo_last_action {
+            return Some(ActionResult::action(
+                ActionType::SystemControl,
+                serde_json::json!({"action": "undo_last_action"}),
+            ));
+        }
+        if let Some(n) = parse_repeat_command(&t) {
+            return Some(ActionResult::action(
+                ActionType::SystemControl,
+                serde_json::json!({"action": "repeat_command", "n": n}),
+            ));
+        }
         if t == "undo" || t == "undo that" {
             return Some(ActionResult::action(ActionType::KeyboardShortcut, serde_json::json!({"shortcut": "undo"})));
         }
@@ -1117,6 +1937,12 @@ impl GroqClient {
         if t == "restore" || t == "restore window" {
             return Some(ActionResult::action(ActionType::WindowControl, serde_json::json!({"action": "restore"})));
         }
+        // Named windows and layouts need a window list, which only Linux has;
+        // elsewhere "switch to slack" falls through to opening the app
+        #[cfg(target_os = "linux")]
+        if let Some(payload) = parse_window_command(&t) {
+            return Some(ActionResult::action(ActionType::WindowControl, payload));
+        }
         
         // Quick responses (time, date, etc.)
         if t.contains("what time") || t.contains("what's the time") || t == "time" {
@@ -1135,249 +1961,45 @@ impl GroqClient {
     }
 
     /// Build the system prompt with context
-    fn build_system_prompt(&self, voice_context: &VoiceContext, conv_context: &ConversationContext) -> String {
-        let mut prompt = String::from(r#"You are ListenOS, a voice-to-action assistant. Analyze user voice input and decide: COMMAND or DICTATION.
-
-=== COMMAND DETECTION ===
-
-Treat as COMMAND if the input:
-1. STARTS with a command verb: open, launch, start, search, google, play, pause, stop, next, previous, skip, mute, unmute, volume, lock, screenshot, shutdown, restart, reboot, sleep, brightness, bluetooth, wifi, close, quit, exit
-2. Is a SHORT phrase (1-4 words) that matches a command pattern
-3. Contains "my computer", "the computer", "my PC" with a system action
-
-Examples of COMMANDS (execute these):
-- "Open Chrome" → open_app
-- "Search for pizza" → web_search  
-- "Play music" → spotify_control
-- "Pause" → spotify_control
-- "Lock my computer" → system_control
-- "Shutdown" → system_control
-- "Volume up" → volume_control
-- "Take a screenshot" → system_control
-- "Organize my downloads" → system_control
-
-=== DICTATION ===
-
-Treat as DICTATION (type_text) if:
-1. It's a complete sentence the user wants typed
-2. Command words appear MID-SENTENCE (not at start)
-3. It's conversational or descriptive text
-
-Examples of DICTATION (type these):
-- "I want to open a new chapter" → type_text (open is mid-sentence)
-- "The meeting was great" → type_text
-- "Please search for the document" → type_text (starts with please)
-- "Can you help me" → type_text
-
-"#);
-
-        // Add context information
-        prompt.push_str("=== CURRENT CONTEXT ===\n");
-        
-        if let Some(ref app) = voice_context.active_app {
-            prompt.push_str(&format!("Active app: {}\n", app));
-        }
-        
-        if let Some(ref clipboard) = conv_context.clipboard_preview {
-            prompt.push_str(&format!("Clipboard preview: \"{}\"\n", clipboard));
-        }
-        
-        if let Some(ref last_action) = conv_context.last_action {
-            prompt.push_str(&format!("Last action: {}\n", last_action));
-        }
-
-        prompt.push_str(&format!("OS: {}\n\n", voice_context.os));
-
-        // Add user's custom commands if any
-        if !conv_context.custom_commands.is_empty() {
-            prompt.push_str("=== USER'S CUSTOM COMMANDS (HIGHEST PRIORITY) ===\n");
-            prompt.push_str("If the user says ANY of these trigger phrases, use custom_command action:\n\n");
-            for (trigger, name, id) in &conv_context.custom_commands {
-                prompt.push_str(&format!(
-                    "- \"{}\" -> {{\"action\": \"custom_command\", \"payload\": {{\"command_id\": \"{}\", \"trigger_phrase\": \"{}\"}}}} ({})",
-                    trigger, id, trigger, name
-                ));
-                prompt.push('\n');
-            }
-            prompt.push('\n');
-        }
-
-        // Add user's text expansion snippets if any
-        if !conv_context.snippets.is_empty() {
-            prompt.push_str("=== USER'S TEXT SNIPPETS (EXPAND ON MATCH) ===\n");
-            prompt.push_str("If the user says EXACTLY one of these trigger phrases, expand to the text using type_text:\n\n");
-            for (trigger, expansion) in &conv_context.snippets {
-                let preview = if expansion.len() > 50 { 
-                    format!("{}...", &expansion[..50]) 
-                } else { 
-                    expansion.clone() 
-                };
-                prompt.push_str(&format!(
-                    "- \"{}\" -> {{\"action\": \"type_text\", \"refined_text\": \"{}\"}}\n",
-                    trigger, preview
-                ));
-            }
-            prompt.push('\n');
-        }
-
-        // Add available actions with clear triggers
-        prompt.push_str(r#"=== AVAILABLE COMMANDS (only when explicitly triggered) ===
-
-APPS & BROWSER:
-- open_app: Trigger words: "open", "launch", "start" + app name
-  Example: "Open Chrome" -> {"action": "open_app", "payload": {"app": "chrome"}}
-  
-- open_url: Trigger words: "open", "go to" + URL/website
-  Example: "Open google.com" -> {"action": "open_url", "payload": {"url": "https://google.com"}}
-  
-- web_search: Trigger words: "search", "google", "look up", "find"
-  Example: "Search for weather" -> {"action": "web_search", "payload": {"query": "weather"}}
-
-MEDIA CONTROL:
-- spotify_control: Trigger words: "play", "pause", "stop", "next", "previous", "skip"
-  Example: "Pause the music" -> {"action": "spotify_control", "payload": {"action": "pause"}}
-  Example: "Play some jazz" -> {"action": "spotify_control", "payload": {"action": "search", "query": "jazz"}}
-
-- volume_control: Trigger words: "volume", "louder", "quieter", "mute"
-  Example: "Volume up" -> {"action": "volume_control", "payload": {"direction": "up"}}
-
-SYSTEM:
-- system_control: Trigger words: "lock", "screenshot", "sleep", "shutdown", "restart", "reboot", "brightness", "bluetooth", "wifi", "night light"
-  Example: "Lock my computer" -> {"action": "system_control", "payload": {"action": "lock"}}
-  Example: "Take a screenshot" -> {"action": "system_control", "payload": {"action": "screenshot"}}
-  Example: "Organize my downloads folder" -> {"action": "system_control", "payload": {"action": "organize_downloads"}}
-  Example: "Shutdown the computer" -> {"action": "system_control", "payload": {"action": "shutdown"}}
-  Example: "Restart" -> {"action": "system_control", "payload": {"action": "restart"}}
-  Example: "Put computer to sleep" -> {"action": "system_control", "payload": {"action": "sleep"}}
-  Example: "Turn on bluetooth" -> {"action": "system_control", "payload": {"action": "bluetooth"}}
-  Example: "Turn off wifi" -> {"action": "system_control", "payload": {"action": "wifi_toggle"}}
-  Example: "Set brightness to 50" -> {"action": "system_control", "payload": {"action": "brightness", "level": 50}}
-  Example: "Turn on night light" -> {"action": "system_control", "payload": {"action": "night_light"}}
-
-CLIPBOARD (only when "clipboard" is mentioned):
-- clipboard_format: "format my clipboard", "clipboard as bullets"
-- clipboard_translate: "translate my clipboard to Spanish"
-- clipboard_summarize: "summarize my clipboard"
-
-DICTATION (DEFAULT - use for everything else):
-- type_text: Used when the user is dictating text to be typed
-  The refined_text field contains the EXACT text to type
-  Example: "Hello how are you" -> {"action": "type_text", "refined_text": "Hello, how are you?"}
-  Example: "I need to finish the report by Friday" -> {"action": "type_text", "refined_text": "I need to finish the report by Friday."}
-
-=== RESPONSE FORMAT (JSON only) ===
-
-{
-  "action": "action_type",
-  "payload": {},
-  "refined_text": "only for type_text - the exact text to type with proper punctuation"
-}
-
-=== EXAMPLES - COMMANDS ===
-
-"Open Chrome" -> {"action": "open_app", "payload": {"app": "chrome"}}
-"Search for Italian restaurants" -> {"action": "web_search", "payload": {"query": "Italian restaurants"}}
-"Pause" -> {"action": "spotify_control", "payload": {"action": "pause"}}
-"Lock computer" -> {"action": "system_control", "payload": {"action": "lock"}}
-"Volume down" -> {"action": "volume_control", "payload": {"direction": "down"}}
-
-=== EXAMPLES - DICTATION (type_text) ===
-
-"Hello world" -> {"action": "type_text", "refined_text": "Hello world."}
-"The meeting is scheduled for 3 PM" -> {"action": "type_text", "refined_text": "The meeting is scheduled for 3 PM."}
-"Dear John comma I hope this email finds you well" -> {"action": "type_text", "refined_text": "Dear John, I hope this email finds you well."}
-"Please review the attached document and let me know your thoughts" -> {"action": "type_text", "refined_text": "Please review the attached document and let me know your thoughts."}
-"I think we should open the discussion with" -> {"action": "type_text", "refined_text": "I think we should open the discussion with"}
-"Can you help me with this" -> {"action": "type_text", "refined_text": "Can you help me with this?"}
-"#);
-
-        // Add style-specific punctuation rules
-        let style_rules = match conv_context.dictation_style {
-            DictationStyle::Formal => r#"
-=== PUNCTUATION RULES FOR type_text (FORMAL STYLE) ===
-
-1. Add periods at the end of complete sentences
-2. Add question marks for questions
-3. Convert spoken punctuation: "comma" -> ",", "period" -> ".", "question mark" -> "?"
-4. Capitalize first letter of sentences and proper nouns
-5. Keep the user's words exactly, just add proper formatting
-6. Use full punctuation including commas in complex sentences
-"#,
-            DictationStyle::Casual => r#"
-=== PUNCTUATION RULES FOR type_text (CASUAL STYLE) ===
-
-1. Capitalize first letter of sentences
-2. Add question marks for questions
-3. Convert spoken punctuation: "comma" -> ",", "period" -> ".", "question mark" -> "?"
-4. Use MINIMAL punctuation - skip periods at end of simple sentences
-5. Skip commas unless explicitly spoken
-6. Keep the casual, natural flow of speech
-
-Examples with CASUAL style:
-- "Hey how are you" -> "Hey how are you"
-- "Let's meet at noon" -> "Let's meet at noon"
-- "Sounds good see you then" -> "Sounds good see you then"
-"#,
-            DictationStyle::VeryCasual => r#"
-=== PUNCTUATION RULES FOR type_text (VERY CASUAL STYLE) ===
-
-1. Use ALL LOWERCASE (no capital letters except proper nouns like names)
-2. Use MINIMAL punctuation - skip periods completely
-3. Only add question marks for questions
-4. Skip commas unless explicitly spoken
-5. Keep it natural like texting a friend
-
-Examples with VERY CASUAL style:
-- "Hey how are you" -> "hey how are you"
-- "Let's meet at noon" -> "let's meet at noon"
-- "Sounds good see you then" -> "sounds good see you then"
-- "Thanks for your help" -> "thanks for your help"
-"#,
-        };
-        prompt.push_str(style_rules);
-
-        prompt
+    fn build_system_prompt(&self, voice_context: &VoiceContext, conv_context: &ConversationContext) -> Result<String, String> {
+        prompts::render(prompts::INTENT_ROUTER, &router_prompt_vars(voice_context, conv_context))
     }
 
     /// Parse the LLM response into an ActionResult
-    fn parse_llm_response(&self, parsed: &serde_json::Value, original_text: &str) -> Result<ActionResult, String> {
+    fn parse_llm_response(
+        &self,
+        parsed: &serde_json::Value,
+        original_text: &str,
+        catalog: &ActionCatalog,
+    ) -> Result<ActionResult, String> {
         let action_str = parsed["action"].as_str().unwrap_or("type_text");
+
+        // Integration actions must exist in the catalogue we advertised
+        if !catalog.allows(action_str, &parsed["payload"]) {
+            log::warn!(
+                "LLM chose unavailable action {} {}, falling back to dictation",
+                action_str, parsed["payload"]
+            );
+            return Ok(ActionResult::type_text(post_process_dictation(original_text)));
+        }
         
         let action_type = match action_str {
-            "open_app" => ActionType::OpenApp,
-            "open_url" => ActionType::OpenUrl,
-            "web_search" => ActionType::WebSearch,
-            "run_command" => ActionType::RunCommand,
-            "volume_control" => ActionType::VolumeControl,
-            "send_email" => ActionType::SendEmail,
-            "multi_step" => ActionType::MultiStep,
             // Convert respond/clarify to type_text to avoid confusion
             // (nothing visible happens with respond, which frustrates users)
             "respond" | "clarify" => {
                 // If there's a response_text, type it instead of doing nothing
                 if let Some(response) = parsed["response_text"].as_str() {
-                    return Ok(ActionResult {
-                        action_type: ActionType::TypeText,
-                        payload: serde_json::json!({}),
-                        refined_text: Some(post_process_dictation(response)),
-                        response_text: None,
-                        requires_confirmation: false,
-                    });
+                    return Ok(ActionResult::type_text(post_process_dictation(response)));
                 }
                 ActionType::TypeText
             },
-            "clipboard_format" => ActionType::ClipboardFormat,
-            "clipboard_translate" => ActionType::ClipboardTranslate,
-            "clipboard_summarize" => ActionType::ClipboardSummarize,
-            "clipboard_clean" => ActionType::ClipboardClean,
-            "spotify_control" => ActionType::SpotifyControl,
-            "discord_control" => ActionType::DiscordControl,
-            "system_control" => ActionType::SystemControl,
-            "custom_command" => ActionType::CustomCommand,
-            "keyboard_shortcut" => ActionType::KeyboardShortcut,
-            "window_control" => ActionType::WindowControl,
-            "no_action" => ActionType::NoAction,
-            _ => ActionType::TypeText,
+            other => match action_type_from_llm_name(other) {
+                Some(action_type) => action_type,
+                None => {
+                    log::warn!("LLM chose unknown action {}, asking instead", other);
+                    return Ok(unsupported_action());
+                }
+            },
         };
 
         // For type_text, ensure we have text to type and post-process it
@@ -1391,44 +2013,62 @@ Examples with VERY CASUAL style:
             parsed["refined_text"].as_str().map(|s| s.to_string())
         };
 
-        Ok(ActionResult {
+        // Older prompts and models may omit the score; treat that as fairly sure
+        let confidence = parsed["confidence"].as_f64().unwrap_or(0.8) as f32;
+
+        let mut result = ActionResult {
             action_type,
             payload: parsed["payload"].clone(),
             refined_text,
             response_text: parsed["response_text"].as_str().map(|s| s.to_string()),
             requires_confirmation: false,
-        })
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        }
+        .with_confidence(confidence);
+
+        if let Some(alt) = parsed.get("alternative").filter(|v| v.is_object()) {
+            let alt_name = alt["action"].as_str().unwrap_or("type_text");
+            let alt_type = action_type_from_llm_name(alt_name).filter(|_| catalog.allows(alt_name, &alt["payload"]));
+
+            if let Some(alt_type) = alt_type.filter(|alt_type| *alt_type != action_type || alt["payload"] != result.payload) {
+                let alt_text = if alt_type == ActionType::TypeText {
+                    Some(post_process_dictation(
+                        alt["refined_text"].as_str().unwrap_or(original_text),
+                    ))
+                } else {
+                    None
+                };
+                result = result.with_alternative(ActionCandidate {
+                    action_type: alt_type,
+                    payload: alt["payload"].clone(),
+                    refined_text: alt_text,
+                    confidence: alt["confidence"]
+                        .as_f64()
+                        .map(|c| c as f32)
+                        .unwrap_or(1.0 - confidence)
+                        .clamp(0.0, 1.0),
+                });
+            }
+        }
+
+        Ok(result)
     }
 
     /// Process clipboard operations with LLM
     pub async fn process_clipboard(&self, content: &str, operation: &str, params: &serde_json::Value) -> Result<String, String> {
+        let vars = PromptVars::new().text("content", content);
         let prompt = match operation {
             "format" => {
                 let format_type = params.get("format").and_then(|v| v.as_str()).unwrap_or("paragraph");
-                format!(
-                    "Format the following text as a {}. Only output the formatted text, nothing else:\n\n{}",
-                    format_type, content
-                )
+                prompts::render(prompts::CLIPBOARD_FORMAT, &vars.text("format", format_type))?
             }
             "translate" => {
                 let target = params.get("target_language").and_then(|v| v.as_str()).unwrap_or("Spanish");
-                format!(
-                    "Translate the following text to {}. Only output the translation, nothing else:\n\n{}",
-                    target, content
-                )
-            }
-            "summarize" => {
-                format!(
-                    "Summarize the following text in 2-3 sentences. Only output the summary, nothing else:\n\n{}",
-                    content
-                )
-            }
-            "clean" => {
-                format!(
-                    "Clean up the following text: fix grammar, remove extra whitespace, add proper punctuation. Only output the cleaned text, nothing else:\n\n{}",
-                    content
-                )
+                prompts::render(prompts::CLIPBOARD_TRANSLATE, &vars.text("target_language", target))?
             }
+            "summarize" => prompts::render(prompts::CLIPBOARD_SUMMARIZE, &vars)?,
+            "clean" => prompts::render(prompts::CLIPBOARD_CLEAN, &vars)?,
             _ => return Err(format!("Unknown clipboard operation: {}", operation)),
         };
 
@@ -1457,6 +2097,7 @@ Examples with VERY CASUAL style:
 
         let result: serde_json::Value = response.json().await
             .map_err(|e| format!("Failed to parse response: {}", e))?;
+        crate::usage::record_completion("groq", UsageFeature::Clipboard, &result);
 
         let content = result["choices"][0]["message"]["content"]
             .as_str()
@@ -1504,8 +2145,64 @@ impl Default for DeepgramClient {
 
 /// Public helper for deterministic command routing without calling the LLM.
 /// Returns `Some(ActionResult)` only for unambiguous command phrases.
-pub fn detect_local_command(text: &str) -> Option<ActionResult> {
-    GroqClient::new().detect_local_command(text)
+/// `aliases` are read once per utterance by the caller.
+pub fn detect_local_command(text: &str, aliases: &AliasConfig) -> Option<ActionResult> {
+    GroqClient::new()
+        .detect_local_command(text, aliases)
+        .map(|action| with_local_router_confidence(action, text))
+}
+
+/// Confidence given to every local grammar match. The grammar either matches
+/// or it doesn't, so this is a constant rather than a score, and local
+/// routes never ask for clarification (see `should_ask_for_clarification`).
+pub const LOCAL_ROUTER_CONFIDENCE: f32 = 0.9;
+
+/// Local grammar matches get `LOCAL_ROUTER_CONFIDENCE`; typing the same
+/// words as dictation is always the runner-up
+fn with_local_router_confidence(action: ActionResult, text: &str) -> ActionResult {
+    if matches!(action.action_type, ActionType::TypeText | ActionType::Respond) {
+        return action;
+    }
+
+    action.with_confidence(LOCAL_ROUTER_CONFIDENCE).with_alternative(ActionCandidate {
+        action_type: ActionType::TypeText,
+        payload: serde_json::json!({}),
+        refined_text: Some(post_process_dictation(text)),
+        confidence: 0.1,
+    })
+}
+
+/// What an action the client doesn't know becomes: a question, never typed text
+pub fn unsupported_action() -> ActionResult {
+    ActionResult::clarify("I'm not sure how to do that. Could you say it another way?".to_string())
+}
+
+/// Map a snake_case action name from an LLM response to an ActionType
+fn action_type_from_llm_name(name: &str) -> Option<ActionType> {
+    let action_type = match name {
+        "type_text" => ActionType::TypeText,
+        "open_app" => ActionType::OpenApp,
+        "open_url" => ActionType::OpenUrl,
+        "web_search" => ActionType::WebSearch,
+        "run_command" => ActionType::RunCommand,
+        "volume_control" => ActionType::VolumeControl,
+        "send_email" => ActionType::SendEmail,
+        "multi_step" => ActionType::MultiStep,
+        "clipboard_format" => ActionType::ClipboardFormat,
+        "clipboard_translate" => ActionType::ClipboardTranslate,
+        "clipboard_summarize" => ActionType::ClipboardSummarize,
+        "clipboard_clean" => ActionType::ClipboardClean,
+        "spotify_control" => ActionType::SpotifyControl,
+        "media_control" => ActionType::MediaControl,
+        "discord_control" => ActionType::DiscordControl,
+        "system_control" => ActionType::SystemControl,
+        "custom_command" => ActionType::CustomCommand,
+        "keyboard_shortcut" => ActionType::KeyboardShortcut,
+        "window_control" => ActionType::WindowControl,
+        "no_action" => ActionType::NoAction,
+        _ => return None,
+    };
+    Some(action_type)
 }
 
 /// Encode PCM samples to WAV format for API upload
@@ -1537,3 +2234,240 @@ pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, String>
 
     Ok(buffer.into_inner())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire(json: serde_json::Value) -> Result<ActionResult, String> {
+        serde_json::from_value::<WireAction>(json).unwrap().into_action()
+    }
+
+    #[test]
+    fn converts_wire_actions() {
+        let action = wire(serde_json::json!({ "action_type": "OpenApp", "payload": { "app": "Spotify" } })).unwrap();
+        assert_eq!(action.action_type, ActionType::OpenApp);
+        assert_eq!(action.confidence, 0.8);
+
+        let action = wire(serde_json::json!({
+            "version": 2,
+            "action_type": "TypeText",
+            "confidence": 0.6,
+            "alternative": { "action_type": "NotAnAction" },
+        }))
+        .unwrap();
+        assert!(action.alternatives.is_empty());
+
+        assert_eq!(ActionType::from_wire("CustomCommand"), Ok(ActionType::CustomCommand));
+        assert_eq!(ActionType::from_wire("open_app"), Err("Unknown action type 'open_app'".to_string()));
+        for action_type in ActionType::ALL {
+            assert_eq!(ActionType::from_wire(&format!("{:?}", action_type)), Ok(action_type));
+        }
+        assert_eq!(ActionType::from_name_ignore_case(" runcommand "), Ok(ActionType::RunCommand));
+        assert_eq!(
+            wire(serde_json::json!({ "action_type": "NotAnAction" })).unwrap_err(),
+            "Unknown action type 'NotAnAction'"
+        );
+        assert_eq!(
+            wire(serde_json::json!({ "version": ACTION_WIRE_VERSION + 1, "action_type": "OpenApp" })).unwrap_err(),
+            "Unsupported action wire version 3 (client supports up to 2)"
+        );
+    }
+
+    #[test]
+    fn unknown_llm_actions_ask_instead_of_typing() {
+        let catalog = ActionCatalog::from_integrations(&[], &[]);
+        let parse = |json: serde_json::Value| GroqClient::new().parse_llm_response(&json, "open the pod bay doors", &catalog).unwrap();
+
+        let action = parse(serde_json::json!({ "action": "open_pod_bay_doors", "payload": {} }));
+        assert_eq!(action.action_type, ActionType::Clarify);
+
+        let action = parse(serde_json::json!({
+            "action": "open_app",
+            "payload": { "app": "pods" },
+            "alternative": { "action": "launch_rocket", "payload": {} },
+        }));
+        assert_eq!(action.action_type, ActionType::OpenApp);
+        assert!(action.alternatives.is_empty());
+    }
+
+    #[test]
+    fn parses_repeat_commands() {
+        let cases = [
+            ("repeat last command", Some(1)),
+            ("do that again", Some(1)),
+            ("repeat command 3", Some(3)),
+            ("repeat the command number three", Some(3)),
+            ("repeat command ten", Some(10)),
+            ("repeat command 0", None),
+            ("repeat command eleven", None),
+            ("repeat command", None),
+            ("repeat after me", None),
+            ("repeat the last word", None),
+            ("do that again tomorrow", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_repeat_command(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_engine_searches() {
+        let aliases = AliasConfig::default();
+        let cases = [
+            ("search github for tokio", Some(("tokio", "github"))),
+            ("search for rust traits on youtube", Some(("rust traits", "youtube"))),
+            ("look up alan turing in the wikipedia", Some(("alan turing", "wikipedia"))),
+            ("search amazon for usb hubs", Some(("usb hubs", "amazon"))),
+            ("search for cats in boxes", None),
+            ("search github for", None),
+            ("search for tokio", None),
+            ("find tokio on github", None),
+        ];
+        for (text, expected) in cases {
+            let search = parse_engine_search(text, &aliases);
+            let found = search.as_ref().map(|s| (s["query"].as_str().unwrap(), s["engine"].as_str().unwrap()));
+            assert_eq!(found, expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn user_aliases_win_over_built_in_names() {
+        let mut aliases = AliasConfig::default();
+        aliases.apps.push(crate::config::AppAlias { phrase: "my editor".to_string(), app: "code".to_string() });
+        aliases.websites.push(crate::config::WebsiteAlias {
+            phrase: "jira".to_string(),
+            url: "https://example.atlassian.net".to_string(),
+        });
+        aliases.search_engines.push(crate::config::SearchEngine {
+            keyword: "docs".to_string(),
+            name: "Docs.rs".to_string(),
+            url_template: "https://docs.rs/releases/search?query={query}".to_string(),
+        });
+
+        let route = |text: &str| {
+            let action = detect_local_command(text, &aliases).unwrap();
+            (action.action_type, action.payload)
+        };
+        assert_eq!(route("open my editor"), (ActionType::OpenApp, serde_json::json!({"app": "code"})));
+        assert_eq!(
+            route("open Jira"),
+            (ActionType::OpenUrl, serde_json::json!({"url": "https://example.atlassian.net"}))
+        );
+        assert_eq!(
+            route("search docs for serde"),
+            (ActionType::WebSearch, serde_json::json!({"query": "serde", "engine": "docs"}))
+        );
+        // Without the alias, the same phrase is just an app name
+        assert_eq!(
+            detect_local_command("open my editor", &AliasConfig::default()).map(|a| a.payload),
+            Some(serde_json::json!({"app": "my editor"}))
+        );
+    }
+
+    #[test]
+    fn parses_media_commands() {
+        let cases: &[(&str, Option<serde_json::Value>)] = &[
+            ("pause", Some(serde_json::json!({"action": "pause"}))),
+            ("pause firefox", Some(serde_json::json!({"action": "pause", "player": "firefox"}))),
+            ("pause mozilla firefox", Some(serde_json::json!({"action": "pause", "player": "mozilla firefox"}))),
+            ("resume the spotify", Some(serde_json::json!({"action": "play", "player": "spotify"}))),
+            ("next track on spotify", Some(serde_json::json!({"action": "next", "player": "spotify"}))),
+            ("skip back 10 seconds", Some(serde_json::json!({"action": "seek", "seconds": -10.0}))),
+            ("repeat this song", Some(serde_json::json!({"action": "loop", "mode": "track"}))),
+            ("music volume 40", Some(serde_json::json!({"action": "volume", "level": 40}))),
+            // Whether these name a running player is decided when they run
+            ("pause the video", Some(serde_json::json!({"action": "pause", "player": "video"}))),
+            ("next track on the radio", Some(serde_json::json!({"action": "next", "player": "radio"}))),
+            // Not media commands at all
+            ("pause for a few minutes please", None),
+            ("repeat command 2", None),
+            ("music volume 400", None),
+            ("play the beatles", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(&parse_media_command(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_volume_commands() {
+        let cases: &[(&str, Option<serde_json::Value>)] = &[
+            ("what's the volume", Some(serde_json::json!({"action": "get"}))),
+            ("list audio devices", Some(serde_json::json!({"action": "list_devices"}))),
+            ("switch to my headphones", Some(serde_json::json!({"action": "set_output", "device": "headphones"}))),
+            ("switch audio to hdmi", Some(serde_json::json!({"action": "set_output", "device": "hdmi"}))),
+            ("use the usb microphone", Some(serde_json::json!({"action": "set_input", "device": "usb microphone"}))),
+            ("switch input to webcam", Some(serde_json::json!({"action": "set_input", "device": "webcam"}))),
+            ("mute discord", Some(serde_json::json!({"app": "discord", "direction": "mute"}))),
+            ("unmute the sound", Some(serde_json::json!({"direction": "unmute"}))),
+            ("turn it up", Some(serde_json::json!({"direction": "up"}))),
+            ("turn down firefox", Some(serde_json::json!({"app": "firefox", "direction": "down"}))),
+            ("turn the spotify up", Some(serde_json::json!({"app": "spotify", "direction": "up"}))),
+            ("set the volume to 35 percent", Some(serde_json::json!({"level": 35}))),
+            ("volume 20%", Some(serde_json::json!({"level": 20}))),
+            ("set firefox volume to 30", Some(serde_json::json!({"app": "firefox", "level": 30}))),
+            ("spotify's volume at 30", Some(serde_json::json!({"app": "spotify", "level": 30}))),
+            // Not volume commands
+            ("use the force", None),
+            ("switch to dark mode", None),
+            ("mute my headphones", None),
+            ("mute the microphone in zoom", None),
+            ("turn the brightness up", None),
+            ("turn left at the lights", None),
+            ("turn the living room lights up", None),
+            ("volume 150", None),
+            ("the volume of the ocean", None),
+            ("set the kitchen speaker group volume to 30", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(&parse_volume_command(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_connection_commands() {
+        let cases: &[(&str, Option<serde_json::Value>)] = &[
+            ("what network am i on", Some(serde_json::json!({"action": "network_status"}))),
+            ("list bluetooth devices", Some(serde_json::json!({"action": "bluetooth_devices"}))),
+            ("connect to the office wifi", Some(serde_json::json!({"action": "wifi_connect", "name": "office"}))),
+            ("join cafe guest network", Some(serde_json::json!({"action": "wifi_connect", "name": "cafe guest"}))),
+            ("connect to the work vpn", Some(serde_json::json!({"action": "vpn_connect", "name": "work"}))),
+            ("turn on vpn", Some(serde_json::json!({"action": "vpn_connect"}))),
+            ("turn off the vpn", Some(serde_json::json!({"action": "vpn_disconnect"}))),
+            ("disconnect from vpn", Some(serde_json::json!({"action": "vpn_disconnect"}))),
+            ("connect my airpods", Some(serde_json::json!({"action": "bluetooth_connect", "device": "airpods"}))),
+            ("disconnect the keyboard", Some(serde_json::json!({"action": "bluetooth_disconnect", "device": "keyboard"}))),
+            ("connect to bluetooth speaker", Some(serde_json::json!({"action": "bluetooth_connect", "device": "speaker"}))),
+            // Left to the settings shortcut or the model
+            ("connect to wifi", None),
+            ("disconnect from the wifi", None),
+            ("connect to the database", None),
+            ("connect the dots", None),
+            ("join the meeting", None),
+            ("turn on the lights", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(&parse_connection_command(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_trash_commands() {
+        let cases: &[(&str, Option<serde_json::Value>)] = &[
+            ("what's in the trash", Some(serde_json::json!({"action": "trash_list"}))),
+            ("restore report.pdf from the trash", Some(serde_json::json!({"action": "trash_restore", "name": "report.pdf"}))),
+            ("bring back the budget from the recycle bin", Some(serde_json::json!({"action": "trash_restore", "name": "budget"}))),
+            ("undelete notes", Some(serde_json::json!({"action": "trash_restore", "name": "notes"}))),
+            // Window and layout restores, and emptying, are not restores from the trash
+            ("restore window", None),
+            ("restore my layout", None),
+            ("restore from the trash", None),
+            ("empty the trash", None),
+            ("take out the trash", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(&parse_trash_command(text), expected, "{}", text);
+        }
+    }
+}
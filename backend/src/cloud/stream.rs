@@ -0,0 +1,285 @@
+//! Streaming chat completions (server-sent events)
+//!
+//! Used for spoken questions so the assistant window can show the answer as
+//! it is generated instead of waiting for the whole completion.
+
+use futures_util::StreamExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::usage::TokenUsage;
+
+/// Outcome of a streamed completion
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedAnswer {
+    pub text: String,
+    /// True if the user stopped the answer before it finished
+    pub cancelled: bool,
+    /// Token counts, if the provider reported them before the stream ended
+    pub usage: Option<TokenUsage>,
+}
+
+/// Splits an SSE byte stream into `data:` payloads, buffering partial lines.
+/// Bytes are only decoded once a line is complete, so a multi-byte character
+/// split across two network chunks survives intact.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk and get back every complete `data:` payload in it
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if let Some(data) = line.strip_prefix("data:") {
+                events.push(data.trim_start().to_string());
+            }
+        }
+        events
+    }
+}
+
+/// One decoded stream chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamChunk {
+    pub delta: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Decode one OpenAI-style chunk. `None` means the stream is finished.
+pub fn parse_chunk(data: &str) -> Option<Result<StreamChunk, String>> {
+    if data.trim() == "[DONE]" {
+        return None;
+    }
+
+    let parsed: serde_json::Value = match serde_json::from_str(data) {
+        Ok(v) => v,
+        Err(e) => return Some(Err(format!("Malformed stream chunk: {}", e))),
+    };
+
+    if let Some(error) = parsed.get("error") {
+        return Some(Err(format!("Stream error: {}", error)));
+    }
+
+    Some(Ok(StreamChunk {
+        delta: parsed["choices"][0]["delta"]["content"]
+            .as_str()
+            .unwrap_or("")
+            .to_string(),
+        usage: TokenUsage::from_response(&parsed),
+    }))
+}
+
+/// How often a stream that is waiting on the network checks for cancellation
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// Resolves once `cancel` is set
+async fn cancelled(cancel: &AtomicBool) {
+    while !cancel.load(Ordering::SeqCst) {
+        tokio::time::sleep(CANCEL_POLL).await;
+    }
+}
+
+/// Run a chat completion with `"stream": true`, calling `on_token` for every
+/// delta. Stops early (keeping the partial text) once `cancel` is set, even
+/// while the provider is silent.
+pub async fn stream_chat_completion<F>(
+    endpoint: &str,
+    api_key: &str,
+    mut body: serde_json::Value,
+    cancel: &AtomicBool,
+    mut on_token: F,
+) -> Result<StreamedAnswer, String>
+where
+    F: FnMut(&str),
+{
+    body["stream"] = serde_json::json!(true);
+    body["stream_options"] = serde_json::json!({ "include_usage": true });
+
+    let request = reqwest::Client::new()
+        .post(endpoint)
+        .bearer_auth(api_key)
+        .header("Content-Type", "application/json")
+        .json(&body)
+        .send();
+    let response = tokio::select! {
+        response = request => response.map_err(|e| format!("Groq request failed: {}", e))?,
+        _ = cancelled(cancel) => {
+            return Ok(StreamedAnswer { text: String::new(), cancelled: true, usage: None });
+        }
+    };
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Groq error [{}]: {}", status, error_text));
+    }
+
+    let mut parser = SseParser::new();
+    let mut text = String::new();
+    let mut usage = None;
+    let mut bytes = response.bytes_stream();
+
+    loop {
+        let chunk = tokio::select! {
+            // Checked first so a flood of chunks can't outrun a cancel
+            biased;
+            _ = cancelled(cancel) => return Ok(StreamedAnswer { text, cancelled: true, usage }),
+            chunk = bytes.next() => chunk,
+        };
+        let Some(chunk) = chunk else { break };
+
+        let chunk = chunk.map_err(|e| format!("Stream interrupted: {}", e))?;
+        for data in parser.push(&chunk) {
+            match parse_chunk(&data) {
+                None => return Ok(StreamedAnswer { text, cancelled: false, usage }),
+                Some(Ok(chunk)) => {
+                    if chunk.usage.is_some() {
+                        usage = chunk.usage;
+                    }
+                    if !chunk.delta.is_empty() {
+                        text.push_str(&chunk.delta);
+                        on_token(&chunk.delta);
+                    }
+                }
+                Some(Err(e)) => return Err(e),
+            }
+        }
+    }
+
+    Ok(StreamedAnswer {
+        text,
+        cancelled: cancel.load(Ordering::SeqCst),
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn chunk(content: &str) -> String {
+        format!(
+            "data: {}\n\n",
+            serde_json::json!({ "choices": [{ "delta": { "content": content } }] })
+        )
+    }
+
+    /// Minimal HTTP server that answers one request with a canned SSE body
+    async fn serve_sse(body: String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let _ = socket.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+        format!("http://{}/v1/chat/completions", addr)
+    }
+
+    #[test]
+    fn parser_handles_lines_split_across_chunks() {
+        let mut parser = SseParser::new();
+        let full = chunk("Hel");
+        let (a, b) = full.split_at(10);
+
+        assert!(parser.push(a.as_bytes()).is_empty());
+        let events = parser.push(b.as_bytes());
+        assert_eq!(events.len(), 1);
+        let chunk = parse_chunk(&events[0]).unwrap().unwrap();
+        assert_eq!(chunk.delta, "Hel");
+        assert_eq!(parse_chunk("[DONE]"), None);
+    }
+
+    #[test]
+    fn parser_keeps_characters_split_across_chunks() {
+        let mut parser = SseParser::new();
+        let full = chunk("Café ☕");
+        let split = full.find('é').unwrap() + 1;
+        let (a, b) = full.as_bytes().split_at(split);
+
+        assert!(parser.push(a).is_empty());
+        let events = parser.push(b);
+        assert_eq!(parse_chunk(&events[0]).unwrap().unwrap().delta, "Café ☕");
+    }
+
+    #[tokio::test]
+    async fn streams_tokens_until_done() {
+        let body = format!("{}{}: keep-alive\n\n{}data: [DONE]\n\n", chunk("Paris"), ": ping\n", chunk(" is the capital."));
+        let endpoint = serve_sse(body).await;
+
+        let cancel = AtomicBool::new(false);
+        let mut tokens = Vec::new();
+        let answer = stream_chat_completion(&endpoint, "test", serde_json::json!({}), &cancel, |t| {
+            tokens.push(t.to_string())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(tokens, vec!["Paris", " is the capital."]);
+        assert_eq!(answer.text, "Paris is the capital.");
+        assert!(!answer.cancelled);
+    }
+
+    #[tokio::test]
+    async fn cancelled_stream_keeps_partial_text() {
+        let endpoint = serve_sse(format!("{}data: [DONE]\n\n", chunk("never shown"))).await;
+
+        let cancel = AtomicBool::new(true);
+        let answer = stream_chat_completion(&endpoint, "test", serde_json::json!({}), &cancel, |_| {})
+            .await
+            .unwrap();
+
+        assert!(answer.cancelled);
+        assert!(answer.text.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancels_a_stalled_stream() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/chat/completions", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let _ = socket.read(&mut buf).await;
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n";
+            let _ = socket.write_all(format!("{}{}", head, chunk("Paris")).as_bytes()).await;
+            // Then say nothing, keeping the connection open
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        });
+
+        let cancel = std::sync::Arc::new(AtomicBool::new(false));
+        let stopper = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            stopper.store(true, Ordering::SeqCst);
+        });
+
+        let answer = tokio::time::timeout(
+            Duration::from_secs(5),
+            stream_chat_completion(&endpoint, "test", serde_json::json!({}), &cancel, |_| {}),
+        )
+        .await
+        .expect("a cancelled stream must stop without waiting for the provider")
+        .unwrap();
+        assert!(answer.cancelled);
+        assert_eq!(answer.text, "Paris");
+    }
+}
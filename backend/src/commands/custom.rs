@@ -1,6 +1,7 @@
 //! Custom Commands Engine for ListenOS
 //!
 //! Allows users to define their own voice-triggered command sequences.
+//! Saved window layouts live in the same database.
 
 use chrono::{DateTime, Utc};
 use rusqlite::{Connection, params};
@@ -8,6 +9,9 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Mutex;
 
+use super::plan::FailurePolicy;
+use crate::window::LayoutSlot;
+
 /// A custom user-defined command
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomCommand {
@@ -30,6 +34,14 @@ pub struct ActionStep {
     pub payload: serde_json::Value,
     pub delay_ms: u32,
     pub description: Option<String>,
+    /// Name later steps use to reference this step's output, e.g. `{{shot.output.path}}`
+    #[serde(default)]
+    pub output_key: Option<String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Whether to stop or continue the command if this step fails
+    #[serde(default)]
+    pub on_failure: Option<FailurePolicy>,
 }
 
 impl ActionStep {
@@ -40,6 +52,9 @@ impl ActionStep {
             payload,
             delay_ms: 0,
             description: None,
+            output_key: None,
+            timeout_ms: None,
+            on_failure: None,
         }
     }
 
@@ -54,6 +69,16 @@ impl ActionStep {
     }
 }
 
+/// A named arrangement of windows, e.g. "coding"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub id: String,
+    pub name: String,
+    pub windows: Vec<LayoutSlot>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 /// Built-in command templates
 pub fn get_builtin_templates() -> Vec<CustomCommand> {
     vec![
@@ -98,10 +123,10 @@ pub fn get_builtin_templates() -> Vec<CustomCommand> {
             id: "template_meeting".to_string(),
             name: "Meeting Prep".to_string(),
             trigger_phrase: "meeting prep".to_string(),
-            description: "Mute Spotify and open video conferencing".to_string(),
+            description: "Pause music and open video conferencing".to_string(),
             actions: vec![
-                ActionStep::new("spotify_control", serde_json::json!({"action": "pause"}))
-                    .with_description("Pause Spotify"),
+                ActionStep::new("media_control", serde_json::json!({"action": "pause"}))
+                    .with_description("Pause Music"),
                 ActionStep::new("open_app", serde_json::json!({"app": "zoom"}))
                     .with_delay(500)
                     .with_description("Open Zoom"),
@@ -196,6 +221,14 @@ impl CustomCommandsStore {
 
             CREATE INDEX IF NOT EXISTS idx_commands_trigger ON custom_commands(trigger_phrase);
             CREATE INDEX IF NOT EXISTS idx_commands_enabled ON custom_commands(enabled);
+
+            CREATE TABLE IF NOT EXISTS window_layouts (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                windows TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
             "
         ).map_err(|e| format!("Failed to initialize tables: {}", e))?;
 
@@ -332,4 +365,74 @@ impl CustomCommandsStore {
         serde_json::to_string_pretty(&commands)
             .map_err(|e| format!("Failed to serialize: {}", e))
     }
+
+    /// Save a window layout, replacing any layout with the same name
+    pub fn save_layout(&self, layout: &WindowLayout) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        let windows_json = serde_json::to_string(&layout.windows)
+            .map_err(|e| format!("Failed to serialize layout: {}", e))?;
+
+        conn.execute(
+            "DELETE FROM window_layouts WHERE name = ?1 AND id != ?2",
+            params![layout.name, layout.id],
+        ).map_err(|e| format!("Failed to save layout: {}", e))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO window_layouts (id, name, windows, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                layout.id,
+                layout.name,
+                windows_json,
+                layout.created_at.to_rfc3339(),
+                layout.updated_at.to_rfc3339(),
+            ],
+        ).map_err(|e| format!("Failed to save layout: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Get all window layouts
+    pub fn get_layouts(&self) -> Result<Vec<WindowLayout>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, windows, created_at, updated_at FROM window_layouts ORDER BY name ASC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let parse_time = |value: String| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now())
+        };
+        let layouts = stmt.query_map([], |row| {
+            let windows_json: String = row.get(2)?;
+            Ok(WindowLayout {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                windows: serde_json::from_str(&windows_json).unwrap_or_default(),
+                created_at: parse_time(row.get(3)?),
+                updated_at: parse_time(row.get(4)?),
+            })
+        }).map_err(|e| format!("Failed to query layouts: {}", e))?;
+
+        layouts.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect layouts: {}", e))
+    }
+
+    /// Find a layout by name, ignoring case
+    pub fn find_layout(&self, name: &str) -> Result<Option<WindowLayout>, String> {
+        let name = name.trim();
+        Ok(self.get_layouts()?.into_iter().find(|l| l.name.eq_ignore_ascii_case(name)))
+    }
+
+    /// Delete a window layout
+    pub fn delete_layout(&self, id: &str) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        conn.execute("DELETE FROM window_layouts WHERE id = ?1", [id])
+            .map_err(|e| format!("Failed to delete layout: {}", e))?;
+
+        Ok(())
+    }
 }
@@ -4,20 +4,44 @@
 //! Users just speak - we handle everything.
 
 pub mod custom;
+pub mod plan;
+pub mod policy;
+pub mod sandbox;
 
 use crate::AppState;
 use crate::audio::AudioDevice;
+use crate::cloud::stream::{self, StreamedAnswer};
 use crate::cloud::{self, GroqClient, ActionResult, ActionType, VoiceContext, VoiceMode, ConversationContext};
 use crate::config::{
+    AliasConfig,
+    ClarificationConfig,
+    CommandSandboxConfig,
     LanguagePreferences,
     LocalApiSettings,
+    TextInjectionConfig,
+    UsageBudgetConfig,
     VibeActivationMode,
     VibeCodingConfig,
     VibeDetailLevel,
     VibeTargetTool,
 };
+use crate::config::{ConfirmationPolicy, RouteSource};
+use policy::PendingReply;
+use crate::connectivity;
+use crate::injection;
+use crate::window;
+use crate::mixer::{self, VolumeChange, VolumeCommand};
+#[cfg(target_os = "linux")]
+use crate::apps;
+use crate::history;
+use crate::simulation::{self, EffectKind};
+use crate::journal::{self, FileMove, InverseOp, JournalEntry};
+use crate::prompts::{self, PromptVars};
+use crate::usage::{self, UsageFeature};
+use crate::conversation::ClarificationReply;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Emitter, Manager, State};
 
 /// Status response for frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,12 +92,43 @@ pub struct ActionResultResponse {
     pub pending_action_id: Option<String>,
 }
 
+/// How long a pending action can be confirmed before it is dropped
+const PENDING_ACTION_TIMEOUT_SECS: i64 = 60;
+
 #[derive(Debug, Clone)]
 pub struct PendingAction {
     pub id: String,
     pub action: ActionResult,
     pub transcription: String,
     pub created_at: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// Why the confirmation policy asked
+    pub reason: String,
+    /// Session key to remember once confirmed
+    pub remember_key: Option<String>,
+    /// Asked for by an admin or user rule, which holds even when the
+    /// built-in confirmations are turned off
+    pub from_rule: bool,
+}
+
+impl PendingAction {
+    fn new(action: ActionResult, transcription: String, decision: policy::ConfirmationDecision) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            action,
+            transcription,
+            created_at: now.to_rfc3339(),
+            expires_at: now + chrono::Duration::seconds(PENDING_ACTION_TIMEOUT_SECS),
+            reason: decision.explanation,
+            remember_key: decision.remember_key,
+            from_rule: decision.from_rule,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        chrono::Utc::now() >= self.expires_at
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +139,7 @@ pub struct PendingActionResponse {
     pub transcription: String,
     pub summary: String,
     pub created_at: String,
+    pub expires_at: String,
 }
 
 const SUPPORTED_SOURCE_LANGUAGES: &[&str] = &[
@@ -215,6 +271,7 @@ async fn transform_multilingual_text(
     let target_code = preferences.target_language.as_str();
     let source_name = language_name(source_code);
     let target_name = language_name(target_code);
+    let system_prompt = prompts::render(prompts::MULTILINGUAL_NORMALIZE, &PromptVars::new())?;
 
     let client = reqwest::Client::new();
     let response = client
@@ -229,7 +286,7 @@ async fn transform_multilingual_text(
             "messages": [
                 {
                     "role": "system",
-                    "content": "You normalize multilingual voice transcriptions for an OS assistant. Return STRICT JSON with keys routing_text_english, output_text_target, detected_language. routing_text_english must be concise English preserving intent for command parsing. output_text_target must be polished in the requested target language with corrected grammar/punctuation and no extra commentary. If target language is English, output_text_target MUST be English. If source language is auto, detect language from the transcript; handle romanized speech (e.g., Hinglish written in Latin script) and still translate correctly."
+                    "content": system_prompt
                 },
                 {
                     "role": "user",
@@ -252,6 +309,7 @@ async fn transform_multilingual_text(
 
     let parsed: serde_json::Value = serde_json::from_str(&body)
         .map_err(|e| format!("Failed to parse multilingual transform response: {}", e))?;
+    usage::record_completion("groq", UsageFeature::Multilingual, &parsed);
     let content = parsed["choices"][0]["message"]["content"]
         .as_str()
         .unwrap_or("{}")
@@ -618,6 +676,7 @@ async fn enhance_vibe_coding_prompt(
         "Be explicit when needed, but keep the prompt practical and direct."
     };
     let section_line = format!("Use these sections when relevant: {}.", sections.join(", "));
+    let system_prompt = prompts::render(prompts::VIBE_ENHANCE, &PromptVars::new())?;
 
     let client = reqwest::Client::new();
     let response = client
@@ -632,7 +691,7 @@ async fn enhance_vibe_coding_prompt(
             "messages": [
                 {
                     "role": "system",
-                    "content": "You rewrite rough spoken coding ideas into high-quality prompts for AI coding assistants. Preserve user intent, avoid hype, and never invent missing requirements. Keep output actionable, concrete, and scoped. Return STRICT JSON: {\"enhanced_prompt\":\"...\"}. No markdown fences. No extra keys."
+                    "content": system_prompt
                 },
                 {
                     "role": "user",
@@ -662,6 +721,7 @@ async fn enhance_vibe_coding_prompt(
 
     let parsed: serde_json::Value = serde_json::from_str(&body)
         .map_err(|e| format!("Failed to parse vibe enhancement response: {}", e))?;
+    usage::record_completion("groq", UsageFeature::VibeEnhancement, &parsed);
 
     let content = parsed["choices"][0]["message"]["content"]
         .as_str()
@@ -763,6 +823,7 @@ pub async fn start_listening(state: State<'_, AppState>) -> Result<bool, String>
 /// Stop listening and process audio with Groq AI
 #[tauri::command]
 pub async fn stop_listening(
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
     dictation_only: Option<bool>,
 ) -> Result<VoiceProcessingResult, String> {
@@ -849,17 +910,27 @@ pub async fn stop_listening(
         Ok(store) => store.get_words_for_recognition().unwrap_or_default(),
         Err(_) => Vec::new(),
     };
-    let (language_preferences, vibe_config) = {
+    let (language_preferences, vibe_config, clarification_config, usage_budget) = {
         let config = state.config.lock().await;
         (
             normalized_language_preferences(&config.language_preferences),
             normalized_vibe_coding_config(&config.vibe_coding),
+            normalized_clarification_config(&config.clarification),
+            normalized_usage_budget(&config.usage_budget),
         )
     };
+    // Features are shed as spend approaches the configured budgets
+    let budget = usage::budget_status(&usage_budget);
+    if budget.level != usage::BudgetLevel::Normal {
+        log::info!("Usage budget level: {:?}", budget.level);
+    }
     let transcription_language_hint = language_preferences
         .transcription_language_hint()
         .map(|s| s.to_string());
 
+    // Offline mode recovers on its own once a provider is reachable again
+    refresh_connectivity(&state).await;
+
     // Transcription strategy:
     // 1) Try backend API (recommended for centralized auth/rate policies)
     // 2) Fallback to direct Groq call if server is unavailable/misconfigured
@@ -880,6 +951,13 @@ pub async fn stop_listening(
         match server_transcription {
             Ok(result) => {
                 log::info!("Server transcription: {}", result.text);
+                usage::record(
+                    "server",
+                    UsageFeature::Transcription,
+                    usage::STT_MODEL,
+                    duration_ms as f64 / 1000.0,
+                    usage::TokenUsage::default(),
+                );
                 TranscriptionResult {
                     text: result.text,
                     duration_ms,
@@ -908,6 +986,13 @@ pub async fn stop_listening(
                 {
                     Ok(result) => {
                         log::info!("Direct Groq fallback transcription succeeded");
+                        usage::record(
+                            "groq",
+                            UsageFeature::Transcription,
+                            usage::STT_MODEL,
+                            duration_ms as f64 / 1000.0,
+                            usage::TokenUsage::default(),
+                        );
                         TranscriptionResult {
                             text: result.text,
                             duration_ms,
@@ -921,6 +1006,12 @@ pub async fn stop_listening(
                             server_err, groq_err
                         );
                         log::error!("Transcription failed: {}", combined);
+                        let offline = connectivity::is_network_error(&groq_err)
+                            && (server_err == "remote API disabled"
+                                || connectivity::is_network_error(&server_err));
+                        if offline {
+                            state.connectivity.lock().await.mark_offline(combined.clone());
+                        }
                         {
                             let mut error_log = state.error_log.lock().await;
                             error_log.log_error_with_details(
@@ -931,6 +1022,12 @@ pub async fn stop_listening(
                         }
                         let mut is_processing = state.is_processing.lock().await;
                         *is_processing = false;
+                        if offline {
+                            return Err(
+                                "You're offline. Speech recognition needs a network connection."
+                                    .to_string(),
+                            );
+                        }
                         return Err(format!("Transcription failed: {}", combined));
                     }
                 }
@@ -1027,7 +1124,23 @@ pub async fn stop_listening(
         });
     }
 
-    let multilingual = match transform_multilingual_text(&transcription.text, &language_preferences).await {
+    // While an action waits for confirmation, "yes, do it" or "cancel" answers it
+    let pending_reply = if dictation_only {
+        None
+    } else {
+        take_pending_reply(&state, &transcription.text, &language_preferences).await
+    };
+
+    let offline = !state.connectivity.lock().await.is_online();
+
+    let multilingual_result = if offline {
+        Err("offline".to_string())
+    } else if !budget.allows(UsageFeature::Multilingual) {
+        Err("usage budget reached".to_string())
+    } else {
+        transform_multilingual_text(&transcription.text, &language_preferences).await
+    };
+    let multilingual = match multilingual_result {
         Ok(result) => result,
         Err(err) => {
             log::warn!("Multilingual transform skipped due to error: {}", err);
@@ -1059,93 +1172,64 @@ pub async fn stop_listening(
     }
 
     // Get conversation context for multi-turn dialogues
-    let (conv_context, session_id) = {
+    let (conv_context, session_id, clarification_reply) = {
         let mut conversation = state.conversation.lock().await;
         
         // Add user message to conversation
         conversation.add_user_message(transcription.text.clone());
-        
-        // Build conversation context for LLM
-        let clipboard_preview = {
-            let clipboard = state.clipboard.lock().await;
-            clipboard.get_preview(200).ok()
-        };
-        
-        // Load custom commands for context
-        let custom_commands = match custom::CustomCommandsStore::new() {
-            Ok(store) => {
-                store.get_enabled_commands()
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|c| (c.trigger_phrase, c.name, c.id))
-                    .collect()
-            }
-            Err(_) => Vec::new()
-        };
-        
-        // Load snippets for context
-        let snippets = match crate::snippets::SnippetsStore::new() {
-            Ok(store) => {
-                store.get_all_snippets()
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|s| (s.trigger, s.expansion))
-                    .collect()
-            }
-            Err(_) => Vec::new()
-        };
-        
-        // Determine dictation style based on active app
-        let dictation_style = {
-            let config = state.config.lock().await;
-            let active_app = context.active_app.as_ref().map(|s| s.to_lowercase());
-            
-            let style_config = &config.dictation_style;
-            
-            // Detect app category based on name
-            let config_style = match active_app.as_deref() {
-                // Personal messengers
-                Some(app) if app.contains("whatsapp") || app.contains("messenger") || 
-                             app.contains("telegram") || app.contains("imessage") ||
-                             app.contains("signal") || app.contains("discord") => style_config.personal,
-                // Work apps
-                Some(app) if app.contains("slack") || app.contains("teams") || 
-                             app.contains("zoom") => style_config.work,
-                // Email
-                Some(app) if app.contains("mail") || app.contains("outlook") || 
-                             app.contains("gmail") || app.contains("thunderbird") => style_config.email,
-                // Default
-                _ => style_config.other,
-            };
-            
-            // Convert config style to cloud style
-            match config_style {
-                crate::config::DictationStyle::Formal => cloud::DictationStyle::Formal,
-                crate::config::DictationStyle::Casual => cloud::DictationStyle::Casual,
-                crate::config::DictationStyle::VeryCasual => cloud::DictationStyle::VeryCasual,
-            }
+
+        // The previous turn may have asked which interpretation was meant
+        let clarification_reply = if dictation_only {
+            conversation.pending_clarification = None;
+            ClarificationReply::Unrelated
+        } else {
+            conversation.take_clarification_reply(&intent_text, clarification_config.answer_window_secs)
         };
         
-        let ctx = ConversationContext {
-            history: conversation.format_for_llm(),
-            last_action: conversation.last_action.clone(),
-            last_payload: conversation.last_action_payload.clone(),
-            clipboard_preview,
-            user_facts: conversation.extracted_facts.iter()
-                .map(|f| format!("{}: {}", f.key, f.value))
-                .collect(),
-            custom_commands,
-            snippets,
-            dictation_style,
-        };
+        // Build conversation context for LLM
+        let ctx = build_conversation_context(&state, &context, &conversation).await;
         
-        (ctx, conversation.session_id.clone())
+        (ctx, conversation.session_id.clone(), clarification_reply)
+    };
+    let answers_clarification = pending_reply.is_some()
+        || !matches!(clarification_reply, ClarificationReply::Unrelated);
+
+    let local_router_action = cloud::detect_local_command(&intent_text, &conv_context.aliases);
+
+    let is_question = local_router_action.is_none()
+        && !answers_clarification
+        && should_handle_as_question(&intent_text, &context);
+
+    // Q&A needs an LLM; while offline the question is queued instead
+    let offline_question_action = if offline && is_question {
+        let mut monitor = state.connectivity.lock().await;
+        let task = monitor.enqueue(connectivity::OfflineTask::new(
+            connectivity::OfflineTaskKind::Question,
+            intent_text.clone(),
+        ));
+        let mut action = ActionResult::action(
+            ActionType::NoAction,
+            serde_json::json!({ "offline": true, "queued_task_id": task.id }),
+        );
+        action.response_text = Some(task.kind.explanation().to_string());
+        Some(action)
+    } else {
+        None
     };
 
-    let local_router_action = cloud::detect_local_command(&intent_text);
-
-    let question_action = if local_router_action.is_none() && should_handle_as_question(&intent_text, &context) {
-        match build_question_response_action(&intent_text, &conv_context).await {
+    let question_action = if offline_question_action.is_some() {
+        offline_question_action
+    } else if is_question && !budget.allows(UsageFeature::Question) {
+        log::info!("Usage budget reached, skipping Q&A");
+        let mut action = ActionResult::action(
+            ActionType::NoAction,
+            serde_json::json!({ "budget_level": budget.level }),
+        );
+        action.response_text =
+            Some("You've reached your usage budget, so questions are paused for now.".to_string());
+        Some(action)
+    } else if is_question && !offline {
+        match build_question_response_action(&intent_text, &conv_context, &app, &state.answer_cancel).await {
             Ok(action) => {
                 log::info!("Question router selected local Q&A response");
                 Some(action)
@@ -1159,161 +1243,13 @@ pub async fn stop_listening(
         None
     };
 
-    // Intent routing:
-    // 1) Deterministic local router first for explicit command phrases
-    // 2) If cloud routing is enabled, use server intent
-    // 3) If cloud routing is disabled (fully local), use direct Groq intent
-    // 4) If server intent fails, fallback to direct Groq intent before dictation
-    let resolve_intent_action = || async {
-        let map_server_action = |result: crate::api_client::ActionResponse| -> ActionResult {
-            let action_type = match result.action_type.as_str() {
-                "TypeText" => ActionType::TypeText,
-                "RunCommand" => ActionType::RunCommand,
-                "OpenApp" => ActionType::OpenApp,
-                "OpenUrl" => ActionType::OpenUrl,
-                "WebSearch" => ActionType::WebSearch,
-                "VolumeControl" => ActionType::VolumeControl,
-                "SystemControl" => ActionType::SystemControl,
-                "SpotifyControl" => ActionType::SpotifyControl,
-                "KeyboardShortcut" => ActionType::KeyboardShortcut,
-                "WindowControl" => ActionType::WindowControl,
-                "Respond" => ActionType::Respond,
-                "Clarify" => ActionType::Clarify,
-                "NoAction" => ActionType::NoAction,
-                _ => ActionType::TypeText,
-            };
-            ActionResult {
-                action_type,
-                payload: result.payload,
-                refined_text: result.refined_text,
-                response_text: result.response_text,
-                requires_confirmation: result.requires_confirmation,
-            }
-        };
-
-        if !use_remote_api() {
-            let groq_client = GroqClient::new();
-            match groq_client
-                .process_intent_with_context(&intent_text, &context, &conv_context)
-                .await
-            {
-                Ok(action) => {
-                    log::info!(
-                        "Direct Groq intent action (cloud routing disabled): {:?}",
-                        action.action_type
-                    );
-                    return action;
-                }
-                Err(local_err) => {
-                    log::warn!(
-                        "Direct Groq intent failed (cloud routing disabled), defaulting to dictation: {}",
-                        local_err
-                    );
-                    {
-                        let mut error_log = state.error_log.lock().await;
-                        error_log.log_error_with_details(
-                            crate::error_log::ErrorType::LLMProcessing,
-                            "AI processing unavailable, using dictation mode",
-                            local_err.clone(),
-                        );
-                    }
-                    return ActionResult {
-                        action_type: ActionType::TypeText,
-                        payload: serde_json::json!({}),
-                        refined_text: Some(transcription.text.clone()),
-                        response_text: None,
-                        requires_confirmation: false,
-                    };
-                }
-            }
-        }
-
-        let api_client = state.api_client.lock().await;
-
-        // Build request for server API
-        let process_request = crate::api_client::ProcessRequest {
-            text: intent_text.clone(),
-            context: Some(crate::api_client::VoiceContext {
-                active_app: context.active_app.clone(),
-                selected_text: context.selected_text.clone(),
-                os: context.os.clone(),
-                mode: match context.mode {
-                    cloud::VoiceMode::Dictation => "Dictation".to_string(),
-                    cloud::VoiceMode::Command => "Command".to_string(),
-                },
-            }),
-            conversation_history: Some(conv_context.history.clone()),
-            custom_commands: Some(
-                conv_context
-                    .custom_commands
-                    .iter()
-                    .map(|(trigger, name, id)| crate::api_client::CustomCommand {
-                        trigger: trigger.clone(),
-                        name: name.clone(),
-                        id: id.clone(),
-                    })
-                    .collect(),
-            ),
-            dictation_style: Some(match conv_context.dictation_style {
-                cloud::DictationStyle::Formal => "Formal".to_string(),
-                cloud::DictationStyle::Casual => "Casual".to_string(),
-                cloud::DictationStyle::VeryCasual => "VeryCasual".to_string(),
-            }),
-        };
-
-        match api_client.process_intent(process_request).await {
-            Ok(result) => {
-                log::info!("Server action: {}", result.action_type);
-                map_server_action(result)
-            }
-            Err(e) => {
-                log::warn!(
-                    "Server processing failed, attempting direct Groq fallback: {}",
-                    e
-                );
-                let groq_client = GroqClient::new();
-                match groq_client
-                    .process_intent_with_context(&intent_text, &context, &conv_context)
-                    .await
-                {
-                    Ok(action) => {
-                        log::info!(
-                            "Direct Groq intent action (server fallback): {:?}",
-                            action.action_type
-                        );
-                        action
-                    }
-                    Err(local_err) => {
-                        log::warn!(
-                            "Direct Groq intent failed (server fallback), defaulting to dictation: {}",
-                            local_err
-                        );
-                        {
-                            let mut error_log = state.error_log.lock().await;
-                            error_log.log_error_with_details(
-                                crate::error_log::ErrorType::LLMProcessing,
-                                "AI processing unavailable, using dictation mode",
-                                format!("server error: {}; groq fallback error: {}", e, local_err),
-                            );
-                        }
-                        ActionResult {
-                            action_type: ActionType::TypeText,
-                            payload: serde_json::json!({}),
-                            refined_text: Some(transcription.text.clone()),
-                            response_text: None,
-                            requires_confirmation: false,
-                        }
-                    }
-                }
-            }
-        }
-    };
-
-    let mut action = if dictation_only {
+    // Intent routing: deterministic local router first for explicit command
+    // phrases, then `route_intent` (server, direct Groq, or offline grammar)
+    let (mut action, mut route) = if dictation_only {
             log::info!(
                 "Handsfree dictation mode active, bypassing intent routing and forcing TypeText"
             );
-            ActionResult {
+            let dictation = ActionResult {
                 action_type: ActionType::TypeText,
                 payload: serde_json::json!({
                     "dictation_only": true,
@@ -1322,41 +1258,79 @@ pub async fn stop_listening(
                 refined_text: Some(transcription.text.clone()),
                 response_text: None,
                 requires_confirmation: false,
+                confidence: 1.0,
+                alternatives: Vec::new(),
+            };
+            (dictation, RouteSource::Local)
+        } else if let Some((reply, pending)) = &pending_reply {
+            log::info!("Pending action {} answered by voice: {:?}", pending.id, reply);
+            match reply {
+                PendingReply::Confirm => (pending.action.clone(), RouteSource::Local),
+                PendingReply::Cancel => {
+                    let mut cancelled = ActionResult::action(
+                        ActionType::NoAction,
+                        serde_json::json!({ "cancelled_pending_action": pending.id }),
+                    );
+                    cancelled.response_text = Some("Okay, cancelled.".to_string());
+                    (cancelled, RouteSource::Local)
+                }
             }
+        } else if let ClarificationReply::Choice(candidate) = clarification_reply {
+            log::info!(
+                "Clarification answered with {:?} for transcript '{}'",
+                candidate.action_type,
+                intent_text
+            );
+            (candidate.into_action(), RouteSource::Llm)
+        } else if let ClarificationReply::Cancel = clarification_reply {
+            let cancelled = ActionResult {
+                action_type: ActionType::NoAction,
+                payload: serde_json::json!({ "clarification": "cancelled" }),
+                refined_text: None,
+                response_text: Some("Okay, never mind.".to_string()),
+                requires_confirmation: false,
+                confidence: 1.0,
+                alternatives: Vec::new(),
+            };
+            (cancelled, RouteSource::Local)
         } else if let Some(local_action) = local_router_action {
             log::info!(
                 "Local router selected action {:?} for transcript '{}'",
                 local_action.action_type,
                 intent_text
             );
-            local_action
+            (local_action, RouteSource::Local)
         } else if let Some(question_action) = question_action {
-            question_action
+            (question_action, RouteSource::Llm)
         } else if should_route_locally_first(&intent_text, &context) {
-            if let Some(local_action) = cloud::detect_local_command(&intent_text) {
+            if let Some(local_action) = cloud::detect_local_command(&intent_text, &conv_context.aliases) {
                 log::info!(
                     "Local router first selected action {:?} for transcript '{}'",
                     local_action.action_type,
                     intent_text
                 );
-                local_action
+                (local_action, RouteSource::Local)
             } else {
-            resolve_intent_action().await
+            route_intent(&state, &intent_text, &transcription.text, &context, &conv_context, offline, &budget).await
         }
     } else {
-        resolve_intent_action().await
+        route_intent(&state, &intent_text, &transcription.text, &context, &conv_context, offline, &budget).await
     };
 
     // Deterministic local router fallback.
     // If server returns dictation for an obvious command phrase, prefer local action routing.
-    if !dictation_only && should_use_local_command_fallback(&intent_text, &context, &action) {
-        if let Some(local_action) = cloud::detect_local_command(&intent_text) {
+    if !dictation_only
+        && !answers_clarification
+        && should_use_local_command_fallback(&intent_text, &context, &action)
+    {
+        if let Some(local_action) = cloud::detect_local_command(&intent_text, &conv_context.aliases) {
             log::info!(
                 "Local router fallback selected action {:?} for transcript '{}'",
                 local_action.action_type,
                 intent_text
             );
             action = local_action;
+            route = RouteSource::Local;
         }
     }
 
@@ -1378,7 +1352,97 @@ pub async fn stop_listening(
                     .to_string(),
             ),
             requires_confirmation: false,
+            confidence: 1.0,
+            alternatives: Vec::new(),
+        };
+    }
+
+    // "Repeat last command" replays a stored action through the normal confirmation path
+    if !dictation_only && is_repeat_request(&action) {
+        action = match load_repeat_action(&action.payload, &state) {
+            Ok((repeated, repeated_route)) => {
+                route = repeated_route;
+                repeated
+            }
+            Err(e) => {
+                route = RouteSource::Local;
+                let mut nothing = ActionResult::action(ActionType::NoAction, serde_json::json!({}));
+                nothing.response_text = Some(e);
+                nothing
+            }
+        };
+    }
+
+    // Routing may have just discovered we're offline
+    let offline = offline || !state.connectivity.lock().await.is_online();
+
+    // Clipboard transforms need an LLM; queue them while offline
+    if offline
+        && matches!(
+            action.action_type,
+            ActionType::ClipboardFormat
+                | ActionType::ClipboardTranslate
+                | ActionType::ClipboardSummarize
+                | ActionType::ClipboardClean
+        )
+    {
+        // The transform runs later on what is copied now, and only when the
+        // user runs it, so the policy is asked now and shown with the task
+        let content = state.clipboard.lock().await.get_current().unwrap_or_default();
+        let decision = confirmation_required(&state, &action, context.active_app.as_deref(), route).await;
+        let queued = if content.trim().is_empty() {
+            ActionResult::respond("Clipboard is empty".to_string())
+        } else {
+            let mut task = connectivity::OfflineTask::new(connectivity::OfflineTaskKind::ClipboardTransform, intent_text.clone());
+            task.action = Some(action.clone());
+            task.clipboard = Some(content);
+            task.confirmation = decision.required.then_some(decision.explanation);
+            let task = state.connectivity.lock().await.enqueue(task);
+            let mut queued = ActionResult::action(
+                ActionType::NoAction,
+                serde_json::json!({ "offline": true, "queued_task_id": task.id }),
+            );
+            queued.response_text = Some(task.kind.explanation().to_string());
+            queued
         };
+        action = queued;
+    }
+
+    // Ask instead of guessing when the router was unsure between interpretations
+    if !dictation_only
+        && !answers_clarification
+        && should_ask_for_clarification(&action, route, &clarification_config)
+    {
+        let mut candidates = vec![action.to_candidate()];
+        candidates.extend(action.alternatives.iter().cloned());
+        candidates.truncate(3);
+
+        let question = build_clarification_question(&candidates);
+        log::info!(
+            "Low confidence ({:.2}) for {:?}, asking for clarification",
+            action.confidence,
+            action.action_type
+        );
+
+        {
+            let mut conversation = state.conversation.lock().await;
+            conversation.set_pending_clarification(question.clone(), candidates.clone());
+        }
+
+        let confidence = action.confidence;
+        action = ActionResult::clarify(question);
+        action.payload = serde_json::json!({
+            "confidence": confidence,
+            "candidates": candidates
+                .iter()
+                .map(|c| serde_json::json!({
+                    "action_type": format!("{:?}", c.action_type),
+                    "payload": c.payload,
+                    "summary": summarize_action(&c.clone().into_action()),
+                    "confidence": c.confidence,
+                }))
+                .collect::<Vec<_>>(),
+        });
     }
 
     if multilingual.transformed && action.action_type == ActionType::TypeText {
@@ -1397,68 +1461,90 @@ pub async fn stop_listening(
             // Remove explicit trigger phrase from typed output even if enhancement fails.
             action.refined_text = Some(vibe_input.clone());
 
-            match enhance_vibe_coding_prompt(&vibe_input, &language_preferences, &vibe_config).await
-            {
-                Ok(enhanced_prompt) => {
-                    action.refined_text = Some(enhanced_prompt);
-                    upsert_action_payload_field(
-                        &mut action,
-                        "vibe_enhanced",
-                        serde_json::Value::Bool(true),
-                    );
-                    upsert_action_payload_field(
-                        &mut action,
-                        "vibe_activation_reason",
-                        serde_json::Value::String(activation_reason.to_string()),
-                    );
-                    upsert_action_payload_field(
-                        &mut action,
-                        "vibe_target_tool",
-                        serde_json::Value::String(
-                            vibe_target_tool_name(vibe_config.target_tool).to_string(),
-                        ),
-                    );
-                }
-                Err(err) => {
-                    log::warn!("Vibe prompt enhancement skipped due to error: {}", err);
-                    upsert_action_payload_field(
-                        &mut action,
-                        "vibe_enhancement_error",
-                        serde_json::Value::String(err),
-                    );
+            if offline {
+                let mut monitor = state.connectivity.lock().await;
+                let task = monitor.enqueue(connectivity::OfflineTask::new(
+                    connectivity::OfflineTaskKind::VibeEnhancement,
+                    vibe_input.clone(),
+                ));
+                upsert_action_payload_field(&mut action, "offline", serde_json::Value::Bool(true));
+                upsert_action_payload_field(
+                    &mut action,
+                    "queued_task_id",
+                    serde_json::Value::String(task.id),
+                );
+                action.response_text = Some(task.kind.explanation().to_string());
+            } else if !budget.allows(UsageFeature::VibeEnhancement) {
+                log::info!("Usage budget level {:?}, typing vibe prompt as spoken", budget.level);
+                upsert_action_payload_field(
+                    &mut action,
+                    "vibe_enhancement_error",
+                    serde_json::Value::String("usage budget reached".to_string()),
+                );
+            } else {
+                match enhance_vibe_coding_prompt(&vibe_input, &language_preferences, &vibe_config).await
+                {
+                    Ok(enhanced_prompt) => {
+                        action.refined_text = Some(enhanced_prompt);
+                        upsert_action_payload_field(
+                            &mut action,
+                            "vibe_enhanced",
+                            serde_json::Value::Bool(true),
+                        );
+                        upsert_action_payload_field(
+                            &mut action,
+                            "vibe_activation_reason",
+                            serde_json::Value::String(activation_reason.to_string()),
+                        );
+                        upsert_action_payload_field(
+                            &mut action,
+                            "vibe_target_tool",
+                            serde_json::Value::String(
+                                vibe_target_tool_name(vibe_config.target_tool).to_string(),
+                            ),
+                        );
+                    }
+                    Err(err) => {
+                        log::warn!("Vibe prompt enhancement skipped due to error: {}", err);
+                        upsert_action_payload_field(
+                            &mut action,
+                            "vibe_enhancement_error",
+                            serde_json::Value::String(err),
+                        );
+                    }
                 }
             }
         }
     }
 
     let confirms_enabled = confirmations_enabled();
-    let should_confirm_action = action.requires_confirmation || action_requires_confirmation(&action);
-    // Safety override: even when global confirmations are disabled, always gate power actions.
-    let requires_confirmation = if confirms_enabled {
-        should_confirm_action
-    } else {
-        is_power_system_action(&action)
+    let decision = match &pending_reply {
+        Some((PendingReply::Confirm, pending)) => {
+            if let Some(key) = pending.remember_key.clone() {
+                state.confirmed_rules.lock().await.insert(key);
+            }
+            policy::ConfirmationDecision::new(false, "confirmed by voice")
+        }
+        _ => confirmation_required(&state, &action, context.active_app.as_deref(), route).await,
     };
+    let requires_confirmation = decision.required;
     let mut pending_action_id: Option<String> = None;
 
     if !confirms_enabled {
         let mut pending = state.pending_action.lock().await;
-        *pending = None;
+        if pending.as_ref().is_some_and(|pending| !pending.from_rule) {
+            *pending = None;
+        }
     }
 
     if requires_confirmation {
-        let id = uuid::Uuid::new_v4().to_string();
-        let created_at = chrono::Utc::now().to_rfc3339();
+        let pending_action = PendingAction::new(action.clone(), transcription.text.clone(), decision);
+        let id = pending_action.id.clone();
         let summary = summarize_action(&action);
 
         {
             let mut pending = state.pending_action.lock().await;
-            *pending = Some(PendingAction {
-                id: id.clone(),
-                action: action.clone(),
-                transcription: transcription.text.clone(),
-                created_at,
-            });
+            *pending = Some(pending_action);
         }
 
         pending_action_id = Some(id);
@@ -1574,6 +1660,7 @@ pub async fn stop_listening(
     }
 
     // Processing logic finished
+    let history_action = action.clone();
     let result = VoiceProcessingResult {
         transcription,
         action: ActionResultResponse {
@@ -1590,12 +1677,11 @@ pub async fn stop_listening(
     };
 
     // Save to history
-    {
-        let mut history = state.history.lock().await;
-        history.push(result.clone());
-        // Keep only last 50
-        if history.len() > 50 {
-            history.remove(0);
+    if let Ok(history_guard) = state.history.lock() {
+        if let Some(ref store) = *history_guard {
+            if let Err(e) = store.record(&result, &history_action, route, context.active_app.as_deref()) {
+                log::warn!("Failed to save voice history: {}", e);
+            }
         }
     }
 
@@ -1628,53 +1714,144 @@ pub async fn get_status(state: State<'_, AppState>) -> Result<StatusResponse, St
 /// Get a pending action waiting for user confirmation.
 #[tauri::command]
 pub async fn get_pending_action(state: State<'_, AppState>) -> Result<Option<PendingActionResponse>, String> {
-    let pending = state.pending_action.lock().await;
+    let mut pending = state.pending_action.lock().await;
+    if pending.as_ref().is_some_and(PendingAction::is_expired) {
+        *pending = None;
+    }
     Ok(pending.as_ref().map(|p| PendingActionResponse {
         id: p.id.clone(),
         action_type: format!("{:?}", p.action.action_type),
         payload: p.action.payload.clone(),
         transcription: p.transcription.clone(),
-        summary: summarize_action(&p.action),
+        summary: format!("{} ({})", summarize_action(&p.action), p.reason),
         created_at: p.created_at.clone(),
+        expires_at: p.expires_at.to_rfc3339(),
     }))
 }
 
-/// Confirm and execute the pending action.
-#[tauri::command]
-pub async fn confirm_pending_action(state: State<'_, AppState>) -> Result<CommandResult, String> {
-    let pending = {
-        let pending_guard = state.pending_action.lock().await;
-        pending_guard.clone()
-    };
+/// Take the pending action out of the state if `text` answers it.
+/// Expired actions are dropped and never answered.
+async fn take_pending_reply(
+    state: &AppState,
+    text: &str,
+    language_preferences: &LanguagePreferences,
+) -> Option<(PendingReply, PendingAction)> {
+    let mut pending = state.pending_action.lock().await;
+    if pending.as_ref().is_some_and(PendingAction::is_expired) {
+        log::info!("Dropping expired pending action");
+        *pending = None;
+    }
+    pending.as_ref()?;
 
-    let pending = pending.ok_or_else(|| "No pending action to confirm".to_string())?;
-    let execute_result = execute_action_internal(&pending.action, &state).await;
+    let languages = [
+        language_preferences.source_language.as_str(),
+        language_preferences.target_language.as_str(),
+    ];
+    let reply = policy::parse_pending_reply(text, &languages)?;
+    pending.take().map(|taken| (reply, taken))
+}
 
-    match execute_result {
-        Ok(result) => {
-            let mut pending_guard = state.pending_action.lock().await;
-            *pending_guard = None;
-            Ok(result)
-        }
-        Err(e) => {
-            let mut error_log = state.error_log.lock().await;
-            error_log.log_error_with_details(
-                crate::error_log::ErrorType::ActionExecution,
-                format!("Failed to execute confirmed {:?}", pending.action.action_type),
-                e.clone(),
-            );
-            Err(e)
-        }
+/// Take the pending action out of the state if `id` still names it.
+/// A stale confirmation must never run a newer action.
+async fn take_pending_action(state: &AppState, id: &str) -> Result<PendingAction, String> {
+    take_pending_if_current(&mut *state.pending_action.lock().await, id)
+}
+
+fn take_pending_if_current(pending: &mut Option<PendingAction>, id: &str) -> Result<PendingAction, String> {
+    let current = pending.as_ref().ok_or_else(|| "No pending action to confirm".to_string())?;
+    if current.is_expired() {
+        *pending = None;
+        return Err("The pending action expired; say the command again".to_string());
+    }
+    if current.id != id {
+        return Err("The pending action changed; review it before confirming".to_string());
     }
+    pending.take().ok_or_else(|| "No pending action to confirm".to_string())
 }
 
-/// Cancel the pending action without executing it.
+/// Run an action the user just confirmed
+async fn execute_confirmed_action(pending: &PendingAction, state: &State<'_, AppState>) -> Result<CommandResult, String> {
+    if let Some(key) = pending.remember_key.clone() {
+        state.confirmed_rules.lock().await.insert(key);
+    }
+
+    let execute_result = execute_action_internal(&pending.action, state).await;
+    if let Err(e) = &execute_result {
+        let mut error_log = state.error_log.lock().await;
+        error_log.log_error_with_details(
+            crate::error_log::ErrorType::ActionExecution,
+            format!("Failed to execute confirmed {:?}", pending.action.action_type),
+            e.clone(),
+        );
+    }
+    execute_result
+}
+
+/// Confirm and execute the pending action with the given ID.
+#[tauri::command]
+pub async fn confirm_pending_action(state: State<'_, AppState>, id: String) -> Result<CommandResult, String> {
+    let pending = take_pending_action(&state, &id).await?;
+    execute_confirmed_action(&pending, &state).await
+}
+
+/// Cancel the pending action without executing it. With an `id`, only that
+/// action is cancelled.
 #[tauri::command]
-pub async fn cancel_pending_action(state: State<'_, AppState>) -> Result<bool, String> {
+pub async fn cancel_pending_action(state: State<'_, AppState>, id: Option<String>) -> Result<bool, String> {
     let mut pending = state.pending_action.lock().await;
-    let had_pending = pending.is_some();
-    *pending = None;
-    Ok(had_pending)
+    let matches = match (pending.as_ref(), id.as_deref()) {
+        (Some(current), Some(id)) => current.id == id,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    if matches {
+        *pending = None;
+    }
+    Ok(matches)
+}
+
+/// Stop a streamed answer mid-way. The partial text is kept as the reply.
+#[tauri::command]
+pub async fn cancel_answer(state: State<'_, AppState>) -> Result<(), String> {
+    state.answer_cancel.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Revert the most recent voice action that recorded an inverse
+#[tauri::command]
+pub async fn undo_last_action(state: State<'_, AppState>) -> Result<CommandResult, String> {
+    undo_last_action_internal(&state).await
+}
+
+/// Recent undoable actions, newest first
+#[tauri::command]
+pub async fn get_action_journal(state: State<'_, AppState>) -> Result<Vec<JournalEntry>, String> {
+    Ok(state.action_journal.lock().await.entries())
+}
+
+/// Search stored voice interactions, newest first
+#[tauri::command]
+pub async fn search_history(
+    state: State<'_, AppState>,
+    query: Option<history::HistoryQuery>,
+) -> Result<history::HistoryPage, String> {
+    let history_guard = state.history.lock().map_err(|e| e.to_string())?;
+    let store = history_guard
+        .as_ref()
+        .ok_or_else(|| "History is not available".to_string())?;
+    store.search(&query.unwrap_or_default())
+}
+
+/// Repeat a stored command: a history entry by `id`, or the `n`-th most
+/// recent command (1 = last). Confirmation rules still apply.
+#[tauri::command]
+pub async fn repeat_command(
+    state: State<'_, AppState>,
+    n: Option<usize>,
+    id: Option<i64>,
+) -> Result<CommandResult, String> {
+    let payload = serde_json::json!({ "action": "repeat_command", "n": n, "id": id });
+    repeat_command_internal(&payload, &state).await
 }
 
 /// Get real-time audio level (0.0 to 1.0) for visualization
@@ -1859,6 +2036,28 @@ fn confirmations_enabled() -> bool {
         .unwrap_or(false)
 }
 
+/// Whether an action must wait for explicit confirmation before running,
+/// and why. Admin rules, then the user's rules, then the built-in defaults.
+async fn confirmation_required(
+    state: &AppState,
+    action: &ActionResult,
+    active_app: Option<&str>,
+    route: RouteSource,
+) -> policy::ConfirmationDecision {
+    use chrono::Timelike;
+
+    let user_policy = state.config.lock().await.confirmation_policy.clone();
+    let admin_policy = ConfirmationPolicy::load_admin();
+    let remembered = state.confirmed_rules.lock().await.clone();
+    let ctx = policy::PolicyContext {
+        active_app,
+        hour: chrono::Local::now().hour(),
+        route,
+        confirmations_enabled: confirmations_enabled(),
+    };
+    policy::evaluate(admin_policy.as_ref(), &user_policy, action, &ctx, &remembered)
+}
+
 fn normalize_spoken_command_text(text: &str) -> String {
     let mut t = text.trim().to_lowercase();
 
@@ -1958,66 +2157,330 @@ fn clean_question_text(text: &str) -> String {
     trim_spoken_punctuation(&t)
 }
 
-async fn generate_groq_answer(question: &str, conv_context: &ConversationContext) -> Result<String, String> {
-    let api_key = cloud::get_groq_key();
-    if api_key.trim().is_empty() {
-        return Err("Missing GROQ_API_KEY".to_string());
-    }
-
-    let mut messages = vec![
-        serde_json::json!({
-            "role": "system",
-            "content": "You are ListenOS voice assistant. Reply naturally in 1-3 concise sentences unless the user asks for more detail."
-        })
-    ];
-
-    if !conv_context.history.trim().is_empty() {
-        messages.push(serde_json::json!({
-            "role": "system",
-            "content": format!("Conversation context:\n{}", conv_context.history)
-        }));
+/// Conversation context handed to the intent router
+async fn build_conversation_context(
+    state: &State<'_, AppState>,
+    context: &VoiceContext,
+    conversation: &crate::conversation::ConversationMemory,
+) -> ConversationContext {
+    let clipboard_preview = {
+        let clipboard = state.clipboard.lock().await;
+        clipboard.get_preview(200).ok()
+    };
+    
+    // Load custom commands for context
+    let custom_commands = match custom::CustomCommandsStore::new() {
+        Ok(store) => {
+            store.get_enabled_commands()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|c| cloud::CustomCommandRef {
+                    trigger: c.trigger_phrase,
+                    name: c.name,
+                    id: c.id,
+                })
+                .collect()
+        }
+        Err(_) => Vec::new()
+    };
+    
+    // Load snippets for context
+    let snippets = match crate::snippets::SnippetsStore::new() {
+        Ok(store) => {
+            store.get_all_snippets()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|s| (s.trigger, s.expansion))
+                .collect()
+        }
+        Err(_) => Vec::new()
+    };
+    
+    // Determine dictation style based on active app
+    let dictation_style = {
+        let config = state.config.lock().await;
+        let active_app = context.active_app.as_ref().map(|s| s.to_lowercase());
+        
+        let style_config = &config.dictation_style;
+        
+        // Detect app category based on name
+        let config_style = match active_app.as_deref() {
+            // Personal messengers
+            Some(app) if app.contains("whatsapp") || app.contains("messenger") || 
+                         app.contains("telegram") || app.contains("imessage") ||
+                         app.contains("signal") || app.contains("discord") => style_config.personal,
+            // Work apps
+            Some(app) if app.contains("slack") || app.contains("teams") || 
+                         app.contains("zoom") => style_config.work,
+            // Email
+            Some(app) if app.contains("mail") || app.contains("outlook") || 
+                         app.contains("gmail") || app.contains("thunderbird") => style_config.email,
+            // Default
+            _ => style_config.other,
+        };
+        
+        // Convert config style to cloud style
+        match config_style {
+            crate::config::DictationStyle::Formal => cloud::DictationStyle::Formal,
+            crate::config::DictationStyle::Casual => cloud::DictationStyle::Casual,
+            crate::config::DictationStyle::VeryCasual => cloud::DictationStyle::VeryCasual,
+        }
+    };
+    
+    let action_catalog = {
+        let integrations = state.integrations.lock().await;
+        cloud::ActionCatalog::from_integrations(&integrations.list_integrations(), &custom_commands)
+    };
+
+    ConversationContext {
+        history: conversation.format_for_llm(),
+        last_action: conversation.last_action.clone(),
+        last_payload: conversation.last_action_payload.clone(),
+        clipboard_preview,
+        user_facts: conversation.extracted_facts.iter()
+            .map(|f| format!("{}: {}", f.key, f.value))
+            .collect(),
+        custom_commands,
+        snippets,
+        aliases: state.aliases.lock().await.clone(),
+        dictation_style,
+        action_catalog,
+    }
+}
+
+/// Route an utterance to an action, along with where the decision came from.
+/// 1) While offline or over budget, use the local grammar and rules
+/// 2) If cloud routing is enabled, use server intent
+/// 3) If cloud routing is disabled (fully local), use direct Groq intent
+/// 4) If server intent fails, fallback to direct Groq intent before dictation
+async fn route_intent(
+    state: &State<'_, AppState>,
+    intent_text: &str,
+    transcript: &str,
+    context: &VoiceContext,
+    conv_context: &ConversationContext,
+    offline: bool,
+    budget: &usage::BudgetStatus,
+) -> (ActionResult, RouteSource) {
+    if offline {
+        log::info!("Offline: routing '{}' with local grammar and rules", intent_text);
+        return (connectivity::route_offline(intent_text, &conv_context.aliases), RouteSource::Local);
+    }
+    if !budget.allows(UsageFeature::Intent) {
+        log::info!("Usage budget reached: routing '{}' locally", intent_text);
+        return (connectivity::route_offline(intent_text, &conv_context.aliases), RouteSource::Local);
+    }
+
+    if !use_remote_api() {
+        let groq_client = GroqClient::new();
+        match groq_client
+            .process_intent_with_context(intent_text, context, conv_context)
+            .await
+        {
+            Ok(action) => {
+                log::info!(
+                    "Direct Groq intent action (cloud routing disabled): {:?}",
+                    action.action_type
+                );
+                state.connectivity.lock().await.mark_online();
+                return (action, RouteSource::Llm);
+            }
+            Err(local_err) if connectivity::is_network_error(&local_err) => {
+                log::warn!("Groq unreachable (cloud routing disabled), routing offline: {}", local_err);
+                state.connectivity.lock().await.mark_offline(local_err);
+                return (connectivity::route_offline(intent_text, &conv_context.aliases), RouteSource::Local);
+            }
+            Err(local_err) => {
+                log::warn!(
+                    "Direct Groq intent failed (cloud routing disabled), defaulting to dictation: {}",
+                    local_err
+                );
+                {
+                    let mut error_log = state.error_log.lock().await;
+                    error_log.log_error_with_details(
+                        crate::error_log::ErrorType::LLMProcessing,
+                        "AI processing unavailable, using dictation mode",
+                        local_err.clone(),
+                    );
+                }
+                let dictation = ActionResult {
+                    action_type: ActionType::TypeText,
+                    payload: serde_json::json!({}),
+                    refined_text: Some(transcript.to_string()),
+                    response_text: None,
+                    requires_confirmation: false,
+                    confidence: 1.0,
+                    alternatives: Vec::new(),
+                };
+                return (dictation, RouteSource::Local);
+            }
+        }
+    }
+
+    let api_client = state.api_client.lock().await;
+
+    // Build request for server API
+    let process_request = crate::api_client::ProcessRequest {
+        version: cloud::ACTION_WIRE_VERSION,
+        text: intent_text.to_string(),
+        context: Some(context.clone()),
+        conversation_history: Some(conv_context.history.clone()),
+        custom_commands: Some(conv_context.custom_commands.clone()),
+        dictation_style: Some(conv_context.dictation_style),
+    };
+
+    let request_body = serde_json::to_string(&process_request).unwrap_or_default();
+    let server_result = match api_client.process_intent(process_request).await {
+        Ok(wire) => {
+            // Older servers do not report tokens; estimate from what went over the wire
+            let tokens = wire.usage.unwrap_or_else(|| {
+                usage::TokenUsage::estimate(&request_body, &serde_json::to_string(&wire).unwrap_or_default())
+            });
+            usage::record("server", UsageFeature::Intent, usage::LLM_MODEL, 0.0, tokens);
+            let action_name = wire.action_type.clone();
+            match wire.into_action() {
+                Ok(action) => Ok(action),
+                Err(e) => {
+                    // Never turn an action we don't understand into typed
+                    // text, not even through the Groq fallback: ask instead
+                    let mut error_log = state.error_log.lock().await;
+                    error_log.log_error_with_details(
+                        crate::error_log::ErrorType::LLMProcessing,
+                        format!("Server returned unsupported action '{}'", action_name),
+                        e,
+                    );
+                    Ok(cloud::unsupported_action())
+                }
+            }
+        }
+        Err(e) => Err(e),
+    };
+
+    match server_result {
+        Ok(action) => {
+            log::info!("Server action: {:?}", action.action_type);
+            state.connectivity.lock().await.mark_online();
+            (action, RouteSource::Llm)
+        }
+        Err(e) => {
+            log::warn!(
+                "Server processing failed, attempting direct Groq fallback: {}",
+                e
+            );
+            let groq_client = GroqClient::new();
+            match groq_client
+                .process_intent_with_context(intent_text, context, conv_context)
+                .await
+            {
+                Ok(action) => {
+                    log::info!(
+                        "Direct Groq intent action (server fallback): {:?}",
+                        action.action_type
+                    );
+                    state.connectivity.lock().await.mark_online();
+                    (action, RouteSource::Llm)
+                }
+                Err(local_err)
+                    if connectivity::is_network_error(&e)
+                        && connectivity::is_network_error(&local_err) =>
+                {
+                    log::warn!("Server and Groq unreachable, routing offline: {}", local_err);
+                    state
+                        .connectivity
+                        .lock()
+                        .await
+                        .mark_offline(format!("server error: {}; groq error: {}", e, local_err));
+                    (connectivity::route_offline(intent_text, &conv_context.aliases), RouteSource::Local)
+                }
+                Err(local_err) => {
+                    log::warn!(
+                        "Direct Groq intent failed (server fallback), defaulting to dictation: {}",
+                        local_err
+                    );
+                    {
+                        let mut error_log = state.error_log.lock().await;
+                        error_log.log_error_with_details(
+                            crate::error_log::ErrorType::LLMProcessing,
+                            "AI processing unavailable, using dictation mode",
+                            format!("server error: {}; groq fallback error: {}", e, local_err),
+                        );
+                    }
+                    let dictation = ActionResult {
+                        action_type: ActionType::TypeText,
+                        payload: serde_json::json!({}),
+                        refined_text: Some(transcript.to_string()),
+                        response_text: None,
+                        requires_confirmation: false,
+                        confidence: 1.0,
+                        alternatives: Vec::new(),
+                    };
+                    (dictation, RouteSource::Local)
+                }
+            }
+        }
+    }
+}
+
+/// Answer a question with a streamed completion, calling `on_token` as text arrives
+async fn generate_groq_answer<F>(
+    question: &str,
+    conv_context: &ConversationContext,
+    cancel: &AtomicBool,
+    on_token: F,
+) -> Result<StreamedAnswer, String>
+where
+    F: FnMut(&str),
+{
+    let api_key = cloud::get_groq_key();
+    if api_key.trim().is_empty() {
+        return Err("Missing GROQ_API_KEY".to_string());
     }
 
+    let history = if conv_context.history.trim().is_empty() {
+        String::new()
+    } else {
+        format!("Conversation context:\n{}", conv_context.history)
+    };
+    let vars = PromptVars::new()
+        .facts(&conv_context.user_facts)
+        .text("history", history);
+    let system_prompt = prompts::render(prompts::QUESTION_ANSWER, &vars)?;
+
+    let mut messages = vec![
+        serde_json::json!({
+            "role": "system",
+            "content": system_prompt.trim_end()
+        })
+    ];
+
     messages.push(serde_json::json!({
         "role": "user",
         "content": question
     }));
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.groq.com/openai/v1/chat/completions")
-        .bearer_auth(api_key)
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
+    let answer = stream::stream_chat_completion(
+        "https://api.groq.com/openai/v1/chat/completions",
+        &api_key,
+        serde_json::json!({
             "model": "llama-3.3-70b-versatile",
             "messages": messages,
             "temperature": 0.3,
             "max_tokens": 280
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Groq request failed: {}", e))?;
-
-    let status = response.status();
-    let body = response.text().await.unwrap_or_default();
-    if !status.is_success() {
-        return Err(format!("Groq error [{}]: {}", status, body));
-    }
-
-    let parsed: serde_json::Value = serde_json::from_str(&body)
-        .map_err(|e| format!("Failed to parse Groq response: {}", e))?;
-
-    let answer = parsed["choices"][0]["message"]["content"]
-        .as_str()
-        .unwrap_or("")
-        .trim()
-        .to_string();
+        }),
+        cancel,
+        on_token,
+    )
+    .await?;
+    usage::record("groq", UsageFeature::Question, usage::LLM_MODEL, 0.0, answer.usage.unwrap_or_default());
 
-    if answer.is_empty() {
+    if answer.text.trim().is_empty() && !answer.cancelled {
         return Err("Groq returned empty answer".to_string());
     }
 
-    Ok(answer)
+    Ok(StreamedAnswer {
+        text: answer.text.trim().to_string(),
+        ..answer
+    })
 }
 
 fn upsert_action_payload_field(action: &mut ActionResult, key: &str, value: serde_json::Value) {
@@ -2033,21 +2496,55 @@ fn upsert_action_payload_field(action: &mut ActionResult, key: &str, value: serd
 async fn build_question_response_action(
     transcription: &str,
     conv_context: &ConversationContext,
+    app: &tauri::AppHandle,
+    cancel: &AtomicBool,
 ) -> Result<ActionResult, String> {
     let question = clean_question_text(transcription);
     if question.is_empty() {
         return Err("Question text is empty".to_string());
     }
 
-    let answer = generate_groq_answer(&question, conv_context).await?;
+    // A new answer supersedes any earlier cancel request
+    cancel.store(false, Ordering::SeqCst);
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let assistant = app.get_webview_window("assistant");
+
+    let answer = generate_groq_answer(&question, conv_context, cancel, |delta| {
+        if let Some(ref window) = assistant {
+            let _ = window.emit(
+                "answer-token",
+                serde_json::json!({ "stream_id": stream_id, "delta": delta }),
+            );
+        }
+    })
+    .await?;
+
+    if let Some(ref window) = assistant {
+        let _ = window.emit(
+            "answer-done",
+            serde_json::json!({
+                "stream_id": stream_id,
+                "text": answer.text,
+                "cancelled": answer.cancelled,
+            }),
+        );
+    }
+    if answer.cancelled {
+        log::info!("Answer stream {} cancelled by user", stream_id);
+    }
+
     let action = ActionResult {
         action_type: ActionType::Respond,
         payload: serde_json::json!({
-            "source": "ask_mode"
+            "source": "ask_mode",
+            "stream_id": stream_id,
+            "cancelled": answer.cancelled,
         }),
         refined_text: None,
-        response_text: Some(answer),
+        response_text: Some(answer.text),
         requires_confirmation: false,
+        confidence: 1.0,
+        alternatives: Vec::new(),
     };
 
     Ok(action)
@@ -2151,6 +2648,10 @@ fn looks_like_command_phrase(text: &str) -> bool {
         "look up ",
         "organize ",
         "sort ",
+        "undo last ",
+        "undo the last ",
+        "repeat last command",
+        "repeat command ",
         "volume ",
         "lock ",
         "take a screenshot",
@@ -2192,7 +2693,7 @@ fn is_farewell_phrase(text: &str) -> bool {
     exact.contains(&t.as_str())
 }
 
-fn is_power_system_action(action: &ActionResult) -> bool {
+pub(crate) fn is_power_system_action(action: &ActionResult) -> bool {
     if action.action_type != ActionType::SystemControl {
         return false;
     }
@@ -2207,7 +2708,7 @@ fn is_power_system_action(action: &ActionResult) -> bool {
     matches!(system_action.as_str(), "shutdown" | "restart" | "sleep")
 }
 
-fn action_requires_confirmation(action: &ActionResult) -> bool {
+pub(crate) fn action_requires_confirmation(action: &ActionResult) -> bool {
     match action.action_type {
         ActionType::RunCommand | ActionType::SendEmail | ActionType::MultiStep | ActionType::CustomCommand => true,
         ActionType::SystemControl => {
@@ -2224,12 +2725,42 @@ fn action_requires_confirmation(action: &ActionResult) -> bool {
                     | "factory_reset"
                     | "sign_out"
                     | "organize_downloads"
+                    | "open_file"
             )
         }
         _ => false,
     }
 }
 
+/// Only LLM routes are scored; local grammar matches carry a fixed
+/// confidence and are never ambiguous enough to ask about
+fn should_ask_for_clarification(action: &ActionResult, route: RouteSource, config: &ClarificationConfig) -> bool {
+    config.enabled
+        && route == RouteSource::Llm
+        && action.confidence < config.confidence_threshold
+        && !action.alternatives.is_empty()
+        && !matches!(
+            action.action_type,
+            ActionType::Clarify | ActionType::Respond | ActionType::NoAction
+        )
+}
+
+fn build_clarification_question(candidates: &[cloud::ActionCandidate]) -> String {
+    let ordinals = ["first", "second", "third"];
+    let options: Vec<String> = candidates
+        .iter()
+        .zip(ordinals.iter())
+        .map(|(candidate, ordinal)| {
+            format!("{}: {}", ordinal, summarize_action(&candidate.clone().into_action()))
+        })
+        .collect();
+
+    format!(
+        "I'm not sure what you meant. Did you mean {}? Say which one, or \"cancel\".",
+        options.join(", or ")
+    )
+}
+
 fn summarize_action(action: &ActionResult) -> String {
     match action.action_type {
         ActionType::OpenApp => {
@@ -2242,15 +2773,37 @@ fn summarize_action(action: &ActionResult) -> String {
         }
         ActionType::WebSearch => {
             let query = action.payload.get("query").and_then(|v| v.as_str()).unwrap_or("query");
-            format!("Search web for \"{}\"", query)
+            match action.payload.get("engine").and_then(|v| v.as_str()) {
+                Some(engine) => format!("Search {} for \"{}\"", engine, query),
+                None => format!("Search web for \"{}\"", query),
+            }
         }
         ActionType::SystemControl => {
             let system_action = action.payload.get("action").and_then(|v| v.as_str()).unwrap_or("system action");
             match system_action {
                 "organize_downloads" => "Organize Downloads folder".to_string(),
+                "undo_last_action" => "Undo last action".to_string(),
+                "repeat_command" => "Repeat an earlier command".to_string(),
                 "downloads_count" => "Count items in Downloads folder".to_string(),
                 "screenshot" => "Take a screenshot".to_string(),
                 "open_screenshots_folder" => "Open screenshots folder".to_string(),
+                "trash_file" => format!(
+                    "Move {} to the trash",
+                    action.payload.get("path").and_then(|v| v.as_str()).unwrap_or("file")
+                ),
+                "trash_restore" => format!(
+                    "Restore {} from the trash",
+                    action.payload.get("name").and_then(|v| v.as_str()).unwrap_or("item")
+                ),
+                "wifi_connect" | "vpn_connect" => format!(
+                    "Connect to {}",
+                    action.payload.get("name").and_then(|v| v.as_str()).unwrap_or("VPN")
+                ),
+                "bluetooth_connect" | "bluetooth_disconnect" => format!(
+                    "{} {}",
+                    if system_action == "bluetooth_connect" { "Connect" } else { "Disconnect" },
+                    action.payload.get("device").and_then(|v| v.as_str()).unwrap_or("Bluetooth device")
+                ),
                 _ => format!("System action: {}", system_action),
             }
         }
@@ -2259,13 +2812,17 @@ fn summarize_action(action: &ActionResult) -> String {
             format!("Run command: {}", cmd)
         }
         ActionType::SendEmail => "Send email".to_string(),
-        ActionType::VolumeControl => {
-            let direction = action.payload.get("direction").and_then(|v| v.as_str()).unwrap_or("change");
-            format!("Volume {}", direction)
-        }
+        ActionType::VolumeControl => match VolumeCommand::from_payload(&action.payload) {
+            Ok(command) => command.describe(),
+            Err(_) => "Volume change".to_string(),
+        },
         ActionType::WindowControl => {
             let window_action = action.payload.get("action").and_then(|v| v.as_str()).unwrap_or("window action");
-            format!("Window {}", window_action)
+            let target = action.payload.get("target").or_else(|| action.payload.get("name"));
+            match target.and_then(|v| v.as_str()) {
+                Some(target) => format!("Window {}: {}", window_action, target),
+                None => format!("Window {}", window_action),
+            }
         }
         ActionType::KeyboardShortcut => {
             let shortcut = action.payload.get("shortcut").and_then(|v| v.as_str()).unwrap_or("shortcut");
@@ -2286,10 +2843,30 @@ fn summarize_action(action: &ActionResult) -> String {
 
 // ============ Action Execution ============
 
-async fn open_url_internal(url: &str) -> Result<CommandResult, String> {
-    let normalized_url = normalize_web_target(url)
+/// Result reported by an executor that was skipped in dry-run mode
+fn simulated_result(message: impl Into<String>) -> CommandResult {
+    CommandResult {
+        success: true,
+        message: message.into(),
+        output: None,
+    }
+}
+
+async fn open_url_internal(url: &str, aliases: &AliasConfig) -> Result<CommandResult, String> {
+    // Bookmarks first; a phrase that isn't a URL becomes a search
+    let phrase = trim_spoken_punctuation(url);
+    let normalized_url = aliases
+        .website_for(&phrase)
+        .map(str::to_string)
+        .or_else(|| normalize_web_target(url))
         .or_else(|| infer_web_target_from_phrase(url, false))
-        .unwrap_or_else(|| trim_spoken_punctuation(url));
+        .unwrap_or_else(|| {
+            if phrase.contains(char::is_whitespace) {
+                aliases.default_search_engine().url_for(&phrase)
+            } else {
+                phrase
+            }
+        });
 
     if normalized_url.is_empty() {
         return Ok(CommandResult {
@@ -2301,6 +2878,14 @@ async fn open_url_internal(url: &str) -> Result<CommandResult, String> {
 
     log::info!("Opening URL: {}", normalized_url);
 
+    if simulation::intercept(
+        EffectKind::OpenUrl,
+        format!("Open {}", normalized_url),
+        serde_json::json!({ "url": normalized_url }),
+    ) {
+        return Ok(simulated_result(format!("Opened {}", normalized_url)));
+    }
+
     #[cfg(windows)]
     {
         use std::process::Command;
@@ -2335,8 +2920,12 @@ async fn open_url_internal(url: &str) -> Result<CommandResult, String> {
 
     #[cfg(not(any(windows, target_os = "macos")))]
     {
-        let cmd = format!("xdg-open \"{}\" 2>/dev/null || open \"{}\"", normalized_url, normalized_url);
-        run_system_command(cmd).await
+        open_with_default_handler(&normalized_url).map_err(|e| format!("Failed to open URL: {}", e))?;
+        Ok(CommandResult {
+            success: true,
+            message: format!("Opened: {}", normalized_url),
+            output: None,
+        })
     }
 }
 
@@ -2369,6 +2958,10 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
         ActionType::SpotifyControl => {
             execute_spotify_action(action, state).await
         }
+
+        ActionType::MediaControl => {
+            execute_media_action(action, state).await
+        }
         
         ActionType::DiscordControl => {
             execute_discord_action(action, state).await
@@ -2400,7 +2993,16 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
                 });
             }
             
-            type_text_internal(text).await
+            let count = text.chars().count();
+            let window = focused_window_id().await;
+            let result = type_text_internal(text, state).await?;
+            if result.success {
+                state.action_journal.lock().await.record(
+                    format!("Typed {} characters", count),
+                    InverseOp::Backspace { count, window },
+                );
+            }
+            Ok(result)
         }
         
         ActionType::OpenApp => {
@@ -2421,10 +3023,18 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
             // If the "app" looks like a URL/domain, open it in browser directly.
             if let Some(url) = normalize_web_target(&app) {
                 log::info!("OpenApp target looked like URL, redirecting to browser: {}", url);
-                return open_url_internal(&url).await;
+                return open_url_internal(&url, &*state.aliases.lock().await).await;
             }
             
             log::info!("Opening app: {}", app);
+
+            if simulation::intercept(
+                EffectKind::OpenApp,
+                format!("Open {}", app),
+                serde_json::json!({ "app": app }),
+            ) {
+                return Ok(simulated_result(format!("Opened {}", app)));
+            }
             
             #[cfg(windows)]
             {
@@ -2577,7 +3187,7 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
                         app,
                         url
                     );
-                    return open_url_internal(&url).await;
+                    return open_url_internal(&url, &*state.aliases.lock().await).await;
                 }
 
                 Ok(CommandResult {
@@ -2626,7 +3236,7 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
                 }
 
                 if let Some(url) = infer_web_target_from_phrase(&app, true) {
-                    return open_url_internal(&url).await;
+                    return open_url_internal(&url, &*state.aliases.lock().await).await;
                 }
 
                 Ok(CommandResult {
@@ -2636,10 +3246,57 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
                 })
             }
             
-            #[cfg(not(any(windows, target_os = "macos")))]
+            #[cfg(target_os = "linux")]
+            {
+                // Installed desktop entries first, then a bare executable on
+                // PATH, then the app's website
+                let query = app.clone();
+                let entry = tokio::task::spawn_blocking(move || apps::find(&query))
+                    .await
+                    .map_err(|e| format!("App lookup failed: {}", e))?;
+
+                if let Some(entry) = entry {
+                    apps::launch(&entry)?;
+                    return Ok(CommandResult {
+                        success: true,
+                        message: format!("Opened: {}", entry.name),
+                        output: None,
+                    });
+                }
+
+                if let Some(path) = apps::find_in_path(&app) {
+                    apps::launch_program(&path)?;
+                    return Ok(CommandResult {
+                        success: true,
+                        message: format!("Opened: {}", app),
+                        output: None,
+                    });
+                }
+
+                if let Some(url) = infer_web_target_from_phrase(&app, true) {
+                    log::info!(
+                        "App '{}' is not installed. Falling back to website open: {}",
+                        app,
+                        url
+                    );
+                    return open_url_internal(&url, &*state.aliases.lock().await).await;
+                }
+
+                Ok(CommandResult {
+                    success: false,
+                    message: format!("Could not find installed app '{}'", app),
+                    output: None,
+                })
+            }
+
+            #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
             {
-                let cmd = format!("xdg-open {} 2>/dev/null || open {}", app, app);
-                run_system_command(cmd).await
+                open_with_default_handler(&app).map_err(|e| format!("Failed to open app: {}", e))?;
+                Ok(CommandResult {
+                    success: true,
+                    message: format!("Opened: {}", app),
+                    output: None,
+                })
             }
         }
         
@@ -2657,10 +3314,24 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
                 });
             }
             
-            log::info!("Searching for: {}", query);
-            
-            let encoded_query = query.replace(" ", "+");
-            let url = format!("https://www.google.com/search?q={}", encoded_query);
+            let engine = {
+                let aliases = state.aliases.lock().await;
+                action.payload.get("engine")
+                    .and_then(|v| v.as_str())
+                    .and_then(|keyword| aliases.engine(keyword).cloned())
+                    .unwrap_or_else(|| aliases.default_search_engine())
+            };
+            log::info!("Searching {} for: {}", engine.name, query);
+
+            let url = engine.url_for(query);
+
+            if simulation::intercept(
+                EffectKind::OpenUrl,
+                format!("Search the web for \"{}\"", query),
+                serde_json::json!({ "url": url }),
+            ) {
+                return Ok(simulated_result(format!("Searching: {}", query)));
+            }
             
             #[cfg(windows)]
             {
@@ -2681,61 +3352,45 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
             
             #[cfg(not(windows))]
             {
-                let cmd = format!("open \"{}\"", url);
-                run_system_command(cmd).await
+                open_with_default_handler(&url).map_err(|e| format!("Failed to search: {}", e))?;
+                Ok(CommandResult {
+                    success: true,
+                    message: format!("Searching: {}", query),
+                    output: None,
+                })
             }
         }
         
         ActionType::VolumeControl => {
-            let direction = action.payload.get("direction")
-                .and_then(|v| v.as_str())
-                .unwrap_or("up");
-            
-            #[cfg(windows)]
-            {
-                let key_code = match direction {
-                    "up" => 175,
-                    "down" => 174,
-                    "mute" => 173,
-                    _ => 175,
-                };
-                let cmd = format!(
-                    "powershell -Command \"(New-Object -ComObject WScript.Shell).SendKeys([char]{})\"", 
-                    key_code
-                );
-                let _ = run_system_command(cmd).await;
-            }
-            
-            #[cfg(target_os = "macos")]
-            {
-                use std::process::Command;
-                let script = match direction {
-                    "up" => r#"set volume output volume ((output volume of (get volume settings)) + 10)"#,
-                    "down" => r#"set volume output volume ((output volume of (get volume settings)) - 10)"#,
-                    "mute" => r#"set volume output muted not (output muted of (get volume settings))"#,
-                    _ => r#"set volume output volume ((output volume of (get volume settings)) + 10)"#,
-                };
-                let _ = Command::new("osascript")
-                    .args(["-e", script])
-                    .output();
+            let command = VolumeCommand::from_payload(&action.payload)?;
+            if simulation::intercept(EffectKind::Volume, command.describe(), action.payload.clone()) {
+                return Ok(CommandResult {
+                    success: true,
+                    message: command.describe(),
+                    output: None,
+                });
             }
-            
-            #[cfg(not(any(windows, target_os = "macos")))]
-            {
-                // Linux: use pactl or amixer
-                let cmd = match direction {
-                    "up" => "pactl set-sink-volume @DEFAULT_SINK@ +10%",
-                    "down" => "pactl set-sink-volume @DEFAULT_SINK@ -10%",
-                    "mute" => "pactl set-sink-mute @DEFAULT_SINK@ toggle",
-                    _ => "pactl set-sink-volume @DEFAULT_SINK@ +10%",
-                };
-                let _ = run_system_command(cmd.to_string()).await;
+
+            let outcome = run_volume_command(command.clone()).await?;
+
+            // Only changes to the default output can be undone
+            let inverse = match (&command, outcome.previous_level) {
+                (VolumeCommand::Output(VolumeChange::ToggleMute), _) => Some(InverseOp::opposite_volume_step("mute")),
+                (VolumeCommand::Output(VolumeChange::Up(_) | VolumeChange::Down(_) | VolumeChange::Set(_)), Some(level)) => {
+                    Some(InverseOp::SetVolume { level })
+                }
+                (VolumeCommand::Output(VolumeChange::Up(_)), None) => Some(InverseOp::opposite_volume_step("up")),
+                (VolumeCommand::Output(VolumeChange::Down(_)), None) => Some(InverseOp::opposite_volume_step("down")),
+                _ => None,
+            };
+            if let Some(inverse) = inverse {
+                state.action_journal.lock().await.record(command.describe(), inverse);
             }
             
             Ok(CommandResult {
                 success: true,
-                message: format!("Volume {}", direction),
-                output: None,
+                message: outcome.message,
+                output: outcome.data.map(|data| data.to_string()),
             })
         }
         
@@ -2747,8 +3402,9 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
             if cmd.is_empty() {
                 return Err("No command specified".to_string());
             }
-            
-            run_system_command(cmd.to_string()).await
+            let working_dir = action.payload.get("cwd").and_then(|v| v.as_str());
+
+            run_sandboxed_command(cmd, working_dir, state).await
         }
         
         ActionType::OpenUrl => {
@@ -2756,7 +3412,7 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
                 .and_then(|v| v.as_str())
                 .unwrap_or("")
                 .trim();
-            open_url_internal(url).await
+            open_url_internal(url, &*state.aliases.lock().await).await
         }
         
         ActionType::SendEmail => {
@@ -2782,6 +3438,14 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
             );
             
             log::info!("Opening email compose: to={}", to);
+
+            if simulation::intercept(
+                EffectKind::OpenUrl,
+                format!("Compose an email to {}", to),
+                serde_json::json!({ "url": gmail_url }),
+            ) {
+                return Ok(simulated_result(format!("Composing email to: {}", to)));
+            }
             
             #[cfg(windows)]
             {
@@ -2802,90 +3466,31 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
             
             #[cfg(not(windows))]
             {
-                let cmd = format!("open \"{}\"", gmail_url);
-                run_system_command(cmd).await
+                open_with_default_handler(&gmail_url).map_err(|e| format!("Failed to open email: {}", e))?;
+                Ok(CommandResult {
+                    success: true,
+                    message: format!("Composing email to: {}", to),
+                    output: None,
+                })
             }
         }
         
         ActionType::MultiStep => {
-            // Execute multiple actions in sequence
-            let steps = action.payload.get("steps")
-                .and_then(|v| v.as_array());
-            
-            if let Some(steps) = steps {
-                log::info!("Executing {} steps", steps.len());
-                let mut success_count = 0usize;
-                let mut errors: Vec<String> = Vec::new();
-                
-                for (i, step) in steps.iter().enumerate() {
-                    let step_action_type = match step["action"].as_str().unwrap_or("") {
-                        "open_app" => ActionType::OpenApp,
-                        "open_url" => ActionType::OpenUrl,
-                        "web_search" => ActionType::WebSearch,
-                        "run_command" => ActionType::RunCommand,
-                        "type_text" => ActionType::TypeText,
-                        "volume_control" => ActionType::VolumeControl,
-                        "system_control" => ActionType::SystemControl,
-                        "keyboard_shortcut" => ActionType::KeyboardShortcut,
-                        "window_control" => ActionType::WindowControl,
-                        other => {
-                            errors.push(format!("Unknown step action '{}'", other));
-                            continue;
-                        }
-                    };
-                    
-                    let step_result = ActionResult {
-                        action_type: step_action_type,
-                        payload: step["payload"].clone(),
-                        refined_text: step["refined_text"].as_str().map(|s| s.to_string()),
-                        response_text: None,
-                        requires_confirmation: false,
-                    };
-                    
-                    log::info!("Step {}: {:?}", i + 1, step_action_type);
-                    
-                    // Execute and continue regardless of result
-                    match Box::pin(execute_action_internal(&step_result, state)).await {
-                        Ok(_) => {
-                            success_count += 1;
-                        }
-                        Err(e) => {
-                            log::warn!("Step {} failed: {}", i + 1, e);
-                            errors.push(format!("Step {} failed: {}", i + 1, e));
-                        }
-                    }
-                    
-                    // Small delay between steps
-                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                }
-
-                if success_count == 0 && !errors.is_empty() {
-                    return Err(errors.join("; "));
-                }
-
-                let message = if errors.is_empty() {
-                    format!("Executed {} steps", steps.len())
-                } else {
-                    format!(
-                        "Executed {}/{} steps. {}",
-                        success_count,
-                        steps.len(),
-                        errors.join("; ")
-                    )
-                };
+            let plan = plan::Plan::from_payload(&action.payload)?;
+            log::info!("Executing {} steps", plan.steps.len());
 
-                Ok(CommandResult {
-                    success: errors.is_empty(),
-                    message,
-                    output: None,
-                })
-            } else {
-                Ok(CommandResult {
-                    success: false,
-                    message: "No steps provided".to_string(),
-                    output: None,
-                })
+            let result = plan::execute_plan(&plan, state).await;
+            if result.succeeded == 0 && result.failed > 0 {
+                let errors: Vec<String> = result
+                    .steps
+                    .iter()
+                    .filter(|r| r.status != plan::StepStatus::Skipped)
+                    .map(|r| format!("Step {} failed: {}", r.step, r.message))
+                    .collect();
+                return Err(errors.join("; "));
             }
+
+            Ok(plan::plan_command_result("Multi-step plan", &result))
         }
         
         ActionType::NoAction => {
@@ -2901,169 +3506,285 @@ async fn execute_action_internal(action: &ActionResult, state: &State<'_, AppSta
         }
         
         ActionType::WindowControl => {
-            execute_window_control(action).await
+            execute_window_control(action, state).await
+        }
+    }
+}
+
+/// Run a VolumeControl command against the sound server
+#[cfg(target_os = "linux")]
+async fn run_volume_command(command: VolumeCommand) -> Result<mixer::VolumeOutcome, String> {
+    with_mixer(move |mixer| mixer.run(&command)).await
+}
+
+/// Without a sound server client only the default output can be stepped,
+/// and set or read where the platform allows it
+#[cfg(not(target_os = "linux"))]
+async fn run_volume_command(command: VolumeCommand) -> Result<mixer::VolumeOutcome, String> {
+    match command {
+        VolumeCommand::Output(change) => {
+            let previous_level = current_volume_level().await;
+            match change {
+                VolumeChange::Set(level) => set_volume_level(level).await?,
+                VolumeChange::Up(_) => step_volume("up").await,
+                VolumeChange::Down(_) => step_volume("down").await,
+                VolumeChange::Mute | VolumeChange::Unmute | VolumeChange::ToggleMute => step_volume("mute").await,
+            }
+            Ok(mixer::VolumeOutcome { message: change.describe(), data: None, previous_level })
+        }
+        VolumeCommand::Query { app: None } => {
+            let level = current_volume_level()
+                .await
+                .ok_or("Reading the volume is not supported on this platform")?;
+            Ok(mixer::VolumeOutcome {
+                message: format!("Volume {}%", level),
+                data: Some(serde_json::json!({ "volume": level })),
+                previous_level: None,
+            })
+        }
+        _ => Err("Per-app volume and switching audio devices are only supported on Linux".to_string()),
+    }
+}
+
+/// Run blocking calls against the sound server
+#[cfg(target_os = "linux")]
+async fn with_mixer<T: Send + 'static>(
+    f: impl FnOnce(&mut dyn mixer::Mixer) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(move || f(&mut mixer::connect()?))
+        .await
+        .map_err(|e| format!("Volume control task failed: {}", e))?
+}
+
+/// Step the output volume up, down, or toggle mute
+async fn step_volume(direction: &str) {
+    if simulation::intercept(
+        EffectKind::Volume,
+        format!("Volume {}", direction),
+        serde_json::json!({ "direction": direction }),
+    ) {
+        return;
+    }
+
+    #[cfg(windows)]
+    {
+        let key_code = match direction {
+            "up" => 175,
+            "down" => 174,
+            "mute" => 173,
+            _ => 175,
+        };
+        let cmd = format!(
+            "powershell -Command \"(New-Object -ComObject WScript.Shell).SendKeys([char]{})\"",
+            key_code
+        );
+        let _ = run_shell_command(cmd).await;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::process::Command;
+        let script = match direction {
+            "up" => r#"set volume output volume ((output volume of (get volume settings)) + 10)"#,
+            "down" => r#"set volume output volume ((output volume of (get volume settings)) - 10)"#,
+            "mute" => r#"set volume output muted not (output muted of (get volume settings))"#,
+            _ => r#"set volume output volume ((output volume of (get volume settings)) + 10)"#,
+        };
+        let _ = Command::new("osascript")
+            .args(["-e", script])
+            .output();
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let change = match direction {
+            "down" => VolumeChange::Down(mixer::DEFAULT_STEP),
+            "mute" => VolumeChange::ToggleMute,
+            "unmute" => VolumeChange::Unmute,
+            _ => VolumeChange::Up(mixer::DEFAULT_STEP),
+        };
+        if let Err(e) = run_volume_command(VolumeCommand::Output(change)).await {
+            log::warn!("Volume {} failed: {}", direction, e);
         }
     }
 }
 
-/// Type text into the active window
+/// Current output volume (0-100), where the platform lets us read it
+/// without the sound server client
+#[cfg(not(target_os = "linux"))]
+async fn current_volume_level() -> Option<u32> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("osascript")
+            .args(["-e", "output volume of (get volume settings)"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+}
+
+/// Set the output volume to an absolute level (0-100)
+async fn set_volume_level(level: u32) -> Result<(), String> {
+    let level = level.min(100);
+    if simulation::intercept(
+        EffectKind::Volume,
+        format!("Set volume to {}%", level),
+        serde_json::json!({ "level": level }),
+    ) {
+        return Ok(());
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        let _ = level;
+        Err("Setting an exact volume level is not supported on this platform".to_string())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("osascript")
+            .args(["-e", &format!("set volume output volume {}", level)])
+            .output()
+            .map_err(|e| format!("Failed to set volume: {}", e))?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        run_volume_command(VolumeCommand::Output(VolumeChange::Set(level))).await.map(|_| ())
+    }
+}
+
 #[tauri::command]
-pub async fn type_text(text: String) -> Result<CommandResult, String> {
-    type_text_internal(text).await
+pub async fn type_text(state: State<'_, AppState>, text: String) -> Result<CommandResult, String> {
+    type_text_internal(text, &state).await
 }
 
-async fn type_text_internal(text: String) -> Result<CommandResult, String> {
-    use enigo::{Enigo, Keyboard, Key, Settings, Direction};
-    use arboard::Clipboard;
-    
+async fn type_text_internal(text: String, state: &AppState) -> Result<CommandResult, String> {
     if text.is_empty() {
         return Err("No text to type".to_string());
     }
     
     log::info!("type_text_internal: Starting to type {} chars", text.len());
+
+    if simulation::intercept(
+        EffectKind::TypeText,
+        format!("Type \"{}\"", text),
+        serde_json::json!({ "text": text }),
+    ) {
+        return Ok(simulated_result("Text typed"));
+    }
+
+    let config = state.config.lock().await.text_injection.clone();
+    let active_app = state.current_context.lock().await.active_app.clone();
     
     // Longer delay to ensure focus is restored after Ctrl+Space release
     // Windows needs more time to restore focus to the previous window
     tokio::time::sleep(tokio::time::Duration::from_millis(350)).await;
-    
-    // Use clipboard + Ctrl+V for reliable pasting (more reliable than enigo.text())
-    let mut clipboard = Clipboard::new()
-        .map_err(|e| format!("Failed to access clipboard: {}", e))?;
-    
-    // Save current clipboard content to restore later
-    let previous_content = clipboard.get_text().ok();
-    log::info!("type_text_internal: Saved previous clipboard content");
-    
-    // Set our text to clipboard with retry
-    let mut set_success = false;
-    for attempt in 1..=3 {
-        match clipboard.set_text(&text) {
-            Ok(_) => {
-                // Verify the clipboard was actually set
-                tokio::time::sleep(tokio::time::Duration::from_millis(30)).await;
-                if let Ok(current) = clipboard.get_text() {
-                    if current == text {
-                        set_success = true;
-                        log::info!("type_text_internal: Clipboard set successfully on attempt {}", attempt);
-                        break;
-                    }
-                }
-                log::warn!("type_text_internal: Clipboard verification failed on attempt {}", attempt);
-            }
-            Err(e) => {
-                log::warn!("type_text_internal: Failed to set clipboard on attempt {}: {}", attempt, e);
-            }
-        }
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-    }
-    
-    if !set_success {
-        return Err("Failed to set clipboard after 3 attempts".to_string());
-    }
-    
-    // Additional delay for clipboard to be fully ready
-    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-    
-    // Simulate Ctrl+V to paste with retry
-    let mut paste_success = false;
-    for attempt in 1..=2 {
-        match paste_with_enigo() {
-            Ok(_) => {
-                paste_success = true;
-                log::info!("type_text_internal: Paste successful on attempt {}", attempt);
-                break;
-            }
-            Err(e) => {
-                log::warn!("type_text_internal: Paste failed on attempt {}: {}", attempt, e);
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            }
-        }
-    }
-    
-    if !paste_success {
-        // Fallback: try character-by-character typing for short text
-        if text.len() <= 100 {
-            log::info!("type_text_internal: Falling back to character-by-character typing");
-            if let Ok(mut enigo) = Enigo::new(&Settings::default()) {
-                if enigo.text(&text).is_ok() {
-                    paste_success = true;
-                }
-            }
-        }
-    }
-    
-    // Restore previous clipboard content after a delay
-    tokio::time::sleep(tokio::time::Duration::from_millis(150)).await;
-    if let Some(prev) = previous_content {
-        let _ = clipboard.set_text(&prev);
-        log::info!("type_text_internal: Restored previous clipboard content");
+
+    let backend = injection::inject_text(&text, &config, active_app.as_deref()).await?;
+    log::info!("type_text_internal: Successfully typed text with {:?}", backend);
+
+    let preview: String = text.chars().take(50).collect();
+    Ok(CommandResult {
+        success: true,
+        message: format!("Typed: {}", if preview.len() < text.len() { format!("{}...", preview) } else { text }),
+        output: None,
+    })
+}
+
+/// Run a user or LLM command in the sandbox, reporting a non-zero exit as an error
+async fn run_sandboxed_command(
+    command: &str,
+    working_dir: Option<&str>,
+    state: &AppState,
+) -> Result<CommandResult, String> {
+    let config = state.config.lock().await.command_sandbox.clone();
+    let output = sandbox::run(command, working_dir, &normalized_command_sandbox(&config)).await?;
+
+    if output.timed_out {
+        return Err(format!("`{}` timed out after {}s", output.command, config.timeout_secs));
+    }
+    if !output.success {
+        let stderr = output.stderr.trim();
+        return Err(match output.exit_code {
+            Some(code) if stderr.is_empty() => format!("`{}` exited with code {}", output.command, code),
+            Some(code) => format!("`{}` exited with code {}: {}", output.command, code, stderr),
+            None => format!("`{}` was terminated", output.command),
+        });
     }
-    
-    if paste_success {
-        log::info!("type_text_internal: Successfully typed text");
-        Ok(CommandResult {
-            success: true,
-            message: format!("Typed: {}", if text.len() > 50 { format!("{}...", &text[..50]) } else { text }),
-            output: None,
-        })
-    } else {
-        Err("Failed to paste text into the focused application".to_string())
+
+    let mut stdout = output.stdout;
+    if output.stdout_truncated {
+        stdout.push_str("\n[output truncated]");
     }
+    Ok(CommandResult {
+        success: true,
+        message: "Done".to_string(),
+        output: Some(stdout),
+    })
 }
 
-/// Helper function to perform Ctrl+V (Windows/Linux) or Cmd+V (macOS) paste
-fn paste_with_enigo() -> Result<(), String> {
-    use enigo::{Enigo, Keyboard, Key, Settings, Direction};
-    
-    let mut enigo = Enigo::new(&Settings::default())
-        .map_err(|e| format!("Failed to create enigo: {}", e))?;
-    
-    // Use Cmd on macOS, Ctrl on Windows/Linux
+/// Run a command in the sandbox and return its full result
+#[tauri::command]
+pub async fn run_system_command(
+    state: State<'_, AppState>,
+    command: String,
+    working_dir: Option<String>,
+) -> Result<sandbox::CommandOutput, String> {
+    let config = state.config.lock().await.command_sandbox.clone();
+    sandbox::run(&command, working_dir.as_deref(), &normalized_command_sandbox(&config)).await
+}
+
+/// Hand a URL or path to the desktop's default handler (`open` on macOS,
+/// `xdg-open` elsewhere) as a single argument, so nothing in it reaches a
+/// shell. Callers check [`simulation::intercept`] first.
+#[cfg(not(windows))]
+fn open_with_default_handler(target: &str) -> Result<(), String> {
     #[cfg(target_os = "macos")]
-    let modifier = Key::Meta;
+    let opener = "open";
     #[cfg(not(target_os = "macos"))]
-    let modifier = Key::Control;
-    
-    // Press modifier key
-    enigo.key(modifier, Direction::Press)
-        .map_err(|e| format!("Failed to press modifier: {}", e))?;
-    
-    // Small delay between key presses
-    std::thread::sleep(std::time::Duration::from_millis(20));
-    
-    // Press and release V
-    enigo.key(Key::Unicode('v'), Direction::Click)
-        .map_err(|e| format!("Failed to press V: {}", e))?;
-    
-    // Small delay before releasing
-    std::thread::sleep(std::time::Duration::from_millis(20));
-    
-    // Release modifier key
-    enigo.key(modifier, Direction::Release)
-        .map_err(|e| format!("Failed to release modifier: {}", e))?;
-    
-    Ok(())
+    let opener = "xdg-open";
+
+    std::process::Command::new(opener)
+        .arg(target)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run {}: {}", opener, e))
 }
 
-/// Run a system command
-#[tauri::command]
-pub async fn run_system_command(command: String) -> Result<CommandResult, String> {
+/// Run a fixed command line built by ListenOS itself (the Windows volume key
+/// helper) through `cmd`. Nothing user-supplied may be formatted into it;
+/// openers use [`open_with_default_handler`] and user and LLM commands go
+/// through [`sandbox::run`].
+#[cfg(windows)]
+async fn run_shell_command(command: String) -> Result<CommandResult, String> {
     use std::process::Command;
     
     log::info!("Running: {}", command);
+
+    if simulation::intercept(
+        EffectKind::Command,
+        format!("Run `{}`", command),
+        serde_json::json!({ "command": command }),
+    ) {
+        return Ok(CommandResult {
+            success: true,
+            message: "Done".to_string(),
+            output: Some(String::new()),
+        });
+    }
     
-    #[cfg(windows)]
     let output = Command::new("cmd")
         .args(["/C", &command])
         .output()
         .map_err(|e| format!("Failed: {}", e))?;
     
-    #[cfg(not(windows))]
-    let output = Command::new("sh")
-        .args(["-c", &command])
-        .output()
-        .map_err(|e| format!("Failed: {}", e))?;
-    
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
     
@@ -3101,6 +3822,14 @@ async fn execute_keyboard_shortcut(action: &ActionResult) -> Result<CommandResul
     }
     
     log::info!("Executing keyboard shortcut: {}", shortcut);
+
+    if simulation::intercept(
+        EffectKind::KeyPress,
+        format!("Press the {} shortcut", shortcut),
+        serde_json::json!({ "shortcut": shortcut }),
+    ) {
+        return Ok(simulated_result(format!("Executed: {}", shortcut)));
+    }
     
     // Small delay to ensure focus is on the right window
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -3237,9 +3966,7 @@ fn send_key_combo(enigo: &mut enigo::Enigo, modifiers: &[enigo::Key], key: char)
 // ============ Window Control Helpers ============
 
 /// Execute window control commands (minimize, maximize, close, etc.)
-async fn execute_window_control(action: &ActionResult) -> Result<CommandResult, String> {
-    use enigo::{Enigo, Keyboard, Key, Settings, Direction};
-    
+async fn execute_window_control(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
     let window_action = action.payload.get("action")
         .and_then(|v| v.as_str())
         .unwrap_or("");
@@ -3249,14 +3976,208 @@ async fn execute_window_control(action: &ActionResult) -> Result<CommandResult,
     }
     
     log::info!("Executing window control: {}", window_action);
+
+    if simulation::intercept(
+        EffectKind::Window,
+        format!("Window: {}", window_action),
+        action.payload.clone(),
+    ) {
+        return Ok(simulated_result(format!("Window: {}", window_action)));
+    }
     
     // Small delay to ensure focus is on the right window
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
     
+    let layout_name = action.payload.get("name").and_then(|v| v.as_str()).unwrap_or("");
+    match window_action {
+        "save_layout" => {
+            let layout = save_window_layout_internal(layout_name).await?;
+            return Ok(CommandResult {
+                success: true,
+                message: format!("Saved {} layout with {} windows", layout.name, layout.windows.len()),
+                output: None,
+            });
+        }
+        "restore_layout" => return restore_window_layout_internal(layout_name, state).await,
+        _ => {}
+    }
+
+    let command = window::WindowCommand::from_payload(&action.payload)?;
+    let outcome = match command {
+        #[cfg(not(target_os = "linux"))]
+        window::WindowCommand::Active(window_action) => {
+            press_window_shortcut(window_action.name())?;
+            window::WindowOutcome {
+                message: format!("Window: {}", window_action.name()),
+                windows: None,
+            }
+        }
+        command => with_window_manager(move |manager| manager.run(&command)).await?,
+    };
+    
+    Ok(CommandResult {
+        success: true,
+        message: outcome.message,
+        output: outcome
+            .windows
+            .map(|windows| serde_json::to_string(&windows).unwrap_or_default()),
+    })
+}
+
+/// Run blocking calls against the session's window manager
+#[cfg(target_os = "linux")]
+async fn with_window_manager<T: Send + 'static>(
+    f: impl FnOnce(&dyn window::WindowManager) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(move || f(window::connect()?.as_ref()))
+        .await
+        .map_err(|e| format!("Window control task failed: {}", e))?
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn with_window_manager<T: Send + 'static>(
+    _f: impl FnOnce(&dyn window::WindowManager) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    Err("Finding windows by name is only supported on Linux".to_string())
+}
+
+/// How long restoring a layout waits for relaunched apps to open a window
+const LAYOUT_LAUNCH_TIMEOUT_SECS: u64 = 15;
+
+/// Save the open windows under `name`, replacing an older layout of that name
+async fn save_window_layout_internal(name: &str) -> Result<custom::WindowLayout, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Give the layout a name, like \"save layout as coding\"".to_string());
+    }
+
+    let slots: Vec<window::LayoutSlot> = with_window_manager(|manager| {
+        Ok(manager
+            .list_windows()?
+            .iter()
+            .filter(|window| !window.app.is_empty())
+            .map(window::LayoutSlot::capture)
+            .collect())
+    })
+    .await?;
+    if slots.is_empty() {
+        return Err("There are no windows to save".to_string());
+    }
+
+    let store = custom::CustomCommandsStore::new()?;
+    let existing = store.find_layout(name)?;
+    let now = chrono::Utc::now();
+    let layout = custom::WindowLayout {
+        id: existing.as_ref().map(|l| l.id.clone()).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        name: existing.as_ref().map(|l| l.name.clone()).unwrap_or_else(|| name.to_string()),
+        windows: slots,
+        created_at: existing.map(|l| l.created_at).unwrap_or(now),
+        updated_at: now,
+    };
+    store.save_layout(&layout)?;
+    log::info!("Saved window layout '{}' with {} windows", layout.name, layout.windows.len());
+    Ok(layout)
+}
+
+/// Relaunch the apps a layout needs and move their windows back into place
+async fn restore_window_layout_internal(name: &str, state: &State<'_, AppState>) -> Result<CommandResult, String> {
+    let store = custom::CustomCommandsStore::new()?;
+    let layout = store
+        .find_layout(name)?
+        .ok_or_else(|| format!("There is no layout called \"{}\"", name.trim()))?;
+    let slots = layout.windows.clone();
+
+    let windows = with_window_manager(|manager| manager.list_windows()).await?;
+    let missing: Vec<window::LayoutSlot> = window::match_slots(&slots, &windows)
+        .iter()
+        .zip(&slots)
+        .filter(|(window, _)| window.is_none())
+        .map(|(_, slot)| slot.clone())
+        .collect();
+
+    let mut problems = Vec::new();
+    for slot in &missing {
+        // Start the recorded desktop entry itself; OpenApp's name lookup is
+        // only for slots without one or whose app was uninstalled
+        #[cfg(target_os = "linux")]
+        if let Some(id) = slot.desktop_id.clone() {
+            let entry = tokio::task::spawn_blocking(move || apps::find_by_id(&id))
+                .await
+                .map_err(|e| format!("App lookup failed: {}", e))?;
+            if let Some(entry) = entry {
+                if let Err(e) = apps::launch(&entry) {
+                    problems.push(e);
+                }
+                continue;
+            }
+        }
+        let open = ActionResult::action(ActionType::OpenApp, serde_json::json!({ "app": slot.launch }));
+        match Box::pin(execute_action_internal(&open, state)).await {
+            Ok(result) if result.success => {}
+            Ok(result) => problems.push(result.message),
+            Err(e) => problems.push(e),
+        }
+    }
+
+    if !missing.is_empty() {
+        let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(LAYOUT_LAUNCH_TIMEOUT_SECS);
+        while tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            let slots = slots.clone();
+            let all_open = with_window_manager(move |manager| {
+                let windows = manager.list_windows()?;
+                Ok(window::match_slots(&slots, &windows).iter().all(Option::is_some))
+            })
+            .await?;
+            if all_open {
+                break;
+            }
+        }
+    }
+
+    let (placed, errors) = with_window_manager(move |manager| {
+        let windows = manager.list_windows()?;
+        let mut placed = 0;
+        let mut errors = Vec::new();
+        for (slot, window) in slots.iter().zip(window::match_slots(&slots, &windows)) {
+            match window {
+                Some(window) => match manager.arrange(window, slot) {
+                    Ok(()) => placed += 1,
+                    Err(e) => errors.push(format!("{}: {}", slot.app, e)),
+                },
+                None => errors.push(format!("{} did not open a window", slot.app)),
+            }
+        }
+        Ok((placed, errors))
+    })
+    .await?;
+    problems.extend(errors);
+
+    let mut message = format!(
+        "Restored {} layout ({} of {} windows)",
+        layout.name,
+        placed,
+        layout.windows.len()
+    );
+    if !problems.is_empty() {
+        message.push_str(&format!(". Problems: {}", problems.join("; ")));
+    }
+    Ok(CommandResult {
+        success: placed > 0,
+        message,
+        output: None,
+    })
+}
+
+/// Drive the window manager through its keyboard shortcuts
+#[cfg(not(target_os = "linux"))]
+fn press_window_shortcut(window_action: &str) -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Key, Settings, Direction};
+    
     let mut enigo = Enigo::new(&Settings::default())
         .map_err(|e| format!("Failed to create enigo: {}", e))?;
     
-    let result = match window_action {
+    match window_action {
         "minimize" => {
             // Win+Down (minimize)
             #[cfg(windows)]
@@ -3436,15 +4357,6 @@ async fn execute_window_control(action: &ActionResult) -> Result<CommandResult,
             Ok(())
         }
         _ => Err(format!("Unknown window action: {}", window_action)),
-    };
-    
-    match result {
-        Ok(()) => Ok(CommandResult {
-            success: true,
-            message: format!("Window: {}", window_action),
-            output: None,
-        }),
-        Err(e) => Err(e),
     }
 }
 
@@ -3456,7 +4368,11 @@ async fn execute_clipboard_action(action: &ActionResult, state: &State<'_, AppSt
         let clipboard = state.clipboard.lock().await;
         clipboard.get_current()?
     };
+    transform_clipboard(action, content, state).await
+}
 
+/// Run a clipboard action on `content` and put the result on the clipboard
+async fn transform_clipboard(action: &ActionResult, content: String, state: &State<'_, AppState>) -> Result<CommandResult, String> {
     if content.trim().is_empty() {
         return Ok(CommandResult {
             success: false,
@@ -3473,15 +4389,42 @@ async fn execute_clipboard_action(action: &ActionResult, state: &State<'_, AppSt
         _ => return Err("Invalid clipboard action".to_string()),
     };
 
+    if simulation::intercept(
+        EffectKind::Clipboard,
+        format!("Replace the clipboard with its {} result", operation),
+        serde_json::json!({ "operation": operation, "chars": content.chars().count() }),
+    ) {
+        return Ok(simulated_result(format!("Clipboard {}: done", operation)));
+    }
+
+    let budget = {
+        let config = state.config.lock().await;
+        usage::budget_status(&config.usage_budget)
+    };
+    if !budget.allows(UsageFeature::Clipboard) {
+        return Ok(CommandResult {
+            success: false,
+            message: "Usage budget reached, clipboard AI actions are paused".to_string(),
+            output: None,
+        });
+    }
+
     // Process with LLM
     let client = GroqClient::new();
     let result = client.process_clipboard(&content, operation, &action.payload).await?;
 
-    // Set the result back to clipboard
-    {
+    // Set the result back to clipboard; undo restores what it replaced,
+    // which for a queued task may differ from what was transformed
+    let replaced = {
         let clipboard = state.clipboard.lock().await;
+        let replaced = clipboard.get_current().unwrap_or(content);
         clipboard.set_content(result.clone())?;
-    }
+        replaced
+    };
+    state.action_journal.lock().await.record(
+        format!("Clipboard {}", operation),
+        InverseOp::RestoreClipboard { content: replaced },
+    );
 
     Ok(CommandResult {
         success: true,
@@ -3492,10 +4435,33 @@ async fn execute_clipboard_action(action: &ActionResult, state: &State<'_, AppSt
 
 // ============ Integration Action Helpers ============
 
-async fn execute_spotify_action(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
-    let integrations = state.integrations.lock().await;
-    
-    let spotify_action = action.payload.get("action")
+/// Run an integration action whose backend blocks (D-Bus, network calls) on
+/// the blocking pool, holding the integrations lock only for the checks
+async fn execute_integration_blocking(
+    state: &State<'_, AppState>,
+    integration_name: &str,
+    action_id: String,
+    params: serde_json::Value,
+) -> Result<crate::integrations::IntegrationResult, String> {
+    let prepared = state
+        .integrations
+        .lock()
+        .await
+        .prepare(integration_name, &action_id, &params)?;
+    match prepared {
+        crate::integrations::PreparedAction::Simulated(result) => Ok(result),
+        crate::integrations::PreparedAction::Ready(integration) => {
+            tokio::task::spawn_blocking(move || integration.execute(&action_id, &params))
+                .await
+                .map_err(|e| format!("Integration task failed: {}", e))?
+        }
+    }
+}
+
+async fn execute_spotify_action(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
+    let integrations = state.integrations.lock().await;
+    
+    let spotify_action = action.payload.get("action")
         .and_then(|v| v.as_str())
         .unwrap_or("play_pause");
     
@@ -3511,6 +4477,23 @@ async fn execute_spotify_action(action: &ActionResult, state: &State<'_, AppStat
     }
 }
 
+async fn execute_media_action(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
+    let media_action = action.payload.get("action")
+        .and_then(|v| v.as_str())
+        .unwrap_or("play_pause");
+
+    let media_action_id = format!("media_{}", media_action);
+
+    match execute_integration_blocking(state, "media", media_action_id, action.payload.clone()).await {
+        Ok(result) => Ok(CommandResult {
+            success: result.success,
+            message: result.message,
+            output: result.data.map(|d| d.to_string()),
+        }),
+        Err(e) => Err(e),
+    }
+}
+
 async fn execute_discord_action(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
     let integrations = state.integrations.lock().await;
     
@@ -3531,24 +4514,345 @@ async fn execute_discord_action(action: &ActionResult, state: &State<'_, AppStat
 }
 
 async fn execute_system_action(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
-    let integrations = state.integrations.lock().await;
-    
     let system_action = action.payload.get("action")
         .and_then(|v| v.as_str())
         .unwrap_or("lock");
+
+    if system_action == "undo_last_action" {
+        return undo_last_action_internal(state).await;
+    }
+    if system_action == "repeat_command" {
+        return repeat_command_internal(&action.payload, state).await;
+    }
     
+    // Network and Bluetooth actions wait on D-Bus replies, so they run off
+    // the runtime and without holding the integrations
     let system_action_id = format!("system_{}", system_action);
-    
-    match integrations.execute("system", &system_action_id, &action.payload) {
-        Ok(result) => Ok(CommandResult {
-            success: result.success,
-            message: result.message,
-            output: result.data.map(|d| d.to_string()),
-        }),
+
+    match execute_integration_blocking(state, "system", system_action_id, action.payload.clone()).await {
+        Ok(mut result) => {
+            if result.success {
+                if let Some(data) = result.data.as_mut() {
+                    journal_system_action(system_action, &result.message, data, state).await;
+                }
+            }
+            Ok(CommandResult {
+                success: result.success,
+                message: result.message,
+                output: result.data.map(|d| d.to_string()),
+            })
+        }
         Err(e) => Err(e),
     }
 }
 
+/// Record the inverse of a system action from the data it reported.
+/// The per-file move and created-folder lists are taken out of the data so
+/// they aren't echoed back.
+async fn journal_system_action(
+    system_action: &str,
+    message: &str,
+    data: &mut serde_json::Value,
+    state: &State<'_, AppState>,
+) {
+    let inverse = match system_action {
+        "organize_downloads" => {
+            let mut take = |key: &str| {
+                data.as_object_mut()
+                    .and_then(|obj| obj.remove(key))
+                    .unwrap_or_default()
+            };
+            let moves: Vec<FileMove> = serde_json::from_value(take("moves")).unwrap_or_default();
+            let created_folders: Vec<String> =
+                serde_json::from_value(take("created_folders")).unwrap_or_default();
+            Some(InverseOp::RestoreFiles { moves, created_folders })
+        }
+        "trash_file" => data
+            .get("trashed_path")
+            .and_then(|v| v.as_str())
+            .map(|path| InverseOp::RestoreFromTrash { items: vec![path.to_string()] }),
+        "brightness" => data
+            .get("previous_brightness")
+            .and_then(|v| v.as_u64())
+            .filter(|_| data.get("brightness") != data.get("previous_brightness"))
+            .map(|level| InverseOp::SetBrightness { level: level as u32 }),
+        _ => None,
+    };
+
+    if let Some(inverse) = inverse {
+        state.action_journal.lock().await.record(message, inverse);
+    }
+}
+
+// ============ Undo ============
+
+/// Revert the most recent journaled action
+async fn undo_last_action_internal(state: &State<'_, AppState>) -> Result<CommandResult, String> {
+    if simulation::is_active() {
+        let last = state.action_journal.lock().await.entries().into_iter().next();
+        return Ok(match last {
+            Some(entry) => {
+                simulation::intercept(
+                    EffectKind::Undo,
+                    format!("Undo: {}", entry.description),
+                    serde_json::to_value(&entry.inverse).unwrap_or_default(),
+                );
+                simulated_result(format!("Undone: {}", entry.description))
+            }
+            None => CommandResult {
+                success: false,
+                message: "Nothing to undo".to_string(),
+                output: None,
+            },
+        });
+    }
+
+    let entry = state.action_journal.lock().await.pop_last();
+    let entry = match entry {
+        Some(entry) => entry,
+        None => {
+            return Ok(CommandResult {
+                success: false,
+                message: "Nothing to undo".to_string(),
+                output: None,
+            });
+        }
+    };
+
+    log::info!("Undoing action: {}", entry.description);
+
+    match replay_inverse(&entry.inverse, state).await {
+        Ok(detail) => Ok(CommandResult {
+            success: true,
+            message: format!("Undone: {}", entry.description),
+            output: detail,
+        }),
+        Err(e) => {
+            let message = format!("Couldn't undo \"{}\": {}", entry.description, e);
+            // Typed text can still be undone once its window is focused again,
+            // and the next undo must not skip to an older action meanwhile
+            if matches!(entry.inverse, InverseOp::Backspace { .. }) {
+                state.action_journal.lock().await.push_back(entry);
+            }
+            Ok(CommandResult {
+                success: false,
+                message,
+                output: None,
+            })
+        }
+    }
+}
+
+async fn replay_inverse(inverse: &InverseOp, state: &State<'_, AppState>) -> Result<Option<String>, String> {
+    match inverse {
+        InverseOp::RestoreFiles { moves, created_folders } => {
+            let moves = moves.clone();
+            let created_folders = created_folders.clone();
+            let (restored, errors) =
+                tokio::task::spawn_blocking(move || journal::restore_file_moves(&moves, &created_folders))
+                .await
+                .map_err(|e| format!("File restore failed: {}", e))?;
+            if restored == 0 && !errors.is_empty() {
+                return Err(errors.join("; "));
+            }
+            for error in &errors {
+                log::warn!("Undo file move: {}", error);
+            }
+            if errors.is_empty() {
+                Ok(Some(format!("Moved {} file(s) back", restored)))
+            } else {
+                Ok(Some(format!("Moved {} file(s) back, {} could not be restored", restored, errors.len())))
+            }
+        }
+        InverseOp::RestoreFromTrash { items } => restore_from_trash(items.clone()).await,
+        InverseOp::SetVolume { level } => {
+            set_volume_level(*level).await?;
+            Ok(None)
+        }
+        InverseOp::StepVolume { direction } => {
+            step_volume(direction).await;
+            Ok(None)
+        }
+        InverseOp::SetBrightness { level } => {
+            let integrations = state.integrations.lock().await;
+            let result = integrations.execute(
+                "system",
+                "system_brightness",
+                &serde_json::json!({ "level": level }),
+            )?;
+            if result.success {
+                Ok(None)
+            } else {
+                Err(result.message)
+            }
+        }
+        InverseOp::Backspace { count, window } => {
+            press_backspace(*count, window.as_deref()).await?;
+            Ok(None)
+        }
+        InverseOp::RestoreClipboard { content } => {
+            let clipboard = state.clipboard.lock().await;
+            clipboard.set_content(content.clone())?;
+            Ok(None)
+        }
+    }
+}
+
+/// Put trashed items back where they came from
+#[cfg(target_os = "linux")]
+async fn restore_from_trash(items: Vec<String>) -> Result<Option<String>, String> {
+    let restored = tokio::task::spawn_blocking(move || {
+        items
+            .iter()
+            .map(|item| crate::trash::restore(std::path::Path::new(item)).map(|item| item.original_path))
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|e| format!("Trash restore failed: {}", e))??;
+    Ok(Some(format!("Restored {}", restored.join(", "))))
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn restore_from_trash(_items: Vec<String>) -> Result<Option<String>, String> {
+    Err("Restoring from the trash is only supported on Linux".to_string())
+}
+
+// ============ Repeat ============
+
+fn is_repeat_request(action: &ActionResult) -> bool {
+    action.action_type == ActionType::SystemControl
+        && action.payload.get("action").and_then(|v| v.as_str()) == Some("repeat_command")
+}
+
+/// Load the stored action a repeat request points at: `id` for a specific
+/// history entry, otherwise the `n`-th most recent command (default 1)
+fn load_repeat_action(
+    payload: &serde_json::Value,
+    state: &State<'_, AppState>,
+) -> Result<(ActionResult, RouteSource), String> {
+    let history_guard = state.history.lock().map_err(|e| e.to_string())?;
+    let store = history_guard
+        .as_ref()
+        .ok_or_else(|| "History is not available".to_string())?;
+
+    let entry = match payload.get("id").and_then(|v| v.as_i64()) {
+        Some(id) => store.get(id)?,
+        None => {
+            let n = payload.get("n").and_then(|v| v.as_u64()).unwrap_or(1).max(1);
+            store.nth_command(n as usize)?
+        }
+    };
+    let entry = entry.ok_or_else(|| "There is no earlier command to repeat".to_string())?;
+    let mut action = entry
+        .action
+        .filter(history::is_repeatable)
+        .ok_or_else(|| format!("\"{}\" can't be repeated", entry.transcript))?;
+
+    log::info!("Repeating history entry {}: {:?}", entry.id, action.action_type);
+    upsert_action_payload_field(&mut action, "repeat_of", serde_json::json!(entry.id));
+    // Entries without a stored route are treated as the stricter LLM route
+    Ok((action, entry.route.unwrap_or(RouteSource::Llm)))
+}
+
+/// Re-execute a stored action, holding it for confirmation when required
+async fn repeat_command_internal(payload: &serde_json::Value, state: &State<'_, AppState>) -> Result<CommandResult, String> {
+    let (action, route) = match load_repeat_action(payload, state) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            return Ok(CommandResult {
+                success: false,
+                message: e,
+                output: None,
+            });
+        }
+    };
+
+    let active_app = state.current_context.lock().await.active_app.clone();
+    let decision = confirmation_required(state, &action, active_app.as_deref(), route).await;
+    if decision.required && !simulation::is_active() {
+        let summary = summarize_action(&action);
+        let pending_action = PendingAction::new(action, "repeat command".to_string(), decision);
+        let id = pending_action.id.clone();
+        {
+            let mut pending = state.pending_action.lock().await;
+            *pending = Some(pending_action);
+        }
+        return Ok(CommandResult {
+            success: true,
+            message: format!("Confirmation required: {}", summary),
+            output: Some(serde_json::json!({ "pending_action_id": id }).to_string()),
+        });
+    }
+
+    Box::pin(execute_action_internal(&action, state)).await
+}
+
+/// Delete typed text one character at a time, refusing when `window` (where
+/// it was typed) no longer has focus so nothing else gets deleted
+async fn press_backspace(count: usize, window: Option<&str>) -> Result<(), String> {
+    use enigo::{Enigo, Keyboard, Key, Settings, Direction};
+
+    if simulation::intercept(
+        EffectKind::KeyPress,
+        format!("Press backspace {} times", count),
+        serde_json::json!({ "count": count }),
+    ) {
+        return Ok(());
+    }
+
+    // Same focus delay as typing, so the keys land in the original window
+    tokio::time::sleep(tokio::time::Duration::from_millis(350)).await;
+
+    let Some(window) = window else {
+        return Err(
+            "It isn't known which window the text went to, so it can't be deleted safely. \
+             Undoing typed text needs X11, sway/i3 or Windows to tell which window has focus"
+                .to_string(),
+        );
+    };
+    if focused_window_id().await.as_deref() != Some(window) {
+        return Err("Switch back to the window the text was typed into, then undo again".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let mut enigo = Enigo::new(&Settings::default())
+            .map_err(|e| format!("Failed to create enigo: {}", e))?;
+        for _ in 0..count {
+            enigo.key(Key::Backspace, Direction::Click)
+                .map_err(|e| format!("Failed to press backspace: {}", e))?;
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Backspace task failed: {}", e))?
+}
+
+/// Identifies the window with keyboard focus, or None where that can't be
+/// told: on macOS and on Wayland compositors other than sway this is always
+/// None, so typed text can't be undone there
+async fn focused_window_id() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        tokio::task::spawn_blocking(|| {
+            let windows = crate::window::connect().ok()?.list_windows().ok()?;
+            windows.into_iter().find(|window| window.focused).map(|window| window.id)
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+    #[cfg(windows)]
+    {
+        let hwnd = unsafe { windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow() };
+        (hwnd.0 != 0).then(|| format!("{:x}", hwnd.0))
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        None
+    }
+}
+
 // ============ Custom Command Execution ============
 
 async fn execute_custom_command(action: &ActionResult, state: &State<'_, AppState>) -> Result<CommandResult, String> {
@@ -3580,67 +4884,18 @@ async fn execute_custom_command(action: &ActionResult, state: &State<'_, AppStat
     
     log::info!("Executing custom command: {} ({})", command.name, command.id);
     
-    // Execute each action step in sequence
-    let mut success_count = 0;
-    let total_steps = command.actions.len();
-    
-    for (i, step) in command.actions.iter().enumerate() {
-        log::info!("Step {}/{}: {} - {:?}", i + 1, total_steps, step.action_type, step.payload);
-        
-        // Apply delay before step (except for first step)
-        if step.delay_ms > 0 {
-            tokio::time::sleep(tokio::time::Duration::from_millis(step.delay_ms as u64)).await;
-        }
-        
-        // Map action_type string to ActionType enum and execute
-        let step_action_type = match step.action_type.as_str() {
-            "open_app" => ActionType::OpenApp,
-            "open_url" => ActionType::OpenUrl,
-            "web_search" => ActionType::WebSearch,
-            "run_command" => ActionType::RunCommand,
-            "type_text" => ActionType::TypeText,
-            "volume_control" => ActionType::VolumeControl,
-            "spotify_control" => ActionType::SpotifyControl,
-            "discord_control" => ActionType::DiscordControl,
-            "system_control" => ActionType::SystemControl,
-            _ => {
-                log::warn!("Unknown action type in custom command: {}", step.action_type);
-                continue;
-            }
-        };
-        
-        let step_result = ActionResult {
-            action_type: step_action_type,
-            payload: step.payload.clone(),
-            refined_text: step.payload.get("text").and_then(|v| v.as_str()).map(|s| s.to_string()),
-            response_text: None,
-            requires_confirmation: false,
-        };
-        
-        // Execute the step (recursively call execute_action_internal)
-        match Box::pin(execute_action_internal(&step_result, state)).await {
-            Ok(result) => {
-                if result.success {
-                    success_count += 1;
-                }
-                log::info!("Step {}/{} completed: {}", i + 1, total_steps, result.message);
-            }
-            Err(e) => {
-                log::warn!("Step {}/{} failed: {}", i + 1, total_steps, e);
-            }
-        }
-    }
+    // Execute the steps as a plan so later steps can use earlier outputs
+    let plan = plan::Plan::from_custom_steps(&command.actions);
+    let result = plan::execute_plan(&plan, state).await;
     
     // Record usage
     if let Err(e) = store.record_usage(&command.id) {
         log::warn!("Failed to record command usage: {}", e);
     }
     
-    Ok(CommandResult {
-        success: success_count > 0,
-        message: format!("Executed '{}': {}/{} steps completed", command.name, success_count, total_steps),
-        output: None,
-    })
+    let mut command_result = plan::plan_command_result(&format!("Executed '{}'", command.name), &result);
+    command_result.success = result.succeeded > 0;
+    Ok(command_result)
 }
 
 // ============ Conversation Commands ============
@@ -3720,6 +4975,308 @@ pub async fn set_integration_enabled(
     Ok(integrations.set_enabled(&name, enabled))
 }
 
+// ============ Connectivity Commands ============
+
+/// Probe the network if offline mode is due for a retry
+async fn refresh_connectivity(state: &State<'_, AppState>) {
+    let should_probe = {
+        let mut monitor = state.connectivity.lock().await;
+        let due = monitor.should_probe();
+        if due {
+            monitor.note_probe();
+        }
+        due
+    };
+
+    if should_probe && connectivity::probe().await {
+        state.connectivity.lock().await.mark_online();
+    }
+}
+
+/// Get current online/offline state
+#[tauri::command]
+pub async fn get_connectivity_status(
+    state: State<'_, AppState>,
+) -> Result<connectivity::ConnectivityStatus, String> {
+    let monitor = state.connectivity.lock().await;
+    Ok(monitor.status())
+}
+
+/// Get LLM tasks deferred while offline
+#[tauri::command]
+pub async fn get_offline_queue(
+    state: State<'_, AppState>,
+) -> Result<Vec<connectivity::OfflineTask>, String> {
+    let monitor = state.connectivity.lock().await;
+    Ok(monitor.queued())
+}
+
+/// Run a deferred task now that the network is back
+#[tauri::command]
+pub async fn retry_offline_task(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<CommandResult, String> {
+    let task = state
+        .connectivity
+        .lock()
+        .await
+        .take(&id)
+        .ok_or_else(|| format!("Queued task '{}' not found", id))?;
+
+    run_offline_task(&app, &state, task).await
+}
+
+/// Replay the tasks queued while offline that run by themselves, once
+/// connectivity has returned, and tell the frontend how each went and which
+/// tasks are waiting for the user
+pub async fn replay_offline_tasks(app: &tauri::AppHandle, tasks: Vec<connectivity::OfflineTask>) {
+    let state = app.state::<AppState>();
+    for task in tasks {
+        if !state.connectivity.lock().await.is_online() {
+            // Dropped again mid-replay; keep the rest for next time
+            state.connectivity.lock().await.requeue(task);
+            continue;
+        }
+        log::info!("Replaying offline task {} ({:?})", task.id, task.kind);
+        let (id, kind) = (task.id.clone(), task.kind);
+        let result = run_offline_task(app, &state, task).await;
+        let event = serde_json::json!({
+            "id": id,
+            "kind": kind,
+            "success": result.is_ok(),
+            "message": match &result {
+                Ok(result) => result.message.clone(),
+                Err(e) => e.clone(),
+            },
+        });
+        for label in ["assistant", "dashboard"] {
+            if let Some(window) = app.get_webview_window(label) {
+                let _ = window.emit("offline-task-replayed", &event);
+            }
+        }
+    }
+
+    let waiting = state.connectivity.lock().await.queued();
+    if !waiting.is_empty() {
+        for label in ["assistant", "dashboard"] {
+            if let Some(window) = app.get_webview_window(label) {
+                let _ = window.emit("offline-tasks-waiting", &waiting);
+            }
+        }
+    }
+}
+
+/// Run a deferred task, putting it back in the queue under the same id if it fails
+async fn run_offline_task(
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+    task: connectivity::OfflineTask,
+) -> Result<CommandResult, String> {
+    let result = match task.kind {
+        connectivity::OfflineTaskKind::Question => {
+            build_question_response_action(&task.text, &ConversationContext::default(), app, &state.answer_cancel)
+                .await
+                .map(|action| {
+                    let answer = action.response_text.unwrap_or_default();
+                    CommandResult {
+                        success: true,
+                        message: answer.clone(),
+                        output: Some(answer),
+                    }
+                })
+        }
+        connectivity::OfflineTaskKind::ClipboardTransform => match (task.action.as_ref(), task.clipboard.clone()) {
+            (Some(action), Some(content)) => transform_clipboard(action, content, state).await,
+            _ => Err("Queued clipboard task has no action".to_string()),
+        },
+        connectivity::OfflineTaskKind::VibeEnhancement => {
+            let (language_preferences, vibe_config) = {
+                let config = state.config.lock().await;
+                (
+                    normalized_language_preferences(&config.language_preferences),
+                    normalized_vibe_coding_config(&config.vibe_coding),
+                )
+            };
+            match enhance_vibe_coding_prompt(&task.text, &language_preferences, &vibe_config).await {
+                Ok(enhanced) => {
+                    let clipboard = state.clipboard.lock().await;
+                    clipboard.set_content(enhanced.clone())?;
+                    Ok(CommandResult {
+                        success: true,
+                        message: "Enhanced prompt copied to clipboard".to_string(),
+                        output: Some(enhanced),
+                    })
+                }
+                Err(e) => Err(e),
+            }
+        }
+    };
+
+    if let Err(ref e) = result {
+        let mut monitor = state.connectivity.lock().await;
+        if connectivity::is_network_error(e) {
+            monitor.mark_offline(e.clone());
+        }
+        // Keep the task so the user can try again later
+        monitor.requeue(task);
+    }
+
+    result
+}
+
+/// Drop all deferred tasks
+#[tauri::command]
+pub async fn clear_offline_queue(state: State<'_, AppState>) -> Result<(), String> {
+    let mut monitor = state.connectivity.lock().await;
+    monitor.clear_queue();
+    Ok(())
+}
+
+// ============ Dry Run ============
+
+/// What ListenOS would do with an utterance, without doing it
+#[derive(Debug, Clone, Serialize)]
+pub struct UtterancePreview {
+    pub text: String,
+    pub action: ActionResult,
+    pub summary: String,
+    pub requires_confirmation: bool,
+    /// Why the confirmation policy would or would not ask
+    pub confirmation_reason: String,
+    /// The router was unsure and would ask which interpretation was meant
+    pub would_clarify: bool,
+    /// Result the executors reported in simulation
+    pub result: Option<CommandResult>,
+    pub error: Option<String>,
+    pub effects: Vec<simulation::SimulatedEffect>,
+}
+
+/// Route `text` like a spoken utterance and simulate executing the result.
+/// Questions are not answered and nothing on the machine is changed.
+#[tauri::command]
+pub async fn preview_utterance(
+    state: State<'_, AppState>,
+    text: String,
+) -> Result<UtterancePreview, String> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Nothing to preview".to_string());
+    }
+
+    let context = state.current_context.lock().await.clone();
+    let (clarification_config, usage_budget) = {
+        let config = state.config.lock().await;
+        (
+            normalized_clarification_config(&config.clarification),
+            normalized_usage_budget(&config.usage_budget),
+        )
+    };
+    let budget = usage::budget_status(&usage_budget);
+    let offline = !state.connectivity.lock().await.is_online();
+
+    let conv_context = {
+        let conversation = state.conversation.lock().await;
+        build_conversation_context(&state, &context, &conversation).await
+    };
+
+    // Routing runs inside the simulation too, so asking the LLM neither
+    // records usage nor flips the connectivity state
+    let ((action, route, decision, outcome), effects) = simulation::run(async {
+        let (mut action, mut route) = if let Some(local_action) = cloud::detect_local_command(&text, &conv_context.aliases) {
+            (local_action, RouteSource::Local)
+        } else if should_handle_as_question(&text, &context) {
+            let question = ActionResult::action(ActionType::Respond, serde_json::json!({ "question": text }));
+            (question, RouteSource::Llm)
+        } else {
+            route_intent(&state, &text, &text, &context, &conv_context, offline, &budget).await
+        };
+
+        if should_use_local_command_fallback(&text, &context, &action) {
+            if let Some(local_action) = cloud::detect_local_command(&text, &conv_context.aliases) {
+                action = local_action;
+                route = RouteSource::Local;
+            }
+        }
+        if is_farewell_phrase(&text) && is_power_system_action(&action) {
+            action = ActionResult::action(
+                ActionType::NoAction,
+                serde_json::json!({
+                    "blocked_action": "power_control",
+                    "reason": "farewell_phrase"
+                }),
+            );
+        }
+
+        let decision = confirmation_required(&state, &action, context.active_app.as_deref(), route).await;
+        let outcome = execute_action_internal(&action, &state).await;
+        (action, route, decision, outcome)
+    })
+    .await;
+
+    let would_clarify = should_ask_for_clarification(&action, route, &clarification_config);
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    Ok(UtterancePreview {
+        text,
+        summary: summarize_action(&action),
+        action,
+        requires_confirmation: decision.required,
+        confirmation_reason: decision.explanation,
+        would_clarify,
+        result,
+        error,
+        effects,
+    })
+}
+
+// ============ Prompt Template Commands ============
+
+/// List every LLM prompt template and whether it is overridden
+#[tauri::command]
+pub async fn list_prompt_templates() -> Result<Vec<prompts::PromptTemplateInfo>, String> {
+    Ok(prompts::list())
+}
+
+/// Render a template with the current live context so overrides can be checked
+#[tauri::command]
+pub async fn preview_prompt_template(
+    state: State<'_, AppState>,
+    name: String,
+    sample_text: Option<String>,
+) -> Result<String, String> {
+    let sample = sample_text.unwrap_or_else(|| "Hello world".to_string());
+    let context = state.current_context.lock().await.clone();
+    let conv_context = {
+        let conversation = state.conversation.lock().await;
+        build_conversation_context(&state, &context, &conversation).await
+    };
+
+    let vars = cloud::router_prompt_vars(&context, &conv_context)
+        .text("history", conv_context.history.clone())
+        .text("content", sample)
+        .text("format", "bullet list")
+        .text("target_language", "Spanish");
+
+    prompts::render(&name, &vars)
+}
+
+/// Save a user override for a template after validating its variables
+#[tauri::command]
+pub async fn save_prompt_template(name: String, body: String) -> Result<(), String> {
+    prompts::save_override(&name, &body)
+}
+
+/// Remove a user override and go back to the built-in template
+#[tauri::command]
+pub async fn reset_prompt_template(name: String) -> Result<(), String> {
+    prompts::reset_override(&name)
+}
+
 // ============ Context Commands ============
 
 /// Set voice mode (dictation or command)
@@ -3844,6 +5401,11 @@ pub async fn set_config(
     config.trigger_hotkey = normalize_hotkey_string(&config.trigger_hotkey)?;
     config.language_preferences = normalized_language_preferences(&config.language_preferences);
     config.vibe_coding = normalized_vibe_coding_config(&config.vibe_coding);
+    config.clarification = normalized_clarification_config(&config.clarification);
+    config.usage_budget = normalized_usage_budget(&config.usage_budget);
+    config.command_sandbox = normalized_command_sandbox(&config.command_sandbox);
+    config.text_injection = normalized_text_injection(&config.text_injection);
+    policy::validate(&config.confirmation_policy)?;
 
     let mut current_config = state.config.lock().await;
     
@@ -3862,6 +5424,21 @@ pub async fn set_config(
     if let Err(err) = current_config.vibe_coding.save_to_disk() {
         log::warn!("Failed to persist vibe coding config: {}", err);
     }
+    if let Err(err) = current_config.clarification.save_to_disk() {
+        log::warn!("Failed to persist clarification config: {}", err);
+    }
+    if let Err(err) = current_config.usage_budget.save_to_disk() {
+        log::warn!("Failed to persist usage budget: {}", err);
+    }
+    if let Err(err) = current_config.confirmation_policy.save_to_disk() {
+        log::warn!("Failed to persist confirmation policy: {}", err);
+    }
+    if let Err(err) = current_config.command_sandbox.save_to_disk() {
+        log::warn!("Failed to persist command sandbox: {}", err);
+    }
+    if let Err(err) = current_config.text_injection.save_to_disk() {
+        log::warn!("Failed to persist text injection config: {}", err);
+    }
     Ok(true)
 }
 
@@ -3936,6 +5513,178 @@ pub async fn set_vibe_coding_config(
     Ok(normalized)
 }
 
+fn normalized_clarification_config(config: &ClarificationConfig) -> ClarificationConfig {
+    let mut normalized = config.clone();
+    if !normalized.confidence_threshold.is_finite() {
+        normalized.confidence_threshold = ClarificationConfig::default().confidence_threshold;
+    }
+    normalized.confidence_threshold = normalized.confidence_threshold.clamp(0.0, 1.0);
+    normalized.answer_window_secs = normalized.answer_window_secs.clamp(5, 600);
+    normalized
+}
+
+#[tauri::command]
+pub async fn get_clarification_config(
+    state: State<'_, AppState>,
+) -> Result<ClarificationConfig, String> {
+    let config = state.config.lock().await;
+    Ok(normalized_clarification_config(&config.clarification))
+}
+
+#[tauri::command]
+pub async fn set_clarification_config(
+    state: State<'_, AppState>,
+    config: ClarificationConfig,
+) -> Result<ClarificationConfig, String> {
+    let normalized = normalized_clarification_config(&config);
+
+    let mut app_config = state.config.lock().await;
+    app_config.clarification = normalized.clone();
+    if let Err(err) = app_config.clarification.save_to_disk() {
+        log::warn!("Failed to persist clarification config: {}", err);
+    }
+
+    Ok(normalized)
+}
+
+fn normalized_usage_budget(config: &UsageBudgetConfig) -> UsageBudgetConfig {
+    let positive = |limit: Option<f64>| limit.filter(|v| v.is_finite() && *v >= 0.0);
+    UsageBudgetConfig {
+        enabled: config.enabled,
+        daily_limit_usd: positive(config.daily_limit_usd),
+        monthly_limit_usd: positive(config.monthly_limit_usd),
+    }
+}
+
+#[tauri::command]
+pub async fn get_usage_budget(state: State<'_, AppState>) -> Result<UsageBudgetConfig, String> {
+    let config = state.config.lock().await;
+    Ok(normalized_usage_budget(&config.usage_budget))
+}
+
+#[tauri::command]
+pub async fn set_usage_budget(
+    state: State<'_, AppState>,
+    config: UsageBudgetConfig,
+) -> Result<UsageBudgetConfig, String> {
+    let normalized = normalized_usage_budget(&config);
+
+    let mut app_config = state.config.lock().await;
+    app_config.usage_budget = normalized.clone();
+    if let Err(err) = app_config.usage_budget.save_to_disk() {
+        log::warn!("Failed to persist usage budget: {}", err);
+    }
+
+    Ok(normalized)
+}
+
+fn normalized_command_sandbox(config: &CommandSandboxConfig) -> CommandSandboxConfig {
+    CommandSandboxConfig {
+        allowlist: config
+            .allowlist
+            .iter()
+            .map(|entry| entry.trim().to_string())
+            .filter(|entry| !entry.is_empty())
+            .collect(),
+        allow_shell: config.allow_shell,
+        timeout_secs: config.timeout_secs.clamp(1, 600),
+        max_output_bytes: config.max_output_bytes.clamp(1024, 8 * 1024 * 1024),
+        working_dir: config
+            .working_dir
+            .as_ref()
+            .map(|dir| dir.trim().to_string())
+            .filter(|dir| !dir.is_empty()),
+        env_passthrough: config.env_passthrough.clone(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_command_sandbox(state: State<'_, AppState>) -> Result<CommandSandboxConfig, String> {
+    let config = state.config.lock().await;
+    Ok(normalized_command_sandbox(&config.command_sandbox))
+}
+
+#[tauri::command]
+pub async fn set_command_sandbox(
+    state: State<'_, AppState>,
+    config: CommandSandboxConfig,
+) -> Result<CommandSandboxConfig, String> {
+    let normalized = normalized_command_sandbox(&config);
+
+    let mut app_config = state.config.lock().await;
+    app_config.command_sandbox = normalized.clone();
+    if let Err(err) = app_config.command_sandbox.save_to_disk() {
+        log::warn!("Failed to persist command sandbox: {}", err);
+    }
+
+    Ok(normalized)
+}
+
+fn normalized_text_injection(config: &TextInjectionConfig) -> TextInjectionConfig {
+    TextInjectionConfig {
+        backends: config.backends.clone(),
+        app_rules: config
+            .app_rules
+            .iter()
+            .filter(|rule| !rule.app.trim().is_empty())
+            .cloned()
+            .collect(),
+        chunk_size: config.chunk_size.clamp(1, 512),
+        chunk_delay_ms: config.chunk_delay_ms.min(1000),
+        exclude_from_clipboard_history: config.exclude_from_clipboard_history,
+    }
+}
+
+#[tauri::command]
+pub async fn get_text_injection(state: State<'_, AppState>) -> Result<TextInjectionConfig, String> {
+    let config = state.config.lock().await;
+    Ok(normalized_text_injection(&config.text_injection))
+}
+
+#[tauri::command]
+pub async fn set_text_injection(
+    state: State<'_, AppState>,
+    config: TextInjectionConfig,
+) -> Result<TextInjectionConfig, String> {
+    let normalized = normalized_text_injection(&config);
+
+    let mut app_config = state.config.lock().await;
+    app_config.text_injection = normalized.clone();
+    if let Err(err) = app_config.text_injection.save_to_disk() {
+        log::warn!("Failed to persist text injection config: {}", err);
+    }
+
+    Ok(normalized)
+}
+
+#[tauri::command]
+pub async fn get_confirmation_policy(state: State<'_, AppState>) -> Result<ConfirmationPolicy, String> {
+    let config = state.config.lock().await;
+    Ok(config.confirmation_policy.clone())
+}
+
+/// Replace the user's confirmation rules. Rules remembered for this session
+/// are forgotten so the new policy applies right away.
+#[tauri::command]
+pub async fn set_confirmation_policy(
+    state: State<'_, AppState>,
+    policy: ConfirmationPolicy,
+) -> Result<ConfirmationPolicy, String> {
+    policy::validate(&policy)?;
+    policy.save_to_disk()?;
+
+    state.config.lock().await.confirmation_policy = policy.clone();
+    state.confirmed_rules.lock().await.clear();
+    Ok(policy)
+}
+
+/// Usage and estimated cost for today and this month, plus budget state
+#[tauri::command]
+pub async fn get_usage_summary(state: State<'_, AppState>) -> Result<usage::UsageSummary, String> {
+    let budget = state.config.lock().await.usage_budget.clone();
+    usage::summary(&budget)
+}
+
 fn sanitize_groq_api_key(raw: &str) -> String {
     let cleaned = raw.trim().to_string();
     if cleaned.is_empty() || cleaned.eq_ignore_ascii_case("replace_with_groq_api_key") {
@@ -3963,6 +5712,47 @@ pub async fn set_local_api_settings(
     Ok(settings)
 }
 
+// ============ Aliases ============
+
+/// Get the app, website and search-engine aliases
+#[tauri::command]
+pub async fn get_aliases(state: State<'_, AppState>) -> Result<AliasConfig, String> {
+    let aliases = AliasConfig::load()?.unwrap_or_default();
+    *state.aliases.lock().await = aliases.clone();
+    Ok(aliases)
+}
+
+/// Replace all aliases
+#[tauri::command]
+pub async fn set_aliases(state: State<'_, AppState>, aliases: AliasConfig) -> Result<AliasConfig, String> {
+    aliases.validate()?;
+    // Never overwrite a file we could not read; the edit was based on defaults
+    AliasConfig::load()?;
+    aliases.save_to_disk()?;
+    *state.aliases.lock().await = aliases.clone();
+    Ok(aliases)
+}
+
+/// Export aliases as JSON
+#[tauri::command]
+pub async fn export_aliases() -> Result<String, String> {
+    let aliases = AliasConfig::load()?.unwrap_or_default();
+    serde_json::to_string_pretty(&aliases).map_err(|e| format!("Failed to export aliases: {}", e))
+}
+
+/// Import aliases from JSON, replacing entries with the same phrase or keyword
+#[tauri::command]
+pub async fn import_aliases(state: State<'_, AppState>, json: String) -> Result<usize, String> {
+    let imported: AliasConfig =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid aliases JSON: {}", e))?;
+    let mut aliases = AliasConfig::load()?.unwrap_or_default();
+    let count = aliases.merge(imported);
+    aliases.validate()?;
+    aliases.save_to_disk()?;
+    *state.aliases.lock().await = aliases;
+    Ok(count)
+}
+
 // ============ Custom Commands ============
 
 /// Get all custom commands
@@ -4012,3 +5802,117 @@ pub async fn import_custom_commands(json: String) -> Result<usize, String> {
     let store = custom::CustomCommandsStore::new()?;
     store.import_commands(&json)
 }
+
+// ============ Window Layouts ============
+
+/// Get all saved window layouts
+#[tauri::command]
+pub async fn get_window_layouts() -> Result<Vec<custom::WindowLayout>, String> {
+    let store = custom::CustomCommandsStore::new()?;
+    store.get_layouts()
+}
+
+/// Save the open windows as a named layout
+#[tauri::command]
+pub async fn save_window_layout(name: String) -> Result<custom::WindowLayout, String> {
+    save_window_layout_internal(&name).await
+}
+
+/// Restore a saved layout, relaunching apps that are not running
+#[tauri::command]
+pub async fn restore_window_layout(state: State<'_, AppState>, name: String) -> Result<CommandResult, String> {
+    let action = ActionResult::action(
+        ActionType::WindowControl,
+        serde_json::json!({ "action": "restore_layout", "name": name }),
+    );
+    execute_window_control(&action, &state).await
+}
+
+/// Delete a saved window layout
+#[tauri::command]
+pub async fn delete_window_layout(id: String) -> Result<(), String> {
+    let store = custom::CustomCommandsStore::new()?;
+    store.delete_layout(&id)
+}
+
+// ============ Installed Apps ============
+
+/// Apps OpenApp can launch by name, from the XDG desktop entries
+#[cfg(target_os = "linux")]
+#[tauri::command]
+pub async fn list_installed_apps() -> Result<Vec<apps::DesktopEntry>, String> {
+    tokio::task::spawn_blocking(apps::installed_apps)
+        .await
+        .map_err(|e| format!("App lookup failed: {}", e))
+}
+
+/// Other platforms resolve app names when launching, without an index
+#[cfg(not(target_os = "linux"))]
+#[tauri::command]
+pub async fn list_installed_apps() -> Result<Vec<serde_json::Value>, String> {
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_action() -> PendingAction {
+        let action = ActionResult::action(ActionType::SystemControl, serde_json::json!({ "action": "shutdown" }));
+        PendingAction::new(action, "shut down".to_string(), policy::ConfirmationDecision::new(true, "power actions always ask"))
+    }
+
+    #[test]
+    fn confirms_only_the_current_pending_action() {
+        let action = pending_action();
+        let id = action.id.clone();
+        let mut pending = Some(action);
+
+        assert!(take_pending_if_current(&mut pending, "an-older-id").is_err());
+        assert!(pending.is_some(), "a mismatched id must leave the action pending");
+
+        assert_eq!(take_pending_if_current(&mut pending, &id).map(|taken| taken.id), Ok(id.clone()));
+        assert!(pending.is_none());
+        assert!(take_pending_if_current(&mut pending, &id).is_err());
+    }
+
+    #[test]
+    fn clarifies_only_unsure_llm_routes() {
+        let config = ClarificationConfig::default();
+        let unsure = ActionResult::action(ActionType::OpenApp, serde_json::json!({ "app": "slack" }))
+            .with_confidence(0.4)
+            .with_alternative(cloud::ActionCandidate {
+                action_type: ActionType::TypeText,
+                payload: serde_json::json!({}),
+                refined_text: Some("open slack".to_string()),
+                confidence: 0.3,
+            });
+        assert!(should_ask_for_clarification(&unsure, RouteSource::Llm, &config));
+        assert!(!should_ask_for_clarification(&unsure, RouteSource::Local, &config));
+
+        let local = cloud::detect_local_command("open slack", &crate::config::AliasConfig::default()).unwrap();
+        assert_eq!(local.confidence, cloud::LOCAL_ROUTER_CONFIDENCE);
+        assert!(!should_ask_for_clarification(&local, RouteSource::Local, &config));
+    }
+
+    #[test]
+    fn expired_pending_actions_are_rejected() {
+        let mut action = pending_action();
+        assert!(!action.is_expired());
+        action.expires_at = chrono::Utc::now() - chrono::Duration::seconds(1);
+        assert!(action.is_expired());
+
+        let created = pending_action();
+        let created_at = chrono::DateTime::parse_from_rfc3339(&created.created_at).unwrap();
+        let lifetime = created.expires_at - created_at.with_timezone(&chrono::Utc);
+        assert_eq!(lifetime.num_seconds(), PENDING_ACTION_TIMEOUT_SECS);
+
+        let id = action.id.clone();
+        let mut pending = Some(action);
+        assert_eq!(
+            take_pending_if_current(&mut pending, &id).map(|taken| taken.id),
+            Err("The pending action expired; say the command again".to_string())
+        );
+        assert!(pending.is_none());
+    }
+}
@@ -0,0 +1,421 @@
+//! Multi-step plan executor
+//!
+//! Runs `multi_step` actions and custom commands step by step. A step can use
+//! the result of an earlier one through `{{ref.field}}` placeholders in its
+//! payload or text, where `ref` is `prev`, a step id, or a 1-based step
+//! number, and `field` is `message`, `success`, `output` or a path into the
+//! output such as `output.path`.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use super::{custom::ActionStep, execute_action_internal, CommandResult};
+use crate::cloud::{ActionResult, ActionType};
+use crate::AppState;
+
+/// Per-step timeout when none is given
+const DEFAULT_STEP_TIMEOUT_MS: u64 = 30_000;
+
+/// Pause between steps of an LLM-generated plan
+const DEFAULT_STEP_DELAY_MS: u64 = 500;
+
+/// What to do when a step fails or times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Skip the remaining steps
+    Stop,
+    /// Carry on with the next step
+    Continue,
+}
+
+impl Default for FailurePolicy {
+    fn default() -> Self {
+        Self::Continue
+    }
+}
+
+/// One step of a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Name later steps can reference, e.g. `{{shot.output.path}}`
+    #[serde(default)]
+    pub id: Option<String>,
+    pub action: String,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    #[serde(default)]
+    pub refined_text: Option<String>,
+    /// Checked at await points only: a step doing synchronous work on the
+    /// runtime thread overruns it, and work already handed to the blocking
+    /// pool keeps running after the step is reported as timed out
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Pause before this step runs
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    /// Overrides the plan-wide policy for this step
+    #[serde(default)]
+    pub on_failure: Option<FailurePolicy>,
+}
+
+/// A sequence of steps and how to handle failures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+}
+
+impl Plan {
+    /// Parse a `multi_step` payload: `{"steps": [...], "on_failure": "stop"}`
+    pub fn from_payload(payload: &serde_json::Value) -> Result<Self, String> {
+        let mut plan: Plan = serde_json::from_value(payload.clone())
+            .map_err(|e| format!("Invalid multi-step plan: {}", e))?;
+        if plan.steps.is_empty() {
+            return Err("Multi-step plan has no steps".to_string());
+        }
+        for step in plan.steps.iter_mut().skip(1) {
+            step.delay_ms.get_or_insert(DEFAULT_STEP_DELAY_MS);
+        }
+        Ok(plan)
+    }
+
+    /// Build a plan from a custom command's stored steps
+    pub fn from_custom_steps(steps: &[ActionStep]) -> Self {
+        Self {
+            steps: steps
+                .iter()
+                .map(|step| PlanStep {
+                    id: step.output_key.clone(),
+                    action: step.action_type.clone(),
+                    payload: step.payload.clone(),
+                    refined_text: step
+                        .payload
+                        .get("text")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    timeout_ms: step.timeout_ms,
+                    delay_ms: Some(step.delay_ms as u64),
+                    on_failure: step.on_failure,
+                })
+                .collect(),
+            on_failure: FailurePolicy::Continue,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    TimedOut,
+    /// Not run because an earlier step failed with the stop policy
+    Skipped,
+}
+
+/// Outcome of one step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepResult {
+    /// 1-based step number
+    pub step: usize,
+    pub id: Option<String>,
+    pub action: String,
+    pub status: StepStatus,
+    pub message: String,
+    /// Step output, parsed as JSON when possible
+    pub output: Option<serde_json::Value>,
+    pub duration_ms: u64,
+}
+
+impl StepResult {
+    fn reference_value(&self) -> serde_json::Value {
+        serde_json::json!({
+            "success": self.status == StepStatus::Succeeded,
+            "message": self.message,
+            "output": self.output,
+        })
+    }
+}
+
+/// Outcome of a whole plan, returned as JSON in `CommandResult.output`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanResult {
+    pub steps: Vec<StepResult>,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub stopped_early: bool,
+}
+
+/// Find the step a placeholder reference points at
+fn find_step<'a>(reference: &str, results: &'a [StepResult]) -> Option<&'a StepResult> {
+    if reference == "prev" {
+        return results.last();
+    }
+    if let Ok(number) = reference.parse::<usize>() {
+        return results.iter().find(|r| r.step == number);
+    }
+    results.iter().find(|r| r.id.as_deref() == Some(reference))
+}
+
+/// Resolve `ref.field.path` against earlier results
+fn lookup(expr: &str, results: &[StepResult]) -> Result<serde_json::Value, String> {
+    let mut parts = expr.trim().split('.');
+    let reference = parts.next().unwrap_or_default();
+    let step = find_step(reference, results)
+        .ok_or_else(|| format!("No earlier step '{}' for {{{{{}}}}}", reference, expr))?;
+
+    let mut value = step.reference_value();
+    for part in parts {
+        value = match value {
+            serde_json::Value::Object(ref map) => map.get(part).cloned(),
+            serde_json::Value::Array(ref items) => part.parse::<usize>().ok().and_then(|i| items.get(i).cloned()),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Step '{}' has no field '{}'", reference, expr))?;
+    }
+    Ok(value)
+}
+
+fn value_as_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Substitute placeholders in a string. A string that is exactly one
+/// placeholder takes the referenced value as-is, keeping numbers as numbers.
+fn resolve_str(text: &str, results: &[StepResult]) -> Result<serde_json::Value, String> {
+    let trimmed = text.trim();
+    if trimmed.starts_with("{{") && trimmed.ends_with("}}") && trimmed.matches("{{").count() == 1 {
+        return lookup(&trimmed[2..trimmed.len() - 2], results);
+    }
+
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        out.push_str(&rest[..start]);
+        out.push_str(&value_as_text(&lookup(&rest[start + 2..start + end], results)?));
+        rest = &rest[start + end + 2..];
+    }
+    out.push_str(rest);
+    Ok(serde_json::Value::String(out))
+}
+
+/// Substitute placeholders anywhere in a payload
+pub fn resolve_references(
+    value: &serde_json::Value,
+    results: &[StepResult],
+) -> Result<serde_json::Value, String> {
+    Ok(match value {
+        serde_json::Value::String(s) if s.contains("{{") => resolve_str(s, results)?,
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .iter()
+                .map(|v| resolve_references(v, results))
+                .collect::<Result<_, _>>()?,
+        ),
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), resolve_references(v, results)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+fn step_action_type(name: &str) -> Result<ActionType, String> {
+    match name {
+        "open_app" => Ok(ActionType::OpenApp),
+        "open_url" => Ok(ActionType::OpenUrl),
+        "web_search" => Ok(ActionType::WebSearch),
+        "run_command" => Ok(ActionType::RunCommand),
+        "type_text" => Ok(ActionType::TypeText),
+        "volume_control" => Ok(ActionType::VolumeControl),
+        "spotify_control" => Ok(ActionType::SpotifyControl),
+        "media_control" => Ok(ActionType::MediaControl),
+        "discord_control" => Ok(ActionType::DiscordControl),
+        "system_control" => Ok(ActionType::SystemControl),
+        "keyboard_shortcut" => Ok(ActionType::KeyboardShortcut),
+        "window_control" => Ok(ActionType::WindowControl),
+        "clipboard_format" => Ok(ActionType::ClipboardFormat),
+        "clipboard_translate" => Ok(ActionType::ClipboardTranslate),
+        "clipboard_summarize" => Ok(ActionType::ClipboardSummarize),
+        "clipboard_clean" => Ok(ActionType::ClipboardClean),
+        other => Err(format!("Unknown step action '{}'", other)),
+    }
+}
+
+/// Build the concrete action for a step once earlier outputs are known
+fn prepare_step(step: &PlanStep, results: &[StepResult]) -> Result<ActionResult, String> {
+    let action_type = step_action_type(&step.action)?;
+    let payload = resolve_references(&step.payload, results)?;
+    let refined_text = match &step.refined_text {
+        Some(text) => Some(value_as_text(&resolve_str(text, results)?)),
+        None => payload.get("text").and_then(|v| v.as_str()).map(|s| s.to_string()),
+    };
+
+    Ok(ActionResult {
+        action_type,
+        payload,
+        refined_text,
+        response_text: None,
+        requires_confirmation: false,
+        confidence: 1.0,
+        alternatives: Vec::new(),
+    })
+}
+
+fn parse_output(output: Option<String>) -> Option<serde_json::Value> {
+    output.map(|raw| serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw)))
+}
+
+/// Run every step in order, threading outputs into later steps
+pub async fn execute_plan(plan: &Plan, state: &State<'_, AppState>) -> PlanResult {
+    let mut results: Vec<StepResult> = Vec::with_capacity(plan.steps.len());
+    let mut stop = false;
+
+    for (i, step) in plan.steps.iter().enumerate() {
+        let number = i + 1;
+        let mut result = StepResult {
+            step: number,
+            id: step.id.clone(),
+            action: step.action.clone(),
+            status: StepStatus::Skipped,
+            message: "Skipped after an earlier failure".to_string(),
+            output: None,
+            duration_ms: 0,
+        };
+
+        if stop {
+            results.push(result);
+            continue;
+        }
+
+        if let Some(delay) = step.delay_ms.filter(|d| *d > 0) {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+
+        let started = Instant::now();
+        let timeout = Duration::from_millis(step.timeout_ms.unwrap_or(DEFAULT_STEP_TIMEOUT_MS));
+        log::info!("Plan step {}/{}: {}", number, plan.steps.len(), step.action);
+
+        match prepare_step(step, &results) {
+            Err(e) => {
+                result.status = StepStatus::Failed;
+                result.message = e;
+            }
+            Ok(action) => {
+                // The timeout can't preempt blocking calls; see `PlanStep::timeout_ms`
+                match tokio::time::timeout(timeout, Box::pin(execute_action_internal(&action, state))).await {
+                    Ok(Ok(command)) => {
+                        result.status = if command.success {
+                            StepStatus::Succeeded
+                        } else {
+                            StepStatus::Failed
+                        };
+                        result.message = command.message;
+                        result.output = parse_output(command.output);
+                    }
+                    Ok(Err(e)) => {
+                        result.status = StepStatus::Failed;
+                        result.message = e;
+                    }
+                    Err(_) => {
+                        result.status = StepStatus::TimedOut;
+                        result.message = format!("Timed out after {} ms", timeout.as_millis());
+                    }
+                }
+            }
+        }
+        result.duration_ms = started.elapsed().as_millis() as u64;
+
+        if result.status != StepStatus::Succeeded {
+            log::warn!("Plan step {} failed: {}", number, result.message);
+            stop = step.on_failure.unwrap_or(plan.on_failure) == FailurePolicy::Stop;
+        }
+        results.push(result);
+    }
+
+    let succeeded = results.iter().filter(|r| r.status == StepStatus::Succeeded).count();
+    let failed = results
+        .iter()
+        .filter(|r| matches!(r.status, StepStatus::Failed | StepStatus::TimedOut))
+        .count();
+
+    PlanResult {
+        steps: results,
+        succeeded,
+        failed,
+        stopped_early: stop,
+    }
+}
+
+/// Summarize a plan run as a command result with per-step detail in `output`
+pub fn plan_command_result(label: &str, result: &PlanResult) -> CommandResult {
+    let total = result.steps.len();
+    let mut message = format!("{}: {}/{} steps completed", label, result.succeeded, total);
+    let errors: Vec<String> = result
+        .steps
+        .iter()
+        .filter(|r| matches!(r.status, StepStatus::Failed | StepStatus::TimedOut))
+        .map(|r| format!("step {} {}", r.step, r.message))
+        .collect();
+    if !errors.is_empty() {
+        message.push_str(&format!(". {}", errors.join("; ")));
+    }
+
+    CommandResult {
+        success: result.failed == 0,
+        message,
+        output: serde_json::to_string(result).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn done(step: usize, id: Option<&str>, output: serde_json::Value) -> StepResult {
+        StepResult {
+            step,
+            id: id.map(|s| s.to_string()),
+            action: "system_control".to_string(),
+            status: StepStatus::Succeeded,
+            message: "ok".to_string(),
+            output: Some(output),
+            duration_ms: 0,
+        }
+    }
+
+    #[test]
+    fn resolves_references_to_earlier_outputs() {
+        let results = vec![
+            done(1, Some("shot"), serde_json::json!({ "path": "/tmp/shot.png" })),
+            done(2, None, serde_json::json!({ "top_level_total": 42 })),
+        ];
+
+        let payload = serde_json::json!({
+            "path": "{{shot.output.path}}",
+            "count": "{{prev.output.top_level_total}}",
+            "text": "There are {{2.output.top_level_total}} files",
+        });
+        let resolved = resolve_references(&payload, &results).unwrap();
+
+        assert_eq!(resolved["path"], "/tmp/shot.png");
+        assert_eq!(resolved["count"], 42);
+        assert_eq!(resolved["text"], "There are 42 files");
+    }
+
+    #[test]
+    fn unknown_references_fail_the_step() {
+        let results = vec![done(1, None, serde_json::json!({}))];
+        assert!(resolve_references(&serde_json::json!("{{shot.output.path}}"), &results).is_err());
+        assert!(resolve_references(&serde_json::json!("{{1.output.path}}"), &results).is_err());
+    }
+}
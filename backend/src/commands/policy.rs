@@ -0,0 +1,414 @@
+//! Confirmation policy evaluation
+//!
+//! Decides whether an action waits for explicit confirmation. Admin rules are
+//! checked first, then the user's rules, then the built-in defaults. Every
+//! decision carries a short explanation for the confirmation prompt.
+//!
+//! Also holds the spoken grammar ("yes, do it", "cancel") used to answer a
+//! pending confirmation by voice.
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::{action_requires_confirmation, is_power_system_action};
+use crate::cloud::{ActionResult, ActionType};
+use crate::config::{ConfirmationMode, ConfirmationPolicy, ConfirmationRule, RouteSource};
+
+/// Facts about the current utterance that rules can test
+#[derive(Debug, Clone)]
+pub struct PolicyContext<'a> {
+    pub active_app: Option<&'a str>,
+    /// Local hour, 0-23
+    pub hour: u32,
+    pub route: RouteSource,
+    /// `LISTENOS_REQUIRE_CONFIRMATION` turns on the built-in defaults
+    pub confirmations_enabled: bool,
+}
+
+/// Outcome of evaluating the policy for one action
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfirmationDecision {
+    pub required: bool,
+    pub explanation: String,
+    /// Set when a remember-for-session rule asked; confirming stores it
+    pub remember_key: Option<String>,
+    /// Whether an admin or user rule decided, rather than the built-in defaults
+    pub from_rule: bool,
+}
+
+impl ConfirmationDecision {
+    pub fn new(required: bool, explanation: impl Into<String>) -> Self {
+        Self {
+            required,
+            explanation: explanation.into(),
+            remember_key: None,
+            from_rule: false,
+        }
+    }
+}
+
+/// Check a policy for mistakes before it is saved
+pub fn validate(policy: &ConfirmationPolicy) -> Result<(), String> {
+    for (index, rule) in policy.rules.iter().enumerate() {
+        let label = rule_label(rule, index);
+        if let Some(action_type) = rule.action_type.as_deref() {
+            ActionType::from_name_ignore_case(action_type)
+                .map_err(|e| format!("{}: {}", label, e))?;
+        }
+        for hour in [rule.from_hour, rule.to_hour].into_iter().flatten() {
+            if hour > 23 {
+                return Err(format!("{}: hour {} is not between 0 and 23", label, hour));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decide whether `action` needs confirmation
+pub fn evaluate(
+    admin: Option<&ConfirmationPolicy>,
+    user: &ConfirmationPolicy,
+    action: &ActionResult,
+    ctx: &PolicyContext,
+    remembered: &HashSet<String>,
+) -> ConfirmationDecision {
+    if let Some((index, rule)) = admin.and_then(|policy| first_match(policy, action, ctx)) {
+        return decide(rule, format!("admin {}", rule_label(rule, index)), remember_key("admin", rule), remembered);
+    }
+
+    if let Some((index, rule)) = first_match(user, action, ctx) {
+        let label = rule_label(rule, index);
+        // Only admins can turn off the prompt for power actions
+        if is_power_system_action(action) && rule.mode != ConfirmationMode::Always {
+            return ConfirmationDecision::new(
+                true,
+                format!("power actions always ask; {} can't turn that off", label),
+            );
+        }
+        return decide(rule, label, remember_key("user", rule), remembered);
+    }
+
+    builtin(action, ctx.confirmations_enabled)
+}
+
+/// Session key for a rule, from what it matches rather than its position or
+/// name, so editing the policy never carries a confirmation over to a
+/// different rule
+fn remember_key(scope: &str, rule: &ConfirmationRule) -> String {
+    format!("{}:{}", scope, serde_json::to_string(rule).unwrap_or_default())
+}
+
+fn decide(rule: &ConfirmationRule, label: String, key: String, remembered: &HashSet<String>) -> ConfirmationDecision {
+    let decision = match rule.mode {
+        ConfirmationMode::Always => ConfirmationDecision::new(true, format!("{} always asks", label)),
+        ConfirmationMode::Never => ConfirmationDecision::new(false, format!("{} allows it without asking", label)),
+        ConfirmationMode::RememberForSession => {
+            if remembered.contains(&key) {
+                ConfirmationDecision::new(false, format!("confirmed earlier this session ({})", label))
+            } else {
+                ConfirmationDecision {
+                    remember_key: Some(key),
+                    ..ConfirmationDecision::new(true, format!("{} asks once per session", label))
+                }
+            }
+        }
+    };
+    ConfirmationDecision { from_rule: true, ..decision }
+}
+
+/// Behaviour when no rule matches
+fn builtin(action: &ActionResult, confirmations_enabled: bool) -> ConfirmationDecision {
+    if is_power_system_action(action) {
+        return ConfirmationDecision::new(true, "power actions always ask");
+    }
+    if !confirmations_enabled {
+        return ConfirmationDecision::new(false, "no rule requires confirmation");
+    }
+    if action.requires_confirmation {
+        ConfirmationDecision::new(true, "the assistant flagged this action as risky")
+    } else if action_requires_confirmation(action) {
+        ConfirmationDecision::new(true, "this kind of action asks by default")
+    } else {
+        ConfirmationDecision::new(false, "no rule requires confirmation")
+    }
+}
+
+/// A spoken answer to a pending confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingReply {
+    Confirm,
+    Cancel,
+}
+
+/// Confirm and cancel phrases per language code. Matched against the whole
+/// utterance after punctuation is stripped. Bare "yes", "no" or "stop" are
+/// left out: they are also dictation and commands of their own, and must not
+/// be swallowed while an action happens to be pending.
+const REPLY_PHRASES: &[(&str, &[&str], &[&str])] = &[
+    (
+        "en",
+        &[
+            "confirm", "confirmed", "yes confirm", "do it", "yes do it", "go ahead", "yes go ahead",
+            "go for it", "proceed", "run it", "yes run it",
+        ],
+        &[
+            "cancel", "cancel it", "cancel that", "no cancel", "don t", "do not", "don t do it",
+            "no don t", "abort", "never mind", "nevermind", "forget it", "no thanks",
+        ],
+    ),
+    (
+        "hi",
+        &["ji haan", "haan kar do", "kar do", "जी हाँ", "हाँ कर दो", "कर दो"],
+        &["mat karo", "rehne do", "मत करो", "रहने दो", "रद्द करो"],
+    ),
+    (
+        "es",
+        &["hazlo", "sí hazlo", "si hazlo", "confirmar", "confirmo", "adelante"],
+        &["cancelar", "cancela", "no lo hagas", "olvídalo", "olvidalo"],
+    ),
+    (
+        "fr",
+        &["vas y", "confirmer", "je confirme", "fais le"],
+        &["annuler", "annule", "laisse tomber"],
+    ),
+    (
+        "de",
+        &["mach es", "mach das", "bestätigen", "bestätige"],
+        &["abbrechen", "vergiss es"],
+    ),
+    (
+        "it",
+        &["fallo", "conferma", "confermo", "procedi"],
+        &["annulla", "lascia stare"],
+    ),
+    (
+        "pt",
+        &["faça", "faz isso", "confirmar", "confirma"],
+        &["cancelar", "cancela", "deixa pra lá"],
+    ),
+    ("ru", &["подтверждаю", "делай"], &["отмена", "отменить", "не надо"]),
+    ("zh", &["确认", "执行"], &["不要", "取消", "算了"]),
+    ("ja", &["確認", "実行して"], &["キャンセル", "やめて"]),
+    ("ko", &["확인", "해줘"], &["취소", "하지마"]),
+    ("ar", &["أكد", "نفذ"], &["إلغاء", "الغ"]),
+];
+
+/// Interpret `text` as an answer to a pending confirmation. English is always
+/// understood; `languages` adds others ("auto" enables every language).
+/// Returns None when the utterance is a new request.
+pub fn parse_pending_reply(text: &str, languages: &[&str]) -> Option<PendingReply> {
+    let normalized: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c.is_whitespace() { c } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = normalized.split_whitespace().collect();
+    while words.last().is_some_and(|w| matches!(*w, "please" | "thanks" | "now")) {
+        words.pop();
+    }
+    if words.is_empty() || words.len() > 4 {
+        return None;
+    }
+    let phrase = words.join(" ");
+
+    let any_language = languages.iter().any(|l| l.trim().eq_ignore_ascii_case("auto"));
+    let enabled = |code: &str| {
+        code == "en" || any_language || languages.iter().any(|l| l.trim().eq_ignore_ascii_case(code))
+    };
+
+    let mut reply = None;
+    for (_, confirm, cancel) in REPLY_PHRASES.iter().filter(|(code, _, _)| enabled(code)) {
+        // Cancelling wins if a phrase means yes in one language and no in another
+        if cancel.contains(&phrase.as_str()) {
+            return Some(PendingReply::Cancel);
+        }
+        if confirm.contains(&phrase.as_str()) {
+            reply = Some(PendingReply::Confirm);
+        }
+    }
+    reply
+}
+
+fn rule_label(rule: &ConfirmationRule, index: usize) -> String {
+    if rule.name.trim().is_empty() {
+        format!("rule {}", index + 1)
+    } else {
+        format!("rule \"{}\"", rule.name.trim())
+    }
+}
+
+fn first_match<'p>(
+    policy: &'p ConfirmationPolicy,
+    action: &ActionResult,
+    ctx: &PolicyContext,
+) -> Option<(usize, &'p ConfirmationRule)> {
+    policy
+        .rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule_matches(rule, action, ctx))
+}
+
+fn rule_matches(rule: &ConfirmationRule, action: &ActionResult, ctx: &PolicyContext) -> bool {
+    if let Some(action_type) = rule.action_type.as_deref() {
+        if ActionType::from_name_ignore_case(action_type) != Ok(action.action_type) {
+            return false;
+        }
+    }
+
+    for (field, expected) in &rule.payload {
+        let matches = match (action.payload.get(field), expected) {
+            (Some(serde_json::Value::String(actual)), serde_json::Value::String(expected)) => {
+                actual.trim().eq_ignore_ascii_case(expected.trim())
+            }
+            (Some(actual), expected) => actual == expected,
+            (None, _) => false,
+        };
+        if !matches {
+            return false;
+        }
+    }
+
+    if let Some(app) = rule.app.as_deref() {
+        let active = ctx.active_app.unwrap_or("").to_lowercase();
+        if active.is_empty() || !active.contains(&app.trim().to_lowercase()) {
+            return false;
+        }
+    }
+
+    let in_window = match (rule.from_hour, rule.to_hour) {
+        (Some(from), Some(to)) if from <= to => ctx.hour >= from && ctx.hour < to,
+        (Some(from), Some(to)) => ctx.hour >= from || ctx.hour < to,
+        (Some(from), None) => ctx.hour >= from,
+        (None, Some(to)) => ctx.hour < to,
+        (None, None) => true,
+    };
+    if !in_window {
+        return false;
+    }
+
+    rule.route.map_or(true, |route| route == ctx.route)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(hour: u32, route: RouteSource) -> PolicyContext<'static> {
+        PolicyContext {
+            active_app: Some("Windows Terminal"),
+            hour,
+            route,
+            confirmations_enabled: false,
+        }
+    }
+
+    fn rule(mode: ConfirmationMode) -> ConfirmationRule {
+        ConfirmationRule {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let run = ActionResult::action(ActionType::RunCommand, serde_json::json!({ "command": "ls" }));
+        let policy = ConfirmationPolicy {
+            rules: vec![
+                ConfirmationRule {
+                    name: "LLM commands at night".into(),
+                    action_type: Some("RunCommand".into()),
+                    route: Some(RouteSource::Llm),
+                    from_hour: Some(22),
+                    to_hour: Some(6),
+                    ..rule(ConfirmationMode::Always)
+                },
+                ConfirmationRule {
+                    app: Some("terminal".into()),
+                    ..rule(ConfirmationMode::Never)
+                },
+            ],
+        };
+        let none = HashSet::new();
+
+        let decision = evaluate(None, &policy, &run, &ctx(23, RouteSource::Llm), &none);
+        assert!(decision.required);
+        assert_eq!(decision.explanation, "rule \"LLM commands at night\" always asks");
+
+        let decision = evaluate(None, &policy, &run, &ctx(14, RouteSource::Llm), &none);
+        assert!(!decision.required);
+        assert_eq!(decision.explanation, "rule 2 allows it without asking");
+
+        let shutdown = ActionResult::action(ActionType::SystemControl, serde_json::json!({ "action": "Shutdown" }));
+        assert!(evaluate(None, &policy, &shutdown, &ctx(14, RouteSource::Local), &none).required);
+
+        let admin = ConfirmationPolicy {
+            rules: vec![ConfirmationRule {
+                payload: serde_json::json!({ "action": "shutdown" }).as_object().unwrap().clone(),
+                ..rule(ConfirmationMode::Never)
+            }],
+        };
+        assert!(!evaluate(Some(&admin), &policy, &shutdown, &ctx(14, RouteSource::Local), &none).required);
+    }
+
+    #[test]
+    fn remember_for_session_asks_once() {
+        let email = ActionResult::action(ActionType::SendEmail, serde_json::json!({}));
+        let policy = ConfirmationPolicy {
+            rules: vec![ConfirmationRule {
+                name: "Email".into(),
+                action_type: Some("SendEmail".into()),
+                ..rule(ConfirmationMode::RememberForSession)
+            }],
+        };
+        let mut remembered = HashSet::new();
+
+        let first = evaluate(None, &policy, &email, &ctx(9, RouteSource::Llm), &remembered);
+        assert!(first.required);
+        remembered.insert(first.remember_key.unwrap());
+
+        let second = evaluate(None, &policy, &email, &ctx(9, RouteSource::Llm), &remembered);
+        assert!(!second.required);
+
+        // A different rule taking the same name or position still asks
+        let edited = ConfirmationPolicy {
+            rules: vec![ConfirmationRule {
+                name: "Email".into(),
+                payload: serde_json::json!({ "to": "boss@example.com" }).as_object().unwrap().clone(),
+                ..policy.rules[0].clone()
+            }],
+        };
+        let boss = ActionResult::action(ActionType::SendEmail, serde_json::json!({ "to": "boss@example.com" }));
+        let third = evaluate(None, &edited, &boss, &ctx(9, RouteSource::Llm), &remembered);
+        assert!(third.required && third.from_rule);
+
+        assert!(validate(&ConfirmationPolicy {
+            rules: vec![ConfirmationRule { action_type: Some("Teleport".into()), ..Default::default() }],
+        })
+        .is_err());
+        assert!(validate(&ConfirmationPolicy {
+            rules: vec![ConfirmationRule { action_type: Some("sendemail".into()), ..Default::default() }],
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn understands_spoken_confirmation() {
+        assert_eq!(parse_pending_reply("Yes, do it!", &[]), Some(PendingReply::Confirm));
+        assert_eq!(parse_pending_reply("Go ahead please", &["en"]), Some(PendingReply::Confirm));
+        assert_eq!(parse_pending_reply("Never mind.", &[]), Some(PendingReply::Cancel));
+        assert_eq!(parse_pending_reply("No, cancel!", &[]), Some(PendingReply::Cancel));
+
+        assert_eq!(parse_pending_reply("hazlo", &[]), None);
+        assert_eq!(parse_pending_reply("Sí, hazlo", &["es", "en"]), Some(PendingReply::Confirm));
+        assert_eq!(parse_pending_reply("确认。", &["auto"]), Some(PendingReply::Confirm));
+
+        // One-word commands and dictation stay ordinary utterances
+        for text in ["stop", "no", "yes", "ok", "pause", "next"] {
+            assert_eq!(parse_pending_reply(text, &["auto"]), None, "{}", text);
+        }
+
+        assert_eq!(parse_pending_reply("yes open spotify and play music", &[]), None);
+        assert_eq!(parse_pending_reply("open spotify", &[]), None);
+    }
+}
@@ -47,6 +47,26 @@ pub struct AppConfig {
     /// Vibe coding prompt enhancement settings
     #[serde(default)]
     pub vibe_coding: VibeCodingConfig,
+
+    /// When to ask before acting on an uncertain interpretation
+    #[serde(default)]
+    pub clarification: ClarificationConfig,
+
+    /// Daily and monthly provider spend limits
+    #[serde(default)]
+    pub usage_budget: UsageBudgetConfig,
+
+    /// Rules deciding which actions wait for confirmation
+    #[serde(default)]
+    pub confirmation_policy: ConfirmationPolicy,
+
+    /// Allowlist and limits for RunCommand
+    #[serde(default)]
+    pub command_sandbox: CommandSandboxConfig,
+
+    /// How dictated text is typed into the focused app
+    #[serde(default)]
+    pub text_injection: TextInjectionConfig,
 }
 
 /// Multilingual language preferences.
@@ -199,6 +219,587 @@ impl Default for VibeCodingConfig {
     }
 }
 
+/// Automatic clarification for low-confidence intent routing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClarificationConfig {
+    /// Master toggle.
+    pub enabled: bool,
+    /// Results scoring below this ask the user to pick an interpretation.
+    pub confidence_threshold: f32,
+    /// Seconds a clarification question stays answerable.
+    pub answer_window_secs: u64,
+}
+
+impl ClarificationConfig {
+    fn storage_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("clarification.json"))
+    }
+
+    pub fn load_from_disk() -> Option<Self> {
+        let path = Self::storage_path().ok()?;
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str::<Self>(&content).ok()
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), String> {
+        let path = Self::storage_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create clarification config directory: {}", e))?;
+        }
+
+        let payload = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize clarification config: {}", e))?;
+        std::fs::write(&path, payload)
+            .map_err(|e| format!("Failed to write clarification config: {}", e))?;
+        Ok(())
+    }
+}
+
+impl Default for ClarificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            confidence_threshold: 0.6,
+            answer_window_secs: 60,
+        }
+    }
+}
+
+/// Spend limits for paid providers. As spend approaches a limit, vibe
+/// enhancement is dropped first, then Q&A, and finally everything but
+/// dictation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageBudgetConfig {
+    /// Master toggle.
+    pub enabled: bool,
+    /// Estimated USD allowed per local calendar day.
+    pub daily_limit_usd: Option<f64>,
+    /// Estimated USD allowed per local calendar month.
+    pub monthly_limit_usd: Option<f64>,
+}
+
+impl UsageBudgetConfig {
+    fn storage_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("usage_budget.json"))
+    }
+
+    pub fn load_from_disk() -> Option<Self> {
+        let path = Self::storage_path().ok()?;
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str::<Self>(&content).ok()
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), String> {
+        let path = Self::storage_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create usage budget directory: {}", e))?;
+        }
+
+        let payload = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize usage budget: {}", e))?;
+        std::fs::write(&path, payload)
+            .map_err(|e| format!("Failed to write usage budget: {}", e))?;
+        Ok(())
+    }
+}
+
+/// What a confirmation rule decides when it matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmationMode {
+    /// Ask every time
+    #[default]
+    Always,
+    /// Run without asking
+    Never,
+    /// Ask once, then run without asking until the app restarts
+    RememberForSession,
+}
+
+/// Where the routed action came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteSource {
+    /// Local grammar, rule classifier, or a stored action
+    Local,
+    /// Server or direct LLM intent routing
+    Llm,
+}
+
+/// A confirmation rule. Every condition that is set must hold for the rule
+/// to match; unset conditions match anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmationRule {
+    /// Shown in the confirmation prompt
+    pub name: String,
+    /// Action type name in any case, e.g. "RunCommand"
+    pub action_type: Option<String>,
+    /// Payload fields that must equal these values (strings ignore case)
+    pub payload: serde_json::Map<String, serde_json::Value>,
+    /// Substring of the active app name
+    pub app: Option<String>,
+    /// Local hour (0-23) the window starts at, inclusive
+    pub from_hour: Option<u32>,
+    /// Local hour (0-23) the window ends at, exclusive; may wrap past midnight
+    pub to_hour: Option<u32>,
+    pub route: Option<RouteSource>,
+    pub mode: ConfirmationMode,
+}
+
+lazy_static::lazy_static! {
+    /// The admin policy with the path and modification time it was read at
+    static ref ADMIN_POLICY_CACHE: std::sync::Mutex<Option<(PathBuf, Option<std::time::SystemTime>, Option<ConfirmationPolicy>)>> =
+        std::sync::Mutex::new(None);
+}
+
+/// User-defined confirmation rules, evaluated in order. The first matching
+/// rule wins; actions no rule matches keep the built-in behaviour.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmationPolicy {
+    pub rules: Vec<ConfirmationRule>,
+}
+
+impl ConfirmationPolicy {
+    fn storage_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("confirmation_policy.json"))
+    }
+
+    pub fn load_from_disk() -> Option<Self> {
+        let path = Self::storage_path().ok()?;
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str::<Self>(&content).ok()
+    }
+
+    /// Admin rules from the file named by `LISTENOS_ADMIN_POLICY`. They are
+    /// evaluated before the user's rules and cannot be edited from the app.
+    /// The file is only read again after its modification time changes.
+    pub fn load_admin() -> Option<Self> {
+        let path = PathBuf::from(std::env::var("LISTENOS_ADMIN_POLICY").ok()?.trim());
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+        let mut cache = ADMIN_POLICY_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((cached_path, cached_modified, policy)) = cache.as_ref() {
+            if *cached_path == path && modified.is_some() && *cached_modified == modified {
+                return policy.clone();
+            }
+        }
+        let policy = Self::read_admin(&path);
+        *cache = Some((path, modified, policy.clone()));
+        policy
+    }
+
+    fn read_admin(path: &std::path::Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                log::warn!("Ignoring unreadable admin confirmation policy: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), String> {
+        let path = Self::storage_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create confirmation policy directory: {}", e))?;
+        }
+
+        let payload = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize confirmation policy: {}", e))?;
+        std::fs::write(&path, payload)
+            .map_err(|e| format!("Failed to write confirmation policy: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Limits for commands run on the user's behalf. Commands are split into
+/// argv and started without a shell unless `allow_shell` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandSandboxConfig {
+    /// Allowed commands. An entry without spaces or wildcards allows that
+    /// executable with any arguments ("git"); other entries are glob patterns
+    /// over the whole command line ("git status*", "npm run ?*").
+    pub allowlist: Vec<String>,
+    /// Run commands with pipes or redirection through `sh -c` / `cmd /C`.
+    /// Every command chained with `;`, `&&` or `|` must then be allowed on
+    /// its own, and wildcards don't match shell metacharacters.
+    pub allow_shell: bool,
+    pub timeout_secs: u64,
+    /// Bytes kept from each of stdout and stderr
+    pub max_output_bytes: usize,
+    /// Defaults to the home directory
+    pub working_dir: Option<String>,
+    /// Environment variables passed through; everything else is removed
+    pub env_passthrough: Vec<String>,
+}
+
+impl CommandSandboxConfig {
+    fn storage_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("command_sandbox.json"))
+    }
+
+    pub fn load_from_disk() -> Option<Self> {
+        let path = Self::storage_path().ok()?;
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str::<Self>(&content).ok()
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), String> {
+        let path = Self::storage_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create command sandbox directory: {}", e))?;
+        }
+
+        let payload = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize command sandbox: {}", e))?;
+        std::fs::write(&path, payload)
+            .map_err(|e| format!("Failed to write command sandbox: {}", e))?;
+        Ok(())
+    }
+}
+
+impl Default for CommandSandboxConfig {
+    fn default() -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            // Read-only commands; anything else has to be added by the user
+            allowlist: strings(&[
+                "date", "df", "echo", "free", "hostname", "ipconfig", "ls", "pwd", "uptime", "whoami",
+                // Not `git log`/`git diff`: their --output option writes files
+                "git status*",
+            ]),
+            allow_shell: false,
+            timeout_secs: 30,
+            max_output_bytes: 64 * 1024,
+            working_dir: None,
+            env_passthrough: strings(&[
+                "PATH", "HOME", "USER", "LANG", "LC_ALL", "TERM", "DISPLAY", "WAYLAND_DISPLAY",
+                "XDG_RUNTIME_DIR", "DBUS_SESSION_BUS_ADDRESS", "SYSTEMROOT", "WINDIR", "COMSPEC",
+                "PATHEXT", "TEMP", "TMP", "USERPROFILE", "APPDATA", "LOCALAPPDATA",
+            ]),
+        }
+    }
+}
+
+/// A way of getting text into the focused app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionBackend {
+    /// Put the text on the clipboard, paste, then restore the clipboard
+    ClipboardPaste,
+    /// Send Unicode key events in chunks
+    UnicodeKeys,
+    /// Linux: virtual keyboard through /dev/uinput (US layout, ASCII only)
+    Uinput,
+    /// Linux X11: `xdotool type` over the XTest extension
+    Xtest,
+    /// Linux Wayland: `wtype` over the virtual-keyboard protocol
+    Wtype,
+}
+
+/// Backends to use when the active app matches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppInjectionRule {
+    /// Substring of the active app name, case-insensitive
+    pub app: String,
+    pub backends: Vec<InjectionBackend>,
+}
+
+/// Text injection settings. Backends are tried in order; when one fails the
+/// next one is used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextInjectionConfig {
+    pub backends: Vec<InjectionBackend>,
+    /// Checked in order; the first matching rule replaces `backends`
+    pub app_rules: Vec<AppInjectionRule>,
+    /// Characters sent per key-injection chunk
+    pub chunk_size: usize,
+    /// Pause between chunks so slow apps keep up
+    pub chunk_delay_ms: u64,
+    /// Ask clipboard managers not to record pasted dictation
+    pub exclude_from_clipboard_history: bool,
+}
+
+impl TextInjectionConfig {
+    fn storage_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("text_injection.json"))
+    }
+
+    pub fn load_from_disk() -> Option<Self> {
+        let path = Self::storage_path().ok()?;
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str::<Self>(&content).ok()
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), String> {
+        let path = Self::storage_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create text injection directory: {}", e))?;
+        }
+
+        let payload = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize text injection config: {}", e))?;
+        std::fs::write(&path, payload)
+            .map_err(|e| format!("Failed to write text injection config: {}", e))?;
+        Ok(())
+    }
+
+    /// Backends to try for `active_app`, in order
+    pub fn backends_for_app(&self, active_app: Option<&str>) -> Vec<InjectionBackend> {
+        let app = active_app.unwrap_or("").to_lowercase();
+        let rule = self.app_rules.iter().find(|rule| {
+            let pattern = rule.app.trim().to_lowercase();
+            !pattern.is_empty() && app.contains(&pattern) && !rule.backends.is_empty()
+        });
+        let mut backends = rule.map_or_else(|| self.backends.clone(), |rule| rule.backends.clone());
+        if backends.is_empty() {
+            backends = Self::default().backends;
+        }
+        backends
+    }
+}
+
+impl Default for TextInjectionConfig {
+    fn default() -> Self {
+        Self {
+            backends: vec![
+                InjectionBackend::ClipboardPaste,
+                InjectionBackend::UnicodeKeys,
+                InjectionBackend::Wtype,
+                InjectionBackend::Xtest,
+                InjectionBackend::Uinput,
+            ],
+            app_rules: Vec::new(),
+            chunk_size: 32,
+            chunk_delay_ms: 10,
+            exclude_from_clipboard_history: true,
+        }
+    }
+}
+
+/// Another name for an app: "open my editor" launches `app`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppAlias {
+    pub phrase: String,
+    /// Name passed to OpenApp
+    pub app: String,
+}
+
+/// A website bookmark: "open jira" opens `url`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebsiteAlias {
+    pub phrase: String,
+    pub url: String,
+}
+
+/// A search engine reachable as "search <keyword> for ..."
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchEngine {
+    pub keyword: String,
+    pub name: String,
+    /// Search URL with `{query}` where the encoded query goes
+    pub url_template: String,
+}
+
+impl SearchEngine {
+    fn new(keyword: &str, name: &str, url_template: &str) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            name: name.to_string(),
+            url_template: url_template.to_string(),
+        }
+    }
+
+    pub fn url_for(&self, query: &str) -> String {
+        let encoded: String = url::form_urlencoded::byte_serialize(query.trim().as_bytes()).collect();
+        self.url_template.replace("{query}", &encoded)
+    }
+}
+
+/// User-defined names for apps, websites and search engines. Read from disk
+/// when used, so edits apply to the next command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AliasConfig {
+    pub apps: Vec<AppAlias>,
+    pub websites: Vec<WebsiteAlias>,
+    pub search_engines: Vec<SearchEngine>,
+    /// Keyword of the engine used by plain "search for ..."
+    pub default_engine: String,
+}
+
+fn same_phrase(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+impl AliasConfig {
+    fn storage_path() -> Result<PathBuf, String> {
+        let data_dir =
+            dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("aliases.json"))
+    }
+
+    pub fn load_from_disk() -> Option<Self> {
+        Self::load().unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            None
+        })
+    }
+
+    /// The saved aliases, None when there are none yet. Unlike
+    /// `load_from_disk`, an unreadable or corrupt file is an error, so
+    /// writes don't replace it with the defaults.
+    pub fn load() -> Result<Option<Self>, String> {
+        let path = Self::storage_path()?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read aliases from {}: {}", path.display(), e)),
+        };
+        serde_json::from_str::<Self>(&content)
+            .map(Some)
+            .map_err(|e| format!("Aliases in {} are invalid, fix or remove the file: {}", path.display(), e))
+    }
+
+    pub fn save_to_disk(&self) -> Result<(), String> {
+        let path = Self::storage_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create aliases directory: {}", e))?;
+        }
+
+        let payload = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize aliases: {}", e))?;
+        std::fs::write(&path, payload)
+            .map_err(|e| format!("Failed to write aliases: {}", e))?;
+        Ok(())
+    }
+
+    /// Reject entries that could never work, before they are saved
+    pub fn validate(&self) -> Result<(), String> {
+        for alias in &self.apps {
+            if alias.phrase.trim().is_empty() || alias.app.trim().is_empty() {
+                return Err("App aliases need both a phrase and an app".to_string());
+            }
+        }
+        for alias in &self.websites {
+            if alias.phrase.trim().is_empty() {
+                return Err("Website aliases need a phrase".to_string());
+            }
+            url::Url::parse(alias.url.trim())
+                .map_err(|e| format!("Invalid URL for \"{}\": {}", alias.phrase, e))?;
+        }
+        for engine in &self.search_engines {
+            if engine.keyword.trim().is_empty() {
+                return Err("Search engines need a keyword".to_string());
+            }
+            if !engine.url_template.contains("{query}") {
+                return Err(format!("The URL for {} has no {{query}} placeholder", engine.keyword));
+            }
+            url::Url::parse(&engine.url_for("test"))
+                .map_err(|e| format!("Invalid URL for {}: {}", engine.keyword, e))?;
+        }
+        if !self.default_engine.trim().is_empty() && self.engine(&self.default_engine).is_none() {
+            return Err(format!("Default search engine {} is not defined", self.default_engine));
+        }
+        Ok(())
+    }
+
+    pub fn app_for(&self, phrase: &str) -> Option<&str> {
+        self.apps
+            .iter()
+            .find(|alias| same_phrase(&alias.phrase, phrase))
+            .map(|alias| alias.app.trim())
+    }
+
+    pub fn website_for(&self, phrase: &str) -> Option<&str> {
+        self.websites
+            .iter()
+            .find(|alias| same_phrase(&alias.phrase, phrase))
+            .map(|alias| alias.url.trim())
+    }
+
+    /// Engine by keyword or display name
+    pub fn engine(&self, keyword: &str) -> Option<&SearchEngine> {
+        self.search_engines
+            .iter()
+            .find(|engine| same_phrase(&engine.keyword, keyword) || same_phrase(&engine.name, keyword))
+    }
+
+    /// The engine for plain searches, falling back to the first one defined
+    pub fn default_search_engine(&self) -> SearchEngine {
+        self.engine(&self.default_engine)
+            .or_else(|| self.search_engines.first())
+            .cloned()
+            .unwrap_or_else(|| Self::default().search_engines.remove(0))
+    }
+
+    /// Add imported entries, replacing ones with the same phrase or keyword.
+    /// Returns how many entries were imported.
+    pub fn merge(&mut self, imported: AliasConfig) -> usize {
+        let count = imported.apps.len() + imported.websites.len() + imported.search_engines.len();
+        for alias in imported.apps {
+            self.apps.retain(|existing| !same_phrase(&existing.phrase, &alias.phrase));
+            self.apps.push(alias);
+        }
+        for alias in imported.websites {
+            self.websites.retain(|existing| !same_phrase(&existing.phrase, &alias.phrase));
+            self.websites.push(alias);
+        }
+        for engine in imported.search_engines {
+            self.search_engines.retain(|existing| !same_phrase(&existing.keyword, &engine.keyword));
+            self.search_engines.push(engine);
+        }
+        if !imported.default_engine.trim().is_empty() {
+            self.default_engine = imported.default_engine;
+        }
+        count
+    }
+}
+
+impl Default for AliasConfig {
+    fn default() -> Self {
+        Self {
+            apps: Vec::new(),
+            websites: Vec::new(),
+            search_engines: vec![
+                SearchEngine::new("google", "Google", "https://www.google.com/search?q={query}"),
+                SearchEngine::new("duckduckgo", "DuckDuckGo", "https://duckduckgo.com/?q={query}"),
+                SearchEngine::new("bing", "Bing", "https://www.bing.com/search?q={query}"),
+                SearchEngine::new("youtube", "YouTube", "https://www.youtube.com/results?search_query={query}"),
+                SearchEngine::new("github", "GitHub", "https://github.com/search?q={query}"),
+                SearchEngine::new("wikipedia", "Wikipedia", "https://en.wikipedia.org/w/index.php?search={query}"),
+                SearchEngine::new("amazon", "Amazon", "https://www.amazon.com/s?k={query}"),
+            ],
+            default_engine: "google".to_string(),
+        }
+    }
+}
+
 /// Dictation style configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DictationStyleConfig {
@@ -254,6 +855,11 @@ impl Default for AppConfig {
             dictation_style: DictationStyleConfig::default(),
             language_preferences: LanguagePreferences::default(),
             vibe_coding: VibeCodingConfig::default(),
+            clarification: ClarificationConfig::default(),
+            usage_budget: UsageBudgetConfig::default(),
+            confirmation_policy: ConfirmationPolicy::default(),
+            command_sandbox: CommandSandboxConfig::default(),
+            text_injection: TextInjectionConfig::default(),
         }
     }
 }
@@ -375,3 +981,48 @@ pub enum OverlayPosition {
     BottomRight,
     Center,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_imported_aliases() {
+        let mut aliases = AliasConfig::default();
+        aliases.apps.push(AppAlias { phrase: "My Editor".to_string(), app: "code".to_string() });
+
+        let imported = AliasConfig {
+            apps: vec![AppAlias { phrase: "my editor".to_string(), app: "zed".to_string() }],
+            websites: vec![WebsiteAlias { phrase: "jira".to_string(), url: "https://example.atlassian.net".to_string() }],
+            search_engines: vec![SearchEngine::new("github", "GitHub Code", "https://github.com/search?type=code&q={query}")],
+            default_engine: String::new(),
+        };
+        assert_eq!(aliases.merge(imported), 3);
+
+        assert_eq!(aliases.apps.len(), 1);
+        assert_eq!(aliases.app_for(" MY EDITOR "), Some("zed"));
+        assert_eq!(aliases.website_for("Jira"), Some("https://example.atlassian.net"));
+        assert_eq!(aliases.engine("github").map(|e| e.name.as_str()), Some("GitHub Code"));
+        assert_eq!(aliases.search_engines.len(), AliasConfig::default().search_engines.len());
+        // An empty default in the import keeps the current one
+        assert_eq!(aliases.default_engine, "google");
+        assert!(aliases.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_aliases_that_cannot_work() {
+        let invalid = |edit: fn(&mut AliasConfig)| {
+            let mut aliases = AliasConfig::default();
+            edit(&mut aliases);
+            aliases.validate().unwrap_err()
+        };
+
+        assert!(invalid(|a| a.apps.push(AppAlias { phrase: "editor".to_string(), app: " ".to_string() }))
+            .contains("phrase and an app"));
+        assert!(invalid(|a| a.websites.push(WebsiteAlias { phrase: "jira".to_string(), url: "not a url".to_string() }))
+            .contains("Invalid URL"));
+        assert!(invalid(|a| a.search_engines.push(SearchEngine::new("docs", "Docs", "https://docs.rs/")))
+            .contains("{query}"));
+        assert!(invalid(|a| a.default_engine = "altavista".to_string()).contains("not defined"));
+    }
+}
@@ -0,0 +1,363 @@
+//! Connectivity tracking and offline routing for ListenOS
+//!
+//! When both the backend server and Groq are unreachable, intent routing
+//! falls back to the local grammar and the rule classifier. Features that
+//! only an LLM can provide are queued so they can be retried once the
+//! network comes back.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::ai::{AIEngine, IntentType};
+use crate::cloud::{self, ActionResult, ActionType};
+use crate::config::AliasConfig;
+
+/// Host probed to decide whether we are back online
+const PROBE_HOST: &str = "api.groq.com:443";
+
+/// Minimum time between probes while offline
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Maximum queued LLM tasks kept while offline
+const MAX_QUEUED_TASKS: usize = 20;
+
+/// Feature that needs an LLM and was deferred while offline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OfflineTaskKind {
+    Question,
+    ClipboardTransform,
+    VibeEnhancement,
+}
+
+impl OfflineTaskKind {
+    /// Whether the task runs by itself once the network is back. Answers
+    /// are harmless; anything that writes the clipboard waits for the user.
+    pub fn replays_automatically(&self) -> bool {
+        matches!(self, Self::Question)
+    }
+
+    /// Short user-facing explanation of why the feature was deferred
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Self::Question => "You're offline, so I saved your question and will answer it when the connection is back.",
+            Self::ClipboardTransform => "You're offline, so I saved the clipboard action. Run it from the queue once the connection is back.",
+            Self::VibeEnhancement => "You're offline, so I typed your prompt as spoken. Run the enhancement from the queue once the connection is back.",
+        }
+    }
+}
+
+/// A deferred LLM task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineTask {
+    pub id: String,
+    pub kind: OfflineTaskKind,
+    pub text: String,
+    pub action: Option<ActionResult>,
+    /// Clipboard text when a clipboard transform was queued; the transform
+    /// works on this, not on whatever is copied by the time it runs
+    pub clipboard: Option<String>,
+    /// Why the confirmation policy asked when the task was queued; running
+    /// the task by hand is the confirmation
+    pub confirmation: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OfflineTask {
+    pub fn new(kind: OfflineTaskKind, text: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            text,
+            action: None,
+            clipboard: None,
+            confirmation: None,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Connectivity snapshot for the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectivityStatus {
+    pub online: bool,
+    pub offline_since: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub queued_tasks: usize,
+}
+
+/// Tracks whether network providers are reachable
+pub struct ConnectivityMonitor {
+    online: bool,
+    offline_since: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_probe: Option<Instant>,
+    queue: Vec<OfflineTask>,
+    /// Set when connectivity returns with tasks waiting, until they are replayed
+    replay_due: bool,
+}
+
+impl ConnectivityMonitor {
+    pub fn new() -> Self {
+        Self {
+            online: true,
+            offline_since: None,
+            last_error: None,
+            last_probe: None,
+            queue: Vec::new(),
+            replay_due: false,
+        }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online
+    }
+
+    /// Switch to offline mode after every provider failed. Dry runs leave
+    /// the state alone.
+    pub fn mark_offline(&mut self, error: impl Into<String>) {
+        if crate::simulation::is_active() {
+            return;
+        }
+        if self.online {
+            log::warn!("Network providers unreachable, switching to offline routing");
+            self.offline_since = Some(Utc::now());
+        }
+        self.online = false;
+        self.last_error = Some(error.into());
+        self.last_probe = Some(Instant::now());
+    }
+
+    /// Switch back to online mode. Returns true if this was a transition.
+    pub fn mark_online(&mut self) -> bool {
+        if crate::simulation::is_active() {
+            return false;
+        }
+        let changed = !self.online;
+        if changed {
+            log::info!("Connectivity restored, leaving offline routing");
+            self.replay_due = !self.queue.is_empty();
+        }
+        self.online = true;
+        self.offline_since = None;
+        self.last_error = None;
+        changed
+    }
+
+    /// Whether enough time has passed to probe again while offline
+    pub fn should_probe(&self) -> bool {
+        !self.online
+            && self
+                .last_probe
+                .map(|t| t.elapsed() >= PROBE_INTERVAL)
+                .unwrap_or(true)
+    }
+
+    pub fn note_probe(&mut self) {
+        self.last_probe = Some(Instant::now());
+    }
+
+    /// Defer an LLM-only feature until connectivity returns
+    pub fn enqueue(&mut self, task: OfflineTask) -> OfflineTask {
+        self.queue.push(task.clone());
+        self.trim_queue();
+        task
+    }
+
+    /// Put a task that could not run back in its place, keeping its id
+    pub fn requeue(&mut self, task: OfflineTask) {
+        let index = self.queue.partition_point(|t| t.created_at < task.created_at);
+        self.queue.insert(index, task);
+        self.trim_queue();
+    }
+
+    fn trim_queue(&mut self) {
+        if self.queue.len() > MAX_QUEUED_TASKS {
+            self.queue.remove(0);
+        }
+    }
+
+    pub fn queued(&self) -> Vec<OfflineTask> {
+        self.queue.clone()
+    }
+
+    pub fn take(&mut self, id: &str) -> Option<OfflineTask> {
+        let index = self.queue.iter().position(|t| t.id == id)?;
+        Some(self.queue.remove(index))
+    }
+
+    /// Once after the switch back to online mode, the queued tasks that
+    /// replay by themselves. The rest stay queued for the user to run.
+    pub fn take_replay(&mut self) -> Option<Vec<OfflineTask>> {
+        if !std::mem::take(&mut self.replay_due) {
+            return None;
+        }
+        let (replay, waiting) = std::mem::take(&mut self.queue)
+            .into_iter()
+            .partition(|task| task.kind.replays_automatically());
+        self.queue = waiting;
+        Some(replay)
+    }
+
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+        self.replay_due = false;
+    }
+
+    pub fn status(&self) -> ConnectivityStatus {
+        ConnectivityStatus {
+            online: self.online,
+            offline_since: self.offline_since,
+            last_error: self.last_error.clone(),
+            queued_tasks: self.queue.len(),
+        }
+    }
+}
+
+impl Default for ConnectivityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a provider error means the network is unreachable (as opposed to
+/// an auth or quota problem, where going offline would not help)
+pub fn is_network_error(error: &str) -> bool {
+    let lower = error.to_lowercase();
+    [
+        "error sending request",
+        "dns error",
+        "failed to lookup",
+        "connection refused",
+        "connection reset",
+        "network is unreachable",
+        "timed out",
+        "operation timed out",
+        "tcp connect error",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+}
+
+/// Check whether the LLM provider can be reached at all
+pub async fn probe() -> bool {
+    matches!(
+        tokio::time::timeout(
+            Duration::from_secs(3),
+            tokio::net::TcpStream::connect(PROBE_HOST),
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+/// Route an utterance without any network provider: local grammar first,
+/// then the rule classifier, then plain dictation
+pub fn route_offline(text: &str, aliases: &AliasConfig) -> ActionResult {
+    if let Some(action) = cloud::detect_local_command(text, aliases) {
+        return action;
+    }
+
+    let classification = match AIEngine::default().classify_with_rules(text) {
+        Ok(c) => c,
+        Err(_) => return ActionResult::type_text(cloud::post_process_dictation(text)),
+    };
+    let value = classification.extracted_value.clone().unwrap_or_default();
+
+    let action = match classification.intent_type {
+        IntentType::OpenApp if !value.is_empty() => {
+            ActionResult::action(ActionType::OpenApp, serde_json::json!({ "app": value }))
+        }
+        IntentType::WebSearch if !value.is_empty() => {
+            ActionResult::action(ActionType::WebSearch, serde_json::json!({ "query": value }))
+        }
+        IntentType::VolumeUp => {
+            ActionResult::action(ActionType::VolumeControl, serde_json::json!({ "direction": "up" }))
+        }
+        IntentType::VolumeDown => {
+            ActionResult::action(ActionType::VolumeControl, serde_json::json!({ "direction": "down" }))
+        }
+        IntentType::Mute => {
+            ActionResult::action(ActionType::VolumeControl, serde_json::json!({ "direction": "mute" }))
+        }
+        IntentType::TypeText if !value.is_empty() => {
+            ActionResult::type_text(cloud::post_process_dictation(&value))
+        }
+        // Shell commands are too risky to guess at without the LLM
+        _ => ActionResult::type_text(cloud::post_process_dictation(text)),
+    };
+
+    action.with_confidence(classification.confidence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queues_tasks_while_offline() {
+        let mut monitor = ConnectivityMonitor::new();
+        let first = monitor.enqueue(OfflineTask::new(OfflineTaskKind::Question, "what is rust".to_string()));
+        let second = monitor.enqueue(OfflineTask::new(OfflineTaskKind::VibeEnhancement, "add a button".to_string()));
+        assert!(monitor.take("missing").is_none());
+
+        // A failed retry goes back in its place under the same id
+        let task = monitor.take(&first.id).unwrap();
+        monitor.requeue(task);
+        let ids: Vec<String> = monitor.queued().into_iter().map(|t| t.id).collect();
+        assert_eq!(ids, [first.id.clone(), second.id]);
+
+        for i in 0..MAX_QUEUED_TASKS {
+            monitor.enqueue(OfflineTask::new(OfflineTaskKind::Question, i.to_string()));
+        }
+        assert_eq!(monitor.status().queued_tasks, MAX_QUEUED_TASKS);
+        assert!(monitor.take(&first.id).is_none());
+    }
+
+    #[test]
+    fn replays_queue_once_back_online() {
+        let mut monitor = ConnectivityMonitor::new();
+        assert!(!monitor.mark_online());
+        assert!(!monitor.should_probe());
+
+        monitor.mark_offline("dns error");
+        let status = monitor.status();
+        assert!(!status.online && status.offline_since.is_some());
+        assert_eq!(status.last_error.as_deref(), Some("dns error"));
+        // Just failed, so the next probe waits
+        assert!(!monitor.should_probe());
+        monitor.enqueue(OfflineTask::new(OfflineTaskKind::Question, "what is rust".to_string()));
+        let clipboard = monitor.enqueue(OfflineTask::new(OfflineTaskKind::ClipboardTransform, "translate this".to_string()));
+        assert!(monitor.take_replay().is_none());
+
+        assert!(monitor.mark_online());
+        assert!(!monitor.mark_online());
+        let status = monitor.status();
+        assert!(status.online && status.offline_since.is_none() && status.last_error.is_none());
+        // Only the question replays; the clipboard task waits for the user
+        let replay = monitor.take_replay().unwrap();
+        assert_eq!(replay.iter().map(|t| t.kind).collect::<Vec<_>>(), [OfflineTaskKind::Question]);
+        assert!(monitor.take_replay().is_none());
+        let waiting: Vec<String> = monitor.queued().into_iter().map(|t| t.id).collect();
+        assert_eq!(waiting, [clipboard.id]);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_leave_connectivity_alone() {
+        let mut monitor = ConnectivityMonitor::new();
+        crate::simulation::run(async { monitor.mark_offline("dns error") }).await;
+        assert!(monitor.is_online());
+
+        monitor.mark_offline("dns error");
+        let (changed, _) = crate::simulation::run(async { monitor.mark_online() }).await;
+        assert!(!changed && !monitor.is_online());
+    }
+
+    #[test]
+    fn tells_network_errors_from_provider_errors() {
+        assert!(is_network_error("error sending request for url (https://api.groq.com)"));
+        assert!(is_network_error("Operation timed out"));
+        assert!(!is_network_error("401 Unauthorized: invalid API key"));
+        assert!(!is_network_error("rate limit exceeded"));
+    }
+}
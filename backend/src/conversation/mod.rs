@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-use crate::cloud::ActionType;
+use crate::cloud::{ActionCandidate, ActionType};
 
 /// Maximum messages to keep in short-term memory for LLM context
 const MAX_SHORT_TERM_MESSAGES: usize = 10;
@@ -103,6 +103,28 @@ pub struct ConversationMemory {
     pub last_action: Option<String>,
     pub last_action_payload: Option<serde_json::Value>,
     pub started_at: DateTime<Utc>,
+    /// Clarification question waiting for the user's pick
+    #[serde(default)]
+    pub pending_clarification: Option<PendingClarification>,
+}
+
+/// A clarification question and the interpretations it offered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingClarification {
+    pub question: String,
+    pub candidates: Vec<ActionCandidate>,
+    pub asked_at: DateTime<Utc>,
+}
+
+/// How the next utterance relates to a pending clarification
+#[derive(Debug, Clone)]
+pub enum ClarificationReply {
+    /// The user picked one of the offered interpretations
+    Choice(ActionCandidate),
+    /// The user declined all of them
+    Cancel,
+    /// Not an answer; route the utterance normally
+    Unrelated,
 }
 
 impl Default for ConversationMemory {
@@ -114,6 +136,7 @@ impl Default for ConversationMemory {
             last_action: None,
             last_action_payload: None,
             started_at: Utc::now(),
+            pending_clarification: None,
         }
     }
 }
@@ -217,11 +240,36 @@ impl ConversationMemory {
         }
     }
 
+    /// Remember a clarification question so the next utterance can answer it
+    pub fn set_pending_clarification(&mut self, question: String, candidates: Vec<ActionCandidate>) {
+        self.pending_clarification = Some(PendingClarification {
+            question,
+            candidates,
+            asked_at: Utc::now(),
+        });
+    }
+
+    /// Consume any pending clarification and interpret `text` as the answer.
+    /// Questions older than `window_secs` are dropped unanswered.
+    pub fn take_clarification_reply(&mut self, text: &str, window_secs: u64) -> ClarificationReply {
+        let Some(pending) = self.pending_clarification.take() else {
+            return ClarificationReply::Unrelated;
+        };
+
+        let age = Utc::now().signed_duration_since(pending.asked_at).num_seconds();
+        if age < 0 || age as u64 > window_secs {
+            return ClarificationReply::Unrelated;
+        }
+
+        parse_clarification_reply(text, &pending.candidates)
+    }
+
     /// Clear the session
     pub fn clear(&mut self) {
         self.messages.clear();
         self.last_action = None;
         self.last_action_payload = None;
+        self.pending_clarification = None;
         // Keep facts for continuity
     }
 
@@ -234,6 +282,98 @@ impl ConversationMemory {
     }
 }
 
+/// Words that carry no choice: "the first one please" -> "first"
+const REPLY_FILLER: &[&str] = &[
+    "the", "a", "an", "that", "this", "option", "choice", "number", "please", "i", "meant", "mean", "want", "pick",
+    "choose", "go", "with", "just", "it", "for", "to", "do",
+];
+
+/// Verbs that restate the action rather than name a candidate: "open spotify"
+const REPLY_VERBS: &[&str] = &["open", "launch", "start", "run", "play", "search", "show"];
+
+/// A whole answer made of these declines every candidate ("no thanks", "neither of them")
+const CANCEL_WORDS: &[&str] = &[
+    "cancel", "neither", "none", "nothing", "nevermind", "never", "mind", "forget", "no", "nope", "thanks", "of", "them", "it",
+];
+
+/// Match a short spoken answer ("the first one", "type it", "cancel") against
+/// the offered candidates. The answer has to be just an ordinal or words
+/// naming exactly one candidate; anything else is a new request.
+fn parse_clarification_reply(text: &str, candidates: &[ActionCandidate]) -> ClarificationReply {
+    let normalized: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c.is_whitespace() { c } else { ' ' })
+        .collect();
+    let all_words: Vec<&str> = normalized.split_whitespace().collect();
+
+    // Long utterances are new requests, not answers
+    if all_words.is_empty() || all_words.len() > 6 {
+        return ClarificationReply::Unrelated;
+    }
+
+    if all_words.iter().all(|w| CANCEL_WORDS.contains(w))
+        && all_words.iter().any(|w| ["cancel", "neither", "none", "nothing", "nevermind", "never", "forget", "no", "nope"].contains(w))
+    {
+        return ClarificationReply::Cancel;
+    }
+
+    let mut words: Vec<&str> = all_words.iter().copied().filter(|w| !REPLY_FILLER.contains(w)).collect();
+    // "the other one" -> "other", but a bare "one" is an ordinal
+    if words.len() > 1 && words.last() == Some(&"one") {
+        words.pop();
+    }
+    // "yes, the second" -> "second", but a bare "yes" picks the first
+    if words.len() > 1 && ["yes", "yeah", "yep"].contains(&words[0]) {
+        words.remove(0);
+    }
+    if words.is_empty() {
+        return ClarificationReply::Unrelated;
+    }
+
+    if let [word] = words.as_slice() {
+        let ordinal = match *word {
+            "first" | "one" | "1" | "former" | "yes" | "yeah" | "yep" => Some(0),
+            "second" | "two" | "2" | "latter" | "other" => Some(1),
+            "third" | "three" | "3" => Some(2),
+            _ => None,
+        };
+        if let Some(index) = ordinal {
+            return match candidates.get(index) {
+                Some(candidate) => ClarificationReply::Choice(candidate.clone()),
+                None => ClarificationReply::Unrelated,
+            };
+        }
+    }
+
+    // "type it" / "open spotify" style answers: every remaining word has to
+    // name the same single candidate
+    let content: Vec<&str> = match words.iter().copied().filter(|w| !REPLY_VERBS.contains(w)).collect::<Vec<_>>() {
+        content if content.is_empty() => words,
+        content => content,
+    };
+    let matching: Vec<&ActionCandidate> = candidates
+        .iter()
+        .filter(|c| {
+            if c.action_type == ActionType::TypeText {
+                return content.iter().all(|w| ["type", "typing", "dictate", "write", "text"].contains(w));
+            }
+            let values: Vec<String> = c
+                .payload
+                .as_object()
+                .map(|obj| obj.values().filter_map(|v| v.as_str()).map(str::to_lowercase).collect())
+                .unwrap_or_default();
+            content.iter().all(|w| w.len() > 2 && values.iter().any(|value| value.contains(w)))
+        })
+        .collect();
+
+    if matching.len() == 1 {
+        return ClarificationReply::Choice(matching[0].clone());
+    }
+
+    ClarificationReply::Unrelated
+}
+
 /// Persistent storage for conversation history and facts
 pub struct ConversationStore {
     conn: Mutex<Connection>,
@@ -481,3 +621,64 @@ impl ConversationStore {
 
 // Add dirs_next dependency for data directory
 // This will be added to Cargo.toml
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<ActionCandidate> {
+        let candidate = |action_type, payload| ActionCandidate { action_type, payload, refined_text: None, confidence: 0.5 };
+        vec![
+            candidate(ActionType::TypeText, serde_json::json!({ "text": "hello world" })),
+            candidate(ActionType::OpenApp, serde_json::json!({ "app": "Spotify" })),
+        ]
+    }
+
+    fn answer(text: &str) -> String {
+        match parse_clarification_reply(text, &candidates()) {
+            ClarificationReply::Choice(candidate) => format!("{:?}", candidate.action_type),
+            ClarificationReply::Cancel => "cancel".to_string(),
+            ClarificationReply::Unrelated => "unrelated".to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_clarification_replies() {
+        let cases = [
+            ("the first one", "TypeText"),
+            ("2", "OpenApp"),
+            ("the other one", "OpenApp"),
+            ("yes", "TypeText"),
+            ("yes, the second one please", "OpenApp"),
+            ("type it", "TypeText"),
+            ("open Spotify", "OpenApp"),
+            ("never mind", "cancel"),
+            ("no thanks", "cancel"),
+            ("forget it", "cancel"),
+            ("the third one", "unrelated"),
+            ("yes open the other one", "unrelated"),
+            ("open chrome", "unrelated"),
+            ("no I said open chrome", "unrelated"),
+            ("one more thing about that", "unrelated"),
+            ("type hello into the other window now", "unrelated"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(answer(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn expired_clarifications_are_dropped() {
+        let mut memory = ConversationMemory::default();
+        memory.set_pending_clarification("Type it or open it?".to_string(), candidates());
+        assert!(matches!(memory.take_clarification_reply("second", 30), ClarificationReply::Choice(_)));
+        assert!(memory.pending_clarification.is_none());
+
+        memory.set_pending_clarification("Type it or open it?".to_string(), candidates());
+        if let Some(pending) = memory.pending_clarification.as_mut() {
+            pending.asked_at = pending.asked_at - chrono::Duration::seconds(60);
+        }
+        assert!(matches!(memory.take_clarification_reply("second", 30), ClarificationReply::Unrelated));
+        assert!(memory.pending_clarification.is_none());
+    }
+}
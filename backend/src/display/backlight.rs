@@ -0,0 +1,145 @@
+//! Panel brightness through the kernel's backlight devices
+//!
+//! Writes go through logind's `SetBrightness`, which lets the session user
+//! change the backlight without root. Writing the sysfs file directly is the
+//! fallback for systems without logind where udev rules allow it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use zbus::blocking::Connection;
+
+const SYSFS_BACKLIGHT: &str = "/sys/class/backlight";
+
+/// A backlight device under /sys/class/backlight
+#[derive(Debug, Clone)]
+pub struct Backlight {
+    pub name: String,
+    path: PathBuf,
+    max: u32,
+}
+
+impl Backlight {
+    /// The panel's backlight. Like the desktop, firmware interfaces win over
+    /// platform ones, which win over raw driver ones.
+    pub fn find() -> Result<Self, String> {
+        Self::find_in(Path::new(SYSFS_BACKLIGHT))
+    }
+
+    fn find_in(root: &Path) -> Result<Self, String> {
+        let entries = fs::read_dir(root).map_err(|_| no_backlight())?;
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let max = read_number(&path.join("max_brightness")).ok().filter(|max| *max > 0)?;
+                let rank = match fs::read_to_string(path.join("type")).unwrap_or_default().trim() {
+                    "firmware" => 0,
+                    "platform" => 1,
+                    "raw" => 2,
+                    _ => 3,
+                };
+                let name = entry.file_name().to_string_lossy().into_owned();
+                Some((rank, Self { name, path, max }))
+            })
+            .min_by(|(a, left), (b, right)| a.cmp(b).then_with(|| left.name.cmp(&right.name)))
+            .map(|(_, backlight)| backlight)
+            .ok_or_else(no_backlight)
+    }
+
+    /// Current brightness, 0-100
+    pub fn percent(&self) -> Result<u32, String> {
+        // actual_brightness is what the hardware reports; brightness is the last request
+        let raw = read_number(&self.path.join("actual_brightness"))
+            .or_else(|_| read_number(&self.path.join("brightness")))?;
+        Ok(to_percent(raw, self.max))
+    }
+
+    /// Set the brightness and return the level it ended up at
+    pub fn set_percent(&self, percent: u32) -> Result<u32, String> {
+        let raw = from_percent(percent, self.max);
+        if let Err(logind_error) = set_with_logind(&self.name, raw) {
+            let file = self.path.join("brightness");
+            fs::write(&file, raw.to_string()).map_err(|e| {
+                format!(
+                    "Could not set brightness: logind refused ({}) and {} is not writable ({})",
+                    logind_error,
+                    file.display(),
+                    e
+                )
+            })?;
+        }
+        Ok(to_percent(raw, self.max))
+    }
+}
+
+fn no_backlight() -> String {
+    "No backlight found. Only built-in panels are supported; external monitors need DDC/CI".to_string()
+}
+
+fn read_number(path: &Path) -> Result<u32, String> {
+    fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .trim()
+        .parse()
+        .map_err(|_| format!("Unexpected contents in {}", path.display()))
+}
+
+fn to_percent(raw: u32, max: u32) -> u32 {
+    ((raw.min(max) as u64 * 100 + max as u64 / 2) / max as u64) as u32
+}
+
+/// Percent to a raw level, never below 1 so the panel doesn't go black
+fn from_percent(percent: u32, max: u32) -> u32 {
+    let raw = (percent.min(100) as u64 * max as u64 + 50) / 100;
+    (raw as u32).max(1)
+}
+
+fn set_with_logind(name: &str, value: u32) -> Result<(), String> {
+    let connection = Connection::system().map_err(|e| format!("no system bus: {}", e))?;
+    connection
+        .call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1/session/auto",
+            Some("org.freedesktop.login1.Session"),
+            "SetBrightness",
+            &("backlight", name, value),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(root: &Path, name: &str, kind: &str, max: u32, current: u32) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+        fs::write(dir.join("max_brightness"), format!("{}\n", max)).unwrap();
+        fs::write(dir.join("actual_brightness"), format!("{}\n", current)).unwrap();
+    }
+
+    #[test]
+    fn prefers_firmware_backlights() {
+        let root = std::env::temp_dir().join(format!("listenos-backlight-{}", uuid::Uuid::new_v4()));
+        device(&root, "intel_backlight", "raw", 19393, 9697);
+        device(&root, "acpi_video0", "firmware", 15, 12);
+
+        let backlight = Backlight::find_in(&root).unwrap();
+        assert_eq!(backlight.name, "acpi_video0");
+        assert_eq!(backlight.percent().unwrap(), 80);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(Backlight::find_in(&root).is_err());
+    }
+
+    #[test]
+    fn converts_levels() {
+        assert_eq!(to_percent(9697, 19393), 50);
+        assert_eq!(from_percent(50, 19393), 9697);
+        assert_eq!(from_percent(0, 255), 1);
+        assert_eq!(from_percent(100, 15), 15);
+    }
+}
@@ -0,0 +1,197 @@
+//! Display settings on Linux: backlight brightness, night light and dark mode
+//!
+//! Brightness goes through the kernel backlight (see `backlight`). Night
+//! light and dark mode are desktop settings, so they are read and written
+//! through GNOME's GSettings or KDE's config files and tools. Other desktops
+//! get an error saying so rather than a command that silently does nothing.
+
+mod backlight;
+
+pub use backlight::Backlight;
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use zbus::blocking::Connection;
+
+const GNOME_COLOR_SCHEMA: &str = "org.gnome.settings-daemon.plugins.color";
+const GNOME_INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    Gnome,
+    Kde,
+}
+
+impl Desktop {
+    pub fn detect() -> Result<Self, String> {
+        let current = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        Self::parse(&current).ok_or_else(|| {
+            if current.is_empty() {
+                "Night light and dark mode need GNOME or KDE Plasma, and no desktop was detected".to_string()
+            } else {
+                format!("Night light and dark mode need GNOME or KDE Plasma, not {}", current)
+            }
+        })
+    }
+
+    /// XDG_CURRENT_DESKTOP can list several names, e.g. "ubuntu:GNOME"
+    fn parse(current: &str) -> Option<Self> {
+        current.split(':').find_map(|name| match name.trim().to_lowercase().as_str() {
+            "gnome" | "gnome-classic" | "gnome-flashback" => Some(Self::Gnome),
+            "kde" => Some(Self::Kde),
+            _ => None,
+        })
+    }
+}
+
+/// Turn night light on or off, or toggle it when `enable` is None.
+/// Returns whether it is now on.
+pub fn set_night_light(enable: Option<bool>) -> Result<bool, String> {
+    match Desktop::detect()? {
+        Desktop::Gnome => {
+            let current = gsettings_get(GNOME_COLOR_SCHEMA, "night-light-enabled")? == "true";
+            let enable = enable.unwrap_or(!current);
+            if enable != current {
+                gsettings_set(GNOME_COLOR_SCHEMA, "night-light-enabled", if enable { "true" } else { "false" })?;
+            }
+            Ok(enable)
+        }
+        Desktop::Kde => {
+            let current = kde_config("kwinrc", "NightColor", "Active").as_deref() == Some("true");
+            let enable = enable.unwrap_or(!current);
+            if enable != current {
+                kde_write_config("kwinrc", "NightColor", "Active", if enable { "true" } else { "false" })?;
+                // Older KWin only rereads its config when asked to
+                if let Err(e) = kwin_reconfigure() {
+                    log::warn!("KWin reconfigure failed: {}", e);
+                }
+            }
+            Ok(enable)
+        }
+    }
+}
+
+/// Switch between the dark and light theme, or toggle when `enable` is
+/// None. Returns whether dark mode is now on.
+pub fn set_dark_mode(enable: Option<bool>) -> Result<bool, String> {
+    match Desktop::detect()? {
+        Desktop::Gnome => {
+            let current = gsettings_get(GNOME_INTERFACE_SCHEMA, "color-scheme")?.contains("dark");
+            let enable = enable.unwrap_or(!current);
+            if enable != current {
+                let scheme = if enable { "prefer-dark" } else { "default" };
+                gsettings_set(GNOME_INTERFACE_SCHEMA, "color-scheme", scheme)?;
+            }
+            Ok(enable)
+        }
+        Desktop::Kde => {
+            let current = kde_config("kdeglobals", "General", "ColorScheme")
+                .is_some_and(|scheme| scheme.to_lowercase().contains("dark"));
+            let enable = enable.unwrap_or(!current);
+            if enable != current {
+                let scheme = if enable { "BreezeDark" } else { "BreezeLight" };
+                run("plasma-apply-colorscheme", &[scheme])?;
+            }
+            Ok(enable)
+        }
+    }
+}
+
+/// Run a desktop tool, turning a missing binary into a readable error
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program).args(args).output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            format!("{} is not installed", program)
+        } else {
+            format!("Failed to run {}: {}", program, e)
+        }
+    })?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// A GSettings value with GVariant quoting removed: "'prefer-dark'" -> "prefer-dark"
+fn gsettings_get(schema: &str, key: &str) -> Result<String, String> {
+    let value = run("gsettings", &["get", schema, key])?;
+    Ok(value.trim_matches('\'').to_string())
+}
+
+fn gsettings_set(schema: &str, key: &str, value: &str) -> Result<(), String> {
+    run("gsettings", &["set", schema, key, value]).map(|_| ())
+}
+
+fn config_dir() -> Option<PathBuf> {
+    std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".config")))
+}
+
+/// Read one key from a KDE config file in ~/.config
+fn kde_config(file: &str, group: &str, key: &str) -> Option<String> {
+    let text = std::fs::read_to_string(config_dir()?.join(file)).ok()?;
+    ini_value(&text, group, key)
+}
+
+/// The value of `key` in `[group]` of an INI-style KDE config file
+fn ini_value(text: &str, group: &str, key: &str) -> Option<String> {
+    let header = format!("[{}]", group);
+    let mut in_group = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == header;
+        } else if in_group {
+            if let Some((name, value)) = line.split_once('=') {
+                // Localized or immutable keys look like "Key[$i]"
+                let name = name.trim();
+                let name = name.split_once('[').map_or(name, |(name, _)| name);
+                if name == key {
+                    return Some(value.trim().to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Write a KDE config key and notify running apps, with Plasma 6's tool or Plasma 5's
+fn kde_write_config(file: &str, group: &str, key: &str, value: &str) -> Result<(), String> {
+    let args = ["--file", file, "--group", group, "--key", key, "--notify", value];
+    run("kwriteconfig6", &args)
+        .or_else(|_| run("kwriteconfig5", &args))
+        .map(|_| ())
+}
+
+fn kwin_reconfigure() -> Result<(), String> {
+    let connection = Connection::session().map_err(|e| format!("No session bus: {}", e))?;
+    connection
+        .call_method(Some("org.kde.KWin"), "/KWin", Some("org.kde.KWin"), "reconfigure", &())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_desktops() {
+        assert_eq!(Desktop::parse("ubuntu:GNOME"), Some(Desktop::Gnome));
+        assert_eq!(Desktop::parse("KDE"), Some(Desktop::Kde));
+        assert_eq!(Desktop::parse("sway"), None);
+        assert_eq!(Desktop::parse(""), None);
+    }
+
+    #[test]
+    fn reads_kde_config() {
+        let kwinrc = "[Compositing]\nActive=false\n\n[NightColor]\nMode=Constant\nActive[$i]=true\n";
+        assert_eq!(ini_value(kwinrc, "NightColor", "Active").as_deref(), Some("true"));
+        assert_eq!(ini_value(kwinrc, "Compositing", "Active").as_deref(), Some("false"));
+        assert_eq!(ini_value(kwinrc, "NightColor", "Temperature"), None);
+    }
+}
@@ -0,0 +1,454 @@
+//! Voice History Storage for ListenOS
+//!
+//! Every processed utterance is stored in SQLite with a full-text index over
+//! the transcript and the typed text, so past interactions can be searched,
+//! filtered and repeated.
+
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::cloud::{ActionResult, ActionType};
+use crate::commands::VoiceProcessingResult;
+use crate::config::RouteSource;
+
+/// Oldest rows are dropped once the history grows past this
+const MAX_ENTRIES: i64 = 10_000;
+
+/// Default and maximum page sizes for searches
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// A stored voice interaction
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub session_id: String,
+    pub transcript: String,
+    pub typed_text: Option<String>,
+    pub action_type: String,
+    pub active_app: Option<String>,
+    pub executed: bool,
+    pub result: VoiceProcessingResult,
+    /// Routed action, kept so the interaction can be repeated
+    #[serde(skip)]
+    pub action: Option<ActionResult>,
+    /// Where the action was routed from, so a repeat is confirmed the same way
+    #[serde(skip)]
+    pub route: Option<RouteSource>,
+}
+
+/// Search filters; every field is optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// Full-text search over transcripts and typed text
+    pub text: Option<String>,
+    /// Action type name, e.g. "OpenApp"
+    pub action_type: Option<String>,
+    /// Substring of the app that was active
+    pub app: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// One page of search results
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Matches across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Whether "repeat last command" may replay this action.
+/// Dictation, conversation and the undo/repeat commands themselves are skipped.
+pub fn is_repeatable(action: &ActionResult) -> bool {
+    match action.action_type {
+        ActionType::TypeText | ActionType::NoAction | ActionType::Respond | ActionType::Clarify => false,
+        ActionType::SystemControl => !matches!(
+            action.payload.get("action").and_then(|v| v.as_str()),
+            Some("repeat_command") | Some("undo_last_action")
+        ),
+        _ => true,
+    }
+}
+
+/// Turn free text into an FTS5 query that matches every word as a prefix
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Persistent storage for voice history
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+}
+
+impl HistoryStore {
+    /// Create or open the history database
+    pub fn new() -> Result<Self, String> {
+        let db_path = Self::get_db_path()?;
+
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let conn = Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let store = Self { conn: Mutex::new(conn) };
+        store.init_tables()?;
+        Ok(store)
+    }
+
+    fn get_db_path() -> Result<PathBuf, String> {
+        let data_dir = dirs_next::data_dir()
+            .ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("history.db"))
+    }
+
+    fn init_tables(&self) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS voice_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at INTEGER NOT NULL,
+                session_id TEXT NOT NULL,
+                transcript TEXT NOT NULL,
+                typed_text TEXT,
+                action_type TEXT NOT NULL,
+                active_app TEXT,
+                executed INTEGER NOT NULL,
+                repeatable INTEGER NOT NULL DEFAULT 0,
+                action_json TEXT NOT NULL,
+                result_json TEXT NOT NULL,
+                route TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_history_created_at ON voice_history(created_at);
+            CREATE INDEX IF NOT EXISTS idx_history_action_type ON voice_history(action_type);
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS voice_history_fts USING fts5(
+                transcript, typed_text, content='voice_history', content_rowid='id'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS voice_history_ai AFTER INSERT ON voice_history BEGIN
+                INSERT INTO voice_history_fts(rowid, transcript, typed_text)
+                VALUES (new.id, new.transcript, COALESCE(new.typed_text, ''));
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS voice_history_ad AFTER DELETE ON voice_history BEGIN
+                INSERT INTO voice_history_fts(voice_history_fts, rowid, transcript, typed_text)
+                VALUES ('delete', old.id, old.transcript, COALESCE(old.typed_text, ''));
+            END;
+            "
+        ).map_err(|e| format!("Failed to initialize tables: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Store a processed utterance and return its id
+    pub fn record(
+        &self,
+        result: &VoiceProcessingResult,
+        action: &ActionResult,
+        route: RouteSource,
+        active_app: Option<&str>,
+    ) -> Result<i64, String> {
+        let typed_text = if action.action_type == ActionType::TypeText {
+            action.refined_text.clone()
+        } else {
+            None
+        };
+        let action_json = serde_json::to_string(action)
+            .map_err(|e| format!("Failed to serialize action: {}", e))?;
+        let result_json = serde_json::to_string(result)
+            .map_err(|e| format!("Failed to serialize result: {}", e))?;
+        let route = serde_json::to_value(route)
+            .map_err(|e| format!("Failed to serialize route: {}", e))?;
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO voice_history (created_at, session_id, transcript, typed_text, action_type,
+                                        active_app, executed, repeatable, action_json, result_json, route)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                Utc::now().timestamp(),
+                result.session_id,
+                result.transcription.text,
+                typed_text,
+                result.action.action_type,
+                active_app,
+                result.executed,
+                is_repeatable(action),
+                action_json,
+                result_json,
+                route.as_str(),
+            ],
+        ).map_err(|e| format!("Failed to save history: {}", e))?;
+        let id = conn.last_insert_rowid();
+
+        conn.execute(
+            "DELETE FROM voice_history WHERE id <= ?1",
+            [id - MAX_ENTRIES],
+        ).map_err(|e| format!("Failed to prune history: {}", e))?;
+
+        Ok(id)
+    }
+
+    /// Search with filters, newest first
+    pub fn search(&self, query: &HistoryQuery) -> Result<HistoryPage, String> {
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+
+        if let Some(fts) = query.text.as_deref().and_then(fts_query) {
+            clauses.push("id IN (SELECT rowid FROM voice_history_fts WHERE voice_history_fts MATCH ?)");
+            args.push(Value::Text(fts));
+        }
+        if let Some(action_type) = query.action_type.as_deref().filter(|s| !s.trim().is_empty()) {
+            clauses.push("action_type = ?");
+            args.push(Value::Text(action_type.trim().to_string()));
+        }
+        if let Some(app) = query.app.as_deref().filter(|s| !s.trim().is_empty()) {
+            clauses.push("LOWER(active_app) LIKE ?");
+            args.push(Value::Text(format!("%{}%", app.trim().to_lowercase())));
+        }
+        if let Some(from) = query.from {
+            clauses.push("created_at >= ?");
+            args.push(Value::Integer(from.timestamp()));
+        }
+        if let Some(to) = query.to {
+            clauses.push("created_at <= ?");
+            args.push(Value::Integer(to.timestamp()));
+        }
+
+        let where_sql = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let offset = query.offset.unwrap_or(0);
+
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM voice_history {}", where_sql),
+                params_from_iter(args.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to count history: {}", e))?;
+
+        let mut page_args = args;
+        page_args.push(Value::Integer(limit as i64));
+        page_args.push(Value::Integer(offset as i64));
+        let entries = Self::query_entries(
+            &conn,
+            &format!("{} ORDER BY id DESC LIMIT ? OFFSET ?", where_sql),
+            page_args,
+        )?;
+
+        Ok(HistoryPage {
+            entries,
+            total: total as usize,
+            offset,
+            limit,
+        })
+    }
+
+    /// Most recent entries, newest first
+    pub fn recent(&self, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        Self::query_entries(&conn, "ORDER BY id DESC LIMIT ?", vec![Value::Integer(limit as i64)])
+    }
+
+    /// Get a single entry
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        Ok(Self::query_entries(&conn, "WHERE id = ?", vec![Value::Integer(id)])?
+            .into_iter()
+            .next())
+    }
+
+    /// The `n`-th most recent repeatable command (1 = last)
+    pub fn nth_command(&self, n: usize) -> Result<Option<HistoryEntry>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        Ok(Self::query_entries(
+            &conn,
+            "WHERE repeatable = 1 ORDER BY id DESC LIMIT 1 OFFSET ?",
+            vec![Value::Integer(n.saturating_sub(1) as i64)],
+        )?
+        .into_iter()
+        .next())
+    }
+
+    /// Delete all history
+    pub fn clear(&self) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM voice_history", [])
+            .map_err(|e| format!("Failed to clear history: {}", e))?;
+        Ok(())
+    }
+
+    fn query_entries(conn: &Connection, tail_sql: &str, args: Vec<Value>) -> Result<Vec<HistoryEntry>, String> {
+        let sql = format!(
+            "SELECT id, created_at, session_id, transcript, typed_text, action_type, active_app,
+                    executed, action_json, result_json, route
+             FROM voice_history {}",
+            tail_sql
+        );
+        let mut stmt = conn.prepare(&sql)
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt.query_map(params_from_iter(args.iter()), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, bool>(7)?,
+                row.get::<_, String>(8)?,
+                row.get::<_, String>(9)?,
+                row.get::<_, Option<String>>(10)?,
+            ))
+        }).map_err(|e| format!("Failed to query history: {}", e))?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, created_at, session_id, transcript, typed_text, action_type, active_app, executed, action_json, result_json, route) =
+                row.map_err(|e| format!("Failed to read history: {}", e))?;
+            let result = match serde_json::from_str(&result_json) {
+                Ok(result) => result,
+                Err(e) => {
+                    log::warn!("Skipping unreadable history entry {}: {}", id, e);
+                    continue;
+                }
+            };
+            entries.push(HistoryEntry {
+                id,
+                created_at: Utc.timestamp_opt(created_at, 0).single().unwrap_or_else(Utc::now),
+                session_id,
+                transcript,
+                typed_text,
+                action_type,
+                active_app,
+                executed,
+                result,
+                action: serde_json::from_str(&action_json).ok(),
+                route: route.and_then(|route| serde_json::from_value(serde_json::Value::String(route)).ok()),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{ActionResultResponse, TranscriptionResult};
+
+    fn in_memory() -> HistoryStore {
+        let store = HistoryStore {
+            conn: Mutex::new(Connection::open_in_memory().unwrap()),
+        };
+        store.init_tables().unwrap();
+        store
+    }
+
+    fn record(store: &HistoryStore, text: &str, action: ActionResult, app: &str) -> i64 {
+        let result = VoiceProcessingResult {
+            transcription: TranscriptionResult {
+                text: text.to_string(),
+                duration_ms: 900,
+                confidence: 1.0,
+                is_final: true,
+            },
+            action: ActionResultResponse {
+                action_type: format!("{:?}", action.action_type),
+                payload: action.payload.clone(),
+                refined_text: action.refined_text.clone(),
+                response_text: None,
+                requires_confirmation: false,
+                pending_action_id: None,
+            },
+            executed: true,
+            response_text: None,
+            session_id: "session".to_string(),
+        };
+        store.record(&result, &action, RouteSource::Local, Some(app)).unwrap()
+    }
+
+    #[test]
+    fn searches_filters_and_pages() {
+        let store = in_memory();
+        let mut dictation = ActionResult::action(ActionType::TypeText, serde_json::json!({}));
+        dictation.refined_text = Some("Meeting notes for Tuesday".to_string());
+        record(&store, "meeting notes for tuesday", dictation, "Notepad");
+        record(&store, "open chrome", ActionResult::action(ActionType::OpenApp, serde_json::json!({"app": "chrome"})), "Code");
+        record(&store, "open spotify", ActionResult::action(ActionType::OpenApp, serde_json::json!({"app": "spotify"})), "Code");
+
+        let page = store.search(&HistoryQuery { text: Some("tues".into()), ..Default::default() }).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].typed_text.as_deref(), Some("Meeting notes for Tuesday"));
+
+        let page = store
+            .search(&HistoryQuery { action_type: Some("OpenApp".into()), limit: Some(1), ..Default::default() })
+            .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].transcript, "open spotify");
+
+        let page = store.search(&HistoryQuery { app: Some("notepad".into()), ..Default::default() }).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(fts_query("  \"!! "), None);
+    }
+
+    #[test]
+    fn finds_commands_to_repeat() {
+        let store = in_memory();
+        record(&store, "open chrome", ActionResult::action(ActionType::OpenApp, serde_json::json!({"app": "chrome"})), "Code");
+        record(&store, "volume up", ActionResult::action(ActionType::VolumeControl, serde_json::json!({"direction": "up"})), "Code");
+        record(&store, "hello there", ActionResult::action(ActionType::TypeText, serde_json::json!({})), "Code");
+        record(
+            &store,
+            "repeat last command",
+            ActionResult::action(ActionType::SystemControl, serde_json::json!({"action": "repeat_command"})),
+            "Code",
+        );
+
+        let last = store.nth_command(1).unwrap().unwrap();
+        assert_eq!(last.transcript, "volume up");
+        assert_eq!(last.route, Some(RouteSource::Local));
+        let second = store.nth_command(2).unwrap().unwrap();
+        assert_eq!(second.action.unwrap().payload["app"], "chrome");
+        assert!(store.nth_command(3).unwrap().is_none());
+
+        store.clear().unwrap();
+        assert!(store.recent(10).unwrap().is_empty());
+    }
+}
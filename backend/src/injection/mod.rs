@@ -0,0 +1,346 @@
+//! Text injection for ListenOS
+//!
+//! Gets dictated text into the focused app. Backends are tried in the order
+//! configured for the active app; when one fails part-way, the next backend
+//! continues with the text that was not typed yet.
+
+#[cfg(target_os = "linux")]
+mod uinput;
+#[cfg(target_os = "linux")]
+mod x11_clipboard;
+
+use arboard::{Clipboard, ImageData};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::{InjectionBackend, TextInjectionConfig};
+
+/// Why a backend stopped, and how many characters it typed before that
+struct InjectFailure {
+    typed: usize,
+    error: String,
+}
+
+impl InjectFailure {
+    fn before_typing(error: impl Into<String>) -> Self {
+        Self { typed: 0, error: error.into() }
+    }
+}
+
+/// Type `text` into the focused app and return the backend that finished it
+pub async fn inject_text(
+    text: &str,
+    config: &TextInjectionConfig,
+    active_app: Option<&str>,
+) -> Result<InjectionBackend, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut typed = 0;
+    let mut errors = Vec::new();
+
+    for backend in config.backends_for_app(active_app) {
+        let remaining: String = chars[typed..].iter().collect();
+        let result = match backend {
+            InjectionBackend::ClipboardPaste => {
+                paste_via_clipboard(&remaining, config.exclude_from_clipboard_history).await
+            }
+            InjectionBackend::UnicodeKeys => {
+                // Enigo can't be held across an await, so type on a blocking thread
+                let (chunk_size, delay) = (config.chunk_size, config.chunk_delay_ms);
+                tokio::task::spawn_blocking(move || type_unicode_keys(&remaining, chunk_size, delay))
+                    .await
+                    .unwrap_or_else(|e| Err(InjectFailure::before_typing(e.to_string())))
+            }
+            InjectionBackend::Uinput => type_uinput(&remaining, config).await,
+            InjectionBackend::Xtest => type_with_tool("xdotool", &remaining, config).await,
+            InjectionBackend::Wtype => type_with_tool("wtype", &remaining, config).await,
+        };
+
+        match result {
+            Ok(()) => {
+                log::info!("Typed {} characters with {:?}", chars.len() - typed, backend);
+                return Ok(backend);
+            }
+            Err(failure) => {
+                log::warn!(
+                    "{:?} failed after {} characters: {}",
+                    backend,
+                    failure.typed,
+                    failure.error
+                );
+                typed = (typed + failure.typed).min(chars.len());
+                errors.push(format!("{:?}: {}", backend, failure.error));
+            }
+        }
+    }
+
+    Err(format!(
+        "Failed to type text into the focused application ({})",
+        errors.join("; ")
+    ))
+}
+
+/// Split text into chunks of at most `size` characters
+fn chunk_text(text: &str, size: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    chars.chunks(size.max(1)).map(|chunk| chunk.iter().collect()).collect()
+}
+
+// ============ Clipboard Paste ============
+
+/// The clipboard as it was before we pasted through it
+enum ClipboardSnapshot {
+    /// Every X11 target, restored together
+    #[cfg(target_os = "linux")]
+    Targets(x11_clipboard::Snapshot),
+    /// Everything arboard can read back, where raw targets aren't available
+    Formats {
+        text: Option<String>,
+        html: Option<String>,
+        image: Option<ImageData<'static>>,
+        files: Option<Vec<PathBuf>>,
+    },
+}
+
+impl ClipboardSnapshot {
+    async fn capture(clipboard: &mut Clipboard) -> Self {
+        #[cfg(target_os = "linux")]
+        if x11_clipboard::is_x11_session() {
+            // Each target is a round trip to the owning app
+            let captured = tokio::task::spawn_blocking(x11_clipboard::capture)
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()));
+            match captured {
+                Ok(snapshot) => return Self::Targets(snapshot),
+                Err(e) => log::warn!("Failed to snapshot clipboard targets: {}", e),
+            }
+        }
+        Self::Formats {
+            text: clipboard.get_text().ok(),
+            html: clipboard.get().html().ok(),
+            image: clipboard.get_image().ok(),
+            files: clipboard.get().file_list().ok().filter(|files| !files.is_empty()),
+        }
+    }
+
+    /// Put the saved clipboard back. arboard can only set one format, so
+    /// without raw targets the richest one wins; HTML keeps its plain-text
+    /// alternative and an empty clipboard is cleared again.
+    fn restore(self, clipboard: &mut Clipboard) -> Result<(), String> {
+        let (text, html, image, files) = match self {
+            #[cfg(target_os = "linux")]
+            Self::Targets(snapshot) => return snapshot.restore(),
+            Self::Formats { text, html, image, files } => (text, html, image, files),
+        };
+        let restored = if let Some(image) = image {
+            clipboard.set_image(image)
+        } else if let Some(files) = files {
+            clipboard.set().file_list(&files)
+        } else if let Some(html) = html {
+            clipboard.set_html(html, text)
+        } else if let Some(text) = text {
+            clipboard.set_text(text)
+        } else {
+            clipboard.clear()
+        };
+        restored.map_err(|e| e.to_string())
+    }
+}
+
+fn set_clipboard_text(clipboard: &mut Clipboard, text: &str, exclude_from_history: bool) -> Result<(), arboard::Error> {
+    #[allow(unused_mut)]
+    let mut set = clipboard.set();
+    if exclude_from_history {
+        #[cfg(target_os = "windows")]
+        {
+            use arboard::SetExtWindows;
+            set = set.exclude_from_history().exclude_from_cloud();
+        }
+        #[cfg(target_os = "macos")]
+        {
+            use arboard::SetExtApple;
+            set = set.exclude_from_history();
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            use arboard::SetExtLinux;
+            set = set.exclude_from_history();
+        }
+    }
+    set.text(text)
+}
+
+async fn paste_via_clipboard(text: &str, exclude_from_history: bool) -> Result<(), InjectFailure> {
+    let mut clipboard = Clipboard::new()
+        .map_err(|e| InjectFailure::before_typing(format!("Failed to access clipboard: {}", e)))?;
+
+    let snapshot = ClipboardSnapshot::capture(&mut clipboard).await;
+
+    // Set our text to clipboard with retry
+    let mut set_success = false;
+    for attempt in 1..=3 {
+        match set_clipboard_text(&mut clipboard, text, exclude_from_history) {
+            Ok(_) => {
+                // Verify the clipboard was actually set
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                if clipboard.get_text().is_ok_and(|current| current == text) {
+                    set_success = true;
+                    break;
+                }
+                log::warn!("Clipboard verification failed on attempt {}", attempt);
+            }
+            Err(e) => log::warn!("Failed to set clipboard on attempt {}: {}", attempt, e),
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let result = if set_success {
+        // Additional delay for clipboard to be fully ready
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut pasted = paste_shortcut();
+        if pasted.is_err() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            pasted = paste_shortcut();
+        }
+        pasted
+    } else {
+        Err("Failed to set clipboard after 3 attempts".to_string())
+    };
+
+    // Give the app time to read the clipboard before restoring it
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    if let Err(e) = snapshot.restore(&mut clipboard) {
+        log::warn!("Failed to restore previous clipboard content: {}", e);
+    }
+
+    result.map_err(InjectFailure::before_typing)
+}
+
+/// Press Ctrl+V (Cmd+V on macOS)
+fn paste_shortcut() -> Result<(), String> {
+    use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| format!("Failed to create enigo: {}", e))?;
+
+    #[cfg(target_os = "macos")]
+    let modifier = Key::Meta;
+    #[cfg(not(target_os = "macos"))]
+    let modifier = Key::Control;
+
+    enigo.key(modifier, Direction::Press)
+        .map_err(|e| format!("Failed to press modifier: {}", e))?;
+    std::thread::sleep(Duration::from_millis(20));
+    let clicked = enigo.key(Key::Unicode('v'), Direction::Click)
+        .map_err(|e| format!("Failed to press V: {}", e));
+    std::thread::sleep(Duration::from_millis(20));
+    enigo.key(modifier, Direction::Release)
+        .map_err(|e| format!("Failed to release modifier: {}", e))?;
+    clicked
+}
+
+// ============ Key Injection ============
+
+/// Send the text as Unicode key events, a chunk at a time
+fn type_unicode_keys(text: &str, chunk_size: usize, chunk_delay_ms: u64) -> Result<(), InjectFailure> {
+    use enigo::{Enigo, Keyboard, Settings};
+
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| InjectFailure::before_typing(format!("Failed to create enigo: {}", e)))?;
+
+    let mut typed = 0;
+    for chunk in chunk_text(text, chunk_size) {
+        enigo.text(&chunk).map_err(|e| InjectFailure {
+            typed,
+            error: format!("Failed to send keys: {}", e),
+        })?;
+        typed += chunk.chars().count();
+        std::thread::sleep(Duration::from_millis(chunk_delay_ms));
+    }
+    Ok(())
+}
+
+/// Type through `xdotool` (X11 XTest) or `wtype` (Wayland virtual keyboard)
+async fn type_with_tool(tool: &str, text: &str, config: &TextInjectionConfig) -> Result<(), InjectFailure> {
+    if !cfg!(target_os = "linux") {
+        return Err(InjectFailure::before_typing(format!("{} is only used on Linux", tool)));
+    }
+    let session_var = if tool == "wtype" { "WAYLAND_DISPLAY" } else { "DISPLAY" };
+    if std::env::var_os(session_var).is_none() {
+        return Err(InjectFailure::before_typing(format!("{} is not set", session_var)));
+    }
+
+    let mut typed = 0;
+    for chunk in chunk_text(text, config.chunk_size) {
+        let mut command = tokio::process::Command::new(tool);
+        if tool == "xdotool" {
+            command.args(["type", "--clearmodifiers", "--delay", "0", "--"]);
+        } else {
+            command.arg("--");
+        }
+        let output = command.arg(&chunk).output().await.map_err(|e| InjectFailure {
+            typed,
+            error: format!("Failed to run {}: {}", tool, e),
+        })?;
+        if !output.status.success() {
+            return Err(InjectFailure {
+                typed,
+                error: format!("{} failed: {}", tool, String::from_utf8_lossy(&output.stderr).trim()),
+            });
+        }
+        typed += chunk.chars().count();
+        tokio::time::sleep(Duration::from_millis(config.chunk_delay_ms)).await;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+async fn type_uinput(text: &str, config: &TextInjectionConfig) -> Result<(), InjectFailure> {
+    let keys = uinput::keys_for_text(text).map_err(InjectFailure::before_typing)?;
+    let mut keyboard = uinput::VirtualKeyboard::create().map_err(InjectFailure::before_typing)?;
+
+    // The compositor needs a moment to pick up the new device
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut typed = 0;
+    for chunk in keys.chunks(config.chunk_size.max(1)) {
+        for key in chunk {
+            keyboard.tap(*key).map_err(|error| InjectFailure { typed, error })?;
+            typed += 1;
+        }
+        tokio::time::sleep(Duration::from_millis(config.chunk_delay_ms)).await;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn type_uinput(_text: &str, _config: &TextInjectionConfig) -> Result<(), InjectFailure> {
+    Err(InjectFailure::before_typing("uinput is only available on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppInjectionRule;
+
+    #[test]
+    fn picks_backends_per_app_and_chunks_text() {
+        let config = TextInjectionConfig {
+            backends: vec![InjectionBackend::ClipboardPaste],
+            app_rules: vec![AppInjectionRule {
+                app: "Terminal".to_string(),
+                backends: vec![InjectionBackend::UnicodeKeys, InjectionBackend::Xtest],
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.backends_for_app(Some("GNOME Terminal")),
+            vec![InjectionBackend::UnicodeKeys, InjectionBackend::Xtest]
+        );
+        assert_eq!(config.backends_for_app(Some("Firefox")), vec![InjectionBackend::ClipboardPaste]);
+        assert_eq!(config.backends_for_app(None), vec![InjectionBackend::ClipboardPaste]);
+
+        assert_eq!(chunk_text("héllo wörld", 4), vec!["héll", "o wö", "rld"]);
+        assert_eq!(chunk_text("abc", 0), vec!["a", "b", "c"]);
+    }
+}
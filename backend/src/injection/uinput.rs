@@ -0,0 +1,194 @@
+//! Virtual keyboard through /dev/uinput
+//!
+//! Works under X11 and Wayland alike, but sends raw key codes, so only ASCII
+//! on a US layout can be typed. Needs write access to /dev/uinput.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const SYN_REPORT: u16 = 0;
+const BUS_USB: u16 = 0x03;
+
+// ioctl requests from <linux/uinput.h>
+const UI_SET_EVBIT: u32 = 0x4004_5564;
+const UI_SET_KEYBIT: u32 = 0x4004_5565;
+const UI_DEV_SETUP: u32 = 0x405c_5503;
+const UI_DEV_CREATE: u32 = 0x5501;
+const UI_DEV_DESTROY: u32 = 0x5502;
+
+const KEY_TAB: u16 = 15;
+const KEY_ENTER: u16 = 28;
+const KEY_LEFTSHIFT: u16 = 42;
+const KEY_SPACE: u16 = 57;
+
+/// A key code and whether Shift is held for it
+pub type KeyStroke = (u16, bool);
+
+/// US-layout key strokes for `text`, or an error naming the first character
+/// that has no key
+pub fn keys_for_text(text: &str) -> Result<Vec<KeyStroke>, String> {
+    text.chars()
+        .map(|c| key_for_char(c).ok_or_else(|| format!("uinput can't type '{}'", c)))
+        .collect()
+}
+
+fn key_for_char(c: char) -> Option<KeyStroke> {
+    const LETTERS: [u16; 26] = [
+        30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50, 49, 24, 25, 16, 19, 31, 20, 22, 47, 17,
+        45, 21, 44,
+    ];
+    const SHIFTED_DIGITS: &str = ")!@#$%^&*(";
+
+    if c.is_ascii_lowercase() {
+        return Some((LETTERS[(c as u8 - b'a') as usize], false));
+    }
+    if c.is_ascii_uppercase() {
+        return Some((LETTERS[(c as u8 - b'A') as usize], true));
+    }
+    if let Some(digit) = c.to_digit(10) {
+        return Some((digit_key(digit), false));
+    }
+    if let Some(digit) = SHIFTED_DIGITS.find(c) {
+        return Some((digit_key(digit as u32), true));
+    }
+
+    let key = match c {
+        ' ' => (KEY_SPACE, false),
+        '\n' => (KEY_ENTER, false),
+        '\t' => (KEY_TAB, false),
+        '-' => (12, false),
+        '_' => (12, true),
+        '=' => (13, false),
+        '+' => (13, true),
+        '[' => (26, false),
+        '{' => (26, true),
+        ']' => (27, false),
+        '}' => (27, true),
+        ';' => (39, false),
+        ':' => (39, true),
+        '\'' => (40, false),
+        '"' => (40, true),
+        '`' => (41, false),
+        '~' => (41, true),
+        '\\' => (43, false),
+        '|' => (43, true),
+        ',' => (51, false),
+        '<' => (51, true),
+        '.' => (52, false),
+        '>' => (52, true),
+        '/' => (53, false),
+        '?' => (53, true),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// KEY_1..KEY_9 are 2..10 and KEY_0 is 11
+fn digit_key(digit: u32) -> u16 {
+    if digit == 0 {
+        11
+    } else {
+        digit as u16 + 1
+    }
+}
+
+fn ioctl(file: &File, request: u32, arg: libc::c_ulong) -> Result<(), String> {
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request as libc::Ioctl, arg) };
+    if result < 0 {
+        Err(std::io::Error::last_os_error().to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// A keyboard device that exists until dropped
+pub struct VirtualKeyboard {
+    file: File,
+}
+
+impl VirtualKeyboard {
+    pub fn create() -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")
+            .map_err(|e| format!("Can't open /dev/uinput: {}", e))?;
+
+        ioctl(&file, UI_SET_EVBIT, EV_KEY as libc::c_ulong)?;
+        for code in 1..=KEY_SPACE {
+            ioctl(&file, UI_SET_KEYBIT, code as libc::c_ulong)?;
+        }
+
+        let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+        setup.id.bustype = BUS_USB;
+        setup.id.vendor = 0x1209;
+        setup.id.product = 0x4c4f;
+        for (slot, byte) in setup.name.iter_mut().zip(b"ListenOS virtual keyboard") {
+            *slot = *byte as libc::c_char;
+        }
+        ioctl(&file, UI_DEV_SETUP, &setup as *const libc::uinput_setup as libc::c_ulong)?;
+        ioctl(&file, UI_DEV_CREATE, 0)?;
+
+        Ok(Self { file })
+    }
+
+    fn emit(&mut self, kind: u16, code: u16, value: i32) -> Result<(), String> {
+        let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+        event.type_ = kind;
+        event.code = code;
+        event.value = value;
+
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &event as *const libc::input_event as *const u8,
+                std::mem::size_of::<libc::input_event>(),
+            )
+        };
+        self.file
+            .write_all(bytes)
+            .map_err(|e| format!("Failed to write key event: {}", e))
+    }
+
+    fn key(&mut self, code: u16, pressed: bool) -> Result<(), String> {
+        self.emit(EV_KEY, code, pressed as i32)?;
+        self.emit(EV_SYN, SYN_REPORT, 0)
+    }
+
+    /// Press and release one key, holding Shift if needed
+    pub fn tap(&mut self, (code, shift): KeyStroke) -> Result<(), String> {
+        if shift {
+            self.key(KEY_LEFTSHIFT, true)?;
+        }
+        self.key(code, true)?;
+        self.key(code, false)?;
+        if shift {
+            self.key(KEY_LEFTSHIFT, false)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for VirtualKeyboard {
+    fn drop(&mut self) {
+        let _ = ioctl(&self.file, UI_DEV_DESTROY, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_ascii_to_us_keys() {
+        assert_eq!(
+            keys_for_text("Hi 1!").unwrap(),
+            vec![(35, true), (23, false), (KEY_SPACE, false), (2, false), (2, true)]
+        );
+        assert_eq!(key_for_char('0'), Some((11, false)));
+        assert!(keys_for_text("café").is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Raw X11 clipboard snapshots
+//!
+//! arboard reads and writes one format at a time, so restoring through it
+//! keeps only the richest format. Here every target the clipboard owner
+//! offers is copied as-is, and a background thread serves all of them again
+//! until another app takes the clipboard.
+
+use std::time::{Duration, Instant};
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode, SelectionNotifyEvent,
+    SelectionRequestEvent, Window, WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::CURRENT_TIME;
+
+/// How long the clipboard owner gets to answer one conversion
+const CONVERT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Targets that describe the selection or act on it instead of holding data
+const META_TARGETS: &[&str] = &["TARGETS", "MULTIPLE", "TIMESTAMP", "SAVE_TARGETS", "DELETE"];
+
+/// One clipboard target and its data, as the owner sent it
+struct Target {
+    name: Atom,
+    kind: Atom,
+    format: u8,
+    data: Vec<u8>,
+}
+
+/// Every data target the clipboard held
+pub struct Snapshot {
+    targets: Vec<Target>,
+}
+
+struct Session {
+    conn: RustConnection,
+    window: Window,
+    clipboard: Atom,
+}
+
+impl Session {
+    fn open() -> Result<Self, String> {
+        let (conn, screen) =
+            x11rb::connect(None).map_err(|e| format!("Failed to connect to X server: {}", e))?;
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().map_err(|e| e.to_string())?;
+        conn.create_window(
+            0,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            0,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .map_err(|e| format!("Failed to create clipboard window: {}", e))?;
+        let clipboard = intern(&conn, "CLIPBOARD")?;
+        Ok(Self { conn, window, clipboard })
+    }
+
+    /// Ask the owner for `target` and read the answer back, None when it
+    /// refuses, times out, or wants an incremental transfer
+    fn convert(&self, target: Atom, property: Atom) -> Result<Option<Target>, String> {
+        self.conn
+            .convert_selection(self.window, self.clipboard, target, property, CURRENT_TIME)
+            .map_err(|e| e.to_string())?;
+        self.conn.flush().map_err(|e| e.to_string())?;
+
+        let deadline = Instant::now() + CONVERT_TIMEOUT;
+        let notify = loop {
+            match self.conn.poll_for_event().map_err(|e| e.to_string())? {
+                Some(Event::SelectionNotify(event)) if event.target == target => break event,
+                Some(_) => {}
+                None if Instant::now() >= deadline => return Ok(None),
+                None => std::thread::sleep(Duration::from_millis(5)),
+            }
+        };
+        if notify.property == u32::from(AtomEnum::NONE) {
+            return Ok(None);
+        }
+
+        let reply = self
+            .conn
+            .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX / 4)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("Failed to read the clipboard: {}", e))?;
+        if reply.type_ == intern(&self.conn, "INCR")? {
+            log::debug!("Skipping clipboard target {} sent incrementally", target);
+            return Ok(None);
+        }
+        Ok(Some(Target { name: target, kind: reply.type_, format: reply.format, data: reply.value }))
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.conn.destroy_window(self.window);
+        let _ = self.conn.flush();
+    }
+}
+
+fn intern(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    conn.intern_atom(false, name.as_bytes())
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map(|reply| reply.atom)
+        .map_err(|e| format!("Failed to look up {}: {}", name, e))
+}
+
+/// Whether the clipboard should be handled over X11 (not a Wayland session)
+pub fn is_x11_session() -> bool {
+    std::env::var_os("DISPLAY").is_some() && std::env::var_os("WAYLAND_DISPLAY").is_none()
+}
+
+/// Copy every data target the clipboard currently offers
+pub fn capture() -> Result<Snapshot, String> {
+    let session = Session::open()?;
+    let property = intern(&session.conn, "LISTENOS_CLIPBOARD")?;
+    let targets_atom = intern(&session.conn, "TARGETS")?;
+
+    let mut meta = Vec::new();
+    for name in META_TARGETS {
+        meta.push(intern(&session.conn, name)?);
+    }
+
+    let Some(list) = session.convert(targets_atom, property)? else {
+        return Ok(Snapshot { targets: Vec::new() });
+    };
+    let names: Vec<Atom> = list
+        .data
+        .chunks_exact(4)
+        .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .filter(|atom| !meta.contains(atom))
+        .collect();
+
+    let mut targets = Vec::new();
+    for name in names {
+        if let Some(target) = session.convert(name, property)? {
+            targets.push(target);
+        }
+    }
+    Ok(Snapshot { targets })
+}
+
+impl Snapshot {
+    /// Take the clipboard and serve every saved target from a background
+    /// thread until another app copies something. An empty snapshot just
+    /// gives the clipboard up.
+    pub fn restore(self) -> Result<(), String> {
+        let session = Session::open()?;
+        let owner = if self.targets.is_empty() { u32::from(AtomEnum::NONE) } else { session.window };
+        session
+            .conn
+            .set_selection_owner(owner, session.clipboard, CURRENT_TIME)
+            .map_err(|e| e.to_string())?;
+        let current = session
+            .conn
+            .get_selection_owner(session.clipboard)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("Failed to take the clipboard: {}", e))?;
+        if self.targets.is_empty() {
+            return Ok(());
+        }
+        if current.owner != session.window {
+            return Err("Another app took the clipboard first".to_string());
+        }
+
+        let targets_atom = intern(&session.conn, "TARGETS")?;
+        std::thread::spawn(move || self.serve(session, targets_atom));
+        Ok(())
+    }
+
+    fn serve(self, session: Session, targets_atom: Atom) {
+        loop {
+            match session.conn.wait_for_event() {
+                Ok(Event::SelectionRequest(request)) => {
+                    if let Err(e) = self.answer(&session, targets_atom, &request) {
+                        log::warn!("Failed to answer a clipboard request: {}", e);
+                    }
+                }
+                Ok(Event::SelectionClear(_)) => return,
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("Lost the X connection serving the clipboard: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn answer(&self, session: &Session, targets_atom: Atom, request: &SelectionRequestEvent) -> Result<(), String> {
+        let conn = &session.conn;
+        // Obsolete clients leave the property unset and expect the target name
+        let property = if request.property == u32::from(AtomEnum::NONE) { request.target } else { request.property };
+        // Anything too big for one request would need INCR, which we don't speak
+        let max_bytes = conn.maximum_request_bytes().saturating_sub(32);
+
+        let stored = if request.target == targets_atom {
+            let mut atoms = vec![targets_atom];
+            atoms.extend(self.targets.iter().map(|target| target.name));
+            let data: Vec<u8> = atoms.iter().flat_map(|atom| atom.to_ne_bytes()).collect();
+            conn.change_property(PropMode::REPLACE, request.requestor, property, AtomEnum::ATOM, 32, atoms.len() as u32, &data)
+                .map_err(|e| e.to_string())?;
+            true
+        } else if let Some(target) = self
+            .targets
+            .iter()
+            .find(|target| target.name == request.target && target.data.len() <= max_bytes)
+        {
+            let unit = (target.format as usize / 8).max(1);
+            conn.change_property(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                target.kind,
+                target.format,
+                (target.data.len() / unit) as u32,
+                &target.data,
+            )
+            .map_err(|e| e.to_string())?;
+            true
+        } else {
+            false
+        };
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if stored { property } else { AtomEnum::NONE.into() },
+        };
+        conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify)
+            .map_err(|e| e.to_string())?;
+        conn.flush().map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,266 @@
+//! Media Player Integration for ListenOS
+//!
+//! Controls whatever is playing. On Linux every MPRIS player can be
+//! targeted by name ("pause Firefox") and supports seek, shuffle, loop,
+//! volume and "what's playing". On Windows the basic transport controls are
+//! sent as media keys.
+
+use super::{ActionParameter, AppIntegration, IntegrationAction, IntegrationResult};
+#[cfg(target_os = "linux")]
+use super::mpris::{choose, LoopMode, Mpris};
+
+/// How much "player volume up/down" changes the volume
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const VOLUME_STEP: f64 = 0.1;
+
+pub struct MediaIntegration;
+
+impl MediaIntegration {
+    pub fn new() -> Self {
+        Self
+    }
+
+    #[cfg(target_os = "linux")]
+    fn run(action: &str, params: &serde_json::Value) -> Result<IntegrationResult, String> {
+        let mpris = Mpris::session()?;
+        if action == "media_list_players" {
+            let players = mpris.players()?;
+            if players.is_empty() {
+                return Ok(IntegrationResult::success("No media players are running"));
+            }
+            let names: Vec<String> = players
+                .iter()
+                .map(|p| format!("{} ({})", p.display_name(), p.status.to_lowercase()))
+                .collect();
+            return Ok(IntegrationResult::success_with_data(
+                format!("Players: {}", names.join(", ")),
+                serde_json::json!({ "players": players }),
+            ));
+        }
+
+        // Spoken commands pass whatever followed "pause" or "next track on";
+        // only a running player's name targets it, anything else ("pause the
+        // video") goes to the active player
+        let players = mpris.players()?;
+        let named = params.get("player").and_then(|v| v.as_str());
+        let player = match choose(&players, named) {
+            Ok(player) => player,
+            Err(e) => {
+                log::debug!("{}; using the active player", e);
+                choose(&players, None)?
+            }
+        }
+        .clone();
+        let name = player.display_name().to_string();
+        let number = |key: &str| params.get(key).and_then(|v| v.as_f64());
+
+        let message = match action {
+            "media_play" => {
+                mpris.play(&player)?;
+                format!("Playing {}", name)
+            }
+            "media_pause" => {
+                mpris.pause(&player)?;
+                format!("Paused {}", name)
+            }
+            "media_play_pause" => {
+                mpris.play_pause(&player)?;
+                format!("Toggled {}", name)
+            }
+            "media_stop" => {
+                mpris.stop(&player)?;
+                format!("Stopped {}", name)
+            }
+            "media_next" => {
+                mpris.next(&player)?;
+                format!("Next track on {}", name)
+            }
+            "media_previous" => {
+                mpris.previous(&player)?;
+                format!("Previous track on {}", name)
+            }
+            "media_seek" => {
+                if let Some(position) = number("position") {
+                    mpris.set_position(&player, position)?;
+                    format!("Jumped to {}", format_time(position))
+                } else {
+                    let seconds = number("seconds").unwrap_or(10.0);
+                    mpris.seek(&player, seconds)?;
+                    let direction = if seconds < 0.0 { "back" } else { "forward" };
+                    format!("Skipped {} {}", direction, format_time(seconds.abs()))
+                }
+            }
+            "media_shuffle" => {
+                let enabled = mpris.shuffle(&player, params.get("enabled").and_then(|v| v.as_bool()))?;
+                format!("Shuffle {} on {}", if enabled { "on" } else { "off" }, name)
+            }
+            "media_loop" => {
+                let mode = params.get("mode").and_then(|v| v.as_str()).map(|mode| {
+                    LoopMode::parse(mode).ok_or_else(|| format!("Unknown loop mode: {}", mode))
+                });
+                let mode = mpris.set_loop(&player, mode.transpose()?)?;
+                match mode {
+                    LoopMode::None => format!("Repeat off on {}", name),
+                    LoopMode::Track => format!("Repeating this track on {}", name),
+                    LoopMode::Playlist => format!("Repeating the playlist on {}", name),
+                }
+            }
+            "media_volume" => {
+                let volume = match (number("level"), params.get("direction").and_then(|v| v.as_str())) {
+                    (Some(level), _) => level / 100.0,
+                    (None, Some("down")) => mpris.volume(&player)? - VOLUME_STEP,
+                    (None, Some("mute")) => 0.0,
+                    _ => mpris.volume(&player)? + VOLUME_STEP,
+                };
+                let volume = volume.clamp(0.0, 1.0);
+                mpris.set_volume(&player, volume)?;
+                format!("{} volume {}%", name, (volume * 100.0).round())
+            }
+            "media_now_playing" => {
+                let now = mpris.now_playing(&player)?;
+                return Ok(IntegrationResult::success_with_data(
+                    now.describe(),
+                    serde_json::to_value(&now).unwrap_or_default(),
+                ));
+            }
+            _ => return Err(format!("Unknown media action: {}", action)),
+        };
+        Ok(IntegrationResult::success(message))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn run(action: &str, _params: &serde_json::Value) -> Result<IntegrationResult, String> {
+        let (key, message) = match action {
+            "media_play" | "media_pause" | "media_play_pause" => ("play_pause", "Toggled play/pause"),
+            "media_stop" => ("stop", "Stopped playback"),
+            "media_next" => ("next", "Skipped to next track"),
+            "media_previous" => ("previous", "Went to previous track"),
+            _ => return Err(format!("{} needs MPRIS, which is only available on Linux", action)),
+        };
+        super::spotify::SpotifyIntegration::send_media_key(key)?;
+        Ok(IntegrationResult::success(message))
+    }
+}
+
+/// 75.0 -> "1:15", 30.0 -> "30 seconds"
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds < 60 {
+        format!("{} seconds", seconds)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+impl Default for MediaIntegration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn player_param() -> ActionParameter {
+    ActionParameter {
+        name: "player".to_string(),
+        param_type: "string".to_string(),
+        required: false,
+        description: "Player name, e.g. firefox or spotify; defaults to the one playing".to_string(),
+    }
+}
+
+fn action(id: &str, name: &str, description: &str, parameters: Vec<ActionParameter>, examples: &[&str]) -> IntegrationAction {
+    let mut parameters = parameters;
+    parameters.push(player_param());
+    IntegrationAction {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters,
+        example_phrases: examples.iter().map(|e| e.to_string()).collect(),
+    }
+}
+
+fn param(name: &str, param_type: &str, description: &str) -> ActionParameter {
+    ActionParameter {
+        name: name.to_string(),
+        param_type: param_type.to_string(),
+        required: false,
+        description: description.to_string(),
+    }
+}
+
+impl AppIntegration for MediaIntegration {
+    fn name(&self) -> &str {
+        "media"
+    }
+
+    fn description(&self) -> &str {
+        "Control any media player - play, pause, skip, seek, shuffle, repeat and what's playing"
+    }
+
+    fn is_available(&self) -> bool {
+        cfg!(any(target_os = "linux", windows))
+    }
+
+    fn supported_actions(&self) -> Vec<IntegrationAction> {
+        let mut actions = vec![
+            action("media_play", "Play", "Start or resume playback", vec![], &["play", "resume"]),
+            action("media_pause", "Pause", "Pause playback", vec![], &["pause", "pause firefox"]),
+            action("media_play_pause", "Play/Pause", "Toggle playback", vec![], &["play pause"]),
+            action("media_stop", "Stop", "Stop playback", vec![], &["stop the music"]),
+            action("media_next", "Next Track", "Skip to the next track", vec![], &["next song", "skip"]),
+            action("media_previous", "Previous Track", "Go to the previous track", vec![], &["previous song"]),
+        ];
+        if cfg!(target_os = "linux") {
+            actions.extend([
+                action(
+                    "media_seek",
+                    "Seek",
+                    "Skip forward or back, or jump to a position",
+                    vec![
+                        param("seconds", "number", "Seconds to move; negative goes back"),
+                        param("position", "number", "Absolute position in seconds"),
+                    ],
+                    &["skip forward 30 seconds", "rewind 10 seconds"],
+                ),
+                action(
+                    "media_shuffle",
+                    "Shuffle",
+                    "Turn shuffle on or off",
+                    vec![param("enabled", "boolean", "On or off; toggles when omitted")],
+                    &["shuffle on", "turn off shuffle"],
+                ),
+                action(
+                    "media_loop",
+                    "Repeat",
+                    "Set the repeat mode",
+                    vec![param("mode", "string", "none, track or playlist; cycles when omitted")],
+                    &["repeat this song", "loop off"],
+                ),
+                action(
+                    "media_volume",
+                    "Player Volume",
+                    "Set the player's own volume",
+                    vec![
+                        param("level", "number", "Volume from 0 to 100"),
+                        param("direction", "string", "up, down or mute"),
+                    ],
+                    &["player volume 40", "music volume down"],
+                ),
+                action("media_now_playing", "What's Playing", "Say what is playing", vec![], &["what's playing"]),
+                IntegrationAction {
+                    id: "media_list_players".to_string(),
+                    name: "List Players".to_string(),
+                    description: "List running media players".to_string(),
+                    parameters: vec![],
+                    example_phrases: vec!["list media players".to_string()],
+                },
+            ]);
+        }
+        actions
+    }
+
+    fn execute(&self, action: &str, params: &serde_json::Value) -> Result<IntegrationResult, String> {
+        Self::run(action, params)
+    }
+}
@@ -1,14 +1,18 @@
 //! App Integration Framework for ListenOS
 //!
 //! Provides modular integrations with popular applications
-//! like Spotify, Discord, Slack, and system controls.
+//! like Spotify, Discord, Slack, media players and system controls.
 
 pub mod spotify;
 pub mod discord;
 pub mod system_controls;
+pub mod media;
+#[cfg(target_os = "linux")]
+mod mpris;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Trait for app integrations
 pub trait AppIntegration: Send + Sync {
@@ -81,9 +85,17 @@ impl IntegrationResult {
     }
 }
 
+/// An integration action that passed the checks in `IntegrationManager::prepare`
+pub enum PreparedAction {
+    /// A dry run recorded the call; this is its result
+    Simulated(IntegrationResult),
+    /// Run it with `AppIntegration::execute`, off the async runtime if it blocks
+    Ready(Arc<dyn AppIntegration>),
+}
+
 /// Manages all app integrations
 pub struct IntegrationManager {
-    integrations: HashMap<String, Box<dyn AppIntegration>>,
+    integrations: HashMap<String, Arc<dyn AppIntegration>>,
     enabled: HashMap<String, bool>,
 }
 
@@ -99,6 +111,7 @@ impl IntegrationManager {
         manager.register(Box::new(spotify::SpotifyIntegration::new()));
         manager.register(Box::new(discord::DiscordIntegration::new()));
         manager.register(Box::new(system_controls::SystemControlsIntegration::new()));
+        manager.register(Box::new(media::MediaIntegration::new()));
 
         manager
     }
@@ -107,7 +120,7 @@ impl IntegrationManager {
     pub fn register(&mut self, integration: Box<dyn AppIntegration>) {
         let name = integration.name().to_string();
         self.enabled.insert(name.clone(), true);
-        self.integrations.insert(name, integration);
+        self.integrations.insert(name, Arc::from(integration));
     }
 
     /// Get all registered integrations
@@ -151,6 +164,20 @@ impl IntegrationManager {
         action: &str,
         params: &serde_json::Value,
     ) -> Result<IntegrationResult, String> {
+        match self.prepare(integration_name, action, params)? {
+            PreparedAction::Simulated(result) => Ok(result),
+            PreparedAction::Ready(integration) => integration.execute(action, params),
+        }
+    }
+
+    /// Check that an action may run and hand out its integration, so the
+    /// caller can run it without holding the manager
+    pub fn prepare(
+        &self,
+        integration_name: &str,
+        action: &str,
+        params: &serde_json::Value,
+    ) -> Result<PreparedAction, String> {
         // Check if enabled
         if !self.is_enabled(integration_name) {
             return Err(format!("Integration '{}' is disabled", integration_name));
@@ -162,6 +189,18 @@ impl IntegrationManager {
             .get(integration_name)
             .ok_or_else(|| format!("Integration '{}' not found", integration_name))?;
 
+        // Dry runs record the call instead of touching the app or system
+        if crate::simulation::intercept(
+            crate::simulation::EffectKind::Integration,
+            format!("{}: {}", integration.name(), action),
+            params.clone(),
+        ) {
+            return Ok(PreparedAction::Simulated(IntegrationResult::success_with_data(
+                format!("{} ({})", action, integration.name()),
+                serde_json::json!({ "simulated": true }),
+            )));
+        }
+
         // Check availability
         if !integration.is_available() {
             return Err(format!(
@@ -170,8 +209,7 @@ impl IntegrationManager {
             ));
         }
 
-        // Execute
-        integration.execute(action, params)
+        Ok(PreparedAction::Ready(Arc::clone(integration)))
     }
 
     /// Find which integration can handle a given action
@@ -0,0 +1,520 @@
+//! MPRIS media player control over the D-Bus session bus
+//!
+//! Every player that implements `org.mpris.MediaPlayer2` (Spotify, Firefox,
+//! Chromium, VLC, mpv with the plugin, ...) can be listed, picked by name
+//! and controlled through the same calls.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use zbus::blocking::{fdo::DBusProxy, proxy::Builder, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+
+const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// A running player
+#[derive(Debug, Clone, Serialize)]
+pub struct Player {
+    /// Bus name, e.g. "org.mpris.MediaPlayer2.firefox.instance_1_42"
+    pub bus_name: String,
+    /// Human-readable name from the player, e.g. "Mozilla Firefox"
+    pub identity: String,
+    /// "Playing", "Paused" or "Stopped"
+    pub status: String,
+}
+
+impl Player {
+    /// "org.mpris.MediaPlayer2.firefox.instance_1_42" -> "firefox"
+    pub fn short_name(&self) -> &str {
+        let rest = self.bus_name.strip_prefix(BUS_PREFIX).unwrap_or(&self.bus_name);
+        rest.split('.').next().unwrap_or(rest)
+    }
+
+    /// Name to use in messages
+    pub fn display_name(&self) -> &str {
+        if self.identity.is_empty() {
+            self.short_name()
+        } else {
+            &self.identity
+        }
+    }
+}
+
+/// What a player is playing, from its `Metadata`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NowPlaying {
+    pub player: String,
+    pub status: String,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub url: Option<String>,
+    pub length_secs: Option<u64>,
+    pub position_secs: Option<u64>,
+}
+
+impl NowPlaying {
+    /// "Blinding Lights by The Weeknd (Spotify, paused)"
+    pub fn describe(&self) -> String {
+        let track = match (&self.title, self.artists.is_empty()) {
+            (Some(title), false) => format!("{} by {}", title, self.artists.join(", ")),
+            (Some(title), true) => title.clone(),
+            (None, _) => "Nothing".to_string(),
+        };
+        if self.status == "Playing" {
+            format!("{} ({})", track, self.player)
+        } else {
+            format!("{} ({}, {})", track, self.player, self.status.to_lowercase())
+        }
+    }
+}
+
+/// Repeat mode, MPRIS `LoopStatus`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+    None,
+    Track,
+    Playlist,
+}
+
+impl LoopMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "none" | "off" => Some(Self::None),
+            "track" | "song" | "one" => Some(Self::Track),
+            "playlist" | "all" | "on" => Some(Self::Playlist),
+            _ => None,
+        }
+    }
+
+    fn as_mpris(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Track => "Track",
+            Self::Playlist => "Playlist",
+        }
+    }
+}
+
+/// Pick a player: the one named, else one that is playing, else one that is
+/// paused, else any
+pub fn choose<'p>(players: &'p [Player], name: Option<&str>) -> Result<&'p Player, String> {
+    if players.is_empty() {
+        return Err("No media players are running".to_string());
+    }
+
+    let Some(name) = name.map(|n| n.trim().to_lowercase()).filter(|n| !n.is_empty()) else {
+        return Ok(["Playing", "Paused"]
+            .iter()
+            .find_map(|status| players.iter().find(|p| p.status == *status))
+            .unwrap_or(&players[0]));
+    };
+
+    let exact = players.iter().find(|p| {
+        p.short_name().eq_ignore_ascii_case(&name) || p.identity.eq_ignore_ascii_case(&name)
+    });
+    let compact: String = name.split_whitespace().collect();
+    exact
+        .or_else(|| {
+            players.iter().find(|p| {
+                let identity = p.identity.to_lowercase();
+                let short = p.short_name().to_lowercase();
+                identity.contains(&name) || short.contains(&compact) || name.contains(&short)
+            })
+        })
+        .ok_or_else(|| {
+            let running: Vec<&str> = players.iter().map(|p| p.display_name()).collect();
+            format!("No player called {} (running: {})", name, running.join(", "))
+        })
+}
+
+fn string_of(value: &Value) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(s.to_string()),
+        Value::ObjectPath(path) => Some(path.to_string()),
+        Value::Value(inner) => string_of(inner),
+        _ => None,
+    }
+}
+
+fn strings_of(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(string_of).collect(),
+        Value::Value(inner) => strings_of(inner),
+        other => string_of(other).into_iter().collect(),
+    }
+}
+
+fn integer_of(value: &Value) -> Option<i64> {
+    match value {
+        Value::I64(n) => Some(*n),
+        Value::U64(n) => i64::try_from(*n).ok(),
+        Value::I32(n) => Some(i64::from(*n)),
+        Value::U32(n) => Some(i64::from(*n)),
+        Value::Value(inner) => integer_of(inner),
+        _ => None,
+    }
+}
+
+/// A session bus connection
+pub struct Mpris {
+    conn: Connection,
+}
+
+impl Mpris {
+    pub fn session() -> Result<Self, String> {
+        let conn = Connection::session().map_err(|e| format!("Failed to connect to the session bus: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    #[cfg(test)]
+    fn with_connection(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    fn proxy(&self, bus_name: &str, interface: &'static str) -> Result<Proxy<'static>, String> {
+        Builder::new(&self.conn)
+            .destination(bus_name.to_string())
+            .and_then(|b| b.path(OBJECT_PATH))
+            .and_then(|b| b.interface(interface))
+            .map(|b| b.cache_properties(CacheProperties::No))
+            .and_then(|b| b.build())
+            .map_err(|e| format!("Failed to reach {}: {}", bus_name, e))
+    }
+
+    fn get<T>(&self, player: &Player, property: &str) -> Result<T, String>
+    where
+        T: TryFrom<OwnedValue>,
+        T::Error: Into<zbus::Error>,
+    {
+        self.proxy(&player.bus_name, PLAYER_INTERFACE)?
+            .get_property(property)
+            .map_err(|e| format!("{} has no {}: {}", player.display_name(), property, e))
+    }
+
+    fn set<'t>(&self, player: &Player, property: &str, value: impl Into<Value<'t>> + 't) -> Result<(), String> {
+        self.proxy(&player.bus_name, PLAYER_INTERFACE)?
+            .set_property(property, value)
+            .map_err(|e| format!("{} refused to set {}: {}", player.display_name(), property, e))
+    }
+
+    fn call<B>(&self, player: &Player, method: &str, body: &B) -> Result<(), String>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.proxy(&player.bus_name, PLAYER_INTERFACE)?
+            .call_method(method, body)
+            .map(|_| ())
+            .map_err(|e| format!("{} failed to {}: {}", player.display_name(), method, e))
+    }
+
+    /// All players on the bus, sorted by bus name
+    pub fn players(&self) -> Result<Vec<Player>, String> {
+        let names = DBusProxy::new(&self.conn)
+            .map_err(|e| format!("Failed to reach the bus: {}", e))?
+            .list_names()
+            .map_err(|e| format!("Failed to list bus names: {}", e))?;
+
+        let mut players: Vec<Player> = names
+            .into_iter()
+            .map(|name| name.to_string())
+            .filter(|name| name.starts_with(BUS_PREFIX))
+            .map(|bus_name| {
+                let identity = self
+                    .proxy(&bus_name, ROOT_INTERFACE)
+                    .and_then(|root| root.get_property::<String>("Identity").map_err(|e| e.to_string()))
+                    .unwrap_or_default();
+                let mut player = Player { bus_name, identity, status: String::new() };
+                player.status = self.get::<String>(&player, "PlaybackStatus").unwrap_or_default();
+                player
+            })
+            .collect();
+        players.sort_by(|a, b| a.bus_name.cmp(&b.bus_name));
+        Ok(players)
+    }
+
+    /// The player a command is about
+    pub fn player(&self, name: Option<&str>) -> Result<Player, String> {
+        let players = self.players()?;
+        choose(&players, name).cloned()
+    }
+
+    pub fn play(&self, player: &Player) -> Result<(), String> {
+        self.call(player, "Play", &())
+    }
+
+    pub fn pause(&self, player: &Player) -> Result<(), String> {
+        self.call(player, "Pause", &())
+    }
+
+    pub fn play_pause(&self, player: &Player) -> Result<(), String> {
+        self.call(player, "PlayPause", &())
+    }
+
+    pub fn stop(&self, player: &Player) -> Result<(), String> {
+        self.call(player, "Stop", &())
+    }
+
+    pub fn next(&self, player: &Player) -> Result<(), String> {
+        self.call(player, "Next", &())
+    }
+
+    pub fn previous(&self, player: &Player) -> Result<(), String> {
+        self.call(player, "Previous", &())
+    }
+
+    /// Move by `offset_secs`, backwards when negative
+    pub fn seek(&self, player: &Player, offset_secs: f64) -> Result<(), String> {
+        self.call(player, "Seek", &((offset_secs * 1_000_000.0) as i64))
+    }
+
+    /// Jump to an absolute position in the current track
+    pub fn set_position(&self, player: &Player, position_secs: f64) -> Result<(), String> {
+        let metadata: HashMap<String, OwnedValue> = self.get(player, "Metadata")?;
+        let track_id = metadata
+            .get("mpris:trackid")
+            .and_then(|v| string_of(v))
+            .ok_or_else(|| format!("{} doesn't say which track is playing", player.display_name()))?;
+        let track_id = ObjectPath::try_from(track_id.as_str()).map_err(|e| format!("Invalid track ID: {}", e))?;
+        let position = (position_secs.max(0.0) * 1_000_000.0) as i64;
+        self.call(player, "SetPosition", &(track_id, position))
+    }
+
+    /// Turn shuffle on or off, or flip it when `enabled` is None.
+    /// Returns the new state.
+    pub fn shuffle(&self, player: &Player, enabled: Option<bool>) -> Result<bool, String> {
+        let enabled = match enabled {
+            Some(enabled) => enabled,
+            None => !self.get::<bool>(player, "Shuffle")?,
+        };
+        self.set(player, "Shuffle", enabled)?;
+        Ok(enabled)
+    }
+
+    /// Set the repeat mode, or cycle off -> playlist -> track -> off when
+    /// `mode` is None. Returns the new mode.
+    pub fn set_loop(&self, player: &Player, mode: Option<LoopMode>) -> Result<LoopMode, String> {
+        let mode = match mode {
+            Some(mode) => mode,
+            None => match LoopMode::parse(&self.get::<String>(player, "LoopStatus")?) {
+                Some(LoopMode::None) | None => LoopMode::Playlist,
+                Some(LoopMode::Playlist) => LoopMode::Track,
+                Some(LoopMode::Track) => LoopMode::None,
+            },
+        };
+        self.set(player, "LoopStatus", mode.as_mpris())?;
+        Ok(mode)
+    }
+
+    /// Player volume from 0.0 to 1.0
+    pub fn volume(&self, player: &Player) -> Result<f64, String> {
+        self.get(player, "Volume")
+    }
+
+    pub fn set_volume(&self, player: &Player, volume: f64) -> Result<(), String> {
+        self.set(player, "Volume", volume.clamp(0.0, 1.0))
+    }
+
+    pub fn now_playing(&self, player: &Player) -> Result<NowPlaying, String> {
+        let metadata: HashMap<String, OwnedValue> = self.get(player, "Metadata").unwrap_or_default();
+        let field = |key: &str| metadata.get(key).map(|v| &**v);
+        let micros_to_secs = |micros: i64| u64::try_from(micros / 1_000_000).ok();
+
+        Ok(NowPlaying {
+            player: player.display_name().to_string(),
+            status: self.get::<String>(player, "PlaybackStatus").unwrap_or_else(|_| player.status.clone()),
+            title: field("xesam:title").and_then(string_of).filter(|t| !t.is_empty()),
+            artists: field("xesam:artist").map(strings_of).unwrap_or_default(),
+            album: field("xesam:album").and_then(string_of).filter(|a| !a.is_empty()),
+            url: field("xesam:url").and_then(string_of),
+            length_secs: field("mpris:length").and_then(integer_of).and_then(micros_to_secs),
+            // Not every player reports a position
+            position_secs: self.get::<i64>(player, "Position").ok().and_then(micros_to_secs),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    fn player(bus: &str, identity: &str, status: &str) -> Player {
+        Player {
+            bus_name: format!("{}{}", BUS_PREFIX, bus),
+            identity: identity.to_string(),
+            status: status.to_string(),
+        }
+    }
+
+    #[test]
+    fn chooses_named_or_active_player() {
+        let players = vec![
+            player("firefox.instance_1_42", "Mozilla Firefox", "Paused"),
+            player("spotify", "Spotify", "Playing"),
+            player("vlc", "VLC media player", "Stopped"),
+        ];
+        assert_eq!(choose(&players, None).unwrap().identity, "Spotify");
+        assert_eq!(choose(&players, Some("firefox")).unwrap().identity, "Mozilla Firefox");
+        assert_eq!(choose(&players, Some("VLC")).unwrap().identity, "VLC media player");
+        assert!(choose(&players, Some("rhythmbox")).is_err());
+        assert!(choose(&[], None).is_err());
+    }
+
+    struct FakeRoot;
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2")]
+    impl FakeRoot {
+        #[zbus(property)]
+        fn identity(&self) -> String {
+            "Fake Player".to_string()
+        }
+    }
+
+    struct FakePlayer {
+        status: String,
+        shuffle: bool,
+        loop_status: String,
+        volume: f64,
+        position: i64,
+    }
+
+    #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        fn play_pause(&mut self) {
+            self.status = if self.status == "Playing" { "Paused" } else { "Playing" }.to_string();
+        }
+
+        fn seek(&mut self, offset: i64) {
+            self.position = (self.position + offset).max(0);
+        }
+
+        fn set_position(&mut self, _track_id: ObjectPath<'_>, position: i64) {
+            self.position = position;
+        }
+
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            self.status.clone()
+        }
+
+        #[zbus(property)]
+        fn shuffle(&self) -> bool {
+            self.shuffle
+        }
+
+        #[zbus(property)]
+        fn set_shuffle(&mut self, shuffle: bool) {
+            self.shuffle = shuffle;
+        }
+
+        #[zbus(property)]
+        fn loop_status(&self) -> String {
+            self.loop_status.clone()
+        }
+
+        #[zbus(property)]
+        fn set_loop_status(&mut self, loop_status: String) {
+            self.loop_status = loop_status;
+        }
+
+        #[zbus(property)]
+        fn volume(&self) -> f64 {
+            self.volume
+        }
+
+        #[zbus(property)]
+        fn set_volume(&mut self, volume: f64) {
+            self.volume = volume;
+        }
+
+        #[zbus(property)]
+        fn position(&self) -> i64 {
+            self.position
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let value = |v: Value<'_>| OwnedValue::try_from(v).unwrap();
+            HashMap::from([
+                ("mpris:trackid".to_string(), value(ObjectPath::from_static_str_unchecked("/track/1").into())),
+                ("mpris:length".to_string(), value(Value::I64(200_000_000))),
+                ("xesam:title".to_string(), value(Value::from("Blinding Lights"))),
+                ("xesam:artist".to_string(), value(Value::from(vec!["The Weeknd"]))),
+            ])
+        }
+    }
+
+    /// A private session bus, killed when dropped
+    struct PrivateBus(std::process::Child);
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon; run with --ignored"]
+    fn controls_a_player_on_a_private_bus() {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to start dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        let _bus = PrivateBus(daemon);
+        let address = address.trim();
+
+        let fake = FakePlayer {
+            status: "Paused".to_string(),
+            shuffle: false,
+            loop_status: "None".to_string(),
+            volume: 0.5,
+            position: 10_000_000,
+        };
+        let _server = zbus::blocking::connection::Builder::address(address)
+            .unwrap()
+            .name("org.mpris.MediaPlayer2.fake")
+            .unwrap()
+            .serve_at(OBJECT_PATH, FakeRoot)
+            .unwrap()
+            .serve_at(OBJECT_PATH, fake)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let client = zbus::blocking::connection::Builder::address(address).unwrap().build().unwrap();
+        let mpris = Mpris::with_connection(client);
+
+        let players = mpris.players().unwrap();
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].identity, "Fake Player");
+        let fake = mpris.player(Some("fake")).unwrap();
+        assert_eq!(fake.status, "Paused");
+
+        mpris.play_pause(&fake).unwrap();
+        assert_eq!(mpris.player(None).unwrap().status, "Playing");
+
+        mpris.seek(&fake, 5.0).unwrap();
+        assert_eq!(mpris.now_playing(&fake).unwrap().position_secs, Some(15));
+        mpris.set_position(&fake, 60.0).unwrap();
+        assert_eq!(mpris.now_playing(&fake).unwrap().position_secs, Some(60));
+
+        assert!(mpris.shuffle(&fake, None).unwrap());
+        assert_eq!(mpris.set_loop(&fake, None).unwrap(), LoopMode::Playlist);
+        assert_eq!(mpris.set_loop(&fake, Some(LoopMode::Track)).unwrap(), LoopMode::Track);
+        mpris.set_volume(&fake, 1.5).unwrap();
+        assert_eq!(mpris.volume(&fake).unwrap(), 1.0);
+
+        let now = mpris.now_playing(&fake).unwrap();
+        assert_eq!(now.describe(), "Blinding Lights by The Weeknd (Fake Player)");
+        assert_eq!(now.length_secs, Some(200));
+    }
+}
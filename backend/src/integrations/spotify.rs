@@ -1,6 +1,7 @@
 //! Spotify Integration for ListenOS
 //!
-//! Controls Spotify playback using Windows media keys and Spotify URI schemes.
+//! Controls Spotify playback using Windows media keys (MPRIS on Linux) and
+//! Spotify URI schemes.
 
 use super::{AppIntegration, IntegrationAction, IntegrationResult, ActionParameter};
 use std::process::Command;
@@ -46,12 +47,22 @@ impl SpotifyIntegration {
                 }
             }
         }
+
+        #[cfg(target_os = "linux")]
+        {
+            // Native package, or the Flatpak/Snap desktop entry
+            if crate::apps::find_in_path("spotify").is_some()
+                || crate::apps::find("spotify").is_some_and(|entry| entry.id.to_lowercase().contains("spotify"))
+            {
+                return true;
+            }
+        }
         
         false
     }
 
-    /// Send a media key using PowerShell
-    fn send_media_key(key: &str) -> Result<(), String> {
+    /// Send a media key using PowerShell, or the same control over MPRIS on Linux
+    pub(super) fn send_media_key(key: &str) -> Result<(), String> {
         #[cfg(windows)]
         {
             // Map key names to virtual key codes
@@ -92,7 +103,23 @@ impl SpotifyIntegration {
             Ok(())
         }
 
-        #[cfg(not(windows))]
+        #[cfg(target_os = "linux")]
+        {
+            let mpris = super::mpris::Mpris::session()?;
+            let player = mpris.player(Some("spotify"))?;
+            match key {
+                "play_pause" => mpris.play_pause(&player),
+                "next" => mpris.next(&player),
+                "previous" => mpris.previous(&player),
+                "stop" => mpris.stop(&player),
+                "volume_up" => mpris.set_volume(&player, mpris.volume(&player)? + 0.05),
+                "volume_down" => mpris.set_volume(&player, mpris.volume(&player)? - 0.05),
+                "mute" => mpris.set_volume(&player, 0.0),
+                _ => Err(format!("Unknown media key: {}", key)),
+            }
+        }
+
+        #[cfg(not(any(windows, target_os = "linux")))]
         Err("Media keys not supported on this platform".to_string())
     }
 
@@ -208,6 +235,22 @@ impl AppIntegration for SpotifyIntegration {
                     "play Taylor Swift".to_string(),
                 ],
             },
+            IntegrationAction {
+                id: "spotify_play_song".to_string(),
+                name: "Play Song".to_string(),
+                description: "Find a specific song and start playing it".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "query".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "Song title, optionally with the artist".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "play Blinding Lights by The Weeknd".to_string(),
+                ],
+            },
             IntegrationAction {
                 id: "spotify_open".to_string(),
                 name: "Open Spotify".to_string(),
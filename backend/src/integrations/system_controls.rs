@@ -19,6 +19,7 @@ impl SystemControlsIntegration {
     }
 
     /// Execute a shell command based on platform
+    #[cfg(windows)]
     fn run_command(cmd: &str, args: &[&str]) -> Result<String, String> {
         let output = Command::new(cmd)
             .args(args)
@@ -101,12 +102,9 @@ impl SystemControlsIntegration {
                 });
         }
         
-        #[cfg(not(any(windows, target_os = "macos")))]
+        #[cfg(target_os = "linux")]
         {
-            // Linux: try xrandr or brightnessctl
-            let _ = Command::new("brightnessctl")
-                .args(["set", &format!("{}%", level)])
-                .output();
+            crate::display::Backlight::find()?.set_percent(level)?;
         }
         
         Ok(())
@@ -128,15 +126,25 @@ impl SystemControlsIntegration {
             output.trim().parse().map_err(|_| "Failed to parse brightness".to_string())
         }
         
-        #[cfg(not(windows))]
+        #[cfg(target_os = "macos")]
+        {
+            Err("Reading brightness is not supported on macOS".to_string())
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            crate::display::Backlight::find()?.percent()
+        }
+
+        #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
         {
-            // Return default for non-Windows
-            Ok(50)
+            Err("Reading brightness is not supported on this platform".to_string())
         }
     }
 
-    /// Toggle night light
-    fn toggle_night_light() -> Result<(), String> {
+    /// Turn night light on or off (toggle when `enable` is None). Windows and
+    /// macOS only open the settings page.
+    fn set_night_light(enable: Option<bool>) -> Result<String, String> {
         #[cfg(windows)]
         {
             Command::new("cmd")
@@ -157,13 +165,62 @@ impl SystemControlsIntegration {
             let _ = Self::run_applescript(script);
         }
         
-        #[cfg(not(any(windows, target_os = "macos")))]
+        #[cfg(target_os = "linux")]
         {
-            // Linux: try redshift
-            let _ = Command::new("redshift").args(["-O", "4500"]).spawn();
+            let enabled = crate::display::set_night_light(enable)?;
+            Ok(format!("Night light {}", if enabled { "on" } else { "off" }))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = enable;
+            Ok("Opened Night Light settings".to_string())
+        }
+    }
+
+    /// Switch dark mode on or off (toggle when `enable` is None) and report
+    /// whether it is now on
+    fn set_dark_mode(enable: Option<bool>) -> Result<bool, String> {
+        #[cfg(windows)]
+        {
+            let key = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+            let current = Self::run_command("reg", &["query", key, "/v", "AppsUseLightTheme"])
+                .map(|output| output.trim_end().ends_with("0x0"))
+                .unwrap_or(false);
+            let enable = enable.unwrap_or(!current);
+            let light = if enable { "0" } else { "1" };
+            for value in ["AppsUseLightTheme", "SystemUsesLightTheme"] {
+                Self::run_command("reg", &["add", key, "/v", value, "/t", "REG_DWORD", "/d", light, "/f"])?;
+            }
+            Ok(enable)
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let script = match enable {
+                Some(enable) => format!(
+                    "tell application \"System Events\" to tell appearance preferences to set dark mode to {}",
+                    enable
+                ),
+                None => "tell application \"System Events\" to tell appearance preferences to set dark mode to not dark mode".to_string(),
+            };
+            Self::run_applescript(&script)?;
+            let state = Self::run_applescript(
+                "tell application \"System Events\" to tell appearance preferences to get dark mode",
+            )?;
+            Ok(state.trim() == "true")
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            crate::display::set_dark_mode(enable)
+        }
+
+        #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+        {
+            let _ = enable;
+            Err("Dark mode is not supported on this platform".to_string())
         }
-        
-        Ok(())
     }
 
     /// Lock the workstation
@@ -490,15 +547,16 @@ impl SystemControlsIntegration {
             return Ok(format!("WiFi {}", if action == "on" { "enabled" } else { "disabled" }));
         }
         
-        #[cfg(not(any(windows, target_os = "macos")))]
+        #[cfg(target_os = "linux")]
         {
-            let action = match enable {
-                Some(true) => "up",
-                Some(false) => "down",
-                None => "up", // Default to enable
-            };
-            let _ = Command::new("nmcli").args(["radio", "wifi", action]).output();
-            return Ok(format!("WiFi toggled"));
+            let on = crate::network::NetworkManager::connect()?.set_wifi_enabled(enable)?;
+            return Ok(format!("WiFi {}", if on { "enabled" } else { "disabled" }));
+        }
+
+        #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+        {
+            let _ = enable;
+            Err("Turning WiFi on and off is not supported on this platform".to_string())
         }
     }
 
@@ -605,24 +663,142 @@ impl SystemControlsIntegration {
             }
         }
         
-        #[cfg(not(any(windows, target_os = "macos")))]
+        #[cfg(target_os = "linux")]
         {
-            let action = match enable {
-                Some(true) => "on",
-                Some(false) => "off",
-                None => "on",
+            let on = crate::network::BlueZ::connect()?.set_powered(enable)?;
+            return Ok(format!("Bluetooth {}", if on { "enabled" } else { "disabled" }));
+        }
+
+        #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+        {
+            let _ = enable;
+            Err("Turning Bluetooth on and off is not supported on this platform".to_string())
+        }
+    }
+
+    /// Connect to a Wi-Fi network by name
+    fn connect_wifi(name: &str) -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let network = crate::network::NetworkManager::connect()?.connect_wifi(name)?;
+            Ok(format!("Connecting to {}", network))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            Err("Connecting to a WiFi network by name is only supported on Linux".to_string())
+        }
+    }
+
+    /// Bring up a VPN by name, or the only one set up
+    fn connect_vpn(name: Option<&str>) -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let vpn = crate::network::NetworkManager::connect()?.connect_vpn(name)?;
+            Ok(format!("Connecting to {}", vpn))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            Err("Connecting to a VPN is only supported on Linux".to_string())
+        }
+    }
+
+    /// Take down the named VPN, or every connected one
+    fn disconnect_vpn(name: Option<&str>) -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let vpns = crate::network::NetworkManager::connect()?.disconnect_vpn(name)?;
+            Ok(format!("Disconnected {}", vpns.join(", ")))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            Err("Disconnecting a VPN is only supported on Linux".to_string())
+        }
+    }
+
+    /// The active network connections, the one carrying traffic first
+    fn network_status() -> Result<(String, serde_json::Value), String> {
+        #[cfg(target_os = "linux")]
+        {
+            let connections = crate::network::NetworkManager::connect()?.status()?;
+            let message = if connections.is_empty() {
+                "Not connected to any network".to_string()
+            } else {
+                let described: Vec<String> = connections
+                    .iter()
+                    .map(|c| match (c.kind.as_str(), c.activating) {
+                        (kind, true) => format!("connecting to {} ({})", c.name, kind),
+                        ("wifi", false) => format!("{} (WiFi)", c.name),
+                        ("vpn", false) => format!("{} (VPN)", c.name),
+                        (kind, false) => format!("{} ({})", c.name, kind),
+                    })
+                    .collect();
+                format!("Connected to {}", described.join(", "))
+            };
+            Ok((message, serde_json::json!({ "connections": connections })))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err("Reporting the network connection is only supported on Linux".to_string())
+        }
+    }
+
+    /// Paired Bluetooth devices, connected ones first
+    fn bluetooth_devices() -> Result<(String, serde_json::Value), String> {
+        #[cfg(target_os = "linux")]
+        {
+            let devices = crate::network::BlueZ::connect()?.devices()?;
+            let message = if devices.is_empty() {
+                "No Bluetooth devices are paired".to_string()
+            } else {
+                let described: Vec<String> = devices
+                    .iter()
+                    .map(|d| if d.connected { format!("{} (connected)", d.name) } else { d.name.clone() })
+                    .collect();
+                format!("Paired: {}", described.join(", "))
             };
-            let _ = Command::new("bluetoothctl").args(["power", action]).output();
-            return Ok(format!("Bluetooth toggled"));
+            Ok((message, serde_json::json!({ "devices": devices })))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err("Listing Bluetooth devices is only supported on Linux".to_string())
+        }
+    }
+
+    /// Connect or disconnect a paired Bluetooth device by a fuzzy name
+    fn set_bluetooth_device(name: &str, connect: bool) -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let (device, changed) = crate::network::BlueZ::connect()?.set_connected(name, connect)?;
+            Ok(match (connect, changed) {
+                (true, true) => format!("Connected {}", device.name),
+                (false, true) => format!("Disconnected {}", device.name),
+                (true, false) => format!("{} is already connected", device.name),
+                (false, false) => format!("{} is not connected", device.name),
+            })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (name, connect);
+            Err("Connecting Bluetooth devices by name is only supported on Linux".to_string())
         }
     }
 
-    /// Empty recycle bin / trash
-    fn empty_recycle_bin() -> Result<(), String> {
+    /// Empty recycle bin / trash. Linux reports what was removed.
+    fn empty_recycle_bin() -> Result<(String, Option<serde_json::Value>), String> {
         #[cfg(windows)]
         {
             let script = "Clear-RecycleBin -Force -ErrorAction SilentlyContinue";
             Self::run_powershell(script)?;
+            return Ok(("Recycle bin emptied".to_string(), None));
         }
         
         #[cfg(target_os = "macos")]
@@ -633,14 +809,94 @@ impl SystemControlsIntegration {
                 end tell
             "#;
             Self::run_applescript(script)?;
+            return Ok(("Trash emptied".to_string(), None));
         }
-        
-        #[cfg(not(any(windows, target_os = "macos")))]
+
+        #[cfg(target_os = "linux")]
         {
-            let _ = Command::new("rm").args(["-rf", "~/.local/share/Trash/*"]).output();
+            let report = crate::trash::empty()?;
+            let message = match report.items {
+                0 => "The trash is already empty".to_string(),
+                1 => format!("Emptied the trash: 1 item, {} freed", crate::trash::format_size(report.bytes)),
+                n => format!("Emptied the trash: {} items, {} freed", n, crate::trash::format_size(report.bytes)),
+            };
+            return Ok((message, serde_json::to_value(report).ok()));
+        }
+
+        #[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+        {
+            Err("Emptying the trash is not supported on this platform".to_string())
+        }
+    }
+
+    /// Move a file or folder to the trash instead of deleting it. The path
+    /// must be absolute or start with `~`; the app's working folder is not
+    /// one the user can see.
+    fn trash_file(path: &str) -> Result<(String, serde_json::Value), String> {
+        #[cfg(target_os = "linux")]
+        {
+            let path = path.trim();
+            let path = match path.strip_prefix('~') {
+                Some(rest) if rest.is_empty() || rest.starts_with('/') => dirs_next::home_dir()
+                    .ok_or_else(|| "Could not find home directory".to_string())?
+                    .join(rest.trim_start_matches('/')),
+                _ => PathBuf::from(path),
+            };
+            if !path.is_absolute() {
+                return Err(format!("Give the full path of {} to move it to the trash", path.display()));
+            }
+            let item = crate::trash::move_to_trash(&path)?;
+            let message = format!("Moved {} to the trash", item.name);
+            Ok((message, serde_json::to_value(item).unwrap_or_default()))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = path;
+            Err("Moving files to the trash is only supported on Linux".to_string())
+        }
+    }
+
+    /// What is in the trash, most recently deleted first
+    fn trash_contents() -> Result<(String, serde_json::Value), String> {
+        #[cfg(target_os = "linux")]
+        {
+            const SPOKEN_LIMIT: usize = 10;
+            let items = crate::trash::list();
+            let message = if items.is_empty() {
+                "The trash is empty".to_string()
+            } else {
+                let names: Vec<&str> = items.iter().take(SPOKEN_LIMIT).map(|item| item.name.as_str()).collect();
+                let more = items.len().saturating_sub(SPOKEN_LIMIT);
+                let total = crate::trash::format_size(items.iter().map(|item| item.bytes).sum());
+                match more {
+                    0 => format!("{} in the trash ({}): {}", items.len(), total, names.join(", ")),
+                    _ => format!("{} in the trash ({}): {} and {} more", items.len(), total, names.join(", "), more),
+                }
+            };
+            Ok((message, serde_json::json!({ "items": items })))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err("Listing the trash is only supported on Linux".to_string())
+        }
+    }
+
+    /// Put a trashed item back by name
+    fn restore_from_trash(name: &str) -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let item = crate::trash::find(name)?;
+            let item = crate::trash::restore(Path::new(&item.trashed_path))?;
+            Ok(format!("Restored {} to {}", item.name, item.original_path))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            Err("Restoring from the trash is only supported on Linux".to_string())
         }
-        
-        Ok(())
     }
 
     /// Take a screenshot
@@ -720,6 +976,92 @@ impl SystemControlsIntegration {
         }
     }
 
+    /// Extensions the default-app handler would run rather than display
+    const EXECUTABLE_EXTENSIONS: &'static [&'static str] = &[
+        "sh", "bash", "zsh", "fish", "csh", "ksh", "exe", "com", "msi", "bat", "cmd", "ps1",
+        "psm1", "vbs", "vbe", "js", "jse", "wsf", "wsh", "scr", "lnk", "reg", "hta", "cpl",
+        "desktop", "appimage", "run", "bin", "jar", "py", "pyw", "pl", "rb", "php", "app",
+        "command", "tool", "workflow", "scpt", "pkg", "dmg", "deb", "rpm", "flatpakref",
+    ];
+
+    /// Only files inside the screenshots folder that are not programs or
+    /// scripts may be opened; returns the resolved path
+    fn openable_file(file: &Path) -> Result<PathBuf, String> {
+        if !file.exists() {
+            return Err(format!("File does not exist: {}", file.display()));
+        }
+        let resolved = file
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", file.display(), e))?;
+        let root = Self::screenshots_dir()?
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve screenshots directory: {}", e))?;
+        if !resolved.starts_with(&root) {
+            return Err(format!("Only files in {} can be opened", root.display()));
+        }
+        if !resolved.is_file() {
+            return Err(format!("Not a file: {}", resolved.display()));
+        }
+
+        let extension = resolved
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        if Self::EXECUTABLE_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(format!("Refusing to open executable file: {}", resolved.display()));
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&resolved)
+                .map_err(|e| format!("Failed to read {}: {}", resolved.display(), e))?
+                .permissions()
+                .mode();
+            if mode & 0o111 != 0 {
+                return Err(format!("Refusing to open executable file: {}", resolved.display()));
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn open_file_with_default_app(file: &Path) -> Result<(), String> {
+        let file = Self::openable_file(file)?;
+        let file = file.as_path();
+
+        #[cfg(windows)]
+        {
+            // Explorer does not understand the verbatim prefix canonicalize adds
+            let shown = file.to_string_lossy();
+            let plain = shown.strip_prefix(r"\\?\").unwrap_or(&shown);
+            Command::new("explorer")
+                .arg(plain)
+                .spawn()
+                .map_err(|e| format!("Failed to open file: {}", e))?;
+            return Ok(());
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            Command::new("open")
+                .arg(file)
+                .spawn()
+                .map_err(|e| format!("Failed to open file: {}", e))?;
+            return Ok(());
+        }
+
+        #[cfg(not(any(windows, target_os = "macos")))]
+        {
+            Command::new("xdg-open")
+                .arg(file)
+                .spawn()
+                .map_err(|e| format!("Failed to open file: {}", e))?;
+            Ok(())
+        }
+    }
+
     fn count_download_items(path_override: Option<&str>) -> Result<serde_json::Value, String> {
         let downloads = match path_override {
             Some(p) if !p.trim().is_empty() => PathBuf::from(p.trim()),
@@ -831,6 +1173,9 @@ impl SystemControlsIntegration {
         }
     }
 
+    /// Sort loose files into category folders. A file that can't be moved is
+    /// reported in `failed` and skipped, so the moves that did happen are
+    /// always returned for the undo journal.
     fn organize_downloads(path_override: Option<&str>) -> Result<serde_json::Value, String> {
         let downloads = match path_override {
             Some(p) if !p.trim().is_empty() => PathBuf::from(p.trim()),
@@ -846,27 +1191,46 @@ impl SystemControlsIntegration {
 
         let mut moved_count = 0usize;
         let mut moved_examples: Vec<String> = Vec::new();
+        let mut moves: Vec<serde_json::Value> = Vec::new();
         let mut by_folder: HashMap<String, usize> = HashMap::new();
+        let mut failed: Vec<String> = Vec::new();
+        let mut created_folders: Vec<String> = Vec::new();
 
         for entry in fs::read_dir(&downloads).map_err(|e| format!("Failed to read downloads: {}", e))? {
-            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-            let file_type = entry.file_type().map_err(|e| format!("Failed to read file type: {}", e))?;
-            if !file_type.is_file() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    failed.push(format!("Failed to read entry: {}", e));
+                    continue;
+                }
+            };
+            if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
                 continue;
             }
 
             let source = entry.path();
             let category = Self::categorize_file(&source).to_string();
             let target_dir = downloads.join(&category);
-            fs::create_dir_all(&target_dir).map_err(|e| format!("Failed to create category folder: {}", e))?;
-
-            let file_name = source
-                .file_name()
-                .ok_or_else(|| "Invalid file name".to_string())?;
-            let destination = Self::unique_destination_path(&target_dir.join(file_name));
-
-            Self::move_file(&source, &destination)?;
+            let destination = Self::unique_destination_path(&target_dir.join(entry.file_name()));
+
+            let created = !target_dir.exists();
+            let moved = fs::create_dir_all(&target_dir)
+                .map_err(|e| format!("Failed to create category folder: {}", e))
+                .and_then(|_| {
+                    if created {
+                        created_folders.push(target_dir.to_string_lossy().to_string());
+                    }
+                    Self::move_file(&source, &destination)
+                });
+            if let Err(e) = moved {
+                failed.push(format!("{}: {}", entry.file_name().to_string_lossy(), e));
+                continue;
+            }
             moved_count += 1;
+            moves.push(serde_json::json!({
+                "from": source.to_string_lossy().to_string(),
+                "to": destination.to_string_lossy().to_string(),
+            }));
             *by_folder.entry(category).or_insert(0) += 1;
 
             if moved_examples.len() < 8 {
@@ -885,6 +1249,9 @@ impl SystemControlsIntegration {
             "moved_count": moved_count,
             "by_folder": by_folder,
             "examples": moved_examples,
+            "moves": moves,
+            "created_folders": created_folders,
+            "failed": failed,
         }))
     }
 
@@ -1019,13 +1386,37 @@ impl AppIntegration for SystemControlsIntegration {
             IntegrationAction {
                 id: "system_night_light".to_string(),
                 name: "Night Light".to_string(),
-                description: "Toggle night light / blue light filter".to_string(),
-                parameters: vec![],
+                description: "Turn the night light / blue light filter on or off".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "enable".to_string(),
+                        param_type: "boolean".to_string(),
+                        required: false,
+                        description: "true for on, false for off; toggles when omitted".to_string(),
+                    },
+                ],
                 example_phrases: vec![
                     "turn on night light".to_string(),
                     "enable blue light filter".to_string(),
                 ],
             },
+            IntegrationAction {
+                id: "system_dark_mode".to_string(),
+                name: "Dark Mode".to_string(),
+                description: "Switch between the dark and light theme".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "enable".to_string(),
+                        param_type: "boolean".to_string(),
+                        required: false,
+                        description: "true for dark, false for light; toggles when omitted".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "turn on dark mode".to_string(),
+                    "switch to light mode".to_string(),
+                ],
+            },
             IntegrationAction {
                 id: "system_lock".to_string(),
                 name: "Lock Screen".to_string(),
@@ -1135,12 +1526,123 @@ impl AppIntegration for SystemControlsIntegration {
                 id: "system_wifi_toggle".to_string(),
                 name: "Toggle WiFi".to_string(),
                 description: "Enable or disable WiFi".to_string(),
-                parameters: vec![],
+                parameters: vec![
+                    ActionParameter {
+                        name: "enable".to_string(),
+                        param_type: "boolean".to_string(),
+                        required: false,
+                        description: "true to enable, false to disable".to_string(),
+                    },
+                ],
                 example_phrases: vec![
                     "turn off wifi".to_string(),
                     "disable wifi".to_string(),
                 ],
             },
+            IntegrationAction {
+                id: "system_wifi_connect".to_string(),
+                name: "Connect to WiFi".to_string(),
+                description: "Connect to a saved or nearby WiFi network by name".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "name".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "Network name as spoken".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "connect to the office wifi".to_string(),
+                    "join wifi cafe guest".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_vpn_connect".to_string(),
+                name: "Connect VPN".to_string(),
+                description: "Bring up a VPN by name".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "name".to_string(),
+                        param_type: "string".to_string(),
+                        required: false,
+                        description: "VPN name; the only VPN when omitted".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "connect to the work vpn".to_string(),
+                    "turn on vpn".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_vpn_disconnect".to_string(),
+                name: "Disconnect VPN".to_string(),
+                description: "Take down a VPN, or every connected one".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "name".to_string(),
+                        param_type: "string".to_string(),
+                        required: false,
+                        description: "VPN name; every VPN when omitted".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "disconnect the vpn".to_string(),
+                    "turn off vpn".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_network_status".to_string(),
+                name: "Network Status".to_string(),
+                description: "Report the current network and VPN connections".to_string(),
+                parameters: vec![],
+                example_phrases: vec![
+                    "what network am I on".to_string(),
+                    "am I connected to the vpn".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_bluetooth_devices".to_string(),
+                name: "Bluetooth Devices".to_string(),
+                description: "List paired Bluetooth devices and which are connected".to_string(),
+                parameters: vec![],
+                example_phrases: vec![
+                    "list bluetooth devices".to_string(),
+                    "what bluetooth devices are paired".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_bluetooth_connect".to_string(),
+                name: "Connect Bluetooth Device".to_string(),
+                description: "Connect a paired Bluetooth device by name".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "device".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "Device name as spoken, e.g. \"airpods\" or \"keyboard\"".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "connect my airpods".to_string(),
+                    "connect the bluetooth speaker".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_bluetooth_disconnect".to_string(),
+                name: "Disconnect Bluetooth Device".to_string(),
+                description: "Disconnect a Bluetooth device by name".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "device".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "Device name as spoken".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "disconnect my headphones".to_string(),
+                ],
+            },
             IntegrationAction {
                 id: "system_screenshot".to_string(),
                 name: "Screenshot".to_string(),
@@ -1168,6 +1670,22 @@ impl AppIntegration for SystemControlsIntegration {
                     "show where screenshots are saved".to_string(),
                 ],
             },
+            IntegrationAction {
+                id: "system_open_file".to_string(),
+                name: "Open File".to_string(),
+                description: "Open a screenshot with its default app, e.g. one taken in an earlier step. Only files in the screenshots folder can be opened".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "path".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "Path of the file to open".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "take a screenshot and open it".to_string(),
+                ],
+            },
             IntegrationAction {
                 id: "system_downloads_count".to_string(),
                 name: "Count Downloads".to_string(),
@@ -1212,6 +1730,50 @@ impl AppIntegration for SystemControlsIntegration {
                     "clear trash".to_string(),
                 ],
             },
+            IntegrationAction {
+                id: "system_trash_file".to_string(),
+                name: "Move to Trash".to_string(),
+                description: "Move a file or folder to the trash, where it can be restored; use instead of deleting".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "path".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "Path of the file or folder".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "delete this file".to_string(),
+                    "move it to the trash".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_trash_list".to_string(),
+                name: "List Trash".to_string(),
+                description: "List what is in the trash and where it came from".to_string(),
+                parameters: vec![],
+                example_phrases: vec![
+                    "what's in the trash".to_string(),
+                    "list the trash".to_string(),
+                ],
+            },
+            IntegrationAction {
+                id: "system_trash_restore".to_string(),
+                name: "Restore from Trash".to_string(),
+                description: "Put a trashed item back where it was".to_string(),
+                parameters: vec![
+                    ActionParameter {
+                        name: "name".to_string(),
+                        param_type: "string".to_string(),
+                        required: true,
+                        description: "File name as spoken, with or without its extension".to_string(),
+                    },
+                ],
+                example_phrases: vec![
+                    "restore report.pdf from the trash".to_string(),
+                    "undelete budget".to_string(),
+                ],
+            },
         ]
     }
 
@@ -1219,25 +1781,36 @@ impl AppIntegration for SystemControlsIntegration {
         match action {
             "system_brightness" => {
                 let level = params.get("level");
+                let current = Self::get_brightness();
+                // Reported so the action journal can restore it on undo
+                let previous = current.as_ref().ok().copied();
                 
                 if let Some(level_val) = level {
                     if let Some(n) = level_val.as_u64() {
                         Self::set_brightness(n as u32)?;
-                        return Ok(IntegrationResult::success(format!("Brightness set to {}%", n)));
+                        return Ok(IntegrationResult::success_with_data(
+                            format!("Brightness set to {}%", n),
+                            serde_json::json!({ "brightness": n, "previous_brightness": previous }),
+                        ));
                     }
                     if let Some(s) = level_val.as_str() {
-                        let current = Self::get_brightness().unwrap_or(50);
-                        let new_level = match s {
-                            "up" => (current + 10).min(100),
-                            "down" => current.saturating_sub(10),
-                            _ => current,
+                        let new_level = match s.trim().trim_end_matches('%') {
+                            // Relative changes need a readback, so don't guess a starting point
+                            "up" => (current? + 10).min(100),
+                            "down" => current?.saturating_sub(10),
+                            other => other
+                                .parse::<u32>()
+                                .map_err(|_| format!("Unknown brightness level: {}", s))?,
                         };
                         Self::set_brightness(new_level)?;
-                        return Ok(IntegrationResult::success(format!("Brightness set to {}%", new_level)));
+                        return Ok(IntegrationResult::success_with_data(
+                            format!("Brightness set to {}%", new_level),
+                            serde_json::json!({ "brightness": new_level, "previous_brightness": previous }),
+                        ));
                     }
                 }
                 
-                let current = Self::get_brightness().unwrap_or(50);
+                let current = current?;
                 Ok(IntegrationResult::success_with_data(
                     format!("Current brightness: {}%", current),
                     serde_json::json!({ "brightness": current })
@@ -1245,8 +1818,18 @@ impl AppIntegration for SystemControlsIntegration {
             }
             
             "system_night_light" => {
-                Self::toggle_night_light()?;
-                Ok(IntegrationResult::success("Opened Night Light settings"))
+                let enable = params.get("enable").and_then(|v| v.as_bool());
+                let message = Self::set_night_light(enable)?;
+                Ok(IntegrationResult::success(message))
+            }
+
+            "system_dark_mode" => {
+                let enable = params.get("enable").and_then(|v| v.as_bool());
+                let dark = Self::set_dark_mode(enable)?;
+                Ok(IntegrationResult::success_with_data(
+                    format!("{} mode on", if dark { "Dark" } else { "Light" }),
+                    serde_json::json!({ "dark_mode": dark }),
+                ))
             }
             
             "system_lock" => {
@@ -1306,6 +1889,44 @@ impl AppIntegration for SystemControlsIntegration {
                 let result = Self::toggle_wifi(enable)?;
                 Ok(IntegrationResult::success(result))
             }
+
+            "system_wifi_connect" => {
+                let name = params.get("name")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "No network name provided".to_string())?;
+                let message = Self::connect_wifi(name)?;
+                Ok(IntegrationResult::success(message))
+            }
+
+            "system_vpn_connect" => {
+                let name = params.get("name").and_then(|v| v.as_str());
+                let message = Self::connect_vpn(name)?;
+                Ok(IntegrationResult::success(message))
+            }
+
+            "system_vpn_disconnect" => {
+                let name = params.get("name").and_then(|v| v.as_str());
+                let message = Self::disconnect_vpn(name)?;
+                Ok(IntegrationResult::success(message))
+            }
+
+            "system_network_status" => {
+                let (message, data) = Self::network_status()?;
+                Ok(IntegrationResult::success_with_data(message, data))
+            }
+
+            "system_bluetooth_devices" => {
+                let (message, data) = Self::bluetooth_devices()?;
+                Ok(IntegrationResult::success_with_data(message, data))
+            }
+
+            "system_bluetooth_connect" | "system_bluetooth_disconnect" => {
+                let device = params.get("device")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "No device name provided".to_string())?;
+                let message = Self::set_bluetooth_device(device, action == "system_bluetooth_connect")?;
+                Ok(IntegrationResult::success(message))
+            }
             
             "system_screenshot" => {
                 let path = params.get("path").and_then(|v| v.as_str());
@@ -1325,6 +1946,20 @@ impl AppIntegration for SystemControlsIntegration {
                 ))
             }
 
+            "system_open_file" => {
+                let path = params
+                    .get("path")
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .ok_or_else(|| "No file path provided".to_string())?;
+                Self::open_file_with_default_app(Path::new(path))?;
+                Ok(IntegrationResult::success_with_data(
+                    format!("Opened {}", path),
+                    serde_json::json!({ "path": path }),
+                ))
+            }
+
             "system_open_screenshots_folder" => {
                 let screenshots_dir = Self::screenshots_dir()?;
                 Self::open_folder_in_file_manager(&screenshots_dir)?;
@@ -1361,7 +1996,23 @@ impl AppIntegration for SystemControlsIntegration {
                     .get("moved_count")
                     .and_then(|v| v.as_u64())
                     .unwrap_or(0);
-                if moved_count == 0 {
+                let failed: Vec<&str> = result["failed"]
+                    .as_array()
+                    .map(|failed| failed.iter().filter_map(|v| v.as_str()).collect())
+                    .unwrap_or_default();
+                if moved_count == 0 && !failed.is_empty() {
+                    Ok(IntegrationResult::error(format!(
+                        "Couldn't organize Downloads: {}",
+                        failed.join("; ")
+                    )))
+                } else if !failed.is_empty() {
+                    let message = format!(
+                        "Organized Downloads: moved {} file(s), {} could not be moved",
+                        moved_count,
+                        failed.len()
+                    );
+                    Ok(IntegrationResult::success_with_data(message, result))
+                } else if moved_count == 0 {
                     Ok(IntegrationResult::success_with_data(
                         "Downloads already organized (no loose files found)".to_string(),
                         result,
@@ -1375,8 +2026,32 @@ impl AppIntegration for SystemControlsIntegration {
             }
             
             "system_recycle_bin" => {
-                Self::empty_recycle_bin()?;
-                Ok(IntegrationResult::success("Recycle bin / trash emptied"))
+                let (message, data) = Self::empty_recycle_bin()?;
+                Ok(match data {
+                    Some(data) => IntegrationResult::success_with_data(message, data),
+                    None => IntegrationResult::success(message),
+                })
+            }
+
+            "system_trash_file" => {
+                let path = params.get("path")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "No file path provided".to_string())?;
+                let (message, data) = Self::trash_file(path)?;
+                Ok(IntegrationResult::success_with_data(message, data))
+            }
+
+            "system_trash_list" => {
+                let (message, data) = Self::trash_contents()?;
+                Ok(IntegrationResult::success_with_data(message, data))
+            }
+
+            "system_trash_restore" => {
+                let name = params.get("name")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| "No item name provided".to_string())?;
+                let message = Self::restore_from_trash(name)?;
+                Ok(IntegrationResult::success(message))
             }
             
             _ => Err(format!("Unknown system action: {}", action)),
@@ -0,0 +1,232 @@
+//! Action journal for undoing executed voice actions
+//!
+//! Executors record an inverse operation for every action that can be
+//! reverted (file moves, trashed files, volume and brightness changes,
+//! typed text, clipboard rewrites). "Undo last action" pops the newest entry and
+//! replays its inverse.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+/// Maximum number of undoable actions kept in memory
+const MAX_ENTRIES: usize = 20;
+
+/// A single file move, recorded as it happened
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMove {
+    pub from: String,
+    pub to: String,
+}
+
+/// How to revert an executed action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InverseOp {
+    /// Move every file back to where it came from, then remove the folders
+    /// the original action created if they are left empty
+    RestoreFiles {
+        moves: Vec<FileMove>,
+        #[serde(default)]
+        created_folders: Vec<String>,
+    },
+    /// Put trashed items back, given where they sit in the trash
+    RestoreFromTrash { items: Vec<String> },
+    /// Set the output volume back to a known level (0-100)
+    SetVolume { level: u32 },
+    /// Step the volume the other way when the previous level is unknown
+    StepVolume { direction: String },
+    /// Set display brightness back to a known level (0-100)
+    SetBrightness { level: u32 },
+    /// Delete typed text with backspace, but only while the window it was
+    /// typed into still has focus. `window` is None on macOS and on Wayland
+    /// compositors other than sway, where such undo always fails
+    Backspace {
+        count: usize,
+        #[serde(default)]
+        window: Option<String>,
+    },
+    /// Put the previous clipboard text back
+    RestoreClipboard { content: String },
+}
+
+impl InverseOp {
+    /// Inverse for a relative volume step ("up", "down", "mute")
+    pub fn opposite_volume_step(direction: &str) -> Self {
+        let opposite = match direction {
+            "up" => "down",
+            "down" => "up",
+            other => other,
+        };
+        Self::StepVolume { direction: opposite.to_string() }
+    }
+}
+
+/// An undoable action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: String,
+    /// User-facing description of the original action
+    pub description: String,
+    pub inverse: InverseOp,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Most recent undoable actions, newest last
+pub struct ActionJournal {
+    entries: VecDeque<JournalEntry>,
+}
+
+impl ActionJournal {
+    pub fn new() -> Self {
+        Self { entries: VecDeque::new() }
+    }
+
+    /// Record an inverse for an action that just succeeded
+    pub fn record(&mut self, description: impl Into<String>, inverse: InverseOp) {
+        // Dry runs change nothing, so there is nothing to undo
+        if crate::simulation::is_active() {
+            return;
+        }
+        match &inverse {
+            InverseOp::RestoreFiles { moves, .. } if moves.is_empty() => return,
+            InverseOp::RestoreFromTrash { items } if items.is_empty() => return,
+            _ => {}
+        }
+
+        self.entries.push_back(JournalEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            description: description.into(),
+            inverse,
+            created_at: Utc::now(),
+        });
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Remove and return the newest entry
+    pub fn pop_last(&mut self) -> Option<JournalEntry> {
+        self.entries.pop_back()
+    }
+
+    /// Put back an entry whose undo could not run yet
+    pub fn push_back(&mut self, entry: JournalEntry) {
+        self.entries.push_back(entry);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Entries, newest first
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.iter().rev().cloned().collect()
+    }
+}
+
+impl Default for ActionJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Move files back to their original locations, newest move first, and
+/// drop the folders the original action created once they are empty.
+/// Returns how many files were restored and the failures, if any.
+pub fn restore_file_moves(moves: &[FileMove], created_folders: &[String]) -> (usize, Vec<String>) {
+    let mut restored = 0;
+    let mut errors = Vec::new();
+
+    for file_move in moves.iter().rev() {
+        let current = Path::new(&file_move.to);
+        let original = Path::new(&file_move.from);
+
+        if !current.exists() {
+            errors.push(format!("{} no longer exists", file_move.to));
+            continue;
+        }
+        if original.exists() {
+            errors.push(format!("{} already exists", file_move.from));
+            continue;
+        }
+
+        let moved = fs::rename(current, original).or_else(|_| {
+            fs::copy(current, original).and_then(|_| fs::remove_file(current))
+        });
+        match moved {
+            Ok(_) => restored += 1,
+            Err(e) => errors.push(format!("{}: {}", file_move.to, e)),
+        }
+    }
+
+    // Folders that held files before, or still do, are left alone
+    for folder in created_folders {
+        let _ = fs::remove_dir(folder);
+    }
+
+    (restored, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_keeps_newest_entries() {
+        let mut journal = ActionJournal::new();
+        journal.record(
+            "nothing moved",
+            InverseOp::RestoreFiles { moves: vec![], created_folders: vec![] },
+        );
+        assert!(journal.pop_last().is_none());
+
+        for i in 0..(MAX_ENTRIES + 5) {
+            journal.record(format!("typed {}", i), InverseOp::Backspace { count: i, window: None });
+        }
+        assert_eq!(journal.entries().len(), MAX_ENTRIES);
+
+        let last = journal.pop_last().unwrap();
+        assert_eq!(last.inverse, InverseOp::Backspace { count: MAX_ENTRIES + 4, window: None });
+        journal.push_back(last.clone());
+        assert_eq!(journal.pop_last().map(|entry| entry.id), Some(last.id));
+        assert_eq!(
+            InverseOp::opposite_volume_step("up"),
+            InverseOp::StepVolume { direction: "down".to_string() }
+        );
+    }
+
+    #[test]
+    fn restores_moved_files() {
+        let root = std::env::temp_dir().join(format!("listenos-journal-{}", uuid::Uuid::new_v4()));
+        let images = root.join("Images");
+        let documents = root.join("Documents");
+        fs::create_dir_all(&images).unwrap();
+        fs::create_dir_all(&documents).unwrap();
+
+        let moves: Vec<FileMove> = ["photo.png", "notes.txt"]
+            .iter()
+            .zip([&images, &documents])
+            .map(|(name, folder)| {
+                let to = folder.join(name);
+                fs::write(&to, b"data").unwrap();
+                FileMove {
+                    from: root.join(name).to_string_lossy().to_string(),
+                    to: to.to_string_lossy().to_string(),
+                }
+            })
+            .collect();
+        // Documents existed before the move, so it must survive the undo
+        let created = vec![images.to_string_lossy().to_string()];
+        let (restored, errors) = restore_file_moves(&moves, &created);
+
+        assert_eq!(restored, 2);
+        assert!(errors.is_empty());
+        assert!(root.join("photo.png").exists());
+        assert!(!images.exists());
+        assert!(documents.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
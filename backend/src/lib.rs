@@ -21,6 +21,23 @@ mod rate_limit;
 mod correction;
 mod error_log;
 mod api_client;
+mod connectivity;
+mod prompts;
+mod usage;
+mod journal;
+mod history;
+mod simulation;
+mod injection;
+mod window;
+mod mixer;
+#[cfg(target_os = "linux")]
+mod apps;
+#[cfg(target_os = "linux")]
+mod display;
+#[cfg(target_os = "linux")]
+mod network;
+#[cfg(target_os = "linux")]
+mod trash;
 
 use tauri::{
     Emitter, Manager, AppHandle, PhysicalPosition, Position,
@@ -28,7 +45,9 @@ use tauri::{
     menu::{Menu, MenuItem},
 };
 use tauri_plugin_global_shortcut::ShortcutState;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use tokio::sync::Mutex;
 
 pub use audio::AudioState;
@@ -81,7 +100,7 @@ pub struct AppState {
     pub is_listening: Arc<Mutex<bool>>,
     pub is_processing: Arc<Mutex<bool>>,
     pub current_context: Arc<Mutex<VoiceContext>>,
-    pub history: Arc<Mutex<Vec<VoiceProcessingResult>>>,
+    pub history: Arc<std::sync::Mutex<Option<history::HistoryStore>>>,
     // New: Conversation memory for multi-turn dialogues
     pub conversation: Arc<Mutex<ConversationMemory>>,
     pub conversation_store: Arc<std::sync::Mutex<Option<ConversationStore>>>,
@@ -97,6 +116,17 @@ pub struct AppState {
     pub api_client: Arc<Mutex<ApiClient>>,
     // Pending high-risk action awaiting explicit user confirmation
     pub pending_action: Arc<Mutex<Option<commands::PendingAction>>>,
+    // Network reachability and LLM tasks deferred while offline
+    pub connectivity: Arc<Mutex<connectivity::ConnectivityMonitor>>,
+    // Set to stop the answer currently being streamed
+    pub answer_cancel: Arc<AtomicBool>,
+    // Inverse operations for undoing recent voice actions
+    pub action_journal: Arc<Mutex<journal::ActionJournal>>,
+    // Confirmation rules the user already confirmed this session
+    pub confirmed_rules: Arc<Mutex<HashSet<String>>>,
+    // App, website and search-engine aliases; reloaded whenever they are
+    // read or saved through the settings commands
+    pub aliases: Arc<Mutex<crate::config::AliasConfig>>,
 }
 
 impl Default for AppState {
@@ -133,6 +163,21 @@ impl Default for AppState {
         if let Some(saved_vibe) = crate::config::VibeCodingConfig::load_from_disk() {
             app_config.vibe_coding = saved_vibe;
         }
+        if let Some(saved_clarification) = crate::config::ClarificationConfig::load_from_disk() {
+            app_config.clarification = saved_clarification;
+        }
+        if let Some(saved_budget) = crate::config::UsageBudgetConfig::load_from_disk() {
+            app_config.usage_budget = saved_budget;
+        }
+        if let Some(saved_policy) = crate::config::ConfirmationPolicy::load_from_disk() {
+            app_config.confirmation_policy = saved_policy;
+        }
+        if let Some(saved_sandbox) = crate::config::CommandSandboxConfig::load_from_disk() {
+            app_config.command_sandbox = saved_sandbox;
+        }
+        if let Some(saved_injection) = crate::config::TextInjectionConfig::load_from_disk() {
+            app_config.text_injection = saved_injection;
+        }
 
         Self {
             audio: Arc::new(Mutex::new(AudioState::default())),
@@ -143,7 +188,7 @@ impl Default for AppState {
             is_listening: Arc::new(Mutex::new(false)),
             is_processing: Arc::new(Mutex::new(false)),
             current_context: Arc::new(Mutex::new(VoiceContext::default())),
-            history: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(std::sync::Mutex::new(history::HistoryStore::new().ok())),
             conversation: Arc::new(Mutex::new(conversation)),
             conversation_store: Arc::new(std::sync::Mutex::new(conversation_store)),
             clipboard: Arc::new(Mutex::new(ClipboardService::new())),
@@ -152,6 +197,11 @@ impl Default for AppState {
             error_log: Arc::new(Mutex::new(ErrorLog::new())),
             api_client: Arc::new(Mutex::new(ApiClient::with_config(api_config))),
             pending_action: Arc::new(Mutex::new(None)),
+            connectivity: Arc::new(Mutex::new(connectivity::ConnectivityMonitor::new())),
+            answer_cancel: Arc::new(AtomicBool::new(false)),
+            action_journal: Arc::new(Mutex::new(journal::ActionJournal::new())),
+            confirmed_rules: Arc::new(Mutex::new(HashSet::new())),
+            aliases: Arc::new(Mutex::new(crate::config::AliasConfig::load_from_disk().unwrap_or_default())),
         }
     }
 }
@@ -239,6 +289,11 @@ pub fn run() {
             commands::get_pending_action,
             commands::confirm_pending_action,
             commands::cancel_pending_action,
+            commands::cancel_answer,
+            commands::undo_last_action,
+            commands::get_action_journal,
+            commands::preview_utterance,
+            commands::repeat_command,
             // Audio
             commands::get_audio_devices,
             commands::set_audio_device,
@@ -251,6 +306,27 @@ pub fn run() {
             commands::set_language_preferences,
             commands::get_vibe_coding_config,
             commands::set_vibe_coding_config,
+            commands::get_clarification_config,
+            commands::set_clarification_config,
+            commands::get_usage_budget,
+            commands::set_usage_budget,
+            commands::get_text_injection,
+            commands::set_text_injection,
+            commands::get_command_sandbox,
+            commands::set_command_sandbox,
+            commands::get_confirmation_policy,
+            commands::set_confirmation_policy,
+            commands::get_usage_summary,
+            // Connectivity / offline mode
+            commands::get_connectivity_status,
+            commands::get_offline_queue,
+            commands::retry_offline_task,
+            commands::clear_offline_queue,
+            // Prompt templates
+            commands::list_prompt_templates,
+            commands::preview_prompt_template,
+            commands::save_prompt_template,
+            commands::reset_prompt_template,
             commands::get_local_api_settings,
             commands::set_local_api_settings,
             // Conversation
@@ -272,9 +348,19 @@ pub fn run() {
             commands::set_custom_command_enabled,
             commands::export_custom_commands,
             commands::import_custom_commands,
+            commands::get_aliases,
+            commands::set_aliases,
+            commands::export_aliases,
+            commands::import_aliases,
+            commands::get_window_layouts,
+            commands::save_window_layout,
+            commands::restore_window_layout,
+            commands::delete_window_layout,
+            commands::list_installed_apps,
             // Data
             get_history,
             clear_history,
+            commands::search_history,
             // Window control
             hide_assistant,
             show_dashboard,
@@ -452,6 +538,47 @@ pub fn run() {
             });
             log::info!("Clipboard monitoring with auto-learning started");
 
+            // Probe the network while offline so routing recovers on its own, and
+            // replay the tasks deferred meanwhile once it has
+            let connectivity_state = app.state::<AppState>().connectivity.clone();
+            let connectivity_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
+                loop {
+                    interval.tick().await;
+                    // Answer whatever was deferred as soon as any route finds the network again
+                    let replay = connectivity_state.lock().await.take_replay();
+                    if let Some(replay) = replay {
+                        commands::replay_offline_tasks(&connectivity_handle, replay).await;
+                    }
+
+                    let should_probe = {
+                        let mut monitor = connectivity_state.lock().await;
+                        let due = monitor.should_probe();
+                        if due {
+                            monitor.note_probe();
+                        }
+                        due
+                    };
+                    if !should_probe || !connectivity::probe().await {
+                        continue;
+                    }
+
+                    let status = {
+                        let mut monitor = connectivity_state.lock().await;
+                        if !monitor.mark_online() {
+                            continue;
+                        }
+                        monitor.status()
+                    };
+                    for label in ["assistant", "dashboard"] {
+                        if let Some(window) = connectivity_handle.get_webview_window(label) {
+                            let _ = window.emit("connectivity-changed", &status);
+                        }
+                    }
+                }
+            });
+
             // Ensure autostart is always enabled so the assistant resumes after reboot/login.
             {
                 use tauri_plugin_autostart::ManagerExt;
@@ -493,15 +620,21 @@ async fn show_dashboard(app: AppHandle) -> Result<(), String> {
 
 #[tauri::command]
 async fn get_history(state: tauri::State<'_, AppState>) -> Result<Vec<VoiceProcessingResult>, String> {
-    let history = state.history.lock().await;
-    Ok(history.clone())
+    let history = state.history.lock().map_err(|e| e.to_string())?;
+    match history.as_ref() {
+        // Oldest first, like the in-memory list this replaced
+        Some(store) => Ok(store.recent(50)?.into_iter().rev().map(|entry| entry.result).collect()),
+        None => Ok(Vec::new()),
+    }
 }
 
 #[tauri::command]
 async fn clear_history(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut history = state.history.lock().await;
-    history.clear();
-    Ok(())
+    let history = state.history.lock().map_err(|e| e.to_string())?;
+    match history.as_ref() {
+        Some(store) => store.clear(),
+        None => Ok(()),
+    }
 }
 
 #[tauri::command]
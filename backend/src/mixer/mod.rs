@@ -0,0 +1,545 @@
+//! System volume and audio device control for VolumeControl
+//!
+//! On Linux this talks to the sound server over the PulseAudio native
+//! protocol, which PipeWire also serves through pipewire-pulse. That gives
+//! absolute levels, per-application volume and switching the default output
+//! or input by a spoken name ("switch to headphones"). Other platforms keep
+//! the media-key and AppleScript paths in commands, which only cover the
+//! default output.
+
+// Only the payload parsing is used where there is no sound server client
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+#[cfg(target_os = "linux")]
+mod pulse;
+
+use serde::Serialize;
+
+/// How far "volume up" and "turn Spotify down" move the level, in percent
+pub const DEFAULT_STEP: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    Output,
+    Input,
+}
+
+impl DeviceKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Output => "output",
+            Self::Input => "input",
+        }
+    }
+}
+
+/// A jack or connector on a device, such as the headphone port of a laptop
+#[derive(Debug, Clone, Serialize)]
+pub struct Port {
+    pub name: String,
+    pub description: String,
+    /// False only when the server knows nothing is plugged in
+    pub available: bool,
+}
+
+/// A sound card output or input
+#[derive(Debug, Clone, Serialize)]
+pub struct Device {
+    /// Server-side index
+    pub index: u32,
+    pub kind: DeviceKind,
+    pub name: String,
+    pub description: String,
+    /// 0-100, or above 100 when amplified
+    pub volume: u32,
+    pub muted: bool,
+    pub is_default: bool,
+    /// "headphone", "headset", "speaker"... when the server knows it
+    pub form_factor: Option<String>,
+    pub ports: Vec<Port>,
+    pub active_port: Option<String>,
+    #[serde(skip)]
+    pub channels: u8,
+}
+
+/// Audio one application is playing
+#[derive(Debug, Clone, Serialize)]
+pub struct AppStream {
+    pub index: u32,
+    pub app: String,
+    pub binary: Option<String>,
+    pub title: Option<String>,
+    pub volume: u32,
+    pub muted: bool,
+    /// Index of the output device it plays on
+    pub device: u32,
+    #[serde(skip)]
+    pub channels: u8,
+}
+
+/// A change to one volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeChange {
+    Up(u32),
+    Down(u32),
+    /// Absolute level in percent; also unmutes
+    Set(u32),
+    Mute,
+    Unmute,
+    ToggleMute,
+}
+
+impl VolumeChange {
+    /// The volume and mute state after applying this change
+    fn apply(self, volume: u32, muted: bool) -> (u32, bool) {
+        match self {
+            // Stepping never pushes past 100%, but leaves an amplified level alone
+            Self::Up(step) => ((volume + step).min(volume.max(100)), muted),
+            Self::Down(step) => (volume.saturating_sub(step), muted),
+            Self::Set(level) => (level, false),
+            Self::Mute => (volume, true),
+            Self::Unmute => (volume, false),
+            Self::ToggleMute => (volume, !muted),
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Self::Up(_) => "Volume up".to_string(),
+            Self::Down(_) => "Volume down".to_string(),
+            Self::Set(level) => format!("Volume {}%", level),
+            Self::Mute => "Mute".to_string(),
+            Self::Unmute => "Unmute".to_string(),
+            Self::ToggleMute => "Toggle mute".to_string(),
+        }
+    }
+}
+
+/// A VolumeControl request, read from the action payload
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolumeCommand {
+    /// Change the default output
+    Output(VolumeChange),
+    /// Change every stream of one application
+    App { app: String, change: VolumeChange },
+    /// Report the default output's level, or an application's
+    Query { app: Option<String> },
+    /// Make the device that best matches a spoken name the default
+    SetDefault { kind: DeviceKind, device: String },
+    ListDevices,
+}
+
+impl VolumeCommand {
+    pub fn from_payload(payload: &serde_json::Value) -> Result<Self, String> {
+        let field = |name: &str| {
+            payload
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let number = |name: &str| {
+            payload.get(name).and_then(|v| {
+                v.as_u64()
+                    .or_else(|| v.as_f64().map(|n| n.max(0.0).round() as u64))
+                    .or_else(|| v.as_str().and_then(|s| s.trim().trim_end_matches('%').parse().ok()))
+            })
+        };
+        let app = field("app");
+
+        match field("action").as_deref() {
+            Some("get") | Some("query") => return Ok(Self::Query { app }),
+            Some("list_devices") => return Ok(Self::ListDevices),
+            Some(action @ ("set_output" | "set_input")) => {
+                let kind = if action == "set_input" { DeviceKind::Input } else { DeviceKind::Output };
+                let device = field("device")
+                    .ok_or_else(|| format!("Which {} device should I switch to?", kind.name()))?;
+                return Ok(Self::SetDefault { kind, device });
+            }
+            Some("set") | None => {}
+            Some(other) => return Err(format!("Unknown volume action: {}", other)),
+        }
+
+        let step = number("amount").map(|n| n.min(100) as u32).unwrap_or(DEFAULT_STEP);
+        let change = match (number("level"), field("direction").as_deref()) {
+            (Some(level), _) => VolumeChange::Set(level.min(100) as u32),
+            (None, None) | (None, Some("up" | "increase" | "louder")) => VolumeChange::Up(step),
+            (None, Some("down" | "decrease" | "quieter" | "lower")) => VolumeChange::Down(step),
+            (None, Some("mute")) => VolumeChange::ToggleMute,
+            (None, Some("unmute")) => VolumeChange::Unmute,
+            (None, Some(other)) => return Err(format!("Unknown volume direction: {}", other)),
+        };
+
+        Ok(match app {
+            // "mute Spotify" means mute; the output toggles like the mute key
+            Some(app) if change == VolumeChange::ToggleMute => Self::App { app, change: VolumeChange::Mute },
+            Some(app) => Self::App { app, change },
+            None => Self::Output(change),
+        })
+    }
+
+    /// Short description for dry runs and logs
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Output(change) => change.describe(),
+            Self::App { app, change } => format!("{}: {}", app, change.describe().to_lowercase()),
+            Self::Query { app: Some(app) } => format!("Get {} volume", app),
+            Self::Query { app: None } => "Get volume".to_string(),
+            Self::SetDefault { kind, device } => format!("Switch {} to {}", kind.name(), device),
+            Self::ListDevices => "List audio devices".to_string(),
+        }
+    }
+}
+
+/// What a volume command did
+#[derive(Debug, Clone)]
+pub struct VolumeOutcome {
+    pub message: String,
+    pub data: Option<serde_json::Value>,
+    /// Default output level before the change, for undo
+    pub previous_level: Option<u32>,
+}
+
+/// A sound server that can list and change devices and streams
+pub trait Mixer {
+    /// Outputs or inputs, without monitor sources
+    fn devices(&mut self, kind: DeviceKind) -> Result<Vec<Device>, String>;
+    /// Playback streams of running applications
+    fn streams(&mut self) -> Result<Vec<AppStream>, String>;
+    fn set_device_volume(&mut self, device: &Device, volume: u32) -> Result<(), String>;
+    fn set_device_mute(&mut self, device: &Device, muted: bool) -> Result<(), String>;
+    fn set_stream_volume(&mut self, stream: &AppStream, volume: u32) -> Result<(), String>;
+    fn set_stream_mute(&mut self, stream: &AppStream, muted: bool) -> Result<(), String>;
+    /// Make `device` the default, switching to `port` first when given.
+    /// New outputs also take over the streams that are already playing.
+    fn set_default(&mut self, device: &Device, port: Option<&Port>) -> Result<(), String>;
+
+    fn default_device(&mut self, kind: DeviceKind) -> Result<Device, String> {
+        let mut devices = self.devices(kind)?;
+        let position = devices.iter().position(|d| d.is_default).unwrap_or(0);
+        if devices.is_empty() {
+            return Err(format!("No audio {} devices found", kind.name()));
+        }
+        Ok(devices.swap_remove(position))
+    }
+
+    fn run(&mut self, command: &VolumeCommand) -> Result<VolumeOutcome, String> {
+        match command {
+            VolumeCommand::Output(change) => {
+                let device = self.default_device(DeviceKind::Output)?;
+                let (volume, muted) = change.apply(device.volume, device.muted);
+                if volume != device.volume {
+                    self.set_device_volume(&device, volume)?;
+                }
+                if muted != device.muted {
+                    self.set_device_mute(&device, muted)?;
+                }
+                Ok(VolumeOutcome {
+                    message: level_message("Volume", volume, muted),
+                    data: Some(serde_json::json!({
+                        "device": device.description,
+                        "volume": volume,
+                        "muted": muted,
+                        "previous": device.volume,
+                    })),
+                    previous_level: Some(device.volume),
+                })
+            }
+            VolumeCommand::App { app, change } => {
+                let streams = self.streams()?;
+                let matching = matching_streams(&streams, app);
+                let first = matching.first().ok_or_else(|| format!("{} isn't playing any audio", app))?;
+                let (volume, muted) = change.apply(first.volume, first.muted);
+                for stream in &matching {
+                    if volume != stream.volume {
+                        self.set_stream_volume(stream, volume)?;
+                    }
+                    if muted != stream.muted {
+                        self.set_stream_mute(stream, muted)?;
+                    }
+                }
+                Ok(VolumeOutcome {
+                    message: level_message(&first.app, volume, muted),
+                    data: Some(serde_json::json!({ "app": first.app, "volume": volume, "muted": muted })),
+                    previous_level: None,
+                })
+            }
+            VolumeCommand::Query { app: None } => {
+                let device = self.default_device(DeviceKind::Output)?;
+                Ok(VolumeOutcome {
+                    message: format!("{} on {}", level_message("Volume", device.volume, device.muted), device.description),
+                    data: Some(serde_json::to_value(&device).unwrap_or_default()),
+                    previous_level: None,
+                })
+            }
+            VolumeCommand::Query { app: Some(app) } => {
+                let streams = self.streams()?;
+                let stream = *matching_streams(&streams, app)
+                    .first()
+                    .ok_or_else(|| format!("{} isn't playing any audio", app))?;
+                Ok(VolumeOutcome {
+                    message: level_message(&stream.app, stream.volume, stream.muted),
+                    data: Some(serde_json::to_value(stream).unwrap_or_default()),
+                    previous_level: None,
+                })
+            }
+            VolumeCommand::SetDefault { kind, device } => {
+                let devices = self.devices(*kind)?;
+                let Some(choice) = choose_device(&devices, device) else {
+                    let names: Vec<&str> = devices.iter().map(|d| d.description.as_str()).collect();
+                    return Err(format!(
+                        "No {} device matches \"{}\". Available: {}",
+                        kind.name(),
+                        device,
+                        names.join(", ")
+                    ));
+                };
+                self.set_default(choice.device, choice.port)?;
+                let name = match choice.port {
+                    Some(port) => format!("{} ({})", port.description, choice.device.description),
+                    None => choice.device.description.clone(),
+                };
+                Ok(VolumeOutcome {
+                    message: format!("Switched {} to {}", kind.name(), name),
+                    data: Some(serde_json::json!({
+                        "device": choice.device.name,
+                        "port": choice.port.map(|p| p.name.clone()),
+                    })),
+                    previous_level: None,
+                })
+            }
+            VolumeCommand::ListDevices => {
+                let outputs = self.devices(DeviceKind::Output)?;
+                let inputs = self.devices(DeviceKind::Input)?;
+                let list = |devices: &[Device]| {
+                    devices
+                        .iter()
+                        .map(|d| if d.is_default { format!("{} (default)", d.description) } else { d.description.clone() })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                Ok(VolumeOutcome {
+                    message: format!("Outputs: {}. Inputs: {}", list(&outputs), list(&inputs)),
+                    data: Some(serde_json::json!({ "outputs": outputs, "inputs": inputs })),
+                    previous_level: None,
+                })
+            }
+        }
+    }
+}
+
+fn level_message(name: &str, volume: u32, muted: bool) -> String {
+    if muted {
+        format!("{} muted ({}%)", name, volume)
+    } else {
+        format!("{} {}%", name, volume)
+    }
+}
+
+/// "Google Chrome" -> "googlechrome"
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Streams whose application or executable name matches a spoken app name
+pub fn matching_streams<'a>(streams: &'a [AppStream], app: &str) -> Vec<&'a AppStream> {
+    let wanted = compact(app);
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+    streams
+        .iter()
+        .filter(|stream| {
+            [Some(&stream.app), stream.binary.as_ref()].into_iter().flatten().any(|name| {
+                let name = compact(name);
+                name == wanted || name.contains(&wanted) || (name.len() >= 3 && wanted.contains(&name))
+            })
+        })
+        .collect()
+}
+
+/// The device a spoken name refers to, and the port to switch to when the
+/// name matched a jack rather than the device itself
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceChoice<'a> {
+    pub device: &'a Device,
+    pub port: Option<&'a Port>,
+}
+
+/// Identifiers that mark a kind of device, for names like "headphones"
+/// that rarely appear in a product name
+fn kind_hints(word: &str) -> &'static [&'static str] {
+    match word {
+        "headphones" | "headphone" | "headset" | "earphones" | "earbuds" => {
+            &["headphone", "headset", "hands-free", "handsfree", "earbud"]
+        }
+        "speakers" | "speaker" => &["speaker"],
+        "hdmi" | "tv" | "television" | "displayport" => &["hdmi", "displayport", "tv"],
+        "bluetooth" => &["bluez", "bluetooth"],
+        "usb" => &["usb"],
+        "microphone" | "mic" => &["mic"],
+        "webcam" | "camera" => &["webcam", "camera"],
+        _ => &[],
+    }
+}
+
+/// How well `spoken` describes something called `label`: 100 for the exact
+/// name, 80 when every spoken word is in it, 60 for a kind hint
+fn label_score(label: &str, spoken: &str, words: &[&str]) -> u32 {
+    let label = label.to_lowercase();
+    if label.is_empty() {
+        0
+    } else if label == spoken {
+        100
+    } else if words.iter().all(|w| label.contains(w)) {
+        80
+    } else if words.iter().flat_map(|w| kind_hints(w)).any(|hint| label.contains(hint)) {
+        60
+    } else {
+        0
+    }
+}
+
+/// Pick the device or port that best matches a spoken name
+pub fn choose_device<'a>(devices: &'a [Device], spoken: &str) -> Option<DeviceChoice<'a>> {
+    let spoken = spoken.trim().to_lowercase();
+    let spoken = ["the ", "my ", "our "]
+        .iter()
+        .fold(spoken.as_str(), |rest, filler| rest.strip_prefix(filler).unwrap_or(rest));
+    let spoken = [" output", " input", " audio", " device"]
+        .iter()
+        .fold(spoken, |rest, filler| rest.strip_suffix(filler).unwrap_or(rest))
+        .trim();
+    let words: Vec<&str> = spoken.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(u32, DeviceChoice)> = None;
+    for device in devices {
+        let device_score = [Some(&device.description), Some(&device.name), device.form_factor.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|label| label_score(label, spoken, &words))
+            .max()
+            .unwrap_or(0);
+        let mut candidates = vec![(device_score, None)];
+        for port in device.ports.iter().filter(|p| p.available) {
+            // A jack only wins over a whole device on a closer match
+            let score = label_score(&port.description, spoken, &words)
+                .max(label_score(&port.name, spoken, &words))
+                .saturating_sub(5);
+            candidates.push((score, Some(port)));
+        }
+        for (score, port) in candidates {
+            if score > 0 && best.as_ref().map_or(true, |(top, _)| score > *top) {
+                best = Some((score, DeviceChoice { device, port }));
+            }
+        }
+    }
+    best.map(|(_, choice)| choice)
+}
+
+#[cfg(target_os = "linux")]
+pub fn connect() -> Result<pulse::Pulse, String> {
+    pulse::Pulse::connect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, description: &str, form_factor: Option<&str>, ports: &[(&str, &str, bool)]) -> Device {
+        Device {
+            index: 0,
+            kind: DeviceKind::Output,
+            name: name.to_string(),
+            description: description.to_string(),
+            volume: 50,
+            muted: false,
+            is_default: false,
+            form_factor: form_factor.map(str::to_string),
+            ports: ports
+                .iter()
+                .map(|(name, description, available)| Port {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    available: *available,
+                })
+                .collect(),
+            active_port: None,
+            channels: 2,
+        }
+    }
+
+    #[test]
+    fn reads_payloads() {
+        let parse = |payload: serde_json::Value| VolumeCommand::from_payload(&payload).unwrap();
+        assert_eq!(parse(serde_json::json!({"direction": "up"})), VolumeCommand::Output(VolumeChange::Up(10)));
+        assert_eq!(parse(serde_json::json!({"level": "35%"})), VolumeCommand::Output(VolumeChange::Set(35)));
+        assert_eq!(
+            parse(serde_json::json!({"app": "Spotify", "direction": "down"})),
+            VolumeCommand::App { app: "Spotify".to_string(), change: VolumeChange::Down(10) }
+        );
+        assert_eq!(
+            parse(serde_json::json!({"action": "set_output", "device": "headphones"})),
+            VolumeCommand::SetDefault { kind: DeviceKind::Output, device: "headphones".to_string() }
+        );
+        assert!(VolumeCommand::from_payload(&serde_json::json!({"action": "set_input"})).is_err());
+    }
+
+    #[test]
+    fn steps_stay_within_range() {
+        assert_eq!(VolumeChange::Up(10).apply(95, false), (100, false));
+        assert_eq!(VolumeChange::Up(10).apply(120, false), (120, false));
+        assert_eq!(VolumeChange::Down(10).apply(5, true), (0, true));
+        assert_eq!(VolumeChange::Set(35).apply(80, true), (35, false));
+    }
+
+    #[test]
+    fn chooses_devices_by_spoken_name() {
+        let devices = vec![
+            device(
+                "alsa_output.pci-0000_00_1f.3.analog-stereo",
+                "Built-in Audio Analog Stereo",
+                None,
+                &[("analog-output-speaker", "Speakers", true), ("analog-output-headphones", "Headphones", false)],
+            ),
+            device("bluez_output.AC_80_0A.1", "WH-1000XM4", Some("headphone"), &[]),
+            device("alsa_output.pci-0000_01_00.1.hdmi-stereo", "HD Audio Controller Digital Stereo (HDMI)", None, &[]),
+        ];
+
+        // The wired jack is unplugged, so headphones means the Bluetooth pair
+        let choice = choose_device(&devices, "headphones").unwrap();
+        assert_eq!(choice.device.description, "WH-1000XM4");
+        assert!(choice.port.is_none());
+
+        let choice = choose_device(&devices, "the speakers").unwrap();
+        assert_eq!(choice.port.map(|p| p.name.as_str()), Some("analog-output-speaker"));
+
+        assert_eq!(choose_device(&devices, "hdmi").unwrap().device.name, devices[2].name);
+        assert_eq!(choose_device(&devices, "wh-1000xm4").unwrap().device.description, "WH-1000XM4");
+        assert!(choose_device(&devices, "kitchen radio").is_none());
+    }
+
+    #[test]
+    fn matches_streams_by_app_or_binary() {
+        let stream = |app: &str, binary: &str| AppStream {
+            index: 0,
+            app: app.to_string(),
+            binary: Some(binary.to_string()),
+            title: None,
+            volume: 100,
+            muted: false,
+            device: 0,
+            channels: 2,
+        };
+        let streams = vec![stream("Spotify", "spotify"), stream("Google Chrome", "chrome"), stream("Firefox", "firefox-bin")];
+        assert_eq!(matching_streams(&streams, "spotify").len(), 1);
+        assert_eq!(matching_streams(&streams, "chrome")[0].app, "Google Chrome");
+        assert_eq!(matching_streams(&streams, "firefox")[0].app, "Firefox");
+        assert!(matching_streams(&streams, "vlc").is_empty());
+    }
+}
@@ -0,0 +1,791 @@
+//! PulseAudio native protocol client
+//!
+//! Just enough of the protocol to read devices and streams and change their
+//! volume, mute state and the defaults. pipewire-pulse speaks the same
+//! protocol on the same socket, so this works on PipeWire too.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{AppStream, Device, DeviceKind, Mixer, Port};
+
+/// Newest protocol version whose info replies we know how to read; the
+/// server answers in the older of its version and ours
+const PROTOCOL_VERSION: u32 = 32;
+/// Oldest version that reports ports (PulseAudio 0.9.16)
+const MIN_PROTOCOL_VERSION: u32 = 16;
+const VERSION_MASK: u32 = 0x0000_ffff;
+const COOKIE_LENGTH: usize = 256;
+const CONTROL_CHANNEL: u32 = u32::MAX;
+const INVALID_INDEX: u32 = u32::MAX;
+/// 100% in the server's volume units
+const VOLUME_NORM: u64 = 0x10000;
+/// Upper bound on a reply, well above any device list
+const MAX_PACKET: usize = 16 * 1024 * 1024;
+
+// Command numbers from pulsecore/native-common.h
+const COMMAND_ERROR: u32 = 0;
+const COMMAND_REPLY: u32 = 2;
+const COMMAND_AUTH: u32 = 8;
+const COMMAND_SET_CLIENT_NAME: u32 = 9;
+const COMMAND_GET_SERVER_INFO: u32 = 20;
+const COMMAND_GET_SINK_INFO_LIST: u32 = 22;
+const COMMAND_GET_SOURCE_INFO_LIST: u32 = 24;
+const COMMAND_GET_SINK_INPUT_INFO_LIST: u32 = 30;
+const COMMAND_SET_SINK_VOLUME: u32 = 36;
+const COMMAND_SET_SINK_INPUT_VOLUME: u32 = 37;
+const COMMAND_SET_SOURCE_VOLUME: u32 = 38;
+const COMMAND_SET_SINK_MUTE: u32 = 39;
+const COMMAND_SET_SOURCE_MUTE: u32 = 40;
+const COMMAND_SET_DEFAULT_SINK: u32 = 44;
+const COMMAND_SET_DEFAULT_SOURCE: u32 = 45;
+const COMMAND_MOVE_SINK_INPUT: u32 = 67;
+const COMMAND_SET_SINK_INPUT_MUTE: u32 = 69;
+const COMMAND_SET_SINK_PORT: u32 = 96;
+const COMMAND_SET_SOURCE_PORT: u32 = 97;
+
+// Tagstruct type tags
+const TAG_STRING: u8 = b't';
+const TAG_STRING_NULL: u8 = b'N';
+const TAG_U32: u8 = b'L';
+const TAG_U8: u8 = b'B';
+const TAG_SAMPLE_SPEC: u8 = b'a';
+const TAG_ARBITRARY: u8 = b'x';
+const TAG_TRUE: u8 = b'1';
+const TAG_FALSE: u8 = b'0';
+const TAG_USEC: u8 = b'U';
+const TAG_CHANNEL_MAP: u8 = b'm';
+const TAG_CVOLUME: u8 = b'v';
+const TAG_PROPLIST: u8 = b'P';
+const TAG_VOLUME: u8 = b'V';
+const TAG_FORMAT_INFO: u8 = b'f';
+
+/// Server volume units -> percent
+fn to_percent(volume: u32) -> u32 {
+    ((volume as u64 * 100 + VOLUME_NORM / 2) / VOLUME_NORM) as u32
+}
+
+/// Percent -> server volume units
+fn from_percent(percent: u32) -> u32 {
+    (percent as u64 * VOLUME_NORM / 100) as u32
+}
+
+/// Average of a per-channel volume, in percent
+fn average_percent(channels: &[u32]) -> u32 {
+    if channels.is_empty() {
+        return 0;
+    }
+    let total: u64 = channels.iter().map(|v| *v as u64).sum();
+    to_percent((total / channels.len() as u64) as u32)
+}
+
+fn error_name(code: u32) -> String {
+    match code {
+        1 => "access denied".to_string(),
+        2 => "unknown command".to_string(),
+        3 => "invalid argument".to_string(),
+        5 => "no such device or stream".to_string(),
+        9 => "authentication failed".to_string(),
+        17 => "incompatible protocol version".to_string(),
+        19 => "not supported".to_string(),
+        other => format!("error {}", other),
+    }
+}
+
+/// Builds a tagstruct, the protocol's self-describing message body
+#[derive(Default)]
+struct TagWriter {
+    data: Vec<u8>,
+}
+
+impl TagWriter {
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.data.push(TAG_U32);
+        self.data.extend(value.to_be_bytes());
+        self
+    }
+
+    fn string(&mut self, value: Option<&str>) -> &mut Self {
+        match value {
+            Some(value) => {
+                self.data.push(TAG_STRING);
+                self.data.extend(value.as_bytes());
+                self.data.push(0);
+            }
+            None => self.data.push(TAG_STRING_NULL),
+        }
+        self
+    }
+
+    fn boolean(&mut self, value: bool) -> &mut Self {
+        self.data.push(if value { TAG_TRUE } else { TAG_FALSE });
+        self
+    }
+
+    fn arbitrary(&mut self, value: &[u8]) -> &mut Self {
+        self.data.push(TAG_ARBITRARY);
+        self.data.extend((value.len() as u32).to_be_bytes());
+        self.data.extend(value);
+        self
+    }
+
+    /// The same level on every channel
+    fn cvolume(&mut self, channels: u8, volume: u32) -> &mut Self {
+        self.data.push(TAG_CVOLUME);
+        self.data.push(channels);
+        for _ in 0..channels {
+            self.data.extend(volume.to_be_bytes());
+        }
+        self
+    }
+
+    fn proplist(&mut self, properties: &[(&str, &str)]) -> &mut Self {
+        self.data.push(TAG_PROPLIST);
+        for (key, value) in properties {
+            // String values are stored with their terminating NUL
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            self.string(Some(key)).u32(bytes.len() as u32).arbitrary(&bytes);
+        }
+        self.string(None)
+    }
+}
+
+/// Reads a tagstruct, checking each value's tag
+struct TagReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> TagReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len());
+        let end = end.ok_or("Truncated reply from the sound server")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn raw_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn raw_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn tag(&mut self, expected: u8) -> Result<(), String> {
+        let tag = self.raw_u8()?;
+        if tag == expected {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unexpected reply from the sound server: wanted '{}', got '{}'",
+                expected as char, tag as char
+            ))
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.tag(TAG_U32)?;
+        self.raw_u32()
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        self.tag(TAG_U8)?;
+        self.raw_u8()
+    }
+
+    fn boolean(&mut self) -> Result<bool, String> {
+        match self.raw_u8()? {
+            TAG_TRUE => Ok(true),
+            TAG_FALSE => Ok(false),
+            tag => Err(format!("Unexpected reply from the sound server: wanted a boolean, got '{}'", tag as char)),
+        }
+    }
+
+    fn string(&mut self) -> Result<Option<String>, String> {
+        match self.raw_u8()? {
+            TAG_STRING_NULL => Ok(None),
+            TAG_STRING => {
+                let rest = &self.data[self.pos..];
+                let len = rest.iter().position(|b| *b == 0).ok_or("Unterminated string from the sound server")?;
+                let value = String::from_utf8_lossy(&rest[..len]).into_owned();
+                self.pos += len + 1;
+                Ok(Some(value))
+            }
+            tag => Err(format!("Unexpected reply from the sound server: wanted a string, got '{}'", tag as char)),
+        }
+    }
+
+    fn arbitrary(&mut self) -> Result<&'a [u8], String> {
+        self.tag(TAG_ARBITRARY)?;
+        let len = self.raw_u32()? as usize;
+        self.take(len)
+    }
+
+    fn usec(&mut self) -> Result<(), String> {
+        self.tag(TAG_USEC)?;
+        self.take(8).map(|_| ())
+    }
+
+    fn volume(&mut self) -> Result<u32, String> {
+        self.tag(TAG_VOLUME)?;
+        self.raw_u32()
+    }
+
+    fn sample_spec(&mut self) -> Result<(), String> {
+        self.tag(TAG_SAMPLE_SPEC)?;
+        // format, channels, rate
+        self.take(6).map(|_| ())
+    }
+
+    fn channel_map(&mut self) -> Result<(), String> {
+        self.tag(TAG_CHANNEL_MAP)?;
+        let channels = self.raw_u8()? as usize;
+        self.take(channels).map(|_| ())
+    }
+
+    fn cvolume(&mut self) -> Result<Vec<u32>, String> {
+        self.tag(TAG_CVOLUME)?;
+        let channels = self.raw_u8()?;
+        (0..channels).map(|_| self.raw_u32()).collect()
+    }
+
+    fn proplist(&mut self) -> Result<HashMap<String, String>, String> {
+        self.tag(TAG_PROPLIST)?;
+        let mut properties = HashMap::new();
+        while let Some(key) = self.string()? {
+            let len = self.u32()? as usize;
+            let value = self.arbitrary()?;
+            if value.len() != len {
+                return Err("Malformed property list from the sound server".to_string());
+            }
+            let value = value.strip_suffix(&[0]).unwrap_or(value);
+            properties.insert(key, String::from_utf8_lossy(value).into_owned());
+        }
+        Ok(properties)
+    }
+
+    fn format_info(&mut self) -> Result<(), String> {
+        self.tag(TAG_FORMAT_INFO)?;
+        self.u8()?;
+        self.proplist().map(|_| ())
+    }
+}
+
+pub struct Pulse {
+    stream: UnixStream,
+    version: u32,
+    next_tag: u32,
+}
+
+impl Pulse {
+    pub fn connect() -> Result<Self, String> {
+        let path = socket_path()?;
+        let stream = UnixStream::connect(&path).map_err(|e| {
+            format!("Failed to connect to the sound server at {}: {}", path.display(), e)
+        })?;
+        stream.set_read_timeout(Some(Duration::from_secs(2))).ok();
+        stream.set_write_timeout(Some(Duration::from_secs(2))).ok();
+
+        let mut pulse = Self { stream, version: PROTOCOL_VERSION, next_tag: 0 };
+
+        let mut auth = TagWriter::default();
+        auth.u32(PROTOCOL_VERSION).arbitrary(&cookie());
+        let reply = pulse.request(COMMAND_AUTH, &auth)?;
+        let server_version = TagReader::new(&reply).u32()? & VERSION_MASK;
+        pulse.version = server_version.min(PROTOCOL_VERSION);
+        if pulse.version < MIN_PROTOCOL_VERSION {
+            return Err(format!("The sound server's protocol version {} is too old", server_version));
+        }
+
+        let mut name = TagWriter::default();
+        name.proplist(&[("application.name", "ListenOS"), ("application.id", "com.listenos.app")]);
+        pulse.request(COMMAND_SET_CLIENT_NAME, &name)?;
+        Ok(pulse)
+    }
+
+    /// Send one command and wait for its reply, returning the reply body
+    fn request(&mut self, command: u32, body: &TagWriter) -> Result<Vec<u8>, String> {
+        let tag = self.next_tag;
+        self.next_tag = self.next_tag.wrapping_add(1);
+
+        let mut message = TagWriter::default();
+        message.u32(command).u32(tag);
+        message.data.extend(&body.data);
+
+        let mut packet = Vec::with_capacity(20 + message.data.len());
+        packet.extend((message.data.len() as u32).to_be_bytes());
+        packet.extend(CONTROL_CHANNEL.to_be_bytes());
+        packet.extend([0u8; 12]); // offset high, offset low, flags
+        packet.extend(&message.data);
+        self.stream
+            .write_all(&packet)
+            .map_err(|e| format!("Failed to talk to the sound server: {}", e))?;
+
+        loop {
+            let mut descriptor = [0u8; 20];
+            self.stream
+                .read_exact(&mut descriptor)
+                .map_err(|e| format!("No reply from the sound server: {}", e))?;
+            let len = u32::from_be_bytes([descriptor[0], descriptor[1], descriptor[2], descriptor[3]]) as usize;
+            let channel = u32::from_be_bytes([descriptor[4], descriptor[5], descriptor[6], descriptor[7]]);
+            if len > MAX_PACKET {
+                return Err("Oversized reply from the sound server".to_string());
+            }
+            let mut payload = vec![0u8; len];
+            self.stream
+                .read_exact(&mut payload)
+                .map_err(|e| format!("No reply from the sound server: {}", e))?;
+            if channel != CONTROL_CHANNEL {
+                continue;
+            }
+
+            let mut reader = TagReader::new(&payload);
+            let (reply_command, reply_tag) = (reader.u32()?, reader.u32()?);
+            if reply_tag != tag {
+                // Unsolicited events such as stream requests
+                continue;
+            }
+            return match reply_command {
+                COMMAND_REPLY => Ok(payload.split_off(reader.pos)),
+                COMMAND_ERROR => Err(format!("Sound server refused the request: {}", error_name(reader.u32()?))),
+                other => Err(format!("Unexpected command {} from the sound server", other)),
+            };
+        }
+    }
+
+    /// Names of the default output and input
+    fn defaults(&mut self) -> Result<(Option<String>, Option<String>), String> {
+        let reply = self.request(COMMAND_GET_SERVER_INFO, &TagWriter::default())?;
+        let mut reader = TagReader::new(&reply);
+        for _ in 0..4 {
+            // package name and version, user name, host name
+            reader.string()?;
+        }
+        reader.sample_spec()?;
+        Ok((reader.string()?, reader.string()?))
+    }
+
+    /// One entry of a sink or source info list
+    fn read_device(&self, reader: &mut TagReader, kind: DeviceKind) -> Result<(Device, bool), String> {
+        let index = reader.u32()?;
+        let name = reader.string()?.unwrap_or_default();
+        let description = reader.string()?;
+        reader.sample_spec()?;
+        reader.channel_map()?;
+        reader.u32()?; // owner module
+        let volume = reader.cvolume()?;
+        let muted = reader.boolean()?;
+        // Sinks report their monitor source, sources the sink they monitor
+        let monitor_index = reader.u32()?;
+        reader.string()?;
+        reader.usec()?; // latency
+        reader.string()?; // driver
+        reader.u32()?; // flags
+
+        let mut properties = HashMap::new();
+        if self.version >= 13 {
+            properties = reader.proplist()?;
+            reader.usec()?; // configured latency
+        }
+        if self.version >= 15 {
+            reader.volume()?; // base volume
+            reader.u32()?; // state
+            reader.u32()?; // volume steps
+            reader.u32()?; // card
+        }
+        let mut ports = Vec::new();
+        let mut active_port = None;
+        if self.version >= 16 {
+            let count = reader.u32()?;
+            for _ in 0..count {
+                let port_name = reader.string()?.unwrap_or_default();
+                let port_description = reader.string()?.unwrap_or_default();
+                reader.u32()?; // priority
+                // 0 is unknown, 1 is unplugged, 2 is plugged in
+                let available = if self.version >= 24 { reader.u32()? != 1 } else { true };
+                ports.push(Port { name: port_name, description: port_description, available });
+            }
+            active_port = reader.string()?;
+        }
+        let formats_version = if kind == DeviceKind::Output { 21 } else { 22 };
+        if self.version >= formats_version {
+            let count = reader.u8()?;
+            for _ in 0..count {
+                reader.format_info()?;
+            }
+        }
+
+        let is_monitor = kind == DeviceKind::Input && monitor_index != INVALID_INDEX;
+        let device = Device {
+            index,
+            kind,
+            description: description
+                .or_else(|| properties.get("device.description").cloned())
+                .unwrap_or_else(|| name.clone()),
+            name,
+            volume: average_percent(&volume),
+            muted,
+            is_default: false,
+            form_factor: properties
+                .get("device.form_factor")
+                .cloned()
+                .or_else(|| (properties.get("device.bus").map(String::as_str) == Some("bluetooth")).then(|| "bluetooth".to_string())),
+            ports,
+            active_port,
+            channels: volume.len() as u8,
+        };
+        Ok((device, is_monitor))
+    }
+
+    fn read_stream(&self, reader: &mut TagReader) -> Result<AppStream, String> {
+        let index = reader.u32()?;
+        let media_name = reader.string()?;
+        reader.u32()?; // owner module
+        reader.u32()?; // client
+        let device = reader.u32()?;
+        reader.sample_spec()?;
+        reader.channel_map()?;
+        let volume = reader.cvolume()?;
+        reader.usec()?; // buffer latency
+        reader.usec()?; // sink latency
+        reader.string()?; // resample method
+        reader.string()?; // driver
+        let muted = if self.version >= 11 { reader.boolean()? } else { false };
+        let properties = if self.version >= 13 { reader.proplist()? } else { HashMap::new() };
+        if self.version >= 19 {
+            reader.boolean()?; // corked
+        }
+        if self.version >= 20 {
+            reader.boolean()?; // has volume
+            reader.boolean()?; // volume writable
+        }
+        if self.version >= 21 {
+            reader.format_info()?;
+        }
+
+        let binary = properties.get("application.process.binary").cloned();
+        Ok(AppStream {
+            index,
+            app: properties
+                .get("application.name")
+                .cloned()
+                .or_else(|| binary.clone())
+                .unwrap_or_else(|| media_name.clone().unwrap_or_default()),
+            binary,
+            title: properties.get("media.name").cloned().or(media_name),
+            volume: average_percent(&volume),
+            muted,
+            device,
+            channels: volume.len() as u8,
+        })
+    }
+
+    /// Set a device's volume, mute state or port by index
+    fn device_request(&mut self, device: &Device, commands: [u32; 2], value: impl FnOnce(&mut TagWriter)) -> Result<(), String> {
+        let command = if device.kind == DeviceKind::Output { commands[0] } else { commands[1] };
+        let mut body = TagWriter::default();
+        body.u32(device.index).string(None);
+        value(&mut body);
+        self.request(command, &body).map(|_| ())
+    }
+}
+
+impl Mixer for Pulse {
+    fn devices(&mut self, kind: DeviceKind) -> Result<Vec<Device>, String> {
+        let (default_output, default_input) = self.defaults()?;
+        let (command, default) = match kind {
+            DeviceKind::Output => (COMMAND_GET_SINK_INFO_LIST, default_output),
+            DeviceKind::Input => (COMMAND_GET_SOURCE_INFO_LIST, default_input),
+        };
+        let reply = self.request(command, &TagWriter::default())?;
+        let mut reader = TagReader::new(&reply);
+        let mut devices = Vec::new();
+        while !reader.is_empty() {
+            let (mut device, is_monitor) = self.read_device(&mut reader, kind)?;
+            if !is_monitor {
+                device.is_default = default.as_deref() == Some(device.name.as_str());
+                devices.push(device);
+            }
+        }
+        Ok(devices)
+    }
+
+    fn streams(&mut self) -> Result<Vec<AppStream>, String> {
+        let reply = self.request(COMMAND_GET_SINK_INPUT_INFO_LIST, &TagWriter::default())?;
+        let mut reader = TagReader::new(&reply);
+        let mut streams = Vec::new();
+        while !reader.is_empty() {
+            streams.push(self.read_stream(&mut reader)?);
+        }
+        Ok(streams)
+    }
+
+    fn set_device_volume(&mut self, device: &Device, volume: u32) -> Result<(), String> {
+        let channels = device.channels.max(1);
+        self.device_request(device, [COMMAND_SET_SINK_VOLUME, COMMAND_SET_SOURCE_VOLUME], |body| {
+            body.cvolume(channels, from_percent(volume));
+        })
+    }
+
+    fn set_device_mute(&mut self, device: &Device, muted: bool) -> Result<(), String> {
+        self.device_request(device, [COMMAND_SET_SINK_MUTE, COMMAND_SET_SOURCE_MUTE], |body| {
+            body.boolean(muted);
+        })
+    }
+
+    fn set_stream_volume(&mut self, stream: &AppStream, volume: u32) -> Result<(), String> {
+        let mut body = TagWriter::default();
+        body.u32(stream.index).cvolume(stream.channels.max(1), from_percent(volume));
+        self.request(COMMAND_SET_SINK_INPUT_VOLUME, &body).map(|_| ())
+    }
+
+    fn set_stream_mute(&mut self, stream: &AppStream, muted: bool) -> Result<(), String> {
+        let mut body = TagWriter::default();
+        body.u32(stream.index).boolean(muted);
+        self.request(COMMAND_SET_SINK_INPUT_MUTE, &body).map(|_| ())
+    }
+
+    fn set_default(&mut self, device: &Device, port: Option<&Port>) -> Result<(), String> {
+        if let Some(port) = port {
+            if device.active_port.as_deref() != Some(port.name.as_str()) {
+                self.device_request(device, [COMMAND_SET_SINK_PORT, COMMAND_SET_SOURCE_PORT], |body| {
+                    body.string(Some(&port.name));
+                })?;
+            }
+        }
+
+        let command = match device.kind {
+            DeviceKind::Output => COMMAND_SET_DEFAULT_SINK,
+            DeviceKind::Input => COMMAND_SET_DEFAULT_SOURCE,
+        };
+        let mut body = TagWriter::default();
+        body.string(Some(&device.name));
+        self.request(command, &body)?;
+
+        // Streams that are already playing stay where they are otherwise
+        if device.kind == DeviceKind::Output {
+            for stream in self.streams()?.iter().filter(|s| s.device != device.index) {
+                let mut body = TagWriter::default();
+                body.u32(stream.index).u32(device.index).string(None);
+                if let Err(e) = self.request(COMMAND_MOVE_SINK_INPUT, &body) {
+                    log::warn!("Could not move {} to {}: {}", stream.app, device.description, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The server socket: $PULSE_SERVER when it names a local socket, otherwise
+/// the per-user socket that both PulseAudio and pipewire-pulse create
+fn socket_path() -> Result<PathBuf, String> {
+    if let Ok(server) = std::env::var("PULSE_SERVER") {
+        let local = server
+            .split_whitespace()
+            .find_map(|entry| entry.strip_prefix("unix:").or_else(|| entry.starts_with('/').then_some(entry)));
+        if let Some(path) = local {
+            return Ok(PathBuf::from(path));
+        }
+    }
+    let runtime = std::env::var("XDG_RUNTIME_DIR")
+        .map_err(|_| "XDG_RUNTIME_DIR is not set, so the sound server can't be found".to_string())?;
+    Ok(PathBuf::from(runtime).join("pulse").join("native"))
+}
+
+/// The auth cookie PulseAudio shares with its clients. pipewire-pulse
+/// doesn't check it, so a blank cookie is sent when there is none.
+fn cookie() -> Vec<u8> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    let config = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".config")));
+    let candidates = [
+        std::env::var("PULSE_COOKIE").ok().map(PathBuf::from),
+        config.map(|config| config.join("pulse").join("cookie")),
+        home.map(|home| home.join(".pulse-cookie")),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .filter_map(|path| std::fs::read(path).ok())
+        .find(|cookie| cookie.len() >= COOKIE_LENGTH)
+        .map(|mut cookie| {
+            cookie.truncate(COOKIE_LENGTH);
+            cookie
+        })
+        .unwrap_or_else(|| vec![0; COOKIE_LENGTH])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINK_INFO_LIST_V16: &str = include_str!("fixtures/sink_info_list_v16.hex");
+    const SINK_INFO_LIST_V32: &str = include_str!("fixtures/sink_info_list_v32.hex");
+    const SOURCE_INFO_LIST_V21: &str = include_str!("fixtures/source_info_list_v21.hex");
+    const SINK_INPUT_INFO_LIST_V16: &str = include_str!("fixtures/sink_input_info_list_v16.hex");
+    const SINK_INPUT_INFO_LIST_V32: &str = include_str!("fixtures/sink_input_info_list_v32.hex");
+
+    /// Bytes of an annotated hex fixture; `#` starts a comment
+    fn fixture(text: &str) -> Vec<u8> {
+        let hex: String = text
+            .lines()
+            .filter_map(|line| line.split('#').next())
+            .flat_map(str::split_whitespace)
+            .collect();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// A client speaking `version` to a server that answers each request
+    /// with the next of `replies`
+    fn served(version: u32, replies: Vec<Vec<u8>>) -> Pulse {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        std::thread::spawn(move || {
+            for body in replies {
+                let mut descriptor = [0u8; 20];
+                if server.read_exact(&mut descriptor).is_err() {
+                    return;
+                }
+                let len = u32::from_be_bytes([descriptor[0], descriptor[1], descriptor[2], descriptor[3]]);
+                let mut request = vec![0u8; len as usize];
+                server.read_exact(&mut request).unwrap();
+                let mut reader = TagReader::new(&request);
+                let (_, tag) = (reader.u32().unwrap(), reader.u32().unwrap());
+
+                let mut reply = TagWriter::default();
+                reply.u32(COMMAND_REPLY).u32(tag);
+                reply.data.extend(body);
+                let mut packet = Vec::new();
+                packet.extend((reply.data.len() as u32).to_be_bytes());
+                packet.extend(CONTROL_CHANNEL.to_be_bytes());
+                packet.extend([0u8; 12]);
+                packet.extend(&reply.data);
+                server.write_all(&packet).unwrap();
+            }
+        });
+        Pulse { stream: client, version, next_tag: 0 }
+    }
+
+    fn server_info() -> Vec<u8> {
+        let mut info = TagWriter::default();
+        info.string(Some("pulseaudio"))
+            .string(Some("16.1"))
+            .string(Some("user"))
+            .string(Some("laptop"));
+        info.data.extend([TAG_SAMPLE_SPEC, 3, 2, 0, 0, 0xbb, 0x80]);
+        info.string(Some("alsa_output.pci-0000_00_1f.3.analog-stereo"))
+            .string(Some("alsa_input.pci-0000_00_1f.3.analog-stereo"))
+            .u32(0x1234); // cookie
+        info.data
+    }
+
+    #[test]
+    fn reads_sink_lists_at_old_and_new_versions() {
+        for (version, list) in [(16, SINK_INFO_LIST_V16), (32, SINK_INFO_LIST_V32)] {
+            let mut pulse = served(version, vec![server_info(), fixture(list)]);
+            let devices = pulse.devices(DeviceKind::Output).unwrap();
+            assert_eq!(devices.len(), 2, "v{}", version);
+
+            let speakers = &devices[0];
+            assert_eq!(speakers.description, "Built-in Audio Analog Stereo");
+            assert_eq!((speakers.volume, speakers.muted, speakers.is_default), (50, false, true));
+            assert_eq!(speakers.form_factor.as_deref(), Some("internal"));
+            assert_eq!(speakers.active_port.as_deref(), Some("analog-output-headphones"));
+            let ports: Vec<(&str, bool)> =
+                speakers.ports.iter().map(|p| (p.name.as_str(), p.available)).collect();
+            // Availability is only reported from v24; before that every port counts as available
+            assert_eq!(
+                ports,
+                [("analog-output-speaker", version < 24), ("analog-output-headphones", true)],
+                "v{}",
+                version
+            );
+
+            let headset = &devices[1];
+            assert_eq!(headset.description, "WH-1000XM4");
+            assert_eq!((headset.index, headset.volume, headset.muted), (3, 120, true));
+            assert_eq!(headset.form_factor.as_deref(), Some("bluetooth"));
+            assert_eq!(headset.channels, 2);
+        }
+    }
+
+    #[test]
+    fn reads_sources_without_formats_before_v22() {
+        let mut pulse = served(21, vec![server_info(), fixture(SOURCE_INFO_LIST_V21)]);
+        let devices = pulse.devices(DeviceKind::Input).unwrap();
+
+        // The monitor of the speakers is left out
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "alsa_input.pci-0000_00_1f.3.analog-stereo");
+        assert_eq!((devices[0].volume, devices[0].is_default), (40, true));
+        assert_eq!(devices[0].ports[0].description, "Microphone");
+    }
+
+    #[test]
+    fn reads_sink_input_lists_at_old_and_new_versions() {
+        for (version, list) in [(16, SINK_INPUT_INFO_LIST_V16), (32, SINK_INPUT_INFO_LIST_V32)] {
+            let mut pulse = served(version, vec![fixture(list)]);
+            let streams = pulse.streams().unwrap();
+            assert_eq!(streams.len(), 2, "v{}", version);
+
+            let firefox = &streams[0];
+            assert_eq!(firefox.app, "Firefox");
+            assert_eq!(firefox.binary.as_deref(), Some("firefox"));
+            assert_eq!((firefox.index, firefox.device, firefox.volume, firefox.muted), (12, 0, 100, false));
+
+            // No application name, so the binary stands in
+            let mpv = &streams[1];
+            assert_eq!(mpv.app, "mpv");
+            assert_eq!(mpv.title.as_deref(), Some("song.flac - mpv"));
+            assert_eq!((mpv.index, mpv.device, mpv.volume, mpv.muted), (15, 3, 50, true));
+        }
+    }
+
+    #[test]
+    fn converts_volumes() {
+        assert_eq!(to_percent(VOLUME_NORM as u32), 100);
+        assert_eq!(to_percent(from_percent(35)), 35);
+        assert_eq!(average_percent(&[from_percent(40), from_percent(60)]), 50);
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let mut writer = TagWriter::default();
+        writer
+            .u32(7)
+            .string(Some("alsa_output.analog-stereo"))
+            .string(None)
+            .boolean(true)
+            .cvolume(2, from_percent(30))
+            .proplist(&[("application.name", "Spotify")]);
+
+        let mut reader = TagReader::new(&writer.data);
+        assert_eq!(reader.u32().unwrap(), 7);
+        assert_eq!(reader.string().unwrap().as_deref(), Some("alsa_output.analog-stereo"));
+        assert_eq!(reader.string().unwrap(), None);
+        assert!(reader.boolean().unwrap());
+        assert_eq!(average_percent(&reader.cvolume().unwrap()), 30);
+        assert_eq!(reader.proplist().unwrap().get("application.name").map(String::as_str), Some("Spotify"));
+        assert!(reader.is_empty());
+        assert!(reader.u32().is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! BlueZ client: paired devices, connecting them and the adapter's power
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedObjectPath;
+
+use super::{best_match, clean_name, property, set_property, Properties};
+
+const SERVICE: &str = "org.bluez";
+const ADAPTER: &str = "org.bluez.Adapter1";
+const DEVICE: &str = "org.bluez.Device1";
+
+/// Every BlueZ object with the properties of each of its interfaces
+type Objects = HashMap<OwnedObjectPath, HashMap<String, Properties>>;
+
+/// A paired Bluetooth device
+#[derive(Debug, Clone, Serialize)]
+pub struct BluetoothDevice {
+    pub name: String,
+    pub address: String,
+    /// BlueZ's icon name, such as "audio-headphones" or "input-keyboard"
+    pub icon: Option<String>,
+    pub connected: bool,
+    #[serde(skip)]
+    path: OwnedObjectPath,
+}
+
+pub struct BlueZ {
+    connection: Connection,
+}
+
+impl BlueZ {
+    pub fn connect() -> Result<Self, String> {
+        let connection = Connection::system().map_err(|e| format!("No system bus: {}", e))?;
+        Ok(Self::with_connection(connection))
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    fn objects(&self) -> Result<Objects, String> {
+        self.connection
+            .call_method(Some(SERVICE), "/", Some("org.freedesktop.DBus.ObjectManager"), "GetManagedObjects", &())
+            .map_err(|e| format!("Bluetooth is not available: {}", e))?
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Unexpected reply from BlueZ: {}", e))
+    }
+
+    /// Paired devices, connected ones first
+    pub fn devices(&self) -> Result<Vec<BluetoothDevice>, String> {
+        let mut devices: Vec<BluetoothDevice> = self
+            .objects()?
+            .into_iter()
+            .filter_map(|(path, interfaces)| {
+                let properties = interfaces.get(DEVICE)?;
+                if !property(properties, "Paired").unwrap_or(false) {
+                    return None;
+                }
+                let address: String = property(properties, "Address").unwrap_or_default();
+                Some(BluetoothDevice {
+                    name: property(properties, "Alias")
+                        .or_else(|| property(properties, "Name"))
+                        .unwrap_or_else(|| address.clone()),
+                    address,
+                    icon: property(properties, "Icon"),
+                    connected: property(properties, "Connected").unwrap_or(false),
+                    path,
+                })
+            })
+            .collect();
+        devices.sort_by(|a, b| b.connected.cmp(&a.connected).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+        Ok(devices)
+    }
+
+    /// Power the adapter on or off, or toggle it when `enable` is None.
+    /// Returns whether it is now on.
+    pub fn set_powered(&self, enable: Option<bool>) -> Result<bool, String> {
+        let objects = self.objects()?;
+        // The first adapter by path, which is hci0 on most machines
+        let (path, properties) = objects
+            .iter()
+            .filter_map(|(path, interfaces)| Some((path, interfaces.get(ADAPTER)?)))
+            .min_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()))
+            .ok_or("No Bluetooth adapter found")?;
+        let current = property(properties, "Powered").unwrap_or(false);
+        let enable = enable.unwrap_or(!current);
+        if enable != current {
+            set_property(&self.connection, SERVICE, path.as_str(), ADAPTER, "Powered", enable)?;
+        }
+        Ok(enable)
+    }
+
+    /// Connect or disconnect the paired device a spoken name refers to.
+    /// Returns the device and whether anything changed.
+    pub fn set_connected(&self, spoken: &str, connect: bool) -> Result<(BluetoothDevice, bool), String> {
+        let spoken = clean_name(spoken, &[" bluetooth", " over bluetooth"]);
+        let spoken = spoken.strip_prefix("bluetooth ").unwrap_or(&spoken);
+        if spoken.is_empty() {
+            return Err("Say which Bluetooth device to use".to_string());
+        }
+        let devices = self.devices()?;
+        if devices.is_empty() {
+            return Err("No Bluetooth devices are paired. Pair it in Bluetooth settings first".to_string());
+        }
+        let device = best_match(&devices, spoken, |device| (vec![device.name.as_str()], device.icon.as_deref()))
+            .ok_or_else(|| {
+                let paired: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+                format!("No paired Bluetooth device called \"{}\". Paired: {}", spoken, paired.join(", "))
+            })?
+            .clone();
+        if device.connected == connect {
+            return Ok((device, false));
+        }
+
+        if connect {
+            self.set_powered(Some(true))?;
+        }
+        let method = if connect { "Connect" } else { "Disconnect" };
+        self.connection
+            .call_method(Some(SERVICE), device.path.as_str(), Some(DEVICE), method, &())
+            .map_err(|e| match e {
+                zbus::Error::MethodError(_, Some(reason), _) => format!("Could not reach {}: {}", device.name, reason),
+                e => format!("Could not reach {}: {}", device.name, e),
+            })?;
+        Ok((BluetoothDevice { connected: connect, ..device }, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type Calls = Arc<Mutex<Vec<String>>>;
+
+    struct MockAdapter {
+        powered: bool,
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.bluez.Adapter1")]
+    impl MockAdapter {
+        #[zbus(property)]
+        fn powered(&self) -> bool {
+            self.powered
+        }
+
+        #[zbus(property)]
+        fn set_powered(&mut self, powered: bool) {
+            self.powered = powered;
+            self.calls.lock().unwrap().push(format!("powered {}", powered));
+        }
+    }
+
+    struct MockDevice {
+        alias: &'static str,
+        icon: &'static str,
+        paired: bool,
+        connected: bool,
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.bluez.Device1")]
+    impl MockDevice {
+        #[zbus(property)]
+        fn alias(&self) -> &str {
+            self.alias
+        }
+
+        #[zbus(property)]
+        fn address(&self) -> &str {
+            "00:11:22:33:44:55"
+        }
+
+        #[zbus(property)]
+        fn icon(&self) -> &str {
+            self.icon
+        }
+
+        #[zbus(property)]
+        fn paired(&self) -> bool {
+            self.paired
+        }
+
+        #[zbus(property)]
+        fn connected(&self) -> bool {
+            self.connected
+        }
+
+        fn connect(&mut self) {
+            self.connected = true;
+            self.calls.lock().unwrap().push(format!("connect {}", self.alias));
+        }
+
+        fn disconnect(&mut self) {
+            self.connected = false;
+            self.calls.lock().unwrap().push(format!("disconnect {}", self.alias));
+        }
+    }
+
+    /// A powered-off adapter with paired headphones and keyboard, and a
+    /// speaker that was seen but never paired
+    fn mock_bluez() -> (Connection, BlueZ, Calls) {
+        let calls = Calls::default();
+        let device = |alias, icon, paired, connected| MockDevice { alias, icon, paired, connected, calls: calls.clone() };
+        let adapter = MockAdapter { powered: false, calls: calls.clone() };
+        let (headphones, keyboard, speaker) = (
+            device("Jo's AirPods Pro", "audio-headphones", true, false),
+            device("MX Keys", "input-keyboard", true, true),
+            device("Kitchen Speaker", "audio-card", false, false),
+        );
+        let (server, client) = super::super::mock_bus(move |bus| {
+            bus.serve_at("/", zbus::fdo::ObjectManager)?
+                .serve_at("/org/bluez/hci0", adapter)?
+                .serve_at("/org/bluez/hci0/dev_1", headphones)?
+                .serve_at("/org/bluez/hci0/dev_2", keyboard)?
+                .serve_at("/org/bluez/hci0/dev_3", speaker)
+        });
+        (server, BlueZ::with_connection(client), calls)
+    }
+
+    #[test]
+    fn lists_paired_devices() {
+        let (_server, bluez, _) = mock_bluez();
+        let devices = bluez.devices().unwrap();
+        let names: Vec<(&str, bool)> = devices.iter().map(|d| (d.name.as_str(), d.connected)).collect();
+        assert_eq!(names, [("MX Keys", true), ("Jo's AirPods Pro", false)]);
+    }
+
+    #[test]
+    fn connects_devices_by_name() {
+        let (_server, bluez, calls) = mock_bluez();
+        let (device, changed) = bluez.set_connected("my airpods", true).unwrap();
+        assert_eq!((device.name.as_str(), device.connected, changed), ("Jo's AirPods Pro", true, true));
+        let (_, changed) = bluez.set_connected("bluetooth keyboard", true).unwrap();
+        assert!(!changed);
+        bluez.set_connected("the keyboard", false).unwrap();
+        assert!(bluez.set_connected("kitchen speaker", true).is_err());
+
+        assert_eq!(*calls.lock().unwrap(), ["powered true", "connect Jo's AirPods Pro", "disconnect MX Keys"]);
+    }
+}
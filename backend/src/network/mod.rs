@@ -0,0 +1,167 @@
+//! Wi-Fi, VPN and Bluetooth connections on Linux over the system bus
+//!
+//! NetworkManager connects saved or visible Wi-Fi networks and VPN profiles
+//! by a spoken name and reports what is connected. BlueZ lists paired
+//! Bluetooth devices and connects or disconnects one ("connect my AirPods").
+//! Both clients take a `zbus` connection, so tests run them against mock
+//! services on a peer-to-peer connection instead of the real system bus.
+
+mod bluez;
+mod networkmanager;
+
+pub use bluez::BlueZ;
+pub use networkmanager::NetworkManager;
+
+use std::collections::HashMap;
+
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedValue;
+
+/// Properties of one D-Bus interface, as returned by GetAll
+type Properties = HashMap<String, OwnedValue>;
+
+fn get_all(connection: &Connection, service: &str, path: &str, interface: &str) -> Result<Properties, String> {
+    connection
+        .call_method(Some(service), path, Some("org.freedesktop.DBus.Properties"), "GetAll", &(interface,))
+        .map_err(|e| format!("{} is not available: {}", service, e))?
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Unexpected reply from {}: {}", service, e))
+}
+
+fn set_property(
+    connection: &Connection,
+    service: &str,
+    path: &str,
+    interface: &str,
+    name: &str,
+    value: impl Into<zbus::zvariant::Value<'static>>,
+) -> Result<(), String> {
+    connection
+        .call_method(
+            Some(service),
+            path,
+            Some("org.freedesktop.DBus.Properties"),
+            "Set",
+            &(interface, name, value.into()),
+        )
+        .map_err(|e| format!("Failed to set {}: {}", name, e))?;
+    Ok(())
+}
+
+/// One property converted to a Rust type, or None when missing or mistyped
+fn property<T: TryFrom<OwnedValue>>(properties: &Properties, name: &str) -> Option<T> {
+    T::try_from(properties.get(name)?.try_clone().ok()?).ok()
+}
+
+/// "AirPods Pro" -> "airpodspro"
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// BlueZ icon names that a kind of device is usually announced with, for
+/// names like "my headphones" that rarely appear in a product name
+fn icon_hints(word: &str) -> &'static [&'static str] {
+    match word {
+        "headphones" | "headphone" | "headset" | "earphones" | "earbuds" => &["audio-headphones", "audio-headset"],
+        "speaker" | "speakers" | "soundbar" => &["audio-card"],
+        "keyboard" => &["input-keyboard"],
+        "mouse" | "trackpad" => &["input-mouse", "input-tablet"],
+        "controller" | "gamepad" | "joystick" => &["input-gaming"],
+        "phone" => &["phone"],
+        _ => &[],
+    }
+}
+
+/// How well `spoken` describes something called `label`: 100 for the exact
+/// name, 90 when it is part of it, 80 when every spoken word is in it, 60
+/// when the icon fits the kind of device that was named
+fn match_score(label: &str, icon: Option<&str>, spoken: &str) -> u32 {
+    let (name, wanted) = (compact(label), compact(spoken));
+    let words: Vec<String> = spoken.split_whitespace().map(compact).filter(|w| !w.is_empty()).collect();
+    if name.is_empty() || wanted.is_empty() {
+        0
+    } else if name == wanted {
+        100
+    } else if name.contains(&wanted) {
+        90
+    } else if words.iter().all(|w| name.contains(w.as_str())) {
+        80
+    } else if icon.is_some_and(|icon| spoken.split_whitespace().flat_map(icon_hints).any(|hint| icon == *hint)) {
+        60
+    } else {
+        0
+    }
+}
+
+/// A spoken name without filler: "my AirPods" -> "airpods", "the office wifi" -> "office"
+fn clean_name(spoken: &str, suffixes: &[&str]) -> String {
+    let spoken = spoken.trim().to_lowercase();
+    let spoken = ["the ", "my ", "our "]
+        .iter()
+        .fold(spoken.as_str(), |rest, filler| rest.strip_prefix(filler).unwrap_or(rest));
+    suffixes
+        .iter()
+        .fold(spoken, |rest, filler| rest.strip_suffix(filler).unwrap_or(rest))
+        .trim()
+        .to_string()
+}
+
+/// The item whose best label matches a spoken name best; earlier items win ties
+fn best_match<'a, T>(
+    items: &'a [T],
+    spoken: &str,
+    labels: impl Fn(&T) -> (Vec<&str>, Option<&str>),
+) -> Option<&'a T> {
+    let mut best: Option<(u32, &T)> = None;
+    for item in items {
+        let (names, icon) = labels(item);
+        let score = names.iter().map(|name| match_score(name, icon, spoken)).max().unwrap_or(0);
+        if score > 0 && best.map_or(true, |(top, _)| score > top) {
+            best = Some((score, item));
+        }
+    }
+    best.map(|(_, item)| item)
+}
+
+/// Serve mock services on one end of a socket pair and return a client
+/// connection to them, standing in for the system bus. The server
+/// connection is returned too and has to be kept alive.
+#[cfg(test)]
+fn mock_bus(
+    serve: impl FnOnce(
+            zbus::blocking::connection::Builder<'static>,
+        ) -> zbus::Result<zbus::blocking::connection::Builder<'static>>
+        + Send
+        + 'static,
+) -> (Connection, Connection) {
+    use zbus::blocking::connection::Builder;
+
+    let (server, client) = std::os::unix::net::UnixStream::pair().unwrap();
+    let server = std::thread::spawn(move || {
+        let builder = Builder::unix_stream(server).server(zbus::Guid::generate()).unwrap().p2p();
+        serve(builder).unwrap().build().unwrap()
+    });
+    let client = Builder::unix_stream(client).p2p().build().unwrap();
+    (server.join().unwrap(), client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_spoken_names() {
+        let names = [("Living Room Speaker", "audio-card"), ("Jo's AirPods Pro", "audio-headphones"), ("MX Keys", "input-keyboard")];
+        let find = |spoken: &str| best_match(&names, spoken, |(name, icon)| (vec![*name], Some(*icon))).map(|(name, _)| *name);
+
+        assert_eq!(find("airpods"), Some("Jo's AirPods Pro"));
+        assert_eq!(find("airpods pro"), Some("Jo's AirPods Pro"));
+        assert_eq!(find("headphones"), Some("Jo's AirPods Pro"));
+        assert_eq!(find("keyboard"), Some("MX Keys"));
+        assert_eq!(find("living room"), Some("Living Room Speaker"));
+        assert_eq!(find("car"), None);
+        assert_eq!(clean_name("my AirPods", &[]), "airpods");
+        assert_eq!(clean_name("the office wifi", &[" wifi", " network"]), "office");
+    }
+}
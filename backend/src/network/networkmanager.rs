@@ -0,0 +1,513 @@
+//! NetworkManager client: Wi-Fi, VPN profiles and the current connection
+
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use zbus::blocking::Connection;
+use zbus::zvariant::{DynamicType, ObjectPath, OwnedObjectPath, OwnedValue, Type};
+
+use super::{best_match, clean_name, get_all, match_score, property, set_property};
+
+const SERVICE: &str = "org.freedesktop.NetworkManager";
+const PATH: &str = "/org/freedesktop/NetworkManager";
+const INTERFACE: &str = "org.freedesktop.NetworkManager";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+const DEVICE_TYPE_WIFI: u32 = 2;
+const STATE_ACTIVATING: u32 = 1;
+
+/// A connection NetworkManager has brought up or is bringing up
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveConnection {
+    pub name: String,
+    /// "wifi", "ethernet", "vpn", "mobile", or NetworkManager's own type name
+    pub kind: String,
+    /// Carries the default route
+    pub primary: bool,
+    pub activating: bool,
+    #[serde(skip)]
+    path: OwnedObjectPath,
+}
+
+/// A saved connection profile
+struct Profile {
+    path: OwnedObjectPath,
+    name: String,
+    kind: String,
+    ssid: Option<String>,
+}
+
+/// A Wi-Fi network in range of one of the Wi-Fi devices
+struct AccessPoint {
+    path: OwnedObjectPath,
+    device: OwnedObjectPath,
+    ssid: String,
+    strength: u8,
+}
+
+fn kind_name(connection_type: &str) -> String {
+    match connection_type {
+        "802-11-wireless" => "wifi",
+        "802-3-ethernet" => "ethernet",
+        "vpn" | "wireguard" => "vpn",
+        "gsm" | "cdma" => "mobile",
+        other => other,
+    }
+    .to_string()
+}
+
+fn ssid_text(bytes: Vec<u8>) -> String {
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    names.map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+pub struct NetworkManager {
+    connection: Connection,
+}
+
+impl NetworkManager {
+    pub fn connect() -> Result<Self, String> {
+        let connection = Connection::system().map_err(|e| format!("No system bus: {}", e))?;
+        Ok(Self::with_connection(connection))
+    }
+
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+
+    fn call<R>(&self, path: &str, interface: &str, method: &str, body: &(impl Serialize + DynamicType)) -> Result<R, String>
+    where
+        R: DeserializeOwned + Type,
+    {
+        self.connection
+            .call_method(Some(SERVICE), path, Some(interface), method, body)
+            .map_err(|e| format!("NetworkManager: {}", e))?
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Unexpected reply from NetworkManager: {}", e))
+    }
+
+    /// Active connections, the one carrying the default route first
+    pub fn status(&self) -> Result<Vec<ActiveConnection>, String> {
+        let manager = get_all(&self.connection, SERVICE, PATH, INTERFACE)?;
+        let primary: Option<OwnedObjectPath> = property(&manager, "PrimaryConnection");
+        let paths: Vec<OwnedObjectPath> = property(&manager, "ActiveConnections").unwrap_or_default();
+
+        let mut active = Vec::new();
+        for path in paths {
+            // A connection can go away between listing and reading it
+            let Ok(properties) = get_all(&self.connection, SERVICE, path.as_str(), "org.freedesktop.NetworkManager.Connection.Active")
+            else {
+                continue;
+            };
+            let connection_type: String = property(&properties, "Type").unwrap_or_default();
+            if matches!(connection_type.as_str(), "loopback" | "bridge") {
+                continue;
+            }
+            active.push(ActiveConnection {
+                name: property(&properties, "Id").unwrap_or_default(),
+                kind: kind_name(&connection_type),
+                primary: primary.as_ref() == Some(&path),
+                activating: property(&properties, "State") == Some(STATE_ACTIVATING),
+                path,
+            });
+        }
+        active.sort_by_key(|connection| !connection.primary);
+        Ok(active)
+    }
+
+    /// Turn the Wi-Fi radio on or off, or toggle it when `enable` is None.
+    /// Returns whether it is now on.
+    pub fn set_wifi_enabled(&self, enable: Option<bool>) -> Result<bool, String> {
+        let manager = get_all(&self.connection, SERVICE, PATH, INTERFACE)?;
+        let current = property(&manager, "WirelessEnabled").unwrap_or(false);
+        let enable = enable.unwrap_or(!current);
+        if enable != current {
+            set_property(&self.connection, SERVICE, PATH, INTERFACE, "WirelessEnabled", enable)?;
+        }
+        Ok(enable)
+    }
+
+    /// Connect to a Wi-Fi network by name. A saved profile is activated as
+    /// is; a network that was never joined needs its exact name and gets a
+    /// new profile, with NetworkManager asking the desktop for the password
+    /// when it needs one. Returns the network's name.
+    pub fn connect_wifi(&self, spoken: &str) -> Result<String, String> {
+        let spoken = clean_name(spoken, &[" wifi", " wi-fi", " network", " wireless"]);
+        if spoken.is_empty() {
+            return Err("Say which Wi-Fi network to connect to".to_string());
+        }
+
+        let profiles: Vec<Profile> = self.profiles()?.into_iter().filter(|p| p.kind == "wifi").collect();
+        let saved = best_match(&profiles, &spoken, |profile| {
+            (std::iter::once(profile.name.as_str()).chain(profile.ssid.as_deref()).collect(), None)
+        });
+        if let Some(profile) = saved {
+            self.set_wifi_enabled(Some(true))?;
+            self.activate(&profile.path)?;
+            return Ok(profile.name.clone());
+        }
+
+        // Joining a stranger's network on a loose match is worse than asking
+        let points = self.access_points()?;
+        let Some(point) = points.iter().find(|point| match_score(&point.ssid, None, &spoken) == 100) else {
+            let mut close: Vec<&String> = Vec::new();
+            for point in &points {
+                if match_score(&point.ssid, None, &spoken) > 0 && !close.contains(&&point.ssid) {
+                    close.push(&point.ssid);
+                }
+            }
+            return Err(if close.is_empty() {
+                format!("No Wi-Fi network called \"{}\" is saved or in range", spoken)
+            } else {
+                format!("Which network? In range: {}", names(close.into_iter()))
+            });
+        };
+        self.set_wifi_enabled(Some(true))?;
+        let settings: HashMap<String, HashMap<String, OwnedValue>> = HashMap::new();
+        let _: (OwnedObjectPath, OwnedObjectPath) = self.call(
+            PATH,
+            INTERFACE,
+            "AddAndActivateConnection",
+            &(settings, &*point.device, &*point.path),
+        )?;
+        Ok(point.ssid.clone())
+    }
+
+    /// Bring up a VPN profile by name, or the only one when no name is given.
+    /// Returns the profile's name.
+    pub fn connect_vpn(&self, spoken: Option<&str>) -> Result<String, String> {
+        let vpns: Vec<Profile> = self.profiles()?.into_iter().filter(|p| p.kind == "vpn").collect();
+        let spoken = spoken.map(|spoken| clean_name(spoken, &[" vpn"])).filter(|spoken| !spoken.is_empty());
+        let profile = match (spoken, vpns.as_slice()) {
+            (_, []) => return Err("No VPN connections are set up in NetworkManager".to_string()),
+            (Some(spoken), _) => best_match(&vpns, &spoken, |profile| (vec![profile.name.as_str()], None))
+                .ok_or_else(|| {
+                    format!("No VPN called \"{}\". Saved VPNs: {}", spoken, names(vpns.iter().map(|p| &p.name)))
+                })?,
+            (None, [only]) => only,
+            (None, _) => return Err(format!("Which VPN? Saved VPNs: {}", names(vpns.iter().map(|p| &p.name)))),
+        };
+        self.activate(&profile.path)?;
+        Ok(profile.name.clone())
+    }
+
+    /// Take down the named VPN, or every connected one when no name is given.
+    /// Returns the names of the VPNs that were disconnected.
+    pub fn disconnect_vpn(&self, spoken: Option<&str>) -> Result<Vec<String>, String> {
+        let vpns: Vec<ActiveConnection> = self.status()?.into_iter().filter(|c| c.kind == "vpn").collect();
+        if vpns.is_empty() {
+            return Err("No VPN is connected".to_string());
+        }
+        let targets: Vec<&ActiveConnection> = match spoken.map(|spoken| clean_name(spoken, &[" vpn"])) {
+            Some(spoken) if !spoken.is_empty() => {
+                vec![best_match(&vpns, &spoken, |vpn| (vec![vpn.name.as_str()], None))
+                    .ok_or_else(|| format!("No connected VPN called \"{}\"", spoken))?]
+            }
+            _ => vpns.iter().collect(),
+        };
+        for vpn in &targets {
+            let _: () = self.call(PATH, INTERFACE, "DeactivateConnection", &(&*vpn.path,))?;
+        }
+        Ok(targets.into_iter().map(|vpn| vpn.name.clone()).collect())
+    }
+
+    fn activate(&self, profile: &ObjectPath) -> Result<(), String> {
+        // "/" lets NetworkManager pick the device and access point
+        let any = ObjectPath::from_static_str_unchecked("/");
+        let _: OwnedObjectPath = self.call(PATH, INTERFACE, "ActivateConnection", &(profile, &any, &any))?;
+        Ok(())
+    }
+
+    fn profiles(&self) -> Result<Vec<Profile>, String> {
+        let paths: Vec<OwnedObjectPath> =
+            self.call(SETTINGS_PATH, "org.freedesktop.NetworkManager.Settings", "ListConnections", &())?;
+        let mut profiles = Vec::new();
+        for path in paths {
+            let settings: HashMap<String, HashMap<String, OwnedValue>> =
+                match self.call(path.as_str(), "org.freedesktop.NetworkManager.Settings.Connection", "GetSettings", &()) {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        log::warn!("Skipping connection profile {}: {}", path.as_str(), e);
+                        continue;
+                    }
+                };
+            let Some(connection) = settings.get("connection") else { continue };
+            profiles.push(Profile {
+                name: property(connection, "id").unwrap_or_default(),
+                kind: kind_name(&property::<String>(connection, "type").unwrap_or_default()),
+                ssid: settings.get("802-11-wireless").and_then(|wifi| property(wifi, "ssid")).map(ssid_text),
+                path,
+            });
+        }
+        Ok(profiles)
+    }
+
+    /// Networks in range, strongest first so duplicates of one name go to the best signal
+    fn access_points(&self) -> Result<Vec<AccessPoint>, String> {
+        let devices: Vec<OwnedObjectPath> = self.call(PATH, INTERFACE, "GetDevices", &())?;
+        let mut points = Vec::new();
+        for device in devices {
+            let Ok(properties) = get_all(&self.connection, SERVICE, device.as_str(), "org.freedesktop.NetworkManager.Device") else {
+                continue;
+            };
+            if property(&properties, "DeviceType") != Some(DEVICE_TYPE_WIFI) {
+                continue;
+            }
+            let paths: Vec<OwnedObjectPath> = self
+                .call(device.as_str(), "org.freedesktop.NetworkManager.Device.Wireless", "GetAllAccessPoints", &())
+                .unwrap_or_default();
+            for path in paths {
+                let Ok(ap) = get_all(&self.connection, SERVICE, path.as_str(), "org.freedesktop.NetworkManager.AccessPoint") else {
+                    continue;
+                };
+                let ssid = property(&ap, "Ssid").map(ssid_text).unwrap_or_default();
+                // Hidden networks can only be joined from a saved profile
+                if ssid.is_empty() {
+                    continue;
+                }
+                points.push(AccessPoint {
+                    strength: property(&ap, "Strength").unwrap_or(0),
+                    device: device.clone(),
+                    ssid,
+                    path,
+                });
+            }
+        }
+        points.sort_by(|a, b| b.strength.cmp(&a.strength));
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type Calls = Arc<Mutex<Vec<String>>>;
+
+    fn path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!("{}/{}", PATH, path)).unwrap()
+    }
+
+    struct MockManager {
+        wireless_enabled: bool,
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager")]
+    impl MockManager {
+        #[zbus(property)]
+        fn wireless_enabled(&self) -> bool {
+            self.wireless_enabled
+        }
+
+        #[zbus(property)]
+        fn set_wireless_enabled(&mut self, enabled: bool) {
+            self.wireless_enabled = enabled;
+            self.calls.lock().unwrap().push(format!("wifi {}", enabled));
+        }
+
+        #[zbus(property)]
+        fn primary_connection(&self) -> OwnedObjectPath {
+            path("ActiveConnection/1")
+        }
+
+        #[zbus(property)]
+        fn active_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![path("ActiveConnection/0"), path("ActiveConnection/1"), path("ActiveConnection/2")]
+        }
+
+        fn get_devices(&self) -> Vec<OwnedObjectPath> {
+            vec![path("Devices/1"), path("Devices/2")]
+        }
+
+        fn activate_connection(
+            &self,
+            connection: OwnedObjectPath,
+            _device: OwnedObjectPath,
+            _specific_object: OwnedObjectPath,
+        ) -> OwnedObjectPath {
+            self.calls.lock().unwrap().push(format!("activate {}", connection.as_str()));
+            path("ActiveConnection/9")
+        }
+
+        fn add_and_activate_connection(
+            &self,
+            _settings: HashMap<String, HashMap<String, OwnedValue>>,
+            device: OwnedObjectPath,
+            specific_object: OwnedObjectPath,
+        ) -> (OwnedObjectPath, OwnedObjectPath) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("add {} {}", device.as_str(), specific_object.as_str()));
+            (path("Settings/9"), path("ActiveConnection/9"))
+        }
+
+        fn deactivate_connection(&self, active: OwnedObjectPath) {
+            self.calls.lock().unwrap().push(format!("deactivate {}", active.as_str()));
+        }
+    }
+
+    struct MockSettings;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Settings")]
+    impl MockSettings {
+        fn list_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![path("Settings/1"), path("Settings/2"), path("Settings/3")]
+        }
+    }
+
+    struct MockProfile {
+        id: &'static str,
+        kind: &'static str,
+        ssid: Option<&'static str>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+    impl MockProfile {
+        fn get_settings(&self) -> HashMap<String, HashMap<String, OwnedValue>> {
+            let mut settings = HashMap::new();
+            let connection = HashMap::from([
+                ("id".to_string(), OwnedValue::try_from(zbus::zvariant::Value::from(self.id)).unwrap()),
+                ("type".to_string(), OwnedValue::try_from(zbus::zvariant::Value::from(self.kind)).unwrap()),
+            ]);
+            settings.insert("connection".to_string(), connection);
+            if let Some(ssid) = self.ssid {
+                let ssid = OwnedValue::try_from(zbus::zvariant::Value::from(ssid.as_bytes().to_vec())).unwrap();
+                settings.insert("802-11-wireless".to_string(), HashMap::from([("ssid".to_string(), ssid)]));
+            }
+            settings
+        }
+    }
+
+    struct MockActive {
+        id: &'static str,
+        kind: &'static str,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+    impl MockActive {
+        #[zbus(property)]
+        fn id(&self) -> &str {
+            self.id
+        }
+
+        #[zbus(property, name = "Type")]
+        fn kind(&self) -> &str {
+            self.kind
+        }
+
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            2
+        }
+    }
+
+    struct MockDevice {
+        device_type: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Device")]
+    impl MockDevice {
+        #[zbus(property)]
+        fn device_type(&self) -> u32 {
+            self.device_type
+        }
+    }
+
+    struct MockWireless;
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+    impl MockWireless {
+        fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+            vec![path("AccessPoint/1"), path("AccessPoint/2"), path("AccessPoint/3")]
+        }
+    }
+
+    struct MockAccessPoint {
+        ssid: &'static str,
+        strength: u8,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl MockAccessPoint {
+        #[zbus(property)]
+        fn ssid(&self) -> Vec<u8> {
+            self.ssid.as_bytes().to_vec()
+        }
+
+        #[zbus(property)]
+        fn strength(&self) -> u8 {
+            self.strength
+        }
+    }
+
+    /// A laptop on its home Wi-Fi with a work VPN up, and a café in range
+    fn mock_network_manager() -> (Connection, NetworkManager, Calls) {
+        let calls = Calls::default();
+        let manager = MockManager { wireless_enabled: false, calls: calls.clone() };
+        let (server, client) = super::super::mock_bus(move |bus| {
+            bus.serve_at(PATH, manager)?
+                .serve_at(SETTINGS_PATH, MockSettings)?
+                .serve_at(path("Settings/1"), MockProfile { id: "Home", kind: "802-11-wireless", ssid: Some("HOME-5G") })?
+                .serve_at(path("Settings/2"), MockProfile { id: "Work VPN", kind: "vpn", ssid: None })?
+                .serve_at(path("Settings/3"), MockProfile { id: "Wired", kind: "802-3-ethernet", ssid: None })?
+                .serve_at(path("ActiveConnection/0"), MockActive { id: "lo", kind: "loopback" })?
+                .serve_at(path("ActiveConnection/1"), MockActive { id: "Home", kind: "802-11-wireless" })?
+                .serve_at(path("ActiveConnection/2"), MockActive { id: "Work VPN", kind: "vpn" })?
+                .serve_at(path("Devices/1"), MockDevice { device_type: DEVICE_TYPE_WIFI })?
+                .serve_at(path("Devices/1"), MockWireless)?
+                .serve_at(path("Devices/2"), MockDevice { device_type: 1 })?
+                .serve_at(path("AccessPoint/1"), MockAccessPoint { ssid: "Cafe Guest", strength: 40 })?
+                .serve_at(path("AccessPoint/2"), MockAccessPoint { ssid: "Cafe Guest", strength: 75 })?
+                .serve_at(path("AccessPoint/3"), MockAccessPoint { ssid: "", strength: 90 })
+        });
+        (server, NetworkManager::with_connection(client), calls)
+    }
+
+    #[test]
+    fn reports_connections() {
+        let (_server, network, _) = mock_network_manager();
+        let status = network.status().unwrap();
+        let names: Vec<(&str, &str, bool)> =
+            status.iter().map(|c| (c.name.as_str(), c.kind.as_str(), c.primary)).collect();
+        assert_eq!(names, [("Home", "wifi", true), ("Work VPN", "vpn", false)]);
+    }
+
+    #[test]
+    fn connects_wifi_by_name() {
+        let (_server, network, calls) = mock_network_manager();
+        assert_eq!(network.connect_wifi("the home network").unwrap(), "Home");
+        assert_eq!(network.connect_wifi("cafe guest wifi").unwrap(), "Cafe Guest");
+        assert!(network.connect_wifi("airport").is_err());
+        // Networks that were never joined need their full name
+        assert_eq!(network.connect_wifi("cafe").unwrap_err(), "Which network? In range: Cafe Guest");
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls[0], "wifi true");
+        assert_eq!(calls[1], format!("activate {}", path("Settings/1").as_str()));
+        assert_eq!(calls[2], format!("add {} {}", path("Devices/1").as_str(), path("AccessPoint/2").as_str()));
+    }
+
+    #[test]
+    fn connects_and_disconnects_vpn() {
+        let (_server, network, calls) = mock_network_manager();
+        assert_eq!(network.connect_vpn(None).unwrap(), "Work VPN");
+        assert!(network.connect_vpn(Some("office")).is_err());
+        assert_eq!(network.disconnect_vpn(Some("work vpn")).unwrap(), ["Work VPN"]);
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(
+            *calls,
+            [
+                format!("activate {}", path("Settings/2").as_str()),
+                format!("deactivate {}", path("ActiveConnection/2").as_str()),
+            ]
+        );
+    }
+}
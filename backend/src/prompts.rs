@@ -0,0 +1,661 @@
+//! Prompt templates for every LLM call
+//!
+//! Each prompt is a named template with declared variables. Built-in
+//! defaults can be overridden by dropping `<name>.txt` into
+//! `<data dir>/ListenOS/prompts/`. Overrides are validated on every render
+//! and ignored (with a warning) if they drop a required variable or use one
+//! the template doesn't know about.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::cloud::{ActionCatalog, ConversationContext, DictationStyle, VoiceContext};
+use crate::config::AliasConfig;
+
+/// What kind of value a template variable carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VarKind {
+    /// Free text (transcript, language name, clipboard content, ...)
+    Text,
+    /// Active app, clipboard preview, last action and OS
+    Context,
+    /// Facts remembered about the user
+    Facts,
+    /// Text expansion snippets
+    Snippets,
+    /// App, website and search-engine aliases
+    Aliases,
+    /// Custom commands and integration actions
+    CustomCommands,
+}
+
+/// A variable a template may reference as `{{name}}`
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateVar {
+    pub name: &'static str,
+    pub kind: VarKind,
+    pub required: bool,
+}
+
+const fn var(name: &'static str, kind: VarKind, required: bool) -> TemplateVar {
+    TemplateVar { name, kind, required }
+}
+
+/// Built-in prompt template
+pub struct PromptTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub variables: &'static [TemplateVar],
+    pub default_body: &'static str,
+}
+
+/// Template info for the settings UI
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplateInfo {
+    pub name: String,
+    pub description: String,
+    pub variables: Vec<TemplateVar>,
+    pub overridden: bool,
+    pub override_path: Option<String>,
+    /// Why the override on disk is being ignored, if it is
+    pub override_error: Option<String>,
+}
+
+pub const INTENT_ROUTER: &str = "intent_router";
+pub const DICTATION_STYLE_FORMAL: &str = "dictation_style_formal";
+pub const DICTATION_STYLE_CASUAL: &str = "dictation_style_casual";
+pub const DICTATION_STYLE_VERY_CASUAL: &str = "dictation_style_very_casual";
+pub const MULTILINGUAL_NORMALIZE: &str = "multilingual_normalize";
+pub const VIBE_ENHANCE: &str = "vibe_enhance";
+pub const QUESTION_ANSWER: &str = "question_answer";
+pub const CLIPBOARD_FORMAT: &str = "clipboard_format";
+pub const CLIPBOARD_TRANSLATE: &str = "clipboard_translate";
+pub const CLIPBOARD_SUMMARIZE: &str = "clipboard_summarize";
+pub const CLIPBOARD_CLEAN: &str = "clipboard_clean";
+
+static TEMPLATES: &[PromptTemplate] = &[
+    PromptTemplate {
+        name: INTENT_ROUTER,
+        description: "System prompt that decides between commands and dictation",
+        variables: &[
+            var("context", VarKind::Context, true),
+            var("facts", VarKind::Facts, false),
+            var("catalog", VarKind::CustomCommands, true),
+            var("snippets", VarKind::Snippets, false),
+            var("aliases", VarKind::Aliases, false),
+            var("style_rules", VarKind::Text, false),
+        ],
+        default_body: r##"You are ListenOS, a voice-to-action assistant. Analyze user voice input and decide: COMMAND or DICTATION.
+
+=== COMMAND DETECTION ===
+
+Treat as COMMAND if the input:
+1. STARTS with a command verb: open, launch, start, search, google, play, pause, stop, next, previous, skip, mute, unmute, volume, lock, screenshot, shutdown, restart, reboot, sleep, brightness, bluetooth, wifi, close, quit, exit
+2. Is a SHORT phrase (1-4 words) that matches a command pattern
+3. Contains "my computer", "the computer", "my PC" with a system action
+
+Examples of COMMANDS (execute these):
+- "Open Chrome" → open_app
+- "Search for pizza" → web_search  
+- "Play music" → media_control
+- "Lock my computer" → system_control
+- "Shutdown" → system_control
+- "Volume up" → volume_control
+- "Take a screenshot" → system_control
+- "Organize my downloads" → system_control
+
+=== DICTATION ===
+
+Treat as DICTATION (type_text) if:
+1. It's a complete sentence the user wants typed
+2. Command words appear MID-SENTENCE (not at start)
+3. It's conversational or descriptive text
+
+Examples of DICTATION (type these):
+- "I want to open a new chapter" → type_text (open is mid-sentence)
+- "The meeting was great" → type_text
+- "Please search for the document" → type_text (starts with please)
+- "Can you help me" → type_text
+
+=== CURRENT CONTEXT ===
+{{context}}
+{{facts}}{{catalog}}{{snippets}}{{aliases}}=== AVAILABLE COMMANDS (only when explicitly triggered) ===
+
+APPS & BROWSER:
+- open_app: Trigger words: "open", "launch", "start" + app name
+  Example: "Open Chrome" -> {"action": "open_app", "payload": {"app": "chrome"}}
+  
+- open_url: Trigger words: "open", "go to" + URL/website
+  Example: "Open google.com" -> {"action": "open_url", "payload": {"url": "https://google.com"}}
+  
+- web_search: Trigger words: "search", "google", "look up", "find"
+  Example: "Search for weather" -> {"action": "web_search", "payload": {"query": "weather"}}
+  Add "engine" with a search keyword when the user names one: "Search GitHub for tokio" -> {"action": "web_search", "payload": {"query": "tokio", "engine": "github"}}
+
+VOLUME:
+- volume_control: Trigger words: "volume", "louder", "quieter", "mute", "turn up/down"
+  Example: "Volume up" -> {"action": "volume_control", "payload": {"direction": "up"}}
+  Example: "Volume 35 percent" -> {"action": "volume_control", "payload": {"level": 35}}
+  Example: "Turn Spotify down" -> {"action": "volume_control", "payload": {"app": "spotify", "direction": "down"}}
+  Example: "What's the volume" -> {"action": "volume_control", "payload": {"action": "get"}}
+  Example: "Switch to headphones" -> {"action": "volume_control", "payload": {"action": "set_output", "device": "headphones"}}
+  Use "set_input" for microphones, "list_devices" to list outputs and inputs.
+
+CLIPBOARD (only when "clipboard" is mentioned):
+- clipboard_format: "format my clipboard", "clipboard as bullets"
+- clipboard_translate: "translate my clipboard to Spanish"
+- clipboard_summarize: "summarize my clipboard"
+
+DICTATION (DEFAULT - use for everything else):
+- type_text: Used when the user is dictating text to be typed
+  The refined_text field contains the EXACT text to type
+  Example: "Hello how are you" -> {"action": "type_text", "refined_text": "Hello, how are you?"}
+  Example: "I need to finish the report by Friday" -> {"action": "type_text", "refined_text": "I need to finish the report by Friday."}
+
+=== RESPONSE FORMAT (JSON only) ===
+
+{
+  "action": "action_type",
+  "payload": {},
+  "refined_text": "only for type_text - the exact text to type with proper punctuation",
+  "confidence": 0.0-1.0 (how sure you are this is what the user meant),
+  "alternative": {"action": "...", "payload": {}, "refined_text": "...", "confidence": 0.0-1.0} (the next most likely interpretation, or null)
+}
+
+Use a LOW confidence (below 0.6) when the request could reasonably be either a command or dictation, or could mean two different commands.
+
+=== EXAMPLES - COMMANDS ===
+
+"Open Chrome" -> {"action": "open_app", "payload": {"app": "chrome"}}
+"Search for Italian restaurants" -> {"action": "web_search", "payload": {"query": "Italian restaurants"}}
+"Lock computer" -> {"action": "system_control", "payload": {"action": "lock"}}
+"Set brightness to 40%" -> {"action": "system_control", "payload": {"action": "brightness", "level": 40}}
+"Turn on dark mode" -> {"action": "system_control", "payload": {"action": "dark_mode", "enable": true}}
+"Turn off night light" -> {"action": "system_control", "payload": {"action": "night_light", "enable": false}}
+"Connect to the office wifi" -> {"action": "system_control", "payload": {"action": "wifi_connect", "name": "office"}}
+"Connect to the work VPN" -> {"action": "system_control", "payload": {"action": "vpn_connect", "name": "work"}}
+"Connect my AirPods" -> {"action": "system_control", "payload": {"action": "bluetooth_connect", "device": "airpods"}}
+"Restore report.pdf from the trash" -> {"action": "system_control", "payload": {"action": "trash_restore", "name": "report.pdf"}}
+"Volume down" -> {"action": "volume_control", "payload": {"direction": "down"}}
+"Undo the last action" -> {"action": "system_control", "payload": {"action": "undo_last_action"}}
+"Repeat the last command" -> {"action": "system_control", "payload": {"action": "repeat_command", "n": 1}}
+
+=== MULTI-STEP REQUESTS ===
+
+When the user asks for several actions in one sentence, use multi_step. A step can use an earlier step's result with {{id.output.field}} (or {{prev.output.field}} for the step right before). Add "on_failure": "stop" when later steps make no sense if an earlier one fails.
+
+"Take a screenshot and open it" -> {"action": "multi_step", "payload": {"on_failure": "stop", "steps": [{"id": "shot", "action": "system_control", "payload": {"action": "screenshot"}}, {"action": "system_control", "payload": {"action": "open_file", "path": "{{shot.output.path}}"}}]}}
+"Count my downloads then type the number" -> {"action": "multi_step", "payload": {"on_failure": "stop", "steps": [{"id": "count", "action": "system_control", "payload": {"action": "downloads_count"}}, {"action": "type_text", "refined_text": "{{count.output.top_level_total}}"}]}}
+
+=== EXAMPLES - DICTATION (type_text) ===
+
+"Hello world" -> {"action": "type_text", "refined_text": "Hello world."}
+"The meeting is scheduled for 3 PM" -> {"action": "type_text", "refined_text": "The meeting is scheduled for 3 PM."}
+"Dear John comma I hope this email finds you well" -> {"action": "type_text", "refined_text": "Dear John, I hope this email finds you well."}
+"Please review the attached document and let me know your thoughts" -> {"action": "type_text", "refined_text": "Please review the attached document and let me know your thoughts."}
+"I think we should open the discussion with" -> {"action": "type_text", "refined_text": "I think we should open the discussion with"}
+"Can you help me with this" -> {"action": "type_text", "refined_text": "Can you help me with this?"}
+{{style_rules}}"##,
+    },
+    PromptTemplate {
+        name: DICTATION_STYLE_FORMAL,
+        description: "Punctuation rules appended to the intent prompt for formal dictation",
+        variables: &[],
+        default_body: r##"
+=== PUNCTUATION RULES FOR type_text (FORMAL STYLE) ===
+
+1. Add periods at the end of complete sentences
+2. Add question marks for questions
+3. Convert spoken punctuation: "comma" -> ",", "period" -> ".", "question mark" -> "?"
+4. Capitalize first letter of sentences and proper nouns
+5. Keep the user's words exactly, just add proper formatting
+6. Use full punctuation including commas in complex sentences
+"##,
+    },
+    PromptTemplate {
+        name: DICTATION_STYLE_CASUAL,
+        description: "Punctuation rules appended to the intent prompt for casual dictation",
+        variables: &[],
+        default_body: r##"
+=== PUNCTUATION RULES FOR type_text (CASUAL STYLE) ===
+
+1. Capitalize first letter of sentences
+2. Add question marks for questions
+3. Convert spoken punctuation: "comma" -> ",", "period" -> ".", "question mark" -> "?"
+4. Use MINIMAL punctuation - skip periods at end of simple sentences
+5. Skip commas unless explicitly spoken
+6. Keep the casual, natural flow of speech
+
+Examples with CASUAL style:
+- "Hey how are you" -> "Hey how are you"
+- "Let's meet at noon" -> "Let's meet at noon"
+- "Sounds good see you then" -> "Sounds good see you then"
+"##,
+    },
+    PromptTemplate {
+        name: DICTATION_STYLE_VERY_CASUAL,
+        description: "Punctuation rules appended to the intent prompt for very casual dictation",
+        variables: &[],
+        default_body: r##"
+=== PUNCTUATION RULES FOR type_text (VERY CASUAL STYLE) ===
+
+1. Use ALL LOWERCASE (no capital letters except proper nouns like names)
+2. Use MINIMAL punctuation - skip periods completely
+3. Only add question marks for questions
+4. Skip commas unless explicitly spoken
+5. Keep it natural like texting a friend
+
+Examples with VERY CASUAL style:
+- "Hey how are you" -> "hey how are you"
+- "Let's meet at noon" -> "let's meet at noon"
+- "Sounds good see you then" -> "sounds good see you then"
+- "Thanks for your help" -> "thanks for your help"
+"##,
+    },
+    PromptTemplate {
+        name: MULTILINGUAL_NORMALIZE,
+        description: "System prompt for translating and normalizing multilingual transcripts",
+        variables: &[],
+        default_body: "You normalize multilingual voice transcriptions for an OS assistant. Return STRICT JSON with keys routing_text_english, output_text_target, detected_language. routing_text_english must be concise English preserving intent for command parsing. output_text_target must be polished in the requested target language with corrected grammar/punctuation and no extra commentary. If target language is English, output_text_target MUST be English. If source language is auto, detect language from the transcript; handle romanized speech (e.g., Hinglish written in Latin script) and still translate correctly.",
+    },
+    PromptTemplate {
+        name: VIBE_ENHANCE,
+        description: "System prompt for turning spoken coding ideas into AI coding prompts",
+        variables: &[],
+        default_body: "You rewrite rough spoken coding ideas into high-quality prompts for AI coding assistants. Preserve user intent, avoid hype, and never invent missing requirements. Keep output actionable, concrete, and scoped. Return STRICT JSON: {\"enhanced_prompt\":\"...\"}. No markdown fences. No extra keys.",
+    },
+    PromptTemplate {
+        name: QUESTION_ANSWER,
+        description: "System prompt for answering spoken questions",
+        variables: &[
+            var("history", VarKind::Text, false),
+            var("facts", VarKind::Facts, false),
+        ],
+        default_body: "You are ListenOS voice assistant. Reply naturally in 1-3 concise sentences unless the user asks for more detail.\n\n{{facts}}{{history}}",
+    },
+    PromptTemplate {
+        name: CLIPBOARD_FORMAT,
+        description: "Reformat clipboard text",
+        variables: &[
+            var("format", VarKind::Text, true),
+            var("content", VarKind::Text, true),
+        ],
+        default_body: "Format the following text as a {{format}}. Only output the formatted text, nothing else:\n\n{{content}}",
+    },
+    PromptTemplate {
+        name: CLIPBOARD_TRANSLATE,
+        description: "Translate clipboard text",
+        variables: &[
+            var("target_language", VarKind::Text, true),
+            var("content", VarKind::Text, true),
+        ],
+        default_body: "Translate the following text to {{target_language}}. Only output the translation, nothing else:\n\n{{content}}",
+    },
+    PromptTemplate {
+        name: CLIPBOARD_SUMMARIZE,
+        description: "Summarize clipboard text",
+        variables: &[var("content", VarKind::Text, true)],
+        default_body: "Summarize the following text in 2-3 sentences. Only output the summary, nothing else:\n\n{{content}}",
+    },
+    PromptTemplate {
+        name: CLIPBOARD_CLEAN,
+        description: "Clean up clipboard text",
+        variables: &[var("content", VarKind::Text, true)],
+        default_body: "Clean up the following text: fix grammar, remove extra whitespace, add proper punctuation. Only output the cleaned text, nothing else:\n\n{{content}}",
+    },
+];
+
+/// Values for a render, filled through typed setters
+#[derive(Debug, Clone, Default)]
+pub struct PromptVars {
+    values: BTreeMap<&'static str, String>,
+}
+
+impl PromptVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a free-text variable. Variables declared with another kind are
+    /// only filled by their own setters, so the value is dropped and the
+    /// render fails or leaves the section empty.
+    pub fn text(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        match declared_kind(name) {
+            Some(kind) if kind != VarKind::Text => {
+                log::warn!("Prompt variable '{}' is {:?}, not text; ignoring the value", name, kind);
+            }
+            _ => {
+                self.values.insert(name, value.into());
+            }
+        }
+        self
+    }
+
+    /// Active app, clipboard preview, last action and OS
+    pub fn context(mut self, voice_context: &VoiceContext, conv_context: &ConversationContext) -> Self {
+        let mut out = String::new();
+        if let Some(ref app) = voice_context.active_app {
+            out.push_str(&format!("Active app: {}\n", app));
+        }
+        if let Some(ref clipboard) = conv_context.clipboard_preview {
+            out.push_str(&format!("Clipboard preview: \"{}\"\n", clipboard));
+        }
+        if let Some(ref last_action) = conv_context.last_action {
+            out.push_str(&format!("Last action: {}\n", last_action));
+        }
+        out.push_str(&format!("OS: {}\n", voice_context.os));
+        self.values.insert("context", out);
+        self
+    }
+
+    pub fn facts(mut self, facts: &[String]) -> Self {
+        let mut out = String::new();
+        if !facts.is_empty() {
+            out.push_str("=== KNOWN USER FACTS ===\n");
+            for fact in facts {
+                out.push_str(&format!("- {}\n", fact));
+            }
+            out.push('\n');
+        }
+        self.values.insert("facts", out);
+        self
+    }
+
+    pub fn snippets(mut self, snippets: &[(String, String)]) -> Self {
+        let mut out = String::new();
+        if !snippets.is_empty() {
+            out.push_str("=== USER'S TEXT SNIPPETS (EXPAND ON MATCH) ===\n");
+            out.push_str("If the user says EXACTLY one of these trigger phrases, expand to the text using type_text:\n\n");
+            for (trigger, expansion) in snippets {
+                let preview = if expansion.chars().count() > 50 {
+                    format!("{}...", expansion.chars().take(50).collect::<String>())
+                } else {
+                    expansion.clone()
+                };
+                out.push_str(&format!(
+                    "- \"{}\" -> {{\"action\": \"type_text\", \"refined_text\": \"{}\"}}\n",
+                    trigger, preview
+                ));
+            }
+            out.push('\n');
+        }
+        self.values.insert("snippets", out);
+        self
+    }
+
+    pub fn aliases(mut self, aliases: &AliasConfig) -> Self {
+        let mut out = String::new();
+        if !aliases.apps.is_empty() || !aliases.websites.is_empty() {
+            out.push_str("=== USER'S APP AND WEBSITE NAMES ===\n");
+            for alias in &aliases.apps {
+                out.push_str(&format!(
+                    "- \"open {}\" -> {{\"action\": \"open_app\", \"payload\": {{\"app\": \"{}\"}}}}\n",
+                    alias.phrase, alias.app
+                ));
+            }
+            for alias in &aliases.websites {
+                out.push_str(&format!(
+                    "- \"open {}\" -> {{\"action\": \"open_url\", \"payload\": {{\"url\": \"{}\"}}}}\n",
+                    alias.phrase, alias.url
+                ));
+            }
+            out.push('\n');
+        }
+        if !aliases.search_engines.is_empty() {
+            let keywords: Vec<&str> = aliases.search_engines.iter().map(|e| e.keyword.as_str()).collect();
+            out.push_str(&format!(
+                "Search engine keywords for web_search: {} (default: {})\n\n",
+                keywords.join(", "),
+                aliases.default_search_engine().keyword
+            ));
+        }
+        self.values.insert("aliases", out);
+        self
+    }
+
+    pub fn catalog(mut self, catalog: &ActionCatalog) -> Self {
+        self.values.insert("catalog", catalog.render_prompt());
+        self
+    }
+}
+
+/// The kind a variable name is declared with; a name has the same kind in
+/// every template that uses it
+fn declared_kind(name: &str) -> Option<VarKind> {
+    TEMPLATES
+        .iter()
+        .flat_map(|t| t.variables.iter())
+        .find(|v| v.name == name)
+        .map(|v| v.kind)
+}
+
+/// Find a built-in template by name
+pub fn find(name: &str) -> Option<&'static PromptTemplate> {
+    TEMPLATES.iter().find(|t| t.name == name)
+}
+
+fn overrides_dir() -> Result<PathBuf, String> {
+    let data_dir =
+        dirs_next::data_dir().ok_or_else(|| "Could not find data directory".to_string())?;
+    Ok(data_dir.join("ListenOS").join("prompts"))
+}
+
+fn override_path(name: &str) -> Result<PathBuf, String> {
+    Ok(overrides_dir()?.join(format!("{}.txt", name)))
+}
+
+/// Every `{{ var }}` in a template body: the byte range it spans and its
+/// trimmed name. Dotted names such as `{{prev.output.path}}` are plan step
+/// references meant for the LLM, not template variables, and are skipped.
+fn placeholder_spans(body: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = body[offset..].find("{{").map(|i| offset + i) {
+        let Some(end) = body[start + 2..].find("}}").map(|i| start + 2 + i) else {
+            break;
+        };
+        let name = body[start + 2..end].trim();
+        if !name.contains('.') {
+            spans.push((start..end + 2, name));
+        }
+        offset = end + 2;
+    }
+    spans
+}
+
+/// Names of all placeholders in a template body
+fn placeholders(body: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (_, name) in placeholder_spans(body) {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Substitute declared variables in one pass, so values that themselves
+/// contain `{{...}}` (clipboard text, user input) are left as they are
+fn fill(template: &PromptTemplate, body: &str, vars: &PromptVars) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut copied = 0;
+    for (range, name) in placeholder_spans(body) {
+        let Some(variable) = template.variables.iter().find(|v| v.name == name) else {
+            continue;
+        };
+        out.push_str(&body[copied..range.start]);
+        out.push_str(vars.values.get(variable.name).map(|s| s.as_str()).unwrap_or(""));
+        copied = range.end;
+    }
+    out.push_str(&body[copied..]);
+    out
+}
+
+/// Check that a body only uses declared variables and keeps the required ones
+pub fn validate(template: &PromptTemplate, body: &str) -> Result<(), String> {
+    if body.trim().is_empty() {
+        return Err("Template is empty".to_string());
+    }
+
+    let used = placeholders(body);
+    for name in &used {
+        if !template.variables.iter().any(|v| v.name == name) {
+            return Err(format!("Unknown variable {{{{{}}}}}", name));
+        }
+    }
+    for v in template.variables.iter().filter(|v| v.required) {
+        if !used.iter().any(|u| u == v.name) {
+            return Err(format!("Missing required variable {{{{{}}}}}", v.name));
+        }
+    }
+    Ok(())
+}
+
+/// Load a user override, if one exists
+fn load_override(template: &PromptTemplate) -> Option<Result<String, String>> {
+    let path = override_path(template.name).ok()?;
+    let body = std::fs::read_to_string(&path).ok()?;
+    Some(validate(template, &body).map(|_| body))
+}
+
+/// The body that will actually be used: a valid override or the default
+fn active_body(template: &PromptTemplate) -> String {
+    match load_override(template) {
+        Some(Ok(body)) => body,
+        Some(Err(e)) => {
+            log::warn!("Ignoring prompt override '{}': {}", template.name, e);
+            template.default_body.to_string()
+        }
+        None => template.default_body.to_string(),
+    }
+}
+
+/// Render a template with the given variables
+pub fn render(name: &str, vars: &PromptVars) -> Result<String, String> {
+    let template = find(name).ok_or_else(|| format!("Unknown prompt template '{}'", name))?;
+
+    for v in template.variables.iter().filter(|v| v.required) {
+        if !vars.values.contains_key(v.name) {
+            return Err(format!("Prompt '{}' needs variable '{}'", name, v.name));
+        }
+    }
+
+    Ok(fill(template, &active_body(template), vars))
+}
+
+/// Punctuation rules for the given dictation style
+pub fn style_rules(style: DictationStyle) -> String {
+    let name = match style {
+        DictationStyle::Formal => DICTATION_STYLE_FORMAL,
+        DictationStyle::Casual => DICTATION_STYLE_CASUAL,
+        DictationStyle::VeryCasual => DICTATION_STYLE_VERY_CASUAL,
+    };
+    render(name, &PromptVars::new()).unwrap_or_default()
+}
+
+/// Describe every template and the state of its override
+pub fn list() -> Vec<PromptTemplateInfo> {
+    TEMPLATES
+        .iter()
+        .map(|t| {
+            let path = override_path(t.name).ok();
+            let loaded = load_override(t);
+            PromptTemplateInfo {
+                name: t.name.to_string(),
+                description: t.description.to_string(),
+                variables: t.variables.to_vec(),
+                overridden: matches!(loaded, Some(Ok(_))),
+                override_path: path.map(|p| p.to_string_lossy().to_string()),
+                override_error: loaded.and_then(|r| r.err()),
+            }
+        })
+        .collect()
+}
+
+/// Validate and write a user override
+pub fn save_override(name: &str, body: &str) -> Result<(), String> {
+    let template = find(name).ok_or_else(|| format!("Unknown prompt template '{}'", name))?;
+    validate(template, body)?;
+
+    let dir = overrides_dir()?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create prompts directory: {}", e))?;
+    std::fs::write(override_path(name)?, body)
+        .map_err(|e| format!("Failed to write prompt override: {}", e))
+}
+
+/// Remove a user override so the built-in default applies again
+pub fn reset_override(name: &str) -> Result<(), String> {
+    find(name).ok_or_else(|| format!("Unknown prompt template '{}'", name))?;
+    let path = override_path(name)?;
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove prompt override: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        for template in TEMPLATES {
+            assert!(
+                validate(template, template.default_body).is_ok(),
+                "default for {} failed validation",
+                template.name
+            );
+        }
+    }
+
+    #[test]
+    fn validation_catches_missing_and_unknown_variables() {
+        let template = find(CLIPBOARD_TRANSLATE).unwrap();
+        assert!(validate(template, "Translate {{content}}").is_err());
+        assert!(validate(template, "{{target_language}} {{content}} {{oops}}").is_err());
+        assert!(validate(template, "To {{ target_language }}: {{content}}").is_ok());
+    }
+
+    #[test]
+    fn fills_placeholders_in_one_pass() {
+        let template = find(CLIPBOARD_TRANSLATE).unwrap();
+        let vars = PromptVars::new()
+            .text("target_language", "French")
+            .text("content", "keep {{target_language}} and {{ content }}");
+        assert_eq!(
+            fill(template, "To {{ target_language }}: {{content}} {{prev.output}} {{oops}}", &vars),
+            "To French: keep {{target_language}} and {{ content }} {{prev.output}} {{oops}}"
+        );
+    }
+
+    #[test]
+    fn text_only_fills_text_variables() {
+        for template in TEMPLATES {
+            for variable in template.variables {
+                assert_eq!(declared_kind(variable.name), Some(variable.kind), "{} in {}", variable.name, template.name);
+            }
+        }
+
+        let vars = PromptVars::new().text("catalog", "- open_pod_bay_doors").text("style_rules", "be brief");
+        assert!(!vars.values.contains_key("catalog"));
+        assert_eq!(vars.values["style_rules"], "be brief");
+        assert!(render(INTENT_ROUTER, &vars.context(&VoiceContext::default(), &ConversationContext::default())).is_err());
+    }
+
+    #[test]
+    fn renders_user_aliases() {
+        let mut aliases = AliasConfig::default();
+        aliases.apps.push(crate::config::AppAlias {
+            phrase: "my editor".to_string(),
+            app: "code".to_string(),
+        });
+        let vars = PromptVars::new().aliases(&aliases);
+        let out = &vars.values["aliases"];
+        assert!(out.contains(r#""open my editor" -> {"action": "open_app", "payload": {"app": "code"}}"#));
+        assert!(out.contains("github"));
+        assert!(out.contains("(default: google)"));
+    }
+}
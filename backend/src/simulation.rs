@@ -0,0 +1,94 @@
+//! Dry-run execution for ListenOS
+//!
+//! While a future runs inside [`run`], every side-effecting executor (input
+//! injection, shell commands, file moves, URL and app launches, system and
+//! app integrations) records what it would have done instead of doing it.
+//! Usage accounting and the connectivity state are left untouched as well.
+//! Used by `preview_utterance` and by tests of the action layer.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// Category of a side effect that was skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    TypeText,
+    KeyPress,
+    Command,
+    OpenUrl,
+    OpenApp,
+    Volume,
+    Clipboard,
+    FileSystem,
+    Integration,
+    Window,
+    Undo,
+}
+
+/// A side effect an executor would have performed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedEffect {
+    pub kind: EffectKind,
+    pub description: String,
+    pub detail: serde_json::Value,
+}
+
+type Recorder = Arc<Mutex<Vec<SimulatedEffect>>>;
+
+tokio::task_local! {
+    static RECORDER: Recorder;
+}
+
+/// True while running inside [`run`]
+pub fn is_active() -> bool {
+    RECORDER.try_with(|_| ()).is_ok()
+}
+
+/// Record an effect when simulating. Returns true if the caller must skip
+/// the real side effect.
+pub fn intercept(kind: EffectKind, description: impl Into<String>, detail: serde_json::Value) -> bool {
+    RECORDER
+        .try_with(|recorder| {
+            if let Ok(mut effects) = recorder.lock() {
+                effects.push(SimulatedEffect {
+                    kind,
+                    description: description.into(),
+                    detail,
+                });
+            }
+        })
+        .is_ok()
+}
+
+/// Run `future` in simulation mode and collect the effects it would have had
+pub async fn run<F: Future>(future: F) -> (F::Output, Vec<SimulatedEffect>) {
+    let recorder: Recorder = Arc::new(Mutex::new(Vec::new()));
+    let output = RECORDER.scope(recorder.clone(), future).await;
+    let effects = recorder.lock().map(|effects| effects.clone()).unwrap_or_default();
+    (output, effects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_only_inside_run() {
+        assert!(!intercept(EffectKind::Command, "outside", serde_json::Value::Null));
+
+        let (active, effects) = run(async {
+            intercept(EffectKind::Command, "Run `ls`", serde_json::json!({ "command": "ls" }));
+            intercept(EffectKind::KeyPress, "Press copy", serde_json::Value::Null);
+            is_active()
+        })
+        .await;
+
+        assert!(active);
+        assert!(!is_active());
+        assert_eq!(effects.len(), 2);
+        assert_eq!(effects[0].kind, EffectKind::Command);
+        assert_eq!(effects[1].description, "Press copy");
+    }
+}
@@ -0,0 +1,621 @@
+//! The freedesktop.org Trash on Linux
+//!
+//! Follows the XDG Trash spec, so items trashed here show up in the file
+//! manager's trash and can be restored from either. Files on the home
+//! filesystem go to `$XDG_DATA_HOME/Trash`; files on other drives go to a
+//! per-user trash at the top of that mount, `$topdir/.Trash/$uid` when an
+//! administrator set one up and `$topdir/.Trash-$uid` otherwise. Each item
+//! sits in `files/` next to an `info/<name>.trashinfo` that records its
+//! original path and when it was deleted.
+
+use std::ffi::{CString, OsString};
+use std::fs;
+use std::io::Write;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+const INFO_EXTENSION: &str = ".trashinfo";
+/// Shortest spoken name that may match part of an item's name
+const MIN_PARTIAL_NAME: usize = 3;
+/// Set on an administrator-created `$topdir/.Trash` that users may share
+const STICKY_BIT: u32 = 0o1000;
+
+/// One trash directory, with the mount it belongs to. Original paths in a
+/// mount's trash are stored relative to `topdir`.
+#[derive(Debug, Clone, PartialEq)]
+struct TrashDir {
+    path: PathBuf,
+    topdir: Option<PathBuf>,
+}
+
+impl TrashDir {
+    fn files(&self) -> PathBuf {
+        self.path.join("files")
+    }
+
+    fn info(&self) -> PathBuf {
+        self.path.join("info")
+    }
+
+    /// The trash a file under `files/` belongs to, worked out from its path
+    fn of_item(item: &Path) -> Option<Self> {
+        let path = item.parent()?.parent()?.to_path_buf();
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        let topdir = if name.starts_with(".Trash-") {
+            path.parent().map(Path::to_path_buf)
+        } else if path.parent()?.file_name().is_some_and(|parent| parent == ".Trash") {
+            path.parent()?.parent().map(Path::to_path_buf)
+        } else {
+            None
+        };
+        Some(Self { path, topdir })
+    }
+}
+
+/// Something in the trash
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashItem {
+    /// File name it had before it was trashed
+    pub name: String,
+    pub original_path: String,
+    /// Local time as written in the .trashinfo, e.g. "2026-10-18T09:30:00"
+    pub deleted_at: Option<String>,
+    pub bytes: u64,
+    /// Where it sits in the trash
+    pub trashed_path: String,
+}
+
+/// What emptying the trash removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EmptyReport {
+    pub items: usize,
+    pub bytes: u64,
+}
+
+/// The home trash, `$XDG_DATA_HOME/Trash`
+fn home_trash() -> Result<TrashDir, String> {
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".local/share")))
+        .ok_or("Could not find the home directory")?;
+    Ok(TrashDir { path: data_home.join("Trash"), topdir: None })
+}
+
+fn uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+/// Mount points from /proc/self/mountinfo, longest first
+fn mount_points() -> Vec<PathBuf> {
+    let text = fs::read_to_string("/proc/self/mountinfo").unwrap_or_default();
+    let mut mounts = parse_mount_points(&text);
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.as_os_str().len()));
+    mounts.dedup();
+    mounts
+}
+
+fn parse_mount_points(mountinfo: &str) -> Vec<PathBuf> {
+    mountinfo
+        .lines()
+        .filter_map(|line| line.split(' ').nth(4))
+        .map(|field| PathBuf::from(OsString::from_vec(unescape_mount_field(field))))
+        .collect()
+}
+
+/// mountinfo writes spaces, tabs, newlines and backslashes as octal escapes
+fn unescape_mount_field(field: &str) -> Vec<u8> {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 4).and_then(|octal| {
+            let octal = std::str::from_utf8(octal).ok()?;
+            u8::from_str_radix(octal, 8).ok()
+        });
+        match (bytes[i], escaped) {
+            (b'\\', Some(byte)) => {
+                out.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// The user's trash on another mount, if one exists or `create` asks for it.
+/// A shared `$topdir/.Trash` only counts when it is a real directory with
+/// the sticky bit; otherwise the user's own `$topdir/.Trash-$uid` is used.
+fn topdir_trash(topdir: &Path, create: bool) -> Option<TrashDir> {
+    let uid = uid().to_string();
+    let shared = topdir.join(".Trash");
+    let shared_usable = fs::symlink_metadata(&shared)
+        .is_ok_and(|meta| meta.is_dir() && meta.mode() & STICKY_BIT != 0);
+    let mut candidates = Vec::new();
+    if shared_usable {
+        candidates.push(shared.join(&uid));
+    }
+    candidates.push(topdir.join(format!(".Trash-{}", uid)));
+
+    let topdir = Some(topdir.to_path_buf());
+    if let Some(existing) = candidates.iter().find(|dir| dir.is_dir()) {
+        return Some(TrashDir { path: existing.clone(), topdir });
+    }
+    if !create {
+        return None;
+    }
+    candidates.into_iter().find_map(|dir| {
+        fs::DirBuilder::new().mode(0o700).create(&dir).ok()?;
+        Some(TrashDir { path: dir, topdir: topdir.clone() })
+    })
+}
+
+/// Every trash directory there is: the home trash and the user's trash on
+/// each mounted drive
+fn trash_dirs() -> Vec<TrashDir> {
+    let mut dirs: Vec<TrashDir> = home_trash().into_iter().filter(|home| home.path.is_dir()).collect();
+    for mount in mount_points() {
+        if let Some(dir) = topdir_trash(&mount, false) {
+            if !dirs.iter().any(|known| known.path == dir.path) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+/// The trash a path should go to: the home trash when it is on the same
+/// filesystem, else the trash at the top of its mount
+fn trash_for(path: &Path) -> Result<TrashDir, String> {
+    let device = fs::symlink_metadata(path)
+        .map_err(|e| format!("Cannot trash {}: {}", path.display(), e))?
+        .dev();
+    let home = home_trash()?;
+    let home_device = home.path.ancestors().find_map(|dir| fs::metadata(dir).ok()).map(|meta| meta.dev());
+    if home_device == Some(device) {
+        return Ok(home);
+    }
+
+    let topdir = mount_points()
+        .into_iter()
+        .find(|mount| path.starts_with(mount))
+        .ok_or_else(|| format!("Could not find the drive {} is on", path.display()))?;
+    topdir_trash(&topdir, true).ok_or_else(|| format!("{} has no trash folder and one cannot be created", topdir.display()))
+}
+
+/// Move a file or folder to the trash. Symlinks are trashed themselves,
+/// not what they point to.
+pub fn move_to_trash(path: &Path) -> Result<TrashItem, String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("Cannot trash {}", path.display()))?
+        .to_os_string();
+    // Resolve the folder, not the item, so a symlink stays a symlink
+    let parent = match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => parent.canonicalize(),
+        None => std::env::current_dir(),
+    }
+    .map_err(|e| format!("Cannot trash {}: {}", path.display(), e))?;
+    let path = parent.join(&name);
+
+    let trash = trash_for(&path)?;
+    if trash.path.starts_with(&path) || path.starts_with(&trash.path) {
+        return Err(format!("Cannot trash {}", path.display()));
+    }
+    for dir in [trash.files(), trash.info()] {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let stored_path = match &trash.topdir {
+        Some(topdir) => path.strip_prefix(topdir).unwrap_or(&path),
+        None => &path,
+    };
+    let deleted_at = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let contents = format!("[Trash Info]\nPath={}\nDeletionDate={}\n", encode_path(stored_path), deleted_at);
+
+    // Claiming the .trashinfo name first keeps two trashers from picking the same one
+    let (id, info_path) = claim_info_file(&trash, &name, &contents)?;
+    let trashed = trash.files().join(&id);
+    let bytes = disk_usage(&path);
+    if let Err(e) = fs::rename(&path, &trashed) {
+        let _ = fs::remove_file(&info_path);
+        return Err(format!("Failed to move {} to the trash: {}", path.display(), e));
+    }
+
+    Ok(TrashItem {
+        name: name.to_string_lossy().into_owned(),
+        original_path: path.to_string_lossy().into_owned(),
+        deleted_at: Some(deleted_at),
+        bytes,
+        trashed_path: trashed.to_string_lossy().into_owned(),
+    })
+}
+
+/// Create `info/<id>.trashinfo` under a name no other item uses: "report.pdf",
+/// then "report.2.pdf", "report.3.pdf" and so on
+fn claim_info_file(trash: &TrashDir, name: &std::ffi::OsStr, contents: &str) -> Result<(OsString, PathBuf), String> {
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or(name.as_os_str()).to_os_string();
+    let extension = name.extension().map(|ext| ext.to_os_string());
+    for n in 1..10_000 {
+        let mut id = stem.clone();
+        if n > 1 {
+            id.push(format!(".{}", n));
+        }
+        if let Some(ext) = &extension {
+            id.push(".");
+            id.push(ext);
+        }
+        if trash.files().join(&id).symlink_metadata().is_ok() {
+            continue;
+        }
+        let mut info_name = id.clone();
+        info_name.push(INFO_EXTENSION);
+        let info_path = trash.info().join(info_name);
+        match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())
+                    .map_err(|e| format!("Failed to write {}: {}", info_path.display(), e))?;
+                return Ok((id, info_path));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to write {}: {}", info_path.display(), e)),
+        }
+    }
+    Err(format!("Too many items called {} in the trash", name.display()))
+}
+
+/// Everything in every trash, most recently deleted first
+pub fn list() -> Vec<TrashItem> {
+    let mut items: Vec<TrashItem> = trash_dirs().iter().flat_map(items_in).collect();
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    items
+}
+
+fn items_in(trash: &TrashDir) -> Vec<TrashItem> {
+    let Ok(entries) = fs::read_dir(trash.info()) else { return Vec::new() };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let info_name = entry.file_name();
+            let id = info_name.as_bytes().strip_suffix(INFO_EXTENSION.as_bytes())?;
+            let trashed = trash.files().join(std::ffi::OsStr::from_bytes(id));
+            // An info file whose item is gone is left over from an interrupted delete
+            trashed.symlink_metadata().ok()?;
+            item_at(trash, &trashed)
+        })
+        .collect()
+}
+
+/// Read the .trashinfo for an item under `files/`
+fn item_at(trash: &TrashDir, trashed: &Path) -> Option<TrashItem> {
+    let mut info_name = trashed.file_name()?.to_os_string();
+    info_name.push(INFO_EXTENSION);
+    let info = parse_trash_info(&fs::read_to_string(trash.info().join(info_name)).ok()?)?;
+    let original = match &trash.topdir {
+        Some(topdir) if info.path.is_relative() => topdir.join(&info.path),
+        _ => info.path,
+    };
+    Some(TrashItem {
+        name: original.file_name()?.to_string_lossy().into_owned(),
+        original_path: original.to_string_lossy().into_owned(),
+        deleted_at: info.deleted_at,
+        bytes: disk_usage(trashed),
+        trashed_path: trashed.to_string_lossy().into_owned(),
+    })
+}
+
+struct TrashInfo {
+    path: PathBuf,
+    deleted_at: Option<String>,
+}
+
+fn parse_trash_info(text: &str) -> Option<TrashInfo> {
+    let mut in_section = false;
+    let mut path = None;
+    let mut deleted_at = None;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == "[Trash Info]";
+        } else if in_section {
+            match line.split_once('=') {
+                Some(("Path", value)) => path = Some(PathBuf::from(OsString::from_vec(decode_path(value)))),
+                Some(("DeletionDate", value)) => deleted_at = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    }
+    Some(TrashInfo { path: path?, deleted_at })
+}
+
+/// Percent-encode a path the way URIs are, keeping the slashes
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_path(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// "340 MB", in the decimal units file managers use
+pub fn format_size(bytes: u64) -> String {
+    let units = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < units.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} bytes", bytes),
+        _ if size < 10.0 => format!("{:.1} {}", size, units[unit]),
+        _ => format!("{:.0} {}", size, units[unit]),
+    }
+}
+
+/// Bytes a file or folder takes up, not following symlinks
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else { return 0 };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| disk_usage(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// The trashed item a spoken name refers to, most recently deleted first:
+/// an exact file name, then the name without its extension, then a part of
+/// it as long as only one item has that part
+pub fn find(spoken: &str) -> Result<TrashItem, String> {
+    find_in(&list(), spoken)
+}
+
+fn find_in(items: &[TrashItem], spoken: &str) -> Result<TrashItem, String> {
+    if items.is_empty() {
+        return Err("The trash is empty".to_string());
+    }
+    let spoken = spoken.trim().to_lowercase();
+    let spoken = spoken.strip_prefix("the ").unwrap_or(&spoken);
+    let stem = |item: &TrashItem| {
+        Path::new(&item.name).file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default()
+    };
+    let named = items
+        .iter()
+        .find(|item| item.name.to_lowercase() == spoken)
+        .or_else(|| items.iter().find(|item| stem(item) == spoken));
+    if let Some(item) = named {
+        return Ok(item.clone());
+    }
+
+    let partial: Vec<&TrashItem> = if spoken.chars().count() >= MIN_PARTIAL_NAME {
+        items.iter().filter(|item| item.name.to_lowercase().contains(spoken)).collect()
+    } else {
+        Vec::new()
+    };
+    match partial.as_slice() {
+        [] => Err(format!("Nothing called \"{}\" is in the trash", spoken)),
+        [item] => Ok((*item).clone()),
+        several => {
+            let names: Vec<&str> = several.iter().take(5).map(|item| item.name.as_str()).collect();
+            Err(format!("Which one? The trash has {}", names.join(", ")))
+        }
+    }
+}
+
+/// Put a trashed item back where it was, given where it sits in the trash.
+/// Missing parent folders are recreated; an existing file is never replaced.
+pub fn restore(trashed_path: &Path) -> Result<TrashItem, String> {
+    let trash = TrashDir::of_item(trashed_path).ok_or_else(|| format!("{} is not in a trash folder", trashed_path.display()))?;
+    let item = item_at(&trash, trashed_path)
+        .ok_or_else(|| format!("{} is no longer in the trash", trashed_path.display()))?;
+    let original = Path::new(&item.original_path);
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to recreate {}: {}", parent.display(), e))?;
+    }
+    rename_no_replace(trashed_path, original).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => format!("{} already exists", original.display()),
+        _ => format!("Failed to restore {}: {}", item.name, e),
+    })?;
+
+    let mut info_name = trashed_path.file_name().unwrap_or_default().to_os_string();
+    info_name.push(INFO_EXTENSION);
+    let _ = fs::remove_file(trash.info().join(info_name));
+    Ok(item)
+}
+
+/// Rename `from` to `to` unless `to` exists, checked by the kernel so a file
+/// created in the meantime is never replaced. Filesystems without
+/// RENAME_NOREPLACE get a hard link and unlink, which fails the same way;
+/// folders can't be hard-linked there and are refused.
+fn rename_no_replace(from: &Path, to: &Path) -> std::io::Result<()> {
+    let c_path = |path: &Path| {
+        CString::new(path.as_os_str().as_bytes()).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+    };
+    let (from_c, to_c) = (c_path(from)?, c_path(to)?);
+    // SAFETY: both paths are valid NUL-terminated strings that outlive the call
+    let result = unsafe {
+        libc::renameat2(libc::AT_FDCWD, from_c.as_ptr(), libc::AT_FDCWD, to_c.as_ptr(), libc::RENAME_NOREPLACE)
+    };
+    if result == 0 {
+        return Ok(());
+    }
+    let error = std::io::Error::last_os_error();
+    if error.raw_os_error() != Some(libc::EINVAL) {
+        return Err(error);
+    }
+    fs::hard_link(from, to)?;
+    fs::remove_file(from)
+}
+
+/// Permanently delete everything in every trash. Items that can't be
+/// removed are skipped and reported only when nothing could be.
+pub fn empty() -> Result<EmptyReport, String> {
+    let mut report = EmptyReport::default();
+    let mut errors = Vec::new();
+    for trash in trash_dirs() {
+        empty_dir(&trash, &mut report, &mut errors);
+    }
+    if report.items == 0 && !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    for error in &errors {
+        log::warn!("Empty trash: {}", error);
+    }
+    Ok(report)
+}
+
+fn empty_dir(trash: &TrashDir, report: &mut EmptyReport, errors: &mut Vec<String>) {
+    if let Ok(entries) = fs::read_dir(trash.files()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let bytes = disk_usage(&path);
+            let removed = match entry.file_type() {
+                Ok(kind) if kind.is_dir() => fs::remove_dir_all(&path),
+                _ => fs::remove_file(&path),
+            };
+            match removed {
+                Ok(()) => {
+                    report.items += 1;
+                    report.bytes += bytes;
+                }
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+    }
+    // Info files go once their item has, including strays from interrupted deletes
+    if let Ok(entries) = fs::read_dir(trash.info()) {
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(id) = name.as_bytes().strip_suffix(INFO_EXTENSION.as_bytes()) else { continue };
+            if trash.files().join(std::ffi::OsStr::from_bytes(id)).symlink_metadata().is_err() {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+    let _ = fs::remove_file(trash.path.join("directorysizes"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_mountinfo_and_trashinfo() {
+        let mountinfo = "22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw\n\
+                         41 22 8:17 / /media/jo/USB\\040Drive rw,nosuid shared:2 - vfat /dev/sdb1 rw\n";
+        assert_eq!(parse_mount_points(mountinfo), [PathBuf::from("/"), PathBuf::from("/media/jo/USB Drive")]);
+
+        let path = Path::new("/home/jo/Q3 report (final).pdf");
+        let encoded = encode_path(path);
+        assert_eq!(encoded, "/home/jo/Q3%20report%20%28final%29.pdf");
+        let info = parse_trash_info(&format!("[Trash Info]\nPath={}\nDeletionDate=2026-10-18T09:30:00\n", encoded)).unwrap();
+        assert_eq!(info.path, path);
+        assert_eq!(info.deleted_at.as_deref(), Some("2026-10-18T09:30:00"));
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(340_200_000), "340 MB");
+        assert_eq!(format_size(1_260_000_000), "1.3 GB");
+    }
+
+    #[test]
+    fn trashes_and_restores_on_a_mount() {
+        let topdir = std::env::temp_dir().join(format!("listenos-trash-{}", uuid::Uuid::new_v4()));
+        let trash = TrashDir { path: topdir.join(format!(".Trash-{}", uid())), topdir: Some(topdir.clone()) };
+        let original = topdir.join("notes/todo.txt");
+        fs::create_dir_all(original.parent().unwrap()).unwrap();
+        fs::create_dir_all(trash.files()).unwrap();
+        fs::create_dir_all(trash.info()).unwrap();
+        fs::write(trash.files().join("todo.txt"), "older").unwrap();
+
+        // A second "todo.txt" gets its own name in the trash
+        let (id, info_path) = claim_info_file(&trash, "todo.txt".as_ref(), "[Trash Info]\nPath=notes/todo.txt\n").unwrap();
+        assert_eq!(id, "todo.2.txt");
+        fs::write(trash.files().join(&id), "newest").unwrap();
+        assert_eq!(TrashDir::of_item(&trash.files().join(&id)), Some(trash.clone()));
+
+        // The item whose info is missing is a stray, not a listed item
+        let items = items_in(&trash);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].original_path, original.to_string_lossy());
+        assert_eq!(items[0].bytes, 6);
+
+        // A file that took the name back in the meantime is never replaced
+        fs::write(&original, "recreated").unwrap();
+        assert!(restore(Path::new(&items[0].trashed_path)).unwrap_err().ends_with("already exists"));
+        assert_eq!(fs::read_to_string(&original).unwrap(), "recreated");
+        fs::remove_file(&original).unwrap();
+
+        restore(Path::new(&items[0].trashed_path)).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "newest");
+        assert!(!info_path.exists());
+
+        let mut report = EmptyReport::default();
+        empty_dir(&trash, &mut report, &mut Vec::new());
+        assert_eq!((report.items, report.bytes), (1, 5));
+        assert!(items_in(&trash).is_empty());
+
+        fs::remove_dir_all(&topdir).unwrap();
+    }
+
+    #[test]
+    fn finds_items_by_spoken_name() {
+        let item = |name: &str| TrashItem {
+            name: name.to_string(),
+            original_path: format!("/home/jo/{}", name),
+            deleted_at: None,
+            bytes: 0,
+            trashed_path: format!("/home/jo/.local/share/Trash/files/{}", name),
+        };
+        let items = [item("Budget 2026.xlsx"), item("budget-notes.txt"), item("report.pdf"), item("a.txt")];
+        let found = |spoken: &str| find_in(&items, spoken).map(|item| item.name);
+
+        assert_eq!(found("report.pdf").unwrap(), "report.pdf");
+        assert_eq!(found("the report").unwrap(), "report.pdf");
+        assert_eq!(found("A").unwrap(), "a.txt");
+        assert_eq!(found("notes").unwrap(), "budget-notes.txt");
+        // Too short to match part of a name, or matching more than one item
+        assert!(found("t").unwrap_err().starts_with("Nothing called"));
+        assert_eq!(found("budget").unwrap_err(), "Which one? The trash has Budget 2026.xlsx, budget-notes.txt");
+        assert!(find_in(&[], "report").is_err());
+    }
+}
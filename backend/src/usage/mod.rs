@@ -0,0 +1,479 @@
+//! Usage and cost accounting for ListenOS
+//!
+//! Records speech seconds and LLM tokens per provider and feature, and turns
+//! spend against the user's daily/monthly budgets into a degradation level.
+
+use chrono::{Datelike, Local, TimeZone, Utc};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config::UsageBudgetConfig;
+
+pub const LLM_MODEL: &str = "llama-3.3-70b-versatile";
+pub const STT_MODEL: &str = "whisper-large-v3-turbo";
+
+/// Feature a provider call was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UsageFeature {
+    Transcription,
+    Intent,
+    Question,
+    VibeEnhancement,
+    Multilingual,
+    Clipboard,
+}
+
+impl UsageFeature {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Transcription => "transcription",
+            Self::Intent => "intent",
+            Self::Question => "question",
+            Self::VibeEnhancement => "vibe_enhancement",
+            Self::Multilingual => "multilingual",
+            Self::Clipboard => "clipboard",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "transcription" => Some(Self::Transcription),
+            "intent" => Some(Self::Intent),
+            "question" => Some(Self::Question),
+            "vibe_enhancement" => Some(Self::VibeEnhancement),
+            "multilingual" => Some(Self::Multilingual),
+            "clipboard" => Some(Self::Clipboard),
+            _ => None,
+        }
+    }
+}
+
+/// Token counts from a completion's `usage` field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Read `usage` (or Groq's `x_groq.usage` on the last stream chunk)
+    pub fn from_response(value: &serde_json::Value) -> Option<Self> {
+        let usage = value
+            .get("usage")
+            .filter(|u| u.is_object())
+            .or_else(|| value.get("x_groq").and_then(|x| x.get("usage")))?;
+
+        Some(Self {
+            prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0),
+            completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+        })
+    }
+
+    /// Rough counts for providers that do not report usage, at about four
+    /// characters per token. The provider's own system prompt is not seen
+    /// here, so this errs low.
+    pub fn estimate(prompt: &str, completion: &str) -> Self {
+        let tokens = |text: &str| (text.chars().count() as u64).div_ceil(4);
+        Self {
+            prompt_tokens: tokens(prompt),
+            completion_tokens: tokens(completion),
+        }
+    }
+}
+
+/// Estimated USD cost of a call, from public list prices
+pub fn estimate_cost(model: &str, stt_seconds: f64, tokens: TokenUsage) -> f64 {
+    // (input per 1M tokens, output per 1M tokens, per audio hour)
+    let (input, output, audio_hour) = match model {
+        LLM_MODEL => (0.59, 0.79, 0.0),
+        STT_MODEL => (0.0, 0.0, 0.04),
+        _ => (0.0, 0.0, 0.0),
+    };
+
+    tokens.prompt_tokens as f64 * input / 1_000_000.0
+        + tokens.completion_tokens as f64 * output / 1_000_000.0
+        + stt_seconds / 3600.0 * audio_hour
+}
+
+/// Aggregated usage over a period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub stt_seconds: f64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Usage for one provider/feature pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureUsage {
+    pub provider: String,
+    pub feature: UsageFeature,
+    pub totals: UsageTotals,
+}
+
+/// How far spending has pushed the app into degraded mode. Features are shed
+/// in order: vibe enhancement first, then Q&A, and dictation last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BudgetLevel {
+    Normal,
+    /// Vibe prompt enhancement is skipped
+    SkipVibe,
+    /// Vibe enhancement and spoken Q&A are skipped
+    SkipQuestions,
+    /// Only speech-to-text remains; intents are routed locally
+    DictationOnly,
+}
+
+/// Fraction of a budget at which each level kicks in
+const SKIP_VIBE_AT: f64 = 0.75;
+const SKIP_QUESTIONS_AT: f64 = 0.9;
+const DICTATION_ONLY_AT: f64 = 1.0;
+
+impl BudgetLevel {
+    pub fn from_ratio(ratio: f64) -> Self {
+        if ratio >= DICTATION_ONLY_AT {
+            Self::DictationOnly
+        } else if ratio >= SKIP_QUESTIONS_AT {
+            Self::SkipQuestions
+        } else if ratio >= SKIP_VIBE_AT {
+            Self::SkipVibe
+        } else {
+            Self::Normal
+        }
+    }
+
+    /// Whether a feature may still call a paid provider at this level
+    pub fn allows(&self, feature: UsageFeature) -> bool {
+        match feature {
+            UsageFeature::VibeEnhancement => *self < Self::SkipVibe,
+            UsageFeature::Question => *self < Self::SkipQuestions,
+            UsageFeature::Transcription => true,
+            UsageFeature::Intent | UsageFeature::Multilingual | UsageFeature::Clipboard => {
+                *self < Self::DictationOnly
+            }
+        }
+    }
+}
+
+/// Spend against the configured budgets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub level: BudgetLevel,
+    pub daily_limit_usd: Option<f64>,
+    pub monthly_limit_usd: Option<f64>,
+    pub daily_spent_usd: f64,
+    pub monthly_spent_usd: f64,
+}
+
+impl BudgetStatus {
+    fn unlimited() -> Self {
+        Self {
+            level: BudgetLevel::Normal,
+            daily_limit_usd: None,
+            monthly_limit_usd: None,
+            daily_spent_usd: 0.0,
+            monthly_spent_usd: 0.0,
+        }
+    }
+
+    pub fn allows(&self, feature: UsageFeature) -> bool {
+        self.level.allows(feature)
+    }
+}
+
+/// Usage summary for the dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub today: UsageTotals,
+    pub this_month: UsageTotals,
+    pub month_by_feature: Vec<FeatureUsage>,
+    pub budget: BudgetStatus,
+}
+
+/// Persistent storage for usage events
+pub struct UsageStore {
+    conn: Mutex<Connection>,
+}
+
+impl UsageStore {
+    /// Create or open the usage database
+    pub fn new() -> Result<Self, String> {
+        let db_path = Self::get_db_path()?;
+
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+
+        let conn = Connection::open(&db_path)
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+
+        let store = Self { conn: Mutex::new(conn) };
+        store.init_tables()?;
+        Ok(store)
+    }
+
+    #[cfg(test)]
+    fn in_memory() -> Self {
+        let store = Self { conn: Mutex::new(Connection::open_in_memory().unwrap()) };
+        store.init_tables().unwrap();
+        store
+    }
+
+    fn get_db_path() -> Result<PathBuf, String> {
+        let data_dir = dirs_next::data_dir()
+            .ok_or_else(|| "Could not find data directory".to_string())?;
+        Ok(data_dir.join("ListenOS").join("usage.db"))
+    }
+
+    fn init_tables(&self) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS usage_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                created_at INTEGER NOT NULL,
+                provider TEXT NOT NULL,
+                feature TEXT NOT NULL,
+                model TEXT NOT NULL,
+                stt_seconds REAL NOT NULL DEFAULT 0,
+                prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                completion_tokens INTEGER NOT NULL DEFAULT 0,
+                cost_usd REAL NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_usage_created_at ON usage_events(created_at);
+            "
+        ).map_err(|e| format!("Failed to initialize tables: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Record one provider call
+    pub fn record(
+        &self,
+        provider: &str,
+        feature: UsageFeature,
+        model: &str,
+        stt_seconds: f64,
+        tokens: TokenUsage,
+    ) -> Result<(), String> {
+        // Dry runs (`preview_utterance`) may ask the LLM but are not billed usage
+        if crate::simulation::is_active() {
+            return Ok(());
+        }
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        conn.execute(
+            "INSERT INTO usage_events (created_at, provider, feature, model, stt_seconds, prompt_tokens, completion_tokens, cost_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                Utc::now().timestamp(),
+                provider,
+                feature.as_str(),
+                model,
+                stt_seconds,
+                tokens.prompt_tokens as i64,
+                tokens.completion_tokens as i64,
+                estimate_cost(model, stt_seconds, tokens),
+            ],
+        ).map_err(|e| format!("Failed to record usage: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Totals for everything recorded since `since` (unix seconds)
+    pub fn totals_since(&self, since: i64) -> Result<UsageTotals, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(stt_seconds), 0), COALESCE(SUM(prompt_tokens), 0),
+                    COALESCE(SUM(completion_tokens), 0), COALESCE(SUM(cost_usd), 0)
+             FROM usage_events WHERE created_at >= ?1",
+            [since],
+            |row| {
+                Ok(UsageTotals {
+                    requests: row.get::<_, i64>(0)? as u64,
+                    stt_seconds: row.get(1)?,
+                    prompt_tokens: row.get::<_, i64>(2)? as u64,
+                    completion_tokens: row.get::<_, i64>(3)? as u64,
+                    cost_usd: row.get(4)?,
+                })
+            },
+        ).map_err(|e| format!("Failed to query usage: {}", e))
+    }
+
+    /// Totals since `since`, split by provider and feature
+    pub fn by_feature_since(&self, since: i64) -> Result<Vec<FeatureUsage>, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+
+        let mut stmt = conn.prepare(
+            "SELECT provider, feature, COUNT(*), SUM(stt_seconds), SUM(prompt_tokens),
+                    SUM(completion_tokens), SUM(cost_usd)
+             FROM usage_events WHERE created_at >= ?1
+             GROUP BY provider, feature ORDER BY SUM(cost_usd) DESC"
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt.query_map([since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                UsageTotals {
+                    requests: row.get::<_, i64>(2)? as u64,
+                    stt_seconds: row.get(3)?,
+                    prompt_tokens: row.get::<_, i64>(4)? as u64,
+                    completion_tokens: row.get::<_, i64>(5)? as u64,
+                    cost_usd: row.get(6)?,
+                },
+            ))
+        }).map_err(|e| format!("Failed to query usage: {}", e))?;
+
+        let rows = rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect usage: {}", e))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(provider, feature, totals)| {
+                UsageFeature::from_str(&feature).map(|feature| FeatureUsage { provider, feature, totals })
+            })
+            .collect())
+    }
+}
+
+/// Unix timestamp of local midnight today
+fn start_of_day() -> i64 {
+    let now = Local::now();
+    Local
+        .with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0)
+        .earliest()
+        .map(|t| t.timestamp())
+        .unwrap_or_else(|| now.timestamp() - 86_400)
+}
+
+/// Unix timestamp of local midnight on the first of this month
+fn start_of_month() -> i64 {
+    let now = Local::now();
+    Local
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .earliest()
+        .map(|t| t.timestamp())
+        .unwrap_or_else(|| now.timestamp() - 31 * 86_400)
+}
+
+/// Record a call, logging instead of failing the caller if the store is unavailable
+pub fn record(provider: &str, feature: UsageFeature, model: &str, stt_seconds: f64, tokens: TokenUsage) {
+    let result = UsageStore::new()
+        .and_then(|store| store.record(provider, feature, model, stt_seconds, tokens));
+    if let Err(e) = result {
+        log::warn!("Failed to record {} usage: {}", feature.as_str(), e);
+    }
+}
+
+/// Record a chat completion from its parsed response body
+pub fn record_completion(provider: &str, feature: UsageFeature, response: &serde_json::Value) {
+    let tokens = TokenUsage::from_response(response).unwrap_or_default();
+    record(provider, feature, LLM_MODEL, 0.0, tokens);
+}
+
+/// Current spend against the configured budgets
+pub fn budget_status(config: &UsageBudgetConfig) -> BudgetStatus {
+    if !config.enabled || (config.daily_limit_usd.is_none() && config.monthly_limit_usd.is_none()) {
+        return BudgetStatus::unlimited();
+    }
+
+    let store = match UsageStore::new() {
+        Ok(store) => store,
+        Err(e) => {
+            log::warn!("Usage store unavailable, budgets not enforced: {}", e);
+            return BudgetStatus::unlimited();
+        }
+    };
+    let daily_spent = store.totals_since(start_of_day()).map(|t| t.cost_usd).unwrap_or(0.0);
+    let monthly_spent = store.totals_since(start_of_month()).map(|t| t.cost_usd).unwrap_or(0.0);
+
+    let ratio = |spent: f64, limit: Option<f64>| match limit {
+        Some(limit) if limit > 0.0 => spent / limit,
+        Some(_) => f64::INFINITY,
+        None => 0.0,
+    };
+    let worst = ratio(daily_spent, config.daily_limit_usd)
+        .max(ratio(monthly_spent, config.monthly_limit_usd));
+
+    BudgetStatus {
+        level: BudgetLevel::from_ratio(worst),
+        daily_limit_usd: config.daily_limit_usd,
+        monthly_limit_usd: config.monthly_limit_usd,
+        daily_spent_usd: daily_spent,
+        monthly_spent_usd: monthly_spent,
+    }
+}
+
+/// Everything the usage dashboard needs
+pub fn summary(config: &UsageBudgetConfig) -> Result<UsageSummary, String> {
+    let store = UsageStore::new()?;
+    Ok(UsageSummary {
+        today: store.totals_since(start_of_day())?,
+        this_month: store.totals_since(start_of_month())?,
+        month_by_feature: store.by_feature_since(start_of_month())?,
+        budget: budget_status(config),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_are_shed_in_order() {
+        let level = BudgetLevel::from_ratio(0.8);
+        assert!(!level.allows(UsageFeature::VibeEnhancement));
+        assert!(level.allows(UsageFeature::Question));
+
+        let level = BudgetLevel::from_ratio(0.95);
+        assert!(!level.allows(UsageFeature::Question));
+        assert!(level.allows(UsageFeature::Intent));
+
+        let level = BudgetLevel::from_ratio(1.2);
+        assert!(!level.allows(UsageFeature::Intent));
+        assert!(level.allows(UsageFeature::Transcription));
+    }
+
+    #[test]
+    fn reads_usage_from_stream_chunks() {
+        let chunk = serde_json::json!({
+            "choices": [],
+            "x_groq": { "usage": { "prompt_tokens": 120, "completion_tokens": 30 } }
+        });
+        let tokens = TokenUsage::from_response(&chunk).unwrap();
+        assert_eq!(tokens.prompt_tokens, 120);
+        assert_eq!(tokens.completion_tokens, 30);
+        assert!(estimate_cost(LLM_MODEL, 0.0, tokens) > 0.0);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_record_no_usage() {
+        let store = UsageStore::in_memory();
+        let tokens = TokenUsage { prompt_tokens: 500, completion_tokens: 20 };
+        let (recorded, _) = crate::simulation::run(async {
+            store.record("server", UsageFeature::Intent, LLM_MODEL, 0.0, tokens)
+        })
+        .await;
+        assert!(recorded.is_ok());
+        assert_eq!(store.totals_since(0).unwrap().requests, 0);
+
+        store.record("server", UsageFeature::Intent, LLM_MODEL, 0.0, tokens).unwrap();
+        assert_eq!(store.totals_since(0).unwrap().requests, 1);
+    }
+
+    #[test]
+    fn estimates_unreported_usage() {
+        let tokens = TokenUsage::estimate("open spotify please", "");
+        assert_eq!((tokens.prompt_tokens, tokens.completion_tokens), (5, 0));
+        assert!(estimate_cost(LLM_MODEL, 0.0, TokenUsage::estimate("open spotify", "{}")) > 0.0);
+    }
+}
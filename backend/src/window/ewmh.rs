@@ -0,0 +1,622 @@
+//! X11 window management through EWMH
+//!
+//! Requests are client messages sent to the root window, which any
+//! EWMH-compliant window manager (GNOME, KDE, Xfce, i3, Openbox, ...) acts on.
+
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xproto::{
+    AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt as _, EventMask, Window,
+};
+use x11rb::rust_connection::RustConnection;
+
+use super::{LayoutSlot, MonitorTarget, WindowAction, WindowGeometry, WindowInfo, WindowManager};
+
+/// x, y, width, height in root window coordinates
+type Rect = (i32, i32, u32, u32);
+
+/// `_NET_WM_STATE` client message actions
+const STATE_REMOVE: u32 = 0;
+const STATE_ADD: u32 = 1;
+/// Source indication for requests from pagers and other tools
+const SOURCE_PAGER: u32 = 2;
+/// ICCCM IconicState, used with WM_CHANGE_STATE
+const ICONIC_STATE: u32 = 3;
+
+pub struct Ewmh {
+    conn: RustConnection,
+    /// Index of the screen we connected to in the server setup
+    screen: usize,
+    root: Window,
+}
+
+impl Ewmh {
+    /// Connect to `display`, or to `$DISPLAY` when None
+    pub fn connect(display: Option<&str>) -> Result<Self, String> {
+        let (conn, screen) =
+            x11rb::connect(display).map_err(|e| format!("Failed to connect to X server: {}", e))?;
+        let root = conn.setup().roots[screen].root;
+        Ok(Self { conn, screen, root })
+    }
+
+    /// Connect and make sure a window manager is there to act on requests
+    pub fn open(display: Option<&str>) -> Result<Self, String> {
+        let ewmh = Self::connect(display)?;
+        ewmh.check_window_manager()?;
+        Ok(ewmh)
+    }
+
+    fn atom(&self, name: &str) -> Result<u32, String> {
+        self.conn
+            .intern_atom(false, name.as_bytes())
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map(|reply| reply.atom)
+            .map_err(|e| format!("Failed to look up {}: {}", name, e))
+    }
+
+    /// A 32-bit property as a list, empty when it isn't set
+    fn get_u32s(&self, window: Window, property: &str) -> Result<Vec<u32>, String> {
+        let atom = self.atom(property)?;
+        let reply = self
+            .conn
+            .get_property(false, window, atom, AtomEnum::ANY, 0, u32::MAX / 4)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("Failed to read {}: {}", property, e))?;
+        Ok(reply.value32().map(|values| values.collect()).unwrap_or_default())
+    }
+
+    fn get_u32(&self, window: Window, property: &str) -> Result<Option<u32>, String> {
+        Ok(self.get_u32s(window, property)?.first().copied())
+    }
+
+    fn get_bytes(&self, window: Window, property: &str) -> Result<Vec<u8>, String> {
+        let atom = self.atom(property)?;
+        self.conn
+            .get_property(false, window, atom, AtomEnum::ANY, 0, u32::MAX / 4)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map(|reply| reply.value)
+            .map_err(|e| format!("Failed to read {}: {}", property, e))
+    }
+
+    /// `_NET_WM_NAME` (UTF-8), falling back to the legacy WM_NAME
+    fn window_title(&self, window: Window) -> Result<String, String> {
+        let mut title = self.get_bytes(window, "_NET_WM_NAME")?;
+        if title.is_empty() {
+            title = self.get_bytes(window, "WM_NAME")?;
+        }
+        Ok(String::from_utf8_lossy(&title).trim().to_string())
+    }
+
+    /// The class part of WM_CLASS ("Slack", "firefox"), or the instance name
+    fn window_class(&self, window: Window) -> Result<String, String> {
+        let class = self.get_bytes(window, "WM_CLASS")?;
+        let parts: Vec<String> = class
+            .split(|b| *b == 0)
+            .filter(|part| !part.is_empty())
+            .map(|part| String::from_utf8_lossy(part).to_string())
+            .collect();
+        Ok(parts.get(1).or(parts.first()).cloned().unwrap_or_default())
+    }
+
+    fn send_message(&self, window: Window, message: &str, data: [u32; 5]) -> Result<(), String> {
+        let event = ClientMessageEvent::new(32, window, self.atom(message)?, data);
+        self.conn
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .map_err(|e| format!("Failed to send {}: {}", message, e))?;
+        self.conn.flush().map_err(|e| e.to_string())
+    }
+
+    /// Fail unless the window manager lists `hint` in `_NET_SUPPORTED`
+    fn require(&self, hint: &str) -> Result<(), String> {
+        let atom = self.atom(hint)?;
+        if self.get_u32s(self.root, "_NET_SUPPORTED")?.contains(&atom) {
+            Ok(())
+        } else {
+            Err(format!("The window manager does not support {}", hint))
+        }
+    }
+
+    pub fn check_window_manager(&self) -> Result<(), String> {
+        match self.get_u32(self.root, "_NET_SUPPORTING_WM_CHECK")? {
+            Some(window) if window != 0 => Ok(()),
+            _ => Err("No EWMH-compatible window manager is running".to_string()),
+        }
+    }
+
+    fn active_window(&self) -> Result<Window, String> {
+        self.require("_NET_ACTIVE_WINDOW")?;
+        match self.get_u32(self.root, "_NET_ACTIVE_WINDOW")? {
+            Some(window) if window != 0 => Ok(window),
+            _ => Err("No window is focused".to_string()),
+        }
+    }
+
+    fn current_desktop(&self) -> Result<u32, String> {
+        Ok(self.get_u32(self.root, "_NET_CURRENT_DESKTOP")?.unwrap_or(0))
+    }
+
+    fn set_maximized(&self, window: Window, maximized: bool) -> Result<(), String> {
+        self.require("_NET_WM_STATE")?;
+        let vert = self.atom("_NET_WM_STATE_MAXIMIZED_VERT")?;
+        let horz = self.atom("_NET_WM_STATE_MAXIMIZED_HORZ")?;
+        let mode = if maximized { STATE_ADD } else { STATE_REMOVE };
+        self.send_message(window, "_NET_WM_STATE", [mode, vert, horz, SOURCE_PAGER, 0])
+    }
+
+    /// Application windows, topmost first, skipping docks, panels and the like
+    fn client_windows(&self) -> Result<Vec<Window>, String> {
+        let mut windows = self.get_u32s(self.root, "_NET_CLIENT_LIST_STACKING")?;
+        if windows.is_empty() {
+            self.require("_NET_CLIENT_LIST")?;
+            windows = self.get_u32s(self.root, "_NET_CLIENT_LIST")?;
+        }
+        windows.reverse();
+
+        let normal = self.atom("_NET_WM_WINDOW_TYPE_NORMAL")?;
+        let mut clients = Vec::new();
+        for window in windows {
+            // The client list can name windows that closed a moment ago
+            match self.get_u32s(window, "_NET_WM_WINDOW_TYPE") {
+                Ok(types) if types.is_empty() || types.contains(&normal) => clients.push(window),
+                Ok(_) => {}
+                Err(e) => log::debug!("Skipping window 0x{:08x}: {}", window, e),
+            }
+        }
+        Ok(clients)
+    }
+
+    /// Activate the topmost normal window below the focused one
+    fn switch_window(&self) -> Result<String, String> {
+        let active = self.get_u32(self.root, "_NET_ACTIVE_WINDOW")?.unwrap_or(0);
+        let desktop = self.current_desktop()?;
+
+        for window in self.client_windows()?.into_iter().filter(|&window| window != active) {
+            // 0xFFFFFFFF means the window is on all desktops
+            match self.get_u32(window, "_NET_WM_DESKTOP")? {
+                Some(on) if on != desktop && on != u32::MAX => continue,
+                _ => {}
+            }
+            self.activate(window)?;
+            return Ok("Switched to the next window".to_string());
+        }
+        Err("There is no other window to switch to".to_string())
+    }
+
+    fn activate(&self, window: Window) -> Result<(), String> {
+        let active = self.get_u32(self.root, "_NET_ACTIVE_WINDOW")?.unwrap_or(0);
+        self.send_message(window, "_NET_ACTIVE_WINDOW", [SOURCE_PAGER, 0, active, 0, 0])
+    }
+
+    /// Move and optionally resize a window, through the window manager when
+    /// it supports that
+    fn place(&self, window: Window, x: i32, y: i32, size: Option<(u32, u32)>) -> Result<(), String> {
+        if self.require("_NET_MOVERESIZE_WINDOW").is_ok() {
+            // Gravity from the window, x/y (and width/height) present, pager source
+            let mut flags = (1 << 8) | (1 << 9) | (SOURCE_PAGER << 12);
+            if size.is_some() {
+                flags |= (1 << 10) | (1 << 11);
+            }
+            let (width, height) = size.unwrap_or((0, 0));
+            return self.send_message(
+                window,
+                "_NET_MOVERESIZE_WINDOW",
+                [flags, x as u32, y as u32, width, height],
+            );
+        }
+
+        let mut geometry = ConfigureWindowAux::new().x(x).y(y);
+        if let Some((width, height)) = size {
+            geometry = geometry.width(width).height(height);
+        }
+        self.conn
+            .configure_window(window, &geometry)
+            .map_err(|e| format!("Failed to move the window: {}", e))?;
+        self.conn.flush().map_err(|e| e.to_string())
+    }
+
+    /// The work area of the current desktop, or the whole screen
+    fn workarea(&self) -> Result<(u32, u32, u32, u32), String> {
+        let desktop = self.current_desktop()? as usize;
+        let workarea = self.get_u32s(self.root, "_NET_WORKAREA")?;
+        Ok(match workarea.chunks_exact(4).nth(desktop).or(workarea.chunks_exact(4).next()) {
+            Some(area) => (area[0], area[1], area[2], area[3]),
+            None => {
+                let screen = &self.conn.setup().roots[self.screen];
+                (0, 0, screen.width_in_pixels as u32, screen.height_in_pixels as u32)
+            }
+        })
+    }
+
+    /// Frame position and client size of a window in root coordinates, the
+    /// same terms `_NET_MOVERESIZE_WINDOW` uses
+    fn geometry(&self, window: Window) -> Result<Rect, String> {
+        let size = self
+            .conn
+            .get_geometry(window)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("Failed to read window geometry: {}", e))?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("Failed to read window position: {}", e))?;
+        // Left, right, top and bottom decoration sizes
+        let extents = self.get_u32s(window, "_NET_FRAME_EXTENTS")?;
+        let (left, top) = match extents.as_slice() {
+            [left, _, top, _] => (*left as i32, *top as i32),
+            _ => (0, 0),
+        };
+        Ok((
+            origin.dst_x as i32 - left,
+            origin.dst_y as i32 - top,
+            size.width as u32,
+            size.height as u32,
+        ))
+    }
+
+    /// RandR monitors, left to right
+    fn monitors(&self) -> Result<Vec<Rect>, String> {
+        let reply = self
+            .conn
+            .randr_get_monitors(self.root, true)
+            .map_err(|e| format!("RandR is not available: {}", e))?
+            .reply()
+            .map_err(|e| format!("Failed to list monitors: {}", e))?;
+        let mut monitors: Vec<Rect> = reply
+            .monitors
+            .iter()
+            .map(|m| (m.x as i32, m.y as i32, m.width as u32, m.height as u32))
+            .collect();
+        monitors.sort_by_key(|&(x, y, _, _)| (x, y));
+        Ok(monitors)
+    }
+
+    fn is_maximized(&self, window: Window) -> Result<bool, String> {
+        let state = self.get_u32s(window, "_NET_WM_STATE")?;
+        Ok(state.contains(&self.atom("_NET_WM_STATE_MAXIMIZED_VERT")?)
+            || state.contains(&self.atom("_NET_WM_STATE_MAXIMIZED_HORZ")?))
+    }
+
+    fn window_id(window: &WindowInfo) -> Result<Window, String> {
+        u32::from_str_radix(window.id.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("Invalid X11 window ID {}", window.id))
+    }
+
+    fn snap(&self, right: bool) -> Result<String, String> {
+        let window = self.active_window()?;
+        self.snap_window(window, right)?;
+        Ok(format!("Snapped the window {}", if right { "right" } else { "left" }))
+    }
+
+    fn snap_window(&self, window: Window, right: bool) -> Result<(), String> {
+        self.set_maximized(window, false)?;
+        let (x, y, width, height) = snap_geometry(self.workarea()?, right);
+        self.place(window, x as i32, y as i32, Some((width, height)))
+    }
+
+    fn window_info(&self, window: Window, active: Window) -> Result<WindowInfo, String> {
+        let workspace = match self.get_u32(window, "_NET_WM_DESKTOP")? {
+            Some(u32::MAX) => Some("all".to_string()),
+            Some(desktop) => Some((desktop + 1).to_string()),
+            None => None,
+        };
+        Ok(WindowInfo {
+            id: format!("0x{:08x}", window),
+            app: self.window_class(window)?,
+            title: self.window_title(window)?,
+            workspace,
+            monitor: None,
+            focused: window == active,
+            geometry: self.geometry(window).ok().map(|(x, y, width, height)| WindowGeometry {
+                x,
+                y,
+                width,
+                height,
+            }),
+            pid: self.get_u32(window, "_NET_WM_PID")?,
+        })
+    }
+
+    fn change_desktop(&self, forward: bool) -> Result<String, String> {
+        self.require("_NET_CURRENT_DESKTOP")?;
+        let count = self.get_u32(self.root, "_NET_NUMBER_OF_DESKTOPS")?.unwrap_or(1);
+        let target = adjacent_desktop(self.current_desktop()?, count, forward)?;
+        self.send_message(self.root, "_NET_CURRENT_DESKTOP", [target, 0, 0, 0, 0])?;
+        Ok(format!("Switched to desktop {}", target + 1))
+    }
+}
+
+impl WindowManager for Ewmh {
+    fn perform(&self, action: WindowAction) -> Result<String, String> {
+        match action {
+            WindowAction::Minimize => {
+                let window = self.active_window()?;
+                self.send_message(window, "WM_CHANGE_STATE", [ICONIC_STATE, 0, 0, 0, 0])?;
+                Ok("Minimized the window".to_string())
+            }
+            WindowAction::Maximize => {
+                let window = self.active_window()?;
+                self.set_maximized(window, true)?;
+                Ok("Maximized the window".to_string())
+            }
+            WindowAction::Restore => {
+                let window = self.active_window()?;
+                self.set_maximized(window, false)?;
+                Ok("Restored the window".to_string())
+            }
+            WindowAction::Close => {
+                self.require("_NET_CLOSE_WINDOW")?;
+                let window = self.active_window()?;
+                self.send_message(window, "_NET_CLOSE_WINDOW", [0, SOURCE_PAGER, 0, 0, 0])?;
+                Ok("Closed the window".to_string())
+            }
+            WindowAction::Switch => self.switch_window(),
+            WindowAction::SnapLeft => self.snap(false),
+            WindowAction::SnapRight => self.snap(true),
+            WindowAction::ShowDesktop => {
+                self.require("_NET_SHOWING_DESKTOP")?;
+                let showing = self.get_u32(self.root, "_NET_SHOWING_DESKTOP")?.unwrap_or(0) != 0;
+                self.send_message(self.root, "_NET_SHOWING_DESKTOP", [u32::from(!showing), 0, 0, 0, 0])?;
+                Ok(if showing { "Showing windows again" } else { "Showing the desktop" }.to_string())
+            }
+            WindowAction::NextDesktop => self.change_desktop(true),
+            WindowAction::PreviousDesktop => self.change_desktop(false),
+            WindowAction::TaskView => Err(
+                "Task view is not part of EWMH; use your desktop's overview shortcut".to_string(),
+            ),
+        }
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        let active = self.get_u32(self.root, "_NET_ACTIVE_WINDOW")?.unwrap_or(0);
+        let mut windows = Vec::new();
+        for window in self.client_windows()? {
+            // A window that closes while we read it is left out, not an error
+            match self.window_info(window, active) {
+                Ok(info) => windows.push(info),
+                Err(e) => log::debug!("Skipping window 0x{:08x}: {}", window, e),
+            }
+        }
+        Ok(windows)
+    }
+
+    fn focus(&self, window: &WindowInfo) -> Result<(), String> {
+        self.require("_NET_ACTIVE_WINDOW")?;
+        self.activate(Self::window_id(window)?)
+    }
+
+    fn close(&self, window: &WindowInfo) -> Result<(), String> {
+        self.require("_NET_CLOSE_WINDOW")?;
+        self.send_message(Self::window_id(window)?, "_NET_CLOSE_WINDOW", [0, SOURCE_PAGER, 0, 0, 0])
+    }
+
+    fn move_to_workspace(&self, window: &WindowInfo, workspace: u32) -> Result<(), String> {
+        self.require("_NET_WM_DESKTOP")?;
+        let count = self.get_u32(self.root, "_NET_NUMBER_OF_DESKTOPS")?.unwrap_or(1);
+        if workspace == 0 || workspace > count {
+            return Err(format!("There is no workspace {} (there are {})", workspace, count));
+        }
+        self.send_message(Self::window_id(window)?, "_NET_WM_DESKTOP", [workspace - 1, SOURCE_PAGER, 0, 0, 0])
+    }
+
+    fn move_to_monitor(&self, window: &WindowInfo, monitor: MonitorTarget) -> Result<(), String> {
+        let id = Self::window_id(window)?;
+        let monitors = self.monitors()?;
+        let (x, y, width, height) = self.geometry(id)?;
+        let current = monitor_containing(&monitors, (x + width as i32 / 2, y + height as i32 / 2));
+        let target = pick_monitor(&monitors, current, monitor)?;
+
+        // Keep the same offset from the monitor's corner, but stay on screen
+        let (from_x, from_y, _, _) = monitors[current];
+        let (to_x, to_y, to_width, to_height) = monitors[target];
+        let new_x = (to_x + (x - from_x)).clamp(to_x, to_x + to_width.saturating_sub(width) as i32);
+        let new_y = (to_y + (y - from_y)).clamp(to_y, to_y + to_height.saturating_sub(height) as i32);
+
+        // Maximized windows have to be released before they can move
+        let maximized = self.is_maximized(id)?;
+        if maximized {
+            self.set_maximized(id, false)?;
+        }
+        self.place(id, new_x, new_y, None)?;
+        if maximized {
+            self.set_maximized(id, true)?;
+        }
+        Ok(())
+    }
+
+    fn tile(&self, left: &WindowInfo, right: &WindowInfo) -> Result<(), String> {
+        let desktop = self.current_desktop()?;
+        for (window, on_right) in [(left, false), (right, true)] {
+            let id = Self::window_id(window)?;
+            if self.require("_NET_WM_DESKTOP").is_ok() {
+                self.send_message(id, "_NET_WM_DESKTOP", [desktop, SOURCE_PAGER, 0, 0, 0])?;
+            }
+            self.snap_window(id, on_right)?;
+            self.activate(id)?;
+        }
+        Ok(())
+    }
+
+    fn arrange(&self, window: &WindowInfo, slot: &LayoutSlot) -> Result<(), String> {
+        let id = Self::window_id(window)?;
+        let desktop = slot.workspace.as_deref().and_then(|workspace| workspace.parse::<u32>().ok());
+        if let Some(desktop) = desktop {
+            if window.workspace != slot.workspace {
+                self.move_to_workspace(window, desktop)?;
+            }
+        }
+        if let Some(geometry) = slot.geometry {
+            if self.is_maximized(id)? {
+                self.set_maximized(id, false)?;
+            }
+            self.place(id, geometry.x, geometry.y, Some((geometry.width, geometry.height)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Left or right half of the work area `(x, y, width, height)`
+fn snap_geometry((x, y, width, height): (u32, u32, u32, u32), right: bool) -> (u32, u32, u32, u32) {
+    let half = width / 2;
+    if right {
+        (x + half, y, width - half, height)
+    } else {
+        (x, y, half, height)
+    }
+}
+
+/// Index of the monitor that contains `point`, or the first one
+fn monitor_containing(monitors: &[Rect], (px, py): (i32, i32)) -> usize {
+    monitors
+        .iter()
+        .position(|&(x, y, width, height)| {
+            px >= x && px < x + width as i32 && py >= y && py < y + height as i32
+        })
+        .unwrap_or(0)
+}
+
+/// Resolve a monitor target against monitors sorted left to right
+fn pick_monitor(monitors: &[Rect], current: usize, target: MonitorTarget) -> Result<usize, String> {
+    if monitors.len() < 2 {
+        return Err("Only one monitor is connected".to_string());
+    }
+    match target {
+        MonitorTarget::Next => Ok((current + 1) % monitors.len()),
+        MonitorTarget::Left => current
+            .checked_sub(1)
+            .ok_or_else(|| "There is no monitor to the left".to_string()),
+        MonitorTarget::Right if current + 1 < monitors.len() => Ok(current + 1),
+        MonitorTarget::Right => Err("There is no monitor to the right".to_string()),
+        MonitorTarget::Number(n) if (n as usize) <= monitors.len() => Ok(n as usize - 1),
+        MonitorTarget::Number(n) => Err(format!("There is no monitor {} (there are {})", n, monitors.len())),
+    }
+}
+
+/// The desktop next to `current`, without wrapping around
+fn adjacent_desktop(current: u32, count: u32, forward: bool) -> Result<u32, String> {
+    if forward {
+        if current + 1 >= count {
+            return Err("Already on the last desktop".to_string());
+        }
+        Ok(current + 1)
+    } else {
+        current.checked_sub(1).ok_or_else(|| "Already on the first desktop".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+    use x11rb::wrapper::ConnectionExt as _;
+    use x11rb::protocol::xproto::PropMode;
+
+    #[test]
+    fn computes_snap_halves_and_desktops() {
+        assert_eq!(snap_geometry((0, 32, 1921, 1048), false), (0, 32, 960, 1048));
+        assert_eq!(snap_geometry((0, 32, 1921, 1048), true), (960, 32, 961, 1048));
+        assert_eq!(adjacent_desktop(0, 4, true), Ok(1));
+        assert!(adjacent_desktop(3, 4, true).is_err());
+        assert!(adjacent_desktop(0, 4, false).is_err());
+    }
+
+    #[test]
+    fn picks_target_monitor() {
+        let monitors = [(0, 0, 1920, 1080), (1920, 0, 2560, 1440), (4480, 0, 1920, 1080)];
+        assert_eq!(monitor_containing(&monitors, (2000, 500)), 1);
+        assert_eq!(pick_monitor(&monitors, 2, MonitorTarget::Next), Ok(0));
+        assert_eq!(pick_monitor(&monitors, 1, MonitorTarget::Left), Ok(0));
+        assert!(pick_monitor(&monitors, 2, MonitorTarget::Right).is_err());
+        assert_eq!(pick_monitor(&monitors, 0, MonitorTarget::Number(3)), Ok(2));
+        assert!(pick_monitor(&monitors[..1], 0, MonitorTarget::Next).is_err());
+    }
+
+    struct Xvfb(Child);
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// A display number no running server holds a lock or socket for
+    fn free_display() -> String {
+        (100..1000)
+            .find(|n| {
+                !std::path::Path::new(&format!("/tmp/.X{}-lock", n)).exists()
+                    && !std::path::Path::new(&format!("/tmp/.X11-unix/X{}", n)).exists()
+            })
+            .map(|n| format!(":{}", n))
+            .expect("no free X display number")
+    }
+
+    /// Start a private Xvfb server on a free display
+    fn start_xvfb() -> (Xvfb, Ewmh) {
+        let display = free_display();
+        let child = Command::new("Xvfb")
+            .args([display.as_str(), "-screen", "0", "1280x800x24", "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start Xvfb");
+        let server = Xvfb(child);
+        for _ in 0..50 {
+            if let Ok(ewmh) = Ewmh::connect(Some(&display)) {
+                return (server, ewmh);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        panic!("Xvfb did not come up on {}", display);
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb; run with --ignored"]
+    fn reports_errors_against_nested_x_server() {
+        let (_server, ewmh) = start_xvfb();
+
+        // A bare server has no window manager to act on requests
+        let error = ewmh.check_window_manager().unwrap_err();
+        assert!(error.contains("No EWMH-compatible window manager"), "{}", error);
+
+        // Pretend to be a window manager with two desktops and nothing focused
+        let set = |property: &str, values: &[u32]| {
+            let atom = ewmh.atom(property).unwrap();
+            ewmh.conn
+                .change_property32(PropMode::REPLACE, ewmh.root, atom, AtomEnum::CARDINAL, values)
+                .unwrap();
+        };
+        let supported: Vec<u32> = ["_NET_ACTIVE_WINDOW", "_NET_CURRENT_DESKTOP", "_NET_CLIENT_LIST"]
+            .iter()
+            .map(|name| ewmh.atom(name).unwrap())
+            .collect();
+        set("_NET_SUPPORTING_WM_CHECK", &[ewmh.root]);
+        set("_NET_SUPPORTED", &supported);
+        set("_NET_NUMBER_OF_DESKTOPS", &[2]);
+        set("_NET_CURRENT_DESKTOP", &[1]);
+        set("_NET_ACTIVE_WINDOW", &[0]);
+        // A client that is already gone must not break the window list
+        set("_NET_CLIENT_LIST", &[0x00ab_cdef]);
+        ewmh.conn.flush().unwrap();
+        assert!(ewmh.check_window_manager().is_ok());
+
+        assert_eq!(ewmh.perform(WindowAction::Minimize).unwrap_err(), "No window is focused");
+        assert_eq!(ewmh.perform(WindowAction::NextDesktop).unwrap_err(), "Already on the last desktop");
+        assert_eq!(ewmh.perform(WindowAction::PreviousDesktop).unwrap(), "Switched to desktop 1");
+        assert!(ewmh.perform(WindowAction::ShowDesktop).unwrap_err().contains("_NET_SHOWING_DESKTOP"));
+        assert!(ewmh.perform(WindowAction::TaskView).is_err());
+        assert!(ewmh.list_windows().unwrap().is_empty());
+        let error = ewmh.run(&crate::window::WindowCommand::Focus("slack".to_string())).unwrap_err();
+        assert!(error.contains("No open window matches"), "{}", error);
+    }
+}
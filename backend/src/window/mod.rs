@@ -0,0 +1,566 @@
+//! Native window management for WindowControl
+//!
+//! Windows and macOS drive the window manager through its keyboard
+//! shortcuts. On Linux there is no common shortcut set, so actions go
+//! through EWMH on X11 or the compositor's IPC on Wayland, and anything the
+//! session can't do is reported as an error instead of a silent success.
+//! Windows can also be picked by a spoken app name or title ("switch to
+//! Slack"), which needs a window list and so is Linux-only for now.
+
+// Only the payload parsing is used where there is no window list
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+#[cfg(target_os = "linux")]
+mod ewmh;
+#[cfg(target_os = "linux")]
+mod sway;
+
+use serde::{Deserialize, Serialize};
+
+/// A window action understood by WindowControl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowAction {
+    Minimize,
+    Maximize,
+    Restore,
+    Close,
+    Switch,
+    SnapLeft,
+    SnapRight,
+    ShowDesktop,
+    NextDesktop,
+    PreviousDesktop,
+    TaskView,
+}
+
+impl WindowAction {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().as_str() {
+            "minimize" => Ok(Self::Minimize),
+            "maximize" => Ok(Self::Maximize),
+            "restore" => Ok(Self::Restore),
+            "close" => Ok(Self::Close),
+            "switch" => Ok(Self::Switch),
+            "snap_left" => Ok(Self::SnapLeft),
+            "snap_right" => Ok(Self::SnapRight),
+            "show_desktop" => Ok(Self::ShowDesktop),
+            "next_desktop" => Ok(Self::NextDesktop),
+            "previous_desktop" => Ok(Self::PreviousDesktop),
+            "task_view" => Ok(Self::TaskView),
+            _ => Err(format!("Unknown window action: {}", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Minimize => "minimize",
+            Self::Maximize => "maximize",
+            Self::Restore => "restore",
+            Self::Close => "close",
+            Self::Switch => "switch",
+            Self::SnapLeft => "snap_left",
+            Self::SnapRight => "snap_right",
+            Self::ShowDesktop => "show_desktop",
+            Self::NextDesktop => "next_desktop",
+            Self::PreviousDesktop => "previous_desktop",
+            Self::TaskView => "task_view",
+        }
+    }
+}
+
+/// Outer position and client size of a window, in screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An open window as reported by the window manager
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowInfo {
+    /// Backend-specific handle (X11 window ID or sway container ID)
+    pub id: String,
+    pub app: String,
+    pub title: String,
+    pub workspace: Option<String>,
+    pub monitor: Option<String>,
+    pub focused: bool,
+    /// None where the window manager decides the size (tiled sway windows)
+    pub geometry: Option<WindowGeometry>,
+    pub pid: Option<u32>,
+}
+
+/// Where one window sits in a saved layout
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutSlot {
+    pub app: String,
+    pub title: String,
+    /// What to launch through OpenApp when no window of `app` is open
+    pub launch: String,
+    /// Desktop file ID of the app that opened the window, relaunched
+    /// directly when set
+    #[serde(default)]
+    pub desktop_id: Option<String>,
+    pub workspace: Option<String>,
+    pub monitor: Option<String>,
+    pub geometry: Option<WindowGeometry>,
+}
+
+impl LayoutSlot {
+    /// Record a window along with the installed app that opened it. The
+    /// process behind a window is often a shared server or sandbox helper
+    /// (gnome-terminal-server, bwrap), so the app is looked up from the
+    /// window's app_id or WM_CLASS instead. Reads the app index, so call it
+    /// from a blocking thread.
+    pub fn capture(window: &WindowInfo) -> Self {
+        #[cfg(target_os = "linux")]
+        let entry = crate::apps::find_for_window(&window.app).map(|entry| (entry.id, entry.name));
+        #[cfg(not(target_os = "linux"))]
+        let entry = None;
+        Self::with_entry(window, entry)
+    }
+
+    /// `entry` is the desktop file ID and name of the app behind the window
+    fn with_entry(window: &WindowInfo, entry: Option<(String, String)>) -> Self {
+        let (desktop_id, launch) = match entry {
+            Some((id, name)) => (Some(id), name),
+            None => (None, window.app.to_lowercase()),
+        };
+        Self {
+            app: window.app.clone(),
+            title: window.title.clone(),
+            launch,
+            desktop_id,
+            workspace: window.workspace.clone(),
+            monitor: window.monitor.clone(),
+            geometry: window.geometry,
+        }
+    }
+}
+
+/// Pair each slot with an open window of the same app, preferring one with
+/// the same title. Every window is used at most once.
+pub fn match_slots<'a>(slots: &[LayoutSlot], windows: &'a [WindowInfo]) -> Vec<Option<&'a WindowInfo>> {
+    let mut matched: Vec<Option<&WindowInfo>> = vec![None; slots.len()];
+    let mut used = vec![false; windows.len()];
+
+    for same_title in [true, false] {
+        for (slot, found) in slots.iter().zip(matched.iter_mut()) {
+            if found.is_some() {
+                continue;
+            }
+            let candidate = windows.iter().enumerate().find(|(i, window)| {
+                !used[*i]
+                    && window.app.eq_ignore_ascii_case(&slot.app)
+                    && (!same_title || window.title == slot.title)
+            });
+            if let Some((i, window)) = candidate {
+                used[i] = true;
+                *found = Some(window);
+            }
+        }
+    }
+    matched
+}
+
+/// Which monitor to move a window to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorTarget {
+    Next,
+    Left,
+    Right,
+    /// 1-based, counted from the left
+    Number(u32),
+}
+
+impl MonitorTarget {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "next" | "other" => Ok(Self::Next),
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            other => other
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(Self::Number)
+                .ok_or_else(|| format!("Unknown monitor: {}", value)),
+        }
+    }
+}
+
+/// A WindowControl request, read from the action payload
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowCommand {
+    /// Act on the focused window or the desktop
+    Active(WindowAction),
+    List,
+    Focus(String),
+    Close(String),
+    /// Workspaces are 1-based; no target means the focused window
+    MoveToWorkspace { target: Option<String>, workspace: u32 },
+    MoveToMonitor { target: Option<String>, monitor: MonitorTarget },
+    /// Put the first window on the left half and the second on the right
+    Tile(String, String),
+}
+
+impl WindowCommand {
+    pub fn from_payload(payload: &serde_json::Value) -> Result<Self, String> {
+        let field = |name: &str| {
+            payload
+                .get(name)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let action = field("action").ok_or("No window action specified")?;
+        let target = field("target");
+
+        match action.as_str() {
+            "list" => Ok(Self::List),
+            "focus" => target.map(Self::Focus).ok_or_else(|| "Which window should I focus?".to_string()),
+            "close" => Ok(target.map(Self::Close).unwrap_or(Self::Active(WindowAction::Close))),
+            "move_to_workspace" => {
+                let workspace = payload
+                    .get("workspace")
+                    .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.trim().parse().ok())))
+                    .filter(|n| *n > 0)
+                    .ok_or("Which workspace should the window move to?")?;
+                Ok(Self::MoveToWorkspace { target, workspace: workspace as u32 })
+            }
+            "move_to_monitor" => {
+                let monitor = match payload.get("monitor") {
+                    Some(serde_json::Value::Number(n)) => MonitorTarget::parse(&n.to_string())?,
+                    Some(serde_json::Value::String(s)) => MonitorTarget::parse(s)?,
+                    _ => MonitorTarget::Next,
+                };
+                Ok(Self::MoveToMonitor { target, monitor })
+            }
+            "tile" => {
+                let targets: Vec<String> = payload
+                    .get("targets")
+                    .and_then(|v| v.as_array())
+                    .map(|items| {
+                        items
+                            .iter()
+                            .filter_map(|item| item.as_str())
+                            .map(|item| item.trim().to_string())
+                            .filter(|item| !item.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                match targets.as_slice() {
+                    [left, right] => Ok(Self::Tile(left.clone(), right.clone())),
+                    _ => Err("Tiling needs exactly two windows".to_string()),
+                }
+            }
+            other => WindowAction::parse(other).map(Self::Active),
+        }
+    }
+}
+
+/// What a window command did; `windows` is set for List
+#[derive(Debug, Clone)]
+pub struct WindowOutcome {
+    pub message: String,
+    pub windows: Option<Vec<WindowInfo>>,
+}
+
+impl WindowOutcome {
+    fn message(message: impl Into<String>) -> Self {
+        Self { message: message.into(), windows: None }
+    }
+}
+
+/// A window manager that can act on the focused window and on windows
+/// picked from its window list
+pub trait WindowManager {
+    /// Act on the focused window or the desktop and describe what happened
+    fn perform(&self, action: WindowAction) -> Result<String, String>;
+    /// Normal application windows, topmost first
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String>;
+    fn focus(&self, window: &WindowInfo) -> Result<(), String>;
+    fn close(&self, window: &WindowInfo) -> Result<(), String>;
+    fn move_to_workspace(&self, window: &WindowInfo, workspace: u32) -> Result<(), String>;
+    fn move_to_monitor(&self, window: &WindowInfo, monitor: MonitorTarget) -> Result<(), String>;
+    fn tile(&self, left: &WindowInfo, right: &WindowInfo) -> Result<(), String>;
+    /// Move a window to the workspace and geometry saved in `slot`
+    fn arrange(&self, window: &WindowInfo, slot: &LayoutSlot) -> Result<(), String>;
+
+    fn run(&self, command: &WindowCommand) -> Result<WindowOutcome, String> {
+        match command {
+            WindowCommand::Active(action) => self.perform(*action).map(WindowOutcome::message),
+            WindowCommand::List => {
+                let windows = self.list_windows()?;
+                let message = if windows.is_empty() {
+                    "No windows are open".to_string()
+                } else {
+                    let names: Vec<String> = windows.iter().map(describe).collect();
+                    format!("{} windows open: {}", windows.len(), names.join(", "))
+                };
+                Ok(WindowOutcome { message, windows: Some(windows) })
+            }
+            WindowCommand::Focus(query) => {
+                let windows = self.list_windows()?;
+                let window = find_window(&windows, query)?;
+                self.focus(window)?;
+                Ok(WindowOutcome::message(format!("Switched to {}", describe(window))))
+            }
+            WindowCommand::Close(query) => {
+                let windows = self.list_windows()?;
+                let window = find_window_to_close(&windows, query)?;
+                self.close(window)?;
+                Ok(WindowOutcome::message(format!("Closed {}", describe(window))))
+            }
+            WindowCommand::MoveToWorkspace { target, workspace } => {
+                let windows = self.list_windows()?;
+                let window = target_window(&windows, target.as_deref())?;
+                self.move_to_workspace(window, *workspace)?;
+                Ok(WindowOutcome::message(format!(
+                    "Moved {} to workspace {}",
+                    describe(window),
+                    workspace
+                )))
+            }
+            WindowCommand::MoveToMonitor { target, monitor } => {
+                let windows = self.list_windows()?;
+                let window = target_window(&windows, target.as_deref())?;
+                self.move_to_monitor(window, *monitor)?;
+                Ok(WindowOutcome::message(format!("Moved {} to another monitor", describe(window))))
+            }
+            WindowCommand::Tile(left, right) => {
+                let windows = self.list_windows()?;
+                let left = find_window(&windows, left)?;
+                let right = find_window(&windows, right)?;
+                if left.id == right.id {
+                    return Err(format!("\"{}\" and \"{}\" are the same window", left.title, right.title));
+                }
+                self.tile(left, right)?;
+                Ok(WindowOutcome::message(format!(
+                    "Tiled {} and {} side by side",
+                    describe(left),
+                    describe(right)
+                )))
+            }
+        }
+    }
+}
+
+fn describe(window: &WindowInfo) -> String {
+    if window.title.is_empty() || window.title.eq_ignore_ascii_case(&window.app) {
+        window.app.clone()
+    } else if window.app.is_empty() {
+        window.title.clone()
+    } else {
+        format!("{} ({})", window.app, window.title)
+    }
+}
+
+/// Closing needs at least an app-name substring match
+const CLOSE_MIN_SCORE: u32 = 70;
+
+/// How well `query` names `window`, or 0 when it doesn't
+fn match_score(window: &WindowInfo, query: &str) -> u32 {
+    let app = window.app.to_lowercase();
+    let title = window.title.to_lowercase();
+
+    if app == query {
+        100
+    } else if app.starts_with(query) || (!app.is_empty() && query.starts_with(&app)) {
+        80
+    } else if app.contains(query) {
+        70
+    } else if title.contains(query) {
+        50
+    } else if query.split_whitespace().all(|word| app.contains(word) || title.contains(word)) {
+        40
+    } else if query.chars().count() >= 3 && is_subsequence(query, &app) {
+        20
+    } else {
+        0
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|c| haystack.any(|h| h == c))
+}
+
+/// The best match for a spoken app name or title. Ties go to the window
+/// higher in the stacking order.
+pub fn find_window<'a>(windows: &'a [WindowInfo], query: &str) -> Result<&'a WindowInfo, String> {
+    let query = query.trim().to_lowercase();
+    let mut best: Option<(&WindowInfo, u32)> = None;
+    for window in windows {
+        let score = match_score(window, &query);
+        if score > 0 && best.map_or(true, |(_, top)| score > top) {
+            best = Some((window, score));
+        }
+    }
+    best.map(|(window, _)| window)
+        .ok_or_else(|| format!("No open window matches \"{}\"", query))
+}
+
+/// Like `find_window`, but a title-only or fuzzy match is refused so a
+/// misheard name never closes the wrong window
+fn find_window_to_close<'a>(windows: &'a [WindowInfo], query: &str) -> Result<&'a WindowInfo, String> {
+    let window = find_window(windows, query)?;
+    if match_score(window, &query.trim().to_lowercase()) < CLOSE_MIN_SCORE {
+        return Err(format!(
+            "\"{}\" only loosely matches {}; say the app's name to close it",
+            query.trim(),
+            describe(window)
+        ));
+    }
+    Ok(window)
+}
+
+/// The named window, or the focused one when no name was given
+fn target_window<'a>(windows: &'a [WindowInfo], query: Option<&str>) -> Result<&'a WindowInfo, String> {
+    match query {
+        Some(query) => find_window(windows, query),
+        None => windows
+            .iter()
+            .find(|window| window.focused)
+            .ok_or_else(|| "No window is focused".to_string()),
+    }
+}
+
+/// The window manager for the current session. Blocks on the display
+/// connection, so call it from a blocking thread.
+#[cfg(target_os = "linux")]
+pub fn connect() -> Result<Box<dyn WindowManager>, String> {
+    let env_set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+
+    if env_set("SWAYSOCK") || env_set("I3SOCK") {
+        Ok(Box::new(sway::Sway::from_env()?))
+    } else if env_set("WAYLAND_DISPLAY") {
+        Err("Window control is not supported on this Wayland compositor".to_string())
+    } else if env_set("DISPLAY") {
+        Ok(Box::new(ewmh::Ewmh::open(None)?))
+    } else {
+        Err("No graphical session found (DISPLAY and WAYLAND_DISPLAY are unset)".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: &str, app: &str, title: &str, focused: bool) -> WindowInfo {
+        WindowInfo {
+            id: id.to_string(),
+            app: app.to_string(),
+            title: title.to_string(),
+            workspace: None,
+            monitor: None,
+            focused,
+            geometry: None,
+            pid: None,
+        }
+    }
+
+    #[test]
+    fn finds_windows_by_app_or_title() {
+        let windows = vec![
+            window("1", "firefox", "Pull requests - GitHub", true),
+            window("2", "Slack", "general - Acme", false),
+            window("3", "code", "main.rs - listenos", false),
+            window("4", "gnome-terminal-server", "Terminal", false),
+        ];
+
+        assert_eq!(find_window(&windows, "Slack").unwrap().id, "2");
+        assert_eq!(find_window(&windows, "github").unwrap().id, "1");
+        assert_eq!(find_window(&windows, "terminal").unwrap().id, "4");
+        assert_eq!(find_window(&windows, "main listenos").unwrap().id, "3");
+        assert_eq!(find_window(&windows, "frfx").unwrap().id, "1");
+        assert!(find_window(&windows, "spotify").is_err());
+        assert_eq!(find_window_to_close(&windows, "slack").unwrap().id, "2");
+        assert!(find_window_to_close(&windows, "github").is_err());
+        assert!(find_window_to_close(&windows, "frfx").is_err());
+        assert_eq!(target_window(&windows, None).unwrap().id, "1");
+    }
+
+    #[test]
+    fn reads_commands_from_payload() {
+        let parse = |payload: serde_json::Value| WindowCommand::from_payload(&payload);
+
+        assert_eq!(
+            parse(serde_json::json!({"action": "focus", "target": "slack"})),
+            Ok(WindowCommand::Focus("slack".to_string()))
+        );
+        assert_eq!(
+            parse(serde_json::json!({"action": "close"})),
+            Ok(WindowCommand::Active(WindowAction::Close))
+        );
+        assert_eq!(
+            parse(serde_json::json!({"action": "move_to_workspace", "target": "code", "workspace": "3"})),
+            Ok(WindowCommand::MoveToWorkspace { target: Some("code".to_string()), workspace: 3 })
+        );
+        assert_eq!(
+            parse(serde_json::json!({"action": "move_to_monitor", "monitor": 2})),
+            Ok(WindowCommand::MoveToMonitor { target: None, monitor: MonitorTarget::Number(2) })
+        );
+        assert!(parse(serde_json::json!({"action": "tile", "targets": ["slack"]})).is_err());
+        assert!(parse(serde_json::json!({"action": "fly"})).is_err());
+    }
+
+    #[test]
+    fn matches_layout_slots_to_open_windows() {
+        let windows = vec![
+            window("1", "firefox", "Docs", false),
+            window("2", "Code", "main.rs - listenos", true),
+            window("3", "firefox", "Pull requests - GitHub", false),
+        ];
+        let slot = |app: &str, title: &str| LayoutSlot {
+            app: app.to_string(),
+            title: title.to_string(),
+            launch: app.to_lowercase(),
+            desktop_id: None,
+            workspace: None,
+            monitor: None,
+            geometry: None,
+        };
+        let slots = vec![
+            slot("firefox", "Pull requests - GitHub"),
+            slot("code", "old title"),
+            slot("firefox", "Something else"),
+            slot("firefox", "A third one"),
+            slot("Slack", "general"),
+        ];
+
+        let ids: Vec<Option<&str>> = match_slots(&slots, &windows)
+            .into_iter()
+            .map(|window| window.map(|w| w.id.as_str()))
+            .collect();
+        assert_eq!(ids, vec![Some("3"), Some("2"), Some("1"), None, None]);
+    }
+
+    #[test]
+    fn captures_the_app_to_relaunch() {
+        let terminal = window("4", "Gnome-terminal", "Terminal", false);
+        let slot = LayoutSlot::with_entry(
+            &terminal,
+            Some(("org.gnome.Terminal.desktop".to_string(), "Terminal".to_string())),
+        );
+        assert_eq!(slot.desktop_id.as_deref(), Some("org.gnome.Terminal.desktop"));
+        assert_eq!(slot.launch, "Terminal");
+        assert_eq!(slot.app, "Gnome-terminal");
+
+        let unknown = LayoutSlot::with_entry(&window("5", "MyTool", "", false), None);
+        assert_eq!((unknown.desktop_id, unknown.launch.as_str()), (None, "mytool"));
+
+        // Layouts saved before desktop IDs were recorded still load
+        let old: LayoutSlot = serde_json::from_str(
+            r#"{"app":"code","title":"","launch":"code","workspace":null,"monitor":null,"geometry":null}"#,
+        )
+        .unwrap();
+        assert_eq!(old.desktop_id, None);
+    }
+}
@@ -0,0 +1,343 @@
+//! sway and i3 window management over their IPC socket
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use super::{LayoutSlot, MonitorTarget, WindowAction, WindowGeometry, WindowInfo, WindowManager};
+
+const MAGIC: &[u8] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const GET_OUTPUTS: u32 = 3;
+const GET_TREE: u32 = 4;
+
+pub struct Sway {
+    socket: String,
+}
+
+impl Sway {
+    pub fn from_env() -> Result<Self, String> {
+        let socket = std::env::var("SWAYSOCK")
+            .or_else(|_| std::env::var("I3SOCK"))
+            .map_err(|_| "Neither SWAYSOCK nor I3SOCK is set".to_string())?;
+        Ok(Self { socket })
+    }
+
+    /// Send one message and return the JSON reply
+    fn request(&self, kind: u32, payload: &str) -> Result<serde_json::Value, String> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .map_err(|e| format!("Failed to connect to {}: {}", self.socket, e))?;
+        stream.set_read_timeout(Some(Duration::from_secs(2))).ok();
+
+        let mut message = MAGIC.to_vec();
+        message.extend((payload.len() as u32).to_ne_bytes());
+        message.extend(kind.to_ne_bytes());
+        message.extend(payload.as_bytes());
+        stream
+            .write_all(&message)
+            .map_err(|e| format!("Failed to send window command: {}", e))?;
+
+        let mut header = [0u8; 14];
+        stream
+            .read_exact(&mut header)
+            .map_err(|e| format!("No reply from the compositor: {}", e))?;
+        if &header[..6] != MAGIC {
+            return Err("Unexpected reply from the compositor".to_string());
+        }
+        let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        let mut reply = vec![0u8; length];
+        stream
+            .read_exact(&mut reply)
+            .map_err(|e| format!("Failed to read compositor reply: {}", e))?;
+
+        serde_json::from_slice(&reply).map_err(|e| format!("Invalid compositor reply: {}", e))
+    }
+
+    /// Run a command and check every result in the reply
+    fn run_command(&self, command: &str) -> Result<(), String> {
+        let results = self.request(RUN_COMMAND, command)?;
+        for result in results.as_array().into_iter().flatten() {
+            if !result.get("success").and_then(|v| v.as_bool()).unwrap_or(false) {
+                let error = result.get("error").and_then(|v| v.as_str()).unwrap_or("unknown error");
+                return Err(format!("`{}` failed: {}", command, error));
+            }
+        }
+        Ok(())
+    }
+
+    /// Active output names, left to right
+    fn outputs(&self) -> Result<Vec<String>, String> {
+        let outputs = self.request(GET_OUTPUTS, "")?;
+        let mut active: Vec<(i64, String)> = outputs
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|output| output.get("active").and_then(|v| v.as_bool()).unwrap_or(false))
+            .filter_map(|output| {
+                let x = output.pointer("/rect/x").and_then(|v| v.as_i64()).unwrap_or(0);
+                let name = output.get("name").and_then(|v| v.as_str())?;
+                Some((x, name.to_string()))
+            })
+            .collect();
+        active.sort();
+        Ok(active.into_iter().map(|(_, name)| name).collect())
+    }
+}
+
+/// Collect the windows under `node`, remembering the output and workspace
+/// they sit on
+fn collect_windows(
+    node: &serde_json::Value,
+    output: Option<&str>,
+    workspace: Option<&str>,
+    windows: &mut Vec<WindowInfo>,
+) {
+    let name = node.get("name").and_then(|v| v.as_str());
+    let (output, workspace) = match node.get("type").and_then(|v| v.as_str()) {
+        // The scratchpad hangs off a hidden "__i3" output
+        Some("output") if name == Some("__i3") => (None, workspace),
+        Some("output") => (name, workspace),
+        Some("workspace") if name == Some("__i3_scratch") => (output, Some("scratchpad")),
+        Some("workspace") => (output, name),
+        _ => (output, workspace),
+    };
+
+    let children: Vec<&serde_json::Value> = ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node.get(*key).and_then(|v| v.as_array()))
+        .flatten()
+        .collect();
+
+    // Leaf containers that belong to a client are windows
+    let is_window = node.get("pid").is_some_and(|pid| !pid.is_null())
+        || node.get("window").is_some_and(|window| !window.is_null());
+    if children.is_empty() && is_window {
+        // Only floating windows keep a size of their own; tiling decides the rest
+        let geometry = (node.get("type").and_then(|v| v.as_str()) == Some("floating_con"))
+            .then(|| node.get("rect"))
+            .flatten()
+            .and_then(|rect| {
+                Some(WindowGeometry {
+                    x: rect.get("x")?.as_i64()? as i32,
+                    y: rect.get("y")?.as_i64()? as i32,
+                    width: rect.get("width")?.as_u64()? as u32,
+                    height: rect.get("height")?.as_u64()? as u32,
+                })
+            });
+        let app = node
+            .get("app_id")
+            .and_then(|v| v.as_str())
+            .or_else(|| node.pointer("/window_properties/class").and_then(|v| v.as_str()))
+            .unwrap_or_default();
+        windows.push(WindowInfo {
+            id: node.get("id").map(|id| id.to_string()).unwrap_or_default(),
+            app: app.to_string(),
+            title: name.unwrap_or_default().to_string(),
+            workspace: workspace.map(str::to_string),
+            monitor: output.map(str::to_string),
+            focused: node.get("focused").and_then(|v| v.as_bool()).unwrap_or(false),
+            geometry,
+            pid: node.get("pid").and_then(|v| v.as_u64()).map(|pid| pid as u32),
+        });
+    }
+
+    for child in children {
+        collect_windows(child, output, workspace, windows);
+    }
+}
+
+fn criteria(window: &WindowInfo) -> Result<String, String> {
+    window
+        .id
+        .parse::<u64>()
+        .map(|id| format!("[con_id={}]", id))
+        .map_err(|_| format!("Invalid container ID {}", window.id))
+}
+
+impl WindowManager for Sway {
+    fn perform(&self, action: WindowAction) -> Result<String, String> {
+        let (command, message) = match action {
+            // sway has no minimized state; the scratchpad is the closest thing
+            WindowAction::Minimize => ("move scratchpad", "Moved the window to the scratchpad"),
+            WindowAction::Maximize => ("fullscreen enable", "Made the window fullscreen"),
+            WindowAction::Restore => ("fullscreen disable", "Restored the window"),
+            WindowAction::Close => ("kill", "Closed the window"),
+            WindowAction::Switch => ("focus next", "Switched to the next window"),
+            WindowAction::SnapLeft => ("move left", "Moved the window left"),
+            WindowAction::SnapRight => ("move right", "Moved the window right"),
+            WindowAction::NextDesktop => ("workspace next_on_output", "Switched to the next workspace"),
+            WindowAction::PreviousDesktop => ("workspace prev_on_output", "Switched to the previous workspace"),
+            WindowAction::ShowDesktop | WindowAction::TaskView => {
+                return Err(format!("Window {} is not supported on sway or i3", action.name()));
+            }
+        };
+        self.run_command(command)?;
+        Ok(message.to_string())
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>, String> {
+        let tree = self.request(GET_TREE, "")?;
+        let mut windows = Vec::new();
+        collect_windows(&tree, None, None, &mut windows);
+        Ok(windows)
+    }
+
+    fn focus(&self, window: &WindowInfo) -> Result<(), String> {
+        self.run_command(&format!("{} focus", criteria(window)?))
+    }
+
+    fn close(&self, window: &WindowInfo) -> Result<(), String> {
+        self.run_command(&format!("{} kill", criteria(window)?))
+    }
+
+    fn move_to_workspace(&self, window: &WindowInfo, workspace: u32) -> Result<(), String> {
+        self.run_command(&format!(
+            "{} move container to workspace number {}",
+            criteria(window)?,
+            workspace
+        ))
+    }
+
+    fn move_to_monitor(&self, window: &WindowInfo, monitor: MonitorTarget) -> Result<(), String> {
+        let output = match monitor {
+            MonitorTarget::Left => "left".to_string(),
+            MonitorTarget::Right => "right".to_string(),
+            MonitorTarget::Next => {
+                let outputs = self.outputs()?;
+                let current = outputs
+                    .iter()
+                    .position(|name| Some(name.as_str()) == window.monitor.as_deref())
+                    .unwrap_or(0);
+                if outputs.len() < 2 {
+                    return Err("Only one monitor is connected".to_string());
+                }
+                outputs[(current + 1) % outputs.len()].clone()
+            }
+            MonitorTarget::Number(n) => {
+                let outputs = self.outputs()?;
+                outputs
+                    .get(n as usize - 1)
+                    .cloned()
+                    .ok_or_else(|| format!("There is no monitor {} (there are {})", n, outputs.len()))?
+            }
+        };
+        self.run_command(&format!("{} move container to output {}", criteria(window)?, output))
+    }
+
+    fn tile(&self, left: &WindowInfo, right: &WindowInfo) -> Result<(), String> {
+        // Bring both onto the current workspace, right one first so the left
+        // one lands before it, then lay their parent out horizontally
+        let (left, right) = (criteria(left)?, criteria(right)?);
+        self.run_command(&format!(
+            "{right} floating disable, move container to workspace current; \
+             {left} floating disable, move container to workspace current; \
+             {left} focus; layout splith"
+        ))
+    }
+
+    fn arrange(&self, window: &WindowInfo, slot: &LayoutSlot) -> Result<(), String> {
+        let mut commands = Vec::new();
+        match slot.workspace.as_deref() {
+            Some("scratchpad") => commands.push("move scratchpad".to_string()),
+            Some(workspace) if window.workspace.as_deref() != Some(workspace) => {
+                commands.push(format!("move container to workspace \"{}\"", workspace.replace('"', "\\\"")));
+            }
+            _ => {}
+        }
+        if let Some(geometry) = slot.geometry {
+            commands.push("floating enable".to_string());
+            commands.push(format!("move absolute position {} {}", geometry.x, geometry.y));
+            commands.push(format!("resize set {} {}", geometry.width, geometry.height));
+        }
+        if commands.is_empty() {
+            return Ok(());
+        }
+        self.run_command(&format!("{} {}", criteria(window)?, commands.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_windows_from_the_tree() {
+        let tree = serde_json::json!({
+            "id": 1, "type": "root", "name": "root",
+            "nodes": [
+                {
+                    "id": 2, "type": "output", "name": "__i3",
+                    "nodes": [{
+                        "id": 3, "type": "workspace", "name": "__i3_scratch",
+                        "floating_nodes": [{
+                            "id": 4, "type": "floating_con", "name": "Notes",
+                            "app_id": "org.gnome.TextEditor", "pid": 400,
+                            "rect": { "x": 10, "y": 20, "width": 640, "height": 480 },
+                            "nodes": []
+                        }]
+                    }]
+                },
+                {
+                    "id": 5, "type": "output", "name": "DP-1",
+                    "nodes": [{
+                        "id": 6, "type": "workspace", "name": "2",
+                        "nodes": [
+                            {
+                                "id": 7, "type": "con", "name": null, "pid": null,
+                                "nodes": [{
+                                    "id": 8, "type": "con", "name": "Inbox - Slack",
+                                    "app_id": null, "pid": 800, "focused": true,
+                                    "window_properties": { "class": "Slack" },
+                                    "rect": { "x": 0, "y": 0, "width": 960, "height": 1080 },
+                                    "nodes": []
+                                }]
+                            },
+                            {
+                                "id": 9, "type": "floating_con", "name": "Calculator",
+                                "app_id": "org.gnome.Calculator", "pid": 900,
+                                "rect": { "x": 100, "y": 200, "width": 300, "height": 400 },
+                                "nodes": [], "floating_nodes": []
+                            }
+                        ]
+                    }]
+                }
+            ]
+        });
+
+        let mut windows = Vec::new();
+        collect_windows(&tree, None, None, &mut windows);
+        let summary: Vec<(&str, &str, Option<&str>, Option<&str>, bool)> = windows
+            .iter()
+            .map(|w| (w.id.as_str(), w.app.as_str(), w.workspace.as_deref(), w.monitor.as_deref(), w.focused))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("4", "org.gnome.TextEditor", Some("scratchpad"), None, false),
+                ("8", "Slack", Some("2"), Some("DP-1"), true),
+                ("9", "org.gnome.Calculator", Some("2"), Some("DP-1"), false),
+            ]
+        );
+
+        // Only floating windows report a geometry of their own
+        assert_eq!(windows[0].geometry, Some(WindowGeometry { x: 10, y: 20, width: 640, height: 480 }));
+        assert_eq!(windows[1].geometry, None);
+        assert_eq!(windows[1].title, "Inbox - Slack");
+        assert_eq!(windows[2].pid, Some(900));
+        assert_eq!(criteria(&windows[1]), Ok("[con_id=8]".to_string()));
+    }
+
+    #[test]
+    fn skips_empty_containers_and_workspaces() {
+        let tree = serde_json::json!({
+            "id": 1, "type": "root", "name": "root",
+            "nodes": [{
+                "id": 2, "type": "output", "name": "HDMI-A-1",
+                "nodes": [{ "id": 3, "type": "workspace", "name": "1", "nodes": [] }]
+            }]
+        });
+        let mut windows = Vec::new();
+        collect_windows(&tree, None, None, &mut windows);
+        assert!(windows.is_empty());
+    }
+}
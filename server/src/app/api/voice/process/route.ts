@@ -4,25 +4,57 @@ import { auth } from "@clerk/nextjs/server";
 // Groq API for LLM processing
 const GROQ_LLM_URL = "https://api.groq.com/openai/v1/chat/completions";
 
+// Action wire format version shared with the desktop client.
+// v1 carried the core fields only; v2 added confidence and an alternative.
+const ACTION_WIRE_VERSION = 2;
+
 interface ProcessRequest {
+  version?: number;
   text: string;
   context?: {
-    active_app?: string;
-    selected_text?: string;
+    active_app?: string | null;
+    selected_text?: string | null;
     os: string;
-    mode: "dictation" | "command";
+    timestamp?: string;
+    mode: "Dictation" | "Command";
   };
   conversation_history?: string;
   custom_commands?: Array<{ trigger: string; name: string; id: string }>;
-  dictation_style?: "formal" | "casual" | "very_casual";
+  dictation_style?: "Formal" | "Casual" | "VeryCasual";
 }
 
 interface VoiceActionResponse {
+  version: number;
   action_type: string;
   payload: Record<string, unknown>;
   refined_text: string | null;
   response_text: string | null;
   requires_confirmation: boolean;
+  confidence?: number;
+  alternative?: {
+    action_type: string;
+    payload: Record<string, unknown>;
+    refined_text: string | null;
+    confidence?: number;
+  } | null;
+  // Tokens spent on the request, so the client can account for them
+  usage?: TokenUsage;
+}
+
+interface TokenUsage {
+  prompt_tokens: number;
+  completion_tokens: number;
+}
+
+// No LLM call was made (local fast path)
+const NO_USAGE: TokenUsage = { prompt_tokens: 0, completion_tokens: 0 };
+
+function reportedUsage(result: { usage?: Partial<TokenUsage> }): TokenUsage | undefined {
+  if (!result.usage) return undefined;
+  return {
+    prompt_tokens: result.usage.prompt_tokens ?? 0,
+    completion_tokens: result.usage.completion_tokens ?? 0,
+  };
 }
 
 function normalizeInput(text: string): string {
@@ -104,6 +136,7 @@ export async function POST(request: NextRequest) {
     if (localAction) {
       if (isFarewellPhrase(text) && isPowerControlAction(localAction as VoiceActionResponse)) {
         return NextResponse.json({
+          version: ACTION_WIRE_VERSION,
           action_type: "NoAction",
           payload: {
             blocked_action: "power_control",
@@ -113,9 +146,10 @@ export async function POST(request: NextRequest) {
           response_text:
             "Ignoring shutdown/restart because this sounded like a goodbye phrase.",
           requires_confirmation: false,
+          usage: NO_USAGE,
         });
       }
-      return NextResponse.json(localAction);
+      return NextResponse.json({ version: ACTION_WIRE_VERSION, ...localAction, usage: NO_USAGE });
     }
 
     // Build system prompt
@@ -148,6 +182,7 @@ export async function POST(request: NextRequest) {
       console.error("Groq LLM error:", errorText);
       // Fallback to dictation mode
       return NextResponse.json({
+        version: ACTION_WIRE_VERSION,
         action_type: "TypeText",
         payload: {},
         refined_text: text,
@@ -158,12 +193,14 @@ export async function POST(request: NextRequest) {
 
     const result = await response.json();
     const content = result.choices?.[0]?.message?.content || "{}";
+    const usage = reportedUsage(result);
 
     try {
       const parsed = JSON.parse(content);
       const llmAction = parseLLMResponse(parsed, text);
       if (isFarewellPhrase(text) && isPowerControlAction(llmAction)) {
         return NextResponse.json({
+          version: ACTION_WIRE_VERSION,
           action_type: "NoAction",
           payload: {
             blocked_action: "power_control",
@@ -173,17 +210,20 @@ export async function POST(request: NextRequest) {
           response_text:
             "Ignoring shutdown/restart because this sounded like a goodbye phrase.",
           requires_confirmation: false,
+          usage,
         });
       }
-      return NextResponse.json(llmAction);
+      return NextResponse.json({ ...llmAction, usage });
     } catch {
       // Fallback to dictation
       return NextResponse.json({
+        version: ACTION_WIRE_VERSION,
         action_type: "TypeText",
         payload: {},
         refined_text: text,
         response_text: null,
         requires_confirmation: false,
+        usage,
       });
     }
   } catch (error) {
@@ -221,6 +261,12 @@ function detectLocalCommand(text: string): object | null {
   }
 
   // System controls
+  if (t === "undo last action" || t === "undo the last action") {
+    return { action_type: "SystemControl", payload: { action: "undo_last_action" } };
+  }
+  if (t === "repeat last command" || t === "repeat the last command") {
+    return { action_type: "SystemControl", payload: { action: "repeat_command", n: 1 } };
+  }
   if (t.includes("lock") && (t.includes("computer") || t.includes("screen") || t === "lock")) {
     return { action_type: "SystemControl", payload: { action: "lock" } };
   }
@@ -277,7 +323,7 @@ function buildSystemPrompt(
   dictationStyle?: string
 ): string {
   const os = context?.os || "unknown";
-  const mode = context?.mode || "command";
+  const mode = context?.mode || "Command";
 
   let prompt = `You are ListenOS, a voice assistant. Determine if the user wants to execute a COMMAND or just TYPE text.
 
@@ -309,18 +355,39 @@ ACTIONS (respond with JSON):
    TRIGGERS: "volume up/down", "mute", "louder", "quieter"
 
 6. SystemControl - System actions
-   {"action": "system_control", "system_action": "lock|sleep|screenshot"}
-   TRIGGERS: "lock computer", "take screenshot", "sleep"
+   {"action": "system_control", "system_action": "lock|sleep|screenshot|undo_last_action|repeat_command"}
+   TRIGGERS: "lock computer", "take screenshot", "sleep", "undo the last action", "repeat the last command"
 
 7. SpotifyControl - Media control
    {"action": "spotify_control", "media_action": "play_pause|next|previous"}
    TRIGGERS: "play", "pause", "next song", "previous"
+
+8. DiscordControl - Discord voice controls
+   {"action": "discord_control", "discord_action": "mute|deafen|disconnect"}
+   TRIGGERS: "mute discord", "deafen", "leave the call"
+
+9. Clipboard - Transform clipboard contents (only when "clipboard" is mentioned)
+   {"action": "clipboard_format", "format": "bullets|paragraph|numbered"}
+   {"action": "clipboard_translate", "target_language": "Spanish"}
+   {"action": "clipboard_summarize"}
+   {"action": "clipboard_clean"}
+
+10. KeyboardShortcut - Press a shortcut
+   {"action": "keyboard_shortcut", "shortcut": "copy|paste|undo|redo|save|select_all"}
+
+11. WindowControl - Manage the active window
+   {"action": "window_control", "window_action": "minimize|maximize|close|switch"}
+
+12. MultiStep - Several actions in one request; later steps can use earlier results
+   {"action": "multi_step", "on_failure": "stop|continue", "steps": [{"id": "shot", "action": "system_control", "payload": {"action": "screenshot"}}, {"action": "system_control", "payload": {"action": "open_file", "path": "{{shot.output.path}}"}}]}
+   Reference an earlier step with {{id.output.field}}, or {{prev.output.field}} for the step right before
+   TRIGGERS: "take a screenshot and open it", "count my downloads then type the number"
 `;
 
   if (customCommands && customCommands.length > 0) {
     prompt += `\nCUSTOM COMMANDS:\n`;
     customCommands.forEach((cmd) => {
-      prompt += `- "${cmd.trigger}" -> command "${cmd.name}" (id: ${cmd.id})\n`;
+      prompt += `- "${cmd.trigger}" -> {"action": "custom_command", "command_id": "${cmd.id}", "trigger_phrase": "${cmd.trigger}"} (${cmd.name})\n`;
     });
   }
 
@@ -331,13 +398,55 @@ RULES:
 3. "hello", "how are you", sentences = TypeText (dictation)
 4. Explicit commands with trigger words = appropriate action
 5. When in doubt, TypeText
-6. Always respond with valid JSON`;
+6. Always respond with valid JSON
+7. Include "confidence" (0.0-1.0): how sure you are this is what the user meant
+8. Include "alternative": the next most likely interpretation in the same JSON shape, or null
+9. Use a confidence below 0.6 when the request could be either a command or dictation`;
 
   return prompt;
 }
 
 // Parse LLM response into standardized format
 function parseLLMResponse(parsed: Record<string, unknown>, originalText: string): VoiceActionResponse {
+  const { actionType, payload, refinedText } = buildAction(parsed, originalText);
+  const confidence = clampConfidence(parsed.confidence, 0.8);
+
+  let alternative: VoiceActionResponse["alternative"] = null;
+  if (parsed.alternative && typeof parsed.alternative === "object") {
+    const alt = buildAction(parsed.alternative as Record<string, unknown>, originalText);
+    alternative = {
+      action_type: alt.actionType,
+      payload: alt.payload,
+      refined_text: alt.refinedText,
+      confidence: clampConfidence(
+        (parsed.alternative as Record<string, unknown>).confidence,
+        1 - confidence
+      ),
+    };
+  }
+
+  return {
+    version: ACTION_WIRE_VERSION,
+    action_type: actionType,
+    payload,
+    refined_text: refinedText,
+    response_text: null,
+    requires_confirmation: false,
+    confidence,
+    alternative,
+  };
+}
+
+function clampConfidence(value: unknown, fallback: number): number {
+  const n = typeof value === "number" && Number.isFinite(value) ? value : fallback;
+  return Math.min(1, Math.max(0, n));
+}
+
+// Map one LLM action object onto the client's action type and payload
+function buildAction(
+  parsed: Record<string, unknown>,
+  originalText: string
+): { actionType: string; payload: Record<string, unknown>; refinedText: string | null } {
   const action = parsed.action as string || "type_text";
 
   const actionMap: Record<string, string> = {
@@ -348,6 +457,15 @@ function parseLLMResponse(parsed: Record<string, unknown>, originalText: string)
     volume_control: "VolumeControl",
     system_control: "SystemControl",
     spotify_control: "SpotifyControl",
+    discord_control: "DiscordControl",
+    custom_command: "CustomCommand",
+    multi_step: "MultiStep",
+    clipboard_format: "ClipboardFormat",
+    clipboard_translate: "ClipboardTranslate",
+    clipboard_summarize: "ClipboardSummarize",
+    clipboard_clean: "ClipboardClean",
+    keyboard_shortcut: "KeyboardShortcut",
+    window_control: "WindowControl",
     no_action: "TypeText",
   };
 
@@ -379,13 +497,28 @@ function parseLLMResponse(parsed: Record<string, unknown>, originalText: string)
     case "SpotifyControl":
       payload = { action: parsed.media_action || "play_pause" };
       break;
+    case "DiscordControl":
+      payload = { action: parsed.discord_action || "mute" };
+      break;
+    case "CustomCommand":
+      payload = { command_id: parsed.command_id, trigger_phrase: parsed.trigger_phrase };
+      break;
+    case "MultiStep":
+      payload = { steps: parsed.steps || [], on_failure: parsed.on_failure === "stop" ? "stop" : "continue" };
+      break;
+    case "ClipboardFormat":
+      payload = { format: parsed.format || "paragraph" };
+      break;
+    case "ClipboardTranslate":
+      payload = { target_language: parsed.target_language || "Spanish" };
+      break;
+    case "KeyboardShortcut":
+      payload = { shortcut: parsed.shortcut };
+      break;
+    case "WindowControl":
+      payload = { action: parsed.window_action };
+      break;
   }
 
-  return {
-    action_type: actionType,
-    payload,
-    refined_text: refinedText,
-    response_text: null,
-    requires_confirmation: false,
-  };
+  return { actionType, payload, refinedText };
 }
@@ -23,6 +23,7 @@ const ACTION_TYPES = [
   { id: "type_text", name: "Type Text", icon: "⌨️" },
   { id: "volume_control", name: "Volume Control", icon: "🔊" },
   { id: "spotify_control", name: "Spotify Control", icon: "🎵" },
+  { id: "media_control", name: "Media Control", icon: "🎵" },
   { id: "discord_control", name: "Discord Control", icon: "💬" },
   { id: "system_control", name: "System Control", icon: "⚙️" },
 ];
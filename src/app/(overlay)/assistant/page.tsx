@@ -9,6 +9,8 @@ import {
   getPendingAction,
   confirmPendingAction,
   cancelPendingAction,
+  cancelAnswer,
+  onAnswerToken,
   onShortcutPressed,
   onShortcutReleased,
   getAudioLevel,
@@ -28,8 +30,15 @@ function formatActionFeedback(result: VoiceProcessingResult): string | null {
     case "OpenApp": return `Opening ${result.action?.payload?.app || "app"}`;
     case "WebSearch": return `Searching...`;
     case "OpenUrl": return "Opening URL";
-    case "VolumeControl": return `Volume ${result.action?.payload?.direction}`;
+    case "VolumeControl": {
+      const payload = result.action?.payload;
+      if (typeof payload?.level === "number") return `Volume ${payload.level}%`;
+      if (payload?.device) return `Switching to ${payload.device}`;
+      if (payload?.app) return `${payload.app} ${payload.direction ?? "volume"}`;
+      return payload?.direction ? `Volume ${payload.direction}` : "Volume";
+    }
     case "SpotifyControl": return `${result.action?.payload?.action}`;
+    case "MediaControl":
     case "SystemControl":
       return typeof result.response_text === "string" && result.response_text.length > 0
         ? result.response_text
@@ -73,6 +82,7 @@ export default function AssistantPage() {
   const transcriptionCount = useRef(0);
   const isStartingRef = useRef(false);
   const pendingStopRef = useRef(false);
+  const streamingAnswerRef = useRef(false);
 
   useEffect(() => { setMounted(true); }, []);
   useEffect(() => { stateRef.current = state; }, [state]);
@@ -139,6 +149,32 @@ export default function AssistantPage() {
     return () => { unlisten?.(); };
   }, [mounted]);
 
+  // Show answers as they stream in while stop_listening is still running
+  useEffect(() => {
+    if (!mounted || !isTauri()) return;
+
+    let unlisten: (() => void) | undefined;
+
+    const setup = async () => {
+      try {
+        unlisten = await onAnswerToken((token) => {
+          if (stateRef.current !== "processing") return;
+          if (!streamingAnswerRef.current) {
+            streamingAnswerRef.current = true;
+            setFeedback(token.delta);
+          } else {
+            setFeedback((previous) => (previous || "") + token.delta);
+          }
+        });
+      } catch (e) {
+        console.warn("Failed to listen for answer tokens:", e);
+      }
+    };
+
+    setup();
+    return () => { unlisten?.(); };
+  }, [mounted]);
+
   // Show tooltip on hover with delay
   useEffect(() => {
     if (hovered && state === "idle" && !notification && !pendingAction) {
@@ -196,8 +232,12 @@ export default function AssistantPage() {
     const dictationOnly = stateRef.current === "handsfree";
     setState("processing");
     setFeedback(null);
+    streamingAnswerRef.current = false;
     try {
       const result = await stopListening(dictationOnly);
+      streamingAnswerRef.current = false;
+      // A spoken "yes" or "cancel" may have resolved the pending action
+      void getPendingAction().then(setPendingAction).catch(() => undefined);
       if (result.action?.action_type === "NoAction") {
         setState("idle");
         return;
@@ -280,21 +320,24 @@ export default function AssistantPage() {
     if (!pendingAction) return;
     setState("processing");
     try {
-      const result = await confirmPendingAction();
+      const result = await confirmPendingAction(pendingAction.id);
       setPendingAction(null);
       setFeedback(result.message || "Action confirmed");
       setState("success");
       setTimeout(() => { setState("idle"); setFeedback(null); }, 900);
-    } catch {
+    } catch (error) {
+      // Expired or replaced; show the newest pending action, if any
+      setPendingAction(await getPendingAction().catch(() => null));
+      setFeedback(typeof error === "string" ? error : null);
       setState("error");
-      setTimeout(() => { setState("idle"); setFeedback(null); }, 800);
+      setTimeout(() => { setState("idle"); setFeedback(null); }, 1600);
     }
   }, [pendingAction]);
 
   const handleCancelPending = useCallback(async () => {
     if (!pendingAction) return;
     try {
-      await cancelPendingAction();
+      await cancelPendingAction(pendingAction.id);
       setPendingAction(null);
       setFeedback("Action canceled");
       setState("success");
@@ -310,6 +353,14 @@ export default function AssistantPage() {
     setFeedback(null);
   }, []);
 
+  const handlePillClick = useCallback(() => {
+    if (stateRef.current === "idle") {
+      void start(true);
+    } else if (stateRef.current === "processing" && streamingAnswerRef.current) {
+      void cancelAnswer().catch(() => undefined);
+    }
+  }, [start]);
+
   useEffect(() => {
     if (!mounted || !isTauri()) return;
     let unlistenPressed: (() => void) | undefined;
@@ -395,7 +446,7 @@ export default function AssistantPage() {
         transition={{ type: "spring", stiffness: 400, damping: 30 }}
         onMouseEnter={() => setHovered(true)}
         onMouseLeave={() => setHovered(false)}
-        onClick={() => { if (state === "idle") start(true); }}
+        onClick={handlePillClick}
       >
         <motion.div
           className="absolute inset-0 keep-bg"
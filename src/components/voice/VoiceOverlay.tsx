@@ -266,6 +266,7 @@ export function VoiceOverlay({
       Respond: { icon: "💬", label: "Response" },
       Clarify: { icon: "❓", label: "Question" },
       SpotifyControl: { icon: "🎵", label: "Spotify" },
+      MediaControl: { icon: "🎵", label: "Media" },
       DiscordControl: { icon: "💬", label: "Discord" },
       SystemControl: { icon: "⚙️", label: "System" },
       ClipboardFormat: { icon: "📋", label: "Clipboard" },
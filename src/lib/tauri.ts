@@ -26,6 +26,7 @@ export interface PendingAction {
   transcription: string;
   summary: string;
   created_at: string;
+  expires_at: string;
 }
 
 export interface VoiceProcessingResult {
@@ -217,20 +218,142 @@ export async function typeText(text: string): Promise<{ success: boolean; messag
   return invoke("type_text", { text });
 }
 
-export async function runSystemCommand(command: string): Promise<{ success: boolean; message: string; output?: string }> {
-  return invoke("run_system_command", { command });
+export interface CommandOutput {
+  command: string;
+  argv: string[];
+  shell: boolean;
+  exit_code: number | null;
+  success: boolean;
+  stdout: string;
+  stderr: string;
+  stdout_truncated: boolean;
+  stderr_truncated: boolean;
+  timed_out: boolean;
+  duration_ms: number;
+}
+
+export interface CommandSandboxConfig {
+  allowlist: string[];
+  allow_shell: boolean;
+  timeout_secs: number;
+  max_output_bytes: number;
+  working_dir: string | null;
+  env_passthrough: string[];
+}
+
+// Runs without a shell and only if the command matches the allowlist
+export async function runSystemCommand(command: string, workingDir?: string): Promise<CommandOutput> {
+  return invoke("run_system_command", { command, workingDir: workingDir ?? null });
+}
+
+export type InjectionBackend = "clipboard_paste" | "unicode_keys" | "uinput" | "xtest" | "wtype";
+
+// Backends are tried in order; the first app rule whose name matches wins
+export interface TextInjectionConfig {
+  backends: InjectionBackend[];
+  app_rules: { app: string; backends: InjectionBackend[] }[];
+  chunk_size: number;
+  chunk_delay_ms: number;
+  exclude_from_clipboard_history: boolean;
+}
+
+export async function getTextInjection(): Promise<TextInjectionConfig> {
+  return invoke("get_text_injection");
+}
+
+export async function setTextInjection(config: TextInjectionConfig): Promise<TextInjectionConfig> {
+  return invoke("set_text_injection", { config });
+}
+
+export async function getCommandSandbox(): Promise<CommandSandboxConfig> {
+  return invoke("get_command_sandbox");
+}
+
+export async function setCommandSandbox(config: CommandSandboxConfig): Promise<CommandSandboxConfig> {
+  return invoke("set_command_sandbox", { config });
 }
 
 export async function getPendingAction(): Promise<PendingAction | null> {
   return invoke("get_pending_action");
 }
 
-export async function confirmPendingAction(): Promise<{ success: boolean; message: string; output?: string }> {
-  return invoke("confirm_pending_action");
+// Fails if the pending action expired or was replaced by a newer one
+export async function confirmPendingAction(id: string): Promise<{ success: boolean; message: string; output?: string }> {
+  return invoke("confirm_pending_action", { id });
+}
+
+export async function cancelPendingAction(id?: string): Promise<boolean> {
+  return invoke("cancel_pending_action", { id: id ?? null });
+}
+
+export type ConfirmationMode = "always" | "never" | "remember_for_session";
+
+// Every condition that is set must hold; the first matching rule wins
+export interface ConfirmationRule {
+  name: string;
+  action_type?: string | null;
+  payload?: Record<string, unknown>;
+  app?: string | null;
+  from_hour?: number | null;
+  to_hour?: number | null;
+  route?: "local" | "llm" | null;
+  mode: ConfirmationMode;
+}
+
+export interface ConfirmationPolicy {
+  rules: ConfirmationRule[];
+}
+
+export async function getConfirmationPolicy(): Promise<ConfirmationPolicy> {
+  return invoke("get_confirmation_policy");
+}
+
+export async function setConfirmationPolicy(policy: ConfirmationPolicy): Promise<ConfirmationPolicy> {
+  return invoke("set_confirmation_policy", { policy });
+}
+
+// Stop the answer currently being streamed; the partial text is kept
+export async function cancelAnswer(): Promise<void> {
+  return invoke("cancel_answer");
+}
+
+export interface JournalEntry {
+  id: string;
+  description: string;
+  inverse: { kind: string; [key: string]: unknown };
+  created_at: string;
+}
+
+// Revert the most recent voice action that can be undone
+export async function undoLastAction(): Promise<{ success: boolean; message: string; output?: string }> {
+  return invoke("undo_last_action");
 }
 
-export async function cancelPendingAction(): Promise<boolean> {
-  return invoke("cancel_pending_action");
+export async function getActionJournal(): Promise<JournalEntry[]> {
+  return invoke("get_action_journal");
+}
+
+export interface SimulatedEffect {
+  kind: string;
+  description: string;
+  detail: unknown;
+}
+
+export interface UtterancePreview {
+  text: string;
+  action: { action_type: string; payload: unknown; refined_text?: string | null; response_text?: string | null };
+  summary: string;
+  requires_confirmation: boolean;
+  confirmation_reason: string;
+  would_clarify: boolean;
+  result?: { success: boolean; message: string; output?: string } | null;
+  error?: string | null;
+  effects: SimulatedEffect[];
+}
+
+// Route a phrase and simulate it without touching the machine
+export async function previewUtterance(text: string): Promise<UtterancePreview> {
+  return invoke("preview_utterance", { text });
 }
 
 // ============ Audio Device Commands ============
@@ -258,6 +381,44 @@ export async function clearHistory(): Promise<void> {
   return invoke("clear_history");
 }
 
+export interface HistoryEntry {
+  id: number;
+  created_at: string;
+  session_id: string;
+  transcript: string;
+  typed_text: string | null;
+  action_type: string;
+  active_app: string | null;
+  executed: boolean;
+  result: VoiceProcessingResult;
+}
+
+export interface HistoryQuery {
+  text?: string;
+  action_type?: string;
+  app?: string;
+  from?: string;
+  to?: string;
+  limit?: number;
+  offset?: number;
+}
+
+export interface HistoryPage {
+  entries: HistoryEntry[];
+  total: number;
+  offset: number;
+  limit: number;
+}
+
+export async function searchHistory(query: HistoryQuery = {}): Promise<HistoryPage> {
+  return invoke("search_history", { query });
+}
+
+// Repeat a history entry by id, or the n-th most recent command (1 = last)
+export async function repeatCommand(options: { n?: number; id?: number } = {}): Promise<{ success: boolean; message: string; output?: string }> {
+  return invoke("repeat_command", { n: options.n ?? null, id: options.id ?? null });
+}
+
 // ============ Window Commands ============
 
 export async function hideAssistant(): Promise<void> {
@@ -282,6 +443,17 @@ export function onShortcutReleased(callback: () => void): Promise<UnlistenFn> {
   });
 }
 
+export interface AnswerToken {
+  stream_id: string;
+  delta: string;
+}
+
+export function onAnswerToken(callback: (token: AnswerToken) => void): Promise<UnlistenFn> {
+  return listen<AnswerToken>("answer-token", (event) => {
+    callback(event.payload);
+  });
+}
+
 // ============ Conversation Commands ============
 
 export async function getConversation(): Promise<ConversationMessage[]> {
@@ -350,6 +522,116 @@ export async function importCustomCommands(json: string): Promise<number> {
   return invoke("import_custom_commands", { json });
 }
 
+// ============ Aliases ============
+
+export interface AppAlias {
+  phrase: string;
+  app: string;
+}
+
+export interface WebsiteAlias {
+  phrase: string;
+  url: string;
+}
+
+export interface SearchEngine {
+  keyword: string;
+  name: string;
+  // Search URL with {query} where the encoded query goes
+  url_template: string;
+}
+
+export interface AliasConfig {
+  apps: AppAlias[];
+  websites: WebsiteAlias[];
+  search_engines: SearchEngine[];
+  default_engine: string;
+}
+
+export async function getAliases(): Promise<AliasConfig> {
+  return invoke("get_aliases");
+}
+
+export async function setAliases(aliases: AliasConfig): Promise<AliasConfig> {
+  return invoke("set_aliases", { aliases });
+}
+
+export async function exportAliases(): Promise<string> {
+  return invoke("export_aliases");
+}
+
+// Entries with the same phrase or keyword are replaced
+export async function importAliases(json: string): Promise<number> {
+  return invoke("import_aliases", { json });
+}
+
+// ============ Window Layouts ============
+
+export interface WindowGeometry {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+export interface LayoutSlot {
+  app: string;
+  title: string;
+  launch: string;
+  desktop_id: string | null;
+  workspace: string | null;
+  monitor: string | null;
+  geometry: WindowGeometry | null;
+}
+
+export interface WindowLayout {
+  id: string;
+  name: string;
+  windows: LayoutSlot[];
+  created_at: string;
+  updated_at: string;
+}
+
+export async function getWindowLayouts(): Promise<WindowLayout[]> {
+  return invoke("get_window_layouts");
+}
+
+// Saving under an existing name replaces that layout
+export async function saveWindowLayout(name: string): Promise<WindowLayout> {
+  return invoke("save_window_layout", { name });
+}
+
+export async function restoreWindowLayout(name: string): Promise<{ success: boolean; message: string }> {
+  return invoke("restore_window_layout", { name });
+}
+
+export async function deleteWindowLayout(id: string): Promise<void> {
+  return invoke("delete_window_layout", { id });
+}
+
+// ============ Installed Apps (Linux) ============
+
+export interface InstalledApp {
+  id: string;
+  name: string;
+  generic_name: string | null;
+  localized_names: string[];
+  keywords: string[];
+  categories: string[];
+  exec: string;
+  icon: string | null;
+  working_dir: string | null;
+  terminal: boolean;
+  startup_wm_class: string | null;
+  path: string;
+  source: "system" | "user" | "flatpak" | "snap";
+}
+
+// Empty on Windows and macOS
+export async function listInstalledApps(): Promise<InstalledApp[]> {
+  return invoke("list_installed_apps");
+}
+
 // ============ Autostart Commands ============
 
 export async function getAutostartEnabled(): Promise<boolean> {